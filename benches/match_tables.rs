@@ -0,0 +1,63 @@
+//! Times dense integer `match`es evaluated with and without lowering them
+//! to tables. Run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use expressions::eval::{self, Options};
+use expressions::{parser, typeck};
+
+/// The number of arms in each benchmark's `match`, the last being `_`.
+const ARMS: u32 = 32;
+
+const RUNS: u32 = 5;
+
+/// A loop summing a `match` on `i % ARMS` whose arm `k` has the body
+/// `body(k)`.
+fn program(body: impl Fn(u32) -> String) -> String {
+    let arms: String = (0..ARMS - 1).map(|k| format!("{} => {},\n", k, body(k))).collect();
+    format!(
+        "let mut total = 0;
+        let mut i = 0;
+        while i < 10000 {{
+            total += match i % {} {{
+                {}
+                _ => {},
+            }};
+            i += 1;
+        }}
+        total",
+        ARMS,
+        arms,
+        body(ARMS - 1)
+    )
+}
+
+fn time(src: &str, options: &Options) -> Duration {
+    let program = parser::parse_program(src).expect("the benchmark parses");
+    let (types, _) = typeck::check(&program);
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        eval::eval_program_with(&program, &types, options).expect("the benchmark runs");
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn main() {
+    // Arms that compute something are lowered to a jump table; arms that
+    // are constants, to a table of their values.
+    let jump = program(|k| format!("i * {}", k));
+    let consts = program(|k| (k * 7 % 31).to_string());
+    for (name, src) in [("jump table", jump), ("constant table", consts)] {
+        let linear = time(&src, &Options { match_tables: false, ..Options::default() });
+        let table = time(&src, &Options { match_tables: true, ..Options::default() });
+        println!(
+            "{:<16} linear {:>9.2?}   table {:>9.2?}   speedup {:.2}x",
+            name,
+            linear,
+            table,
+            linear.as_secs_f64() / table.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,738 @@
+//! The syntax tree produced by the parser.
+//!
+//! Rust is an expression language, and so is this tree: blocks, `if`,
+//! `match` and the loops are all [`ExprKind`]s and all produce a value. A
+//! [`Block`] keeps its trailing expression apart from its statements, since
+//! that is what decides the block's value: `{ a; b }` is worth `b`, while
+//! `{ a; b; }` is worth `()`.
+
+use std::sync::Arc;
+
+use crate::span::Span;
+
+/// Identifies an expression, pattern, block or item, so later passes can attach
+/// information (types, resolutions) to it in side tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub u32);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    pub id: NodeId,
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind {
+    Lit(LitKind),
+    /// A variable, constant or function name: `x`, `Vec::new`, `Pet::Hyena`.
+    Path(Path),
+    /// `-x`, `!x` or `*x`.
+    Unary(UnOp, Box<Expr>),
+    /// `&x` or `&mut x`.
+    AddrOf(Mutability, Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    /// `a = b`
+    Assign(Box<Expr>, Box<Expr>),
+    /// `a += b`, `a <<= b`, ...
+    AssignOp(BinOp, Box<Expr>, Box<Expr>),
+    /// `x as usize`
+    Cast(Box<Expr>, Ty),
+    /// `a..b`, `a..`, `..b`, `..`, `a..=b` and `..=b`.
+    Range(Option<Box<Expr>>, Option<Box<Expr>>, RangeLimits),
+    /// `f(a, b)`
+    Call(Box<Expr>, Vec<Expr>),
+    /// `receiver.method::<T>(args)`
+    MethodCall(Box<MethodCall>),
+    /// `game.black_pawns`
+    Field(Box<Expr>, Ident),
+    /// `coords.1`
+    TupleField(Box<Expr>, usize, Span),
+    /// `pieces[i]`
+    Index(Box<Expr>, Box<Expr>),
+    /// `expr?`
+    Try(Box<Expr>),
+    /// `(expr)`, kept so diagnostics and printing can tell it was parenthesized.
+    Paren(Box<Expr>),
+    /// `(a, b)`; the unit value `()` is the empty tuple.
+    Tuple(Vec<Expr>),
+    /// `[a, b, c]`
+    Array(Vec<Expr>),
+    /// `[value; count]`
+    Repeat(Box<Expr>, Box<Expr>),
+    /// `Complex { re: 1.0, im: 0.0 }`
+    Struct(Box<StructExpr>),
+    /// `println!(...)`, `vec![...]`, `assert_eq!(...)`, ...
+    MacCall(MacCall),
+    /// `{ stmts; tail }`
+    Block(Block),
+    /// `if cond { ... } else ...`. The `else` branch is a block or another `if`.
+    If(Box<Expr>, Block, Option<Box<Expr>>),
+    /// `let pat = expr` as the condition of an `if let` or `while let`. The
+    /// parser desugars both into `match`, so later passes never see one.
+    Let(Box<Pat>, Box<Expr>),
+    /// `match scrutinee { arms }`, as written or desugared from an `if let`
+    /// or `while let`.
+    Match(Box<Expr>, Vec<Arm>, MatchSource),
+    /// `'label: while cond { ... }`
+    While(Box<Expr>, Block, Option<Ident>),
+    /// `'label: loop { ... }`, as written or desugared from a `while let`.
+    Loop(Block, Option<Ident>, LoopSource),
+    /// `'label: for pat in iter { ... }`
+    For(Box<Pat>, Box<Expr>, Block, Option<Ident>),
+    /// `break 'label value`
+    Break(Option<Ident>, Option<Box<Expr>>),
+    /// `continue 'label`
+    Continue(Option<Ident>),
+    /// `return value`
+    Return(Option<Box<Expr>>),
+    /// `|x| x % 2 == 0`
+    Closure(Arc<Closure>),
+}
+
+impl ExprKind {
+    /// Block-like expressions end a statement without needing a `;`.
+    pub fn is_block_like(&self) -> bool {
+        matches!(
+            self,
+            ExprKind::Block(_)
+                | ExprKind::If(..)
+                | ExprKind::Match(..)
+                | ExprKind::While(..)
+                | ExprKind::Loop(..)
+                | ExprKind::For(..)
+        )
+    }
+}
+
+/// A `{ ... }` block: statements followed by an optional trailing expression
+/// that gives the block its value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub id: NodeId,
+    pub stmts: Vec<Stmt>,
+    /// The final expression, written without a `;`. A block without one
+    /// evaluates to `()`.
+    pub tail: Option<Box<Expr>>,
+    pub span: Span,
+}
+
+impl Block {
+    /// Whether the block can only give `()` by control reaching its end:
+    /// it has no tail, or ends in a `while`, `while let` or `for` loop.
+    pub fn ends_without_value(&self) -> bool {
+        match self.tail.as_deref().map(|tail| &tail.kind) {
+            None => true,
+            Some(ExprKind::While(..) | ExprKind::For(..)) => true,
+            Some(ExprKind::Loop(_, _, source)) => *source == LoopSource::WhileLet,
+            Some(_) => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StmtKind {
+    /// `let pat: ty = init;` where the type and initializer are optional.
+    Let(Box<Local>),
+    /// A `fn`, `struct`, `enum` or `use` declared inside the block. Its name is in
+    /// scope throughout the block, even before the declaration.
+    Item(Box<Item>),
+    /// A block-like expression used as a statement without a `;`, such as an
+    /// `if` in the middle of a block. Its value must be `()`.
+    Expr(Expr),
+    /// An expression followed by `;`: evaluated, then its value is dropped.
+    Semi(Expr),
+    /// A stray `;`.
+    Empty,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Local {
+    pub pat: Pat,
+    pub ty: Option<Ty>,
+    pub init: Option<Expr>,
+}
+
+/// A declaration that could also appear at the top level of a module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub id: NodeId,
+    pub kind: ItemKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ItemKind {
+    /// Shared, since the evaluator keeps a function alive as a value.
+    Fn(Arc<FnDecl>),
+    /// Shared, like functions, so the evaluator can keep the declarations
+    /// of the types it builds values of.
+    Struct(Arc<StructDecl>),
+    Enum(Arc<EnumDecl>),
+    /// `use std::cmp::Ordering;`
+    Use(UseTree),
+}
+
+impl Item {
+    /// The names the item brings into scope: one for a `fn`, `struct` or
+    /// `enum`, any number for a `use`.
+    pub fn names(&self) -> Vec<&Ident> {
+        match &self.kind {
+            ItemKind::Fn(decl) => vec![&decl.name],
+            ItemKind::Struct(decl) => vec![&decl.name],
+            ItemKind::Enum(decl) => vec![&decl.name],
+            ItemKind::Use(tree) => {
+                let mut names = Vec::new();
+                tree.names(None, &mut names);
+                names
+            }
+        }
+    }
+}
+
+/// `fn name(params) -> ret { body }`
+#[derive(Clone, Debug, PartialEq)]
+pub struct FnDecl {
+    pub name: Ident,
+    pub params: Vec<Param>,
+    /// `None` for a function returning `()`.
+    pub ret: Option<Ty>,
+    pub body: Block,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructDecl {
+    pub name: Ident,
+    pub fields: StructFields,
+}
+
+/// The fields of a struct or of an enum variant.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StructFields {
+    /// `struct Point { x: f64, y: f64 }`
+    Named(Vec<FieldDecl>),
+    /// `struct Point(f64, f64);`
+    Tuple(Vec<Ty>),
+    /// `struct Marker;`
+    Unit,
+}
+
+/// `enum Name { Variant, Variant(ty, ...), Variant { field: ty, ... } }`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumDecl {
+    pub name: Ident,
+    pub variants: Vec<Variant>,
+}
+
+impl EnumDecl {
+    pub fn variant(&self, name: &str) -> Option<&Variant> {
+        self.variants.iter().find(|variant| variant.name.name == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    pub name: Ident,
+    pub fields: StructFields,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldDecl {
+    pub name: Ident,
+    pub ty: Ty,
+}
+
+/// What a `use` imports: `prefix::name`, `prefix::name as alias`,
+/// `prefix::{a, b}` or `prefix::*`. Inside braces, the prefix is relative
+/// to the path before them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UseTree {
+    pub prefix: Path,
+    pub kind: UseTreeKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UseTreeKind {
+    /// The last segment of the prefix, optionally renamed.
+    Simple(Option<Ident>),
+    Nested(Vec<UseTree>),
+    Glob,
+}
+
+impl UseTree {
+    /// `parent` is the segment before a nested tree's braces, which `self`
+    /// inside them stands for: `use std::io::{self, Read};` imports `io`.
+    fn names<'a>(&'a self, parent: Option<&'a Ident>, names: &mut Vec<&'a Ident>) {
+        let last = &self.prefix.last().ident;
+        match &self.kind {
+            UseTreeKind::Simple(Some(alias)) => names.push(alias),
+            UseTreeKind::Simple(None) if last.name == "self" => names.extend(parent),
+            UseTreeKind::Simple(None) => names.push(last),
+            UseTreeKind::Nested(trees) => {
+                trees.iter().for_each(|tree| tree.names(Some(last), names));
+            }
+            UseTreeKind::Glob => {}
+        }
+    }
+}
+
+/// One `pattern if guard => body` arm of a `match`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arm {
+    pub pat: Pat,
+    pub guard: Option<Box<Expr>>,
+    pub body: Expr,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pat {
+    pub id: NodeId,
+    pub kind: PatKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatKind {
+    /// `_`
+    Wild,
+    /// A binding such as `x`, `mut total` or `ref name`, with the pattern
+    /// after `@` that the value must also match: `digit @ 0..=9`.
+    Ident(BindingMode, Ident, Option<Box<Pat>>),
+    /// A literal: `0`, `-1`, `'a'`, `"text"`, `true`.
+    Lit(Box<Expr>),
+    /// `1..=9`, `'a'..='z'` or `0..10`. The bounds are literals or paths to
+    /// constants.
+    Range(Box<Expr>, Box<Expr>, RangeLimits),
+    /// A unit struct, unit variant or constant: `None`, `Pet::Hyena`.
+    Path(Path),
+    /// `Some(count)`, `Err(err)`
+    TupleStruct(Path, Vec<Pat>),
+    /// `Point { x, y: 0, .. }`. The flag says whether the fields end with
+    /// `..`, which leaves the rest unmatched.
+    Struct(Path, Vec<FieldPat>, bool),
+    /// `(a, b)`
+    Tuple(Vec<Pat>),
+    /// `&pat` or `&mut pat`
+    Ref(Box<Pat>, Mutability),
+    /// `pat | pat | ...`: matches if any of them does. Each must bind the
+    /// same variables.
+    Or(Vec<Pat>),
+}
+
+impl Pat {
+    /// The variables the pattern binds, in order. For an or-pattern, those
+    /// of its first alternative, since all of them bind the same.
+    pub fn bindings(&self) -> Vec<(BindingMode, &Ident)> {
+        let mut bindings = Vec::new();
+        self.walk_bindings(&mut bindings);
+        bindings
+    }
+
+    fn walk_bindings<'a>(&'a self, bindings: &mut Vec<(BindingMode, &'a Ident)>) {
+        match &self.kind {
+            PatKind::Ident(mode, name, sub) => {
+                bindings.push((*mode, name));
+                if let Some(sub) = sub {
+                    sub.walk_bindings(bindings);
+                }
+            }
+            PatKind::TupleStruct(_, pats) | PatKind::Tuple(pats) => {
+                pats.iter().for_each(|pat| pat.walk_bindings(bindings));
+            }
+            PatKind::Struct(_, fields, _) => {
+                fields.iter().for_each(|field| field.pat.walk_bindings(bindings));
+            }
+            PatKind::Ref(pat, _) => pat.walk_bindings(bindings),
+            PatKind::Or(pats) => pats[0].walk_bindings(bindings),
+            PatKind::Wild | PatKind::Lit(_) | PatKind::Range(..) | PatKind::Path(_) => {}
+        }
+    }
+}
+
+/// How a pattern binds a variable: by moving or copying the value, or by
+/// borrowing it with `ref` or `ref mut`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingMode {
+    ByValue(Mutability),
+    ByRef(Mutability),
+}
+
+impl BindingMode {
+    /// Whether the variable itself can be assigned to. A `ref mut` binding
+    /// cannot, though what it refers to can.
+    pub fn mutability(self) -> Mutability {
+        match self {
+            BindingMode::ByValue(mutability) => mutability,
+            BindingMode::ByRef(_) => Mutability::Not,
+        }
+    }
+}
+
+/// `name: pat` in a struct pattern, or just `name` as shorthand for
+/// `name: name`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldPat {
+    pub name: Ident,
+    pub pat: Pat,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructExpr {
+    pub path: Path,
+    pub fields: Vec<FieldExpr>,
+}
+
+/// `name: value`, or just `name` as shorthand for `name: name`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldExpr {
+    pub name: Ident,
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Closure {
+    pub capture: CaptureBy,
+    pub params: Vec<Param>,
+    pub ret: Option<Ty>,
+    pub body: Expr,
+}
+
+/// How a closure captures the variables it uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureBy {
+    /// `move |x| ...`: by value, moving or copying them into the closure.
+    Value,
+    /// By reference, as the closure's uses of them need.
+    Ref,
+}
+
+/// A function or closure parameter. Closure parameters may leave out the type.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param {
+    pub pat: Pat,
+    pub ty: Option<Ty>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LitKind {
+    Int(u128, Option<IntTy>),
+    Float(f64, Option<FloatTy>),
+    Bool(bool),
+    Char(char),
+    Str(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Ident {
+    pub fn new(name: impl Into<String>, span: Span) -> Ident {
+        Ident { name: name.into(), span }
+    }
+}
+
+/// A path such as `x`, `std::cmp::Ordering` or `Vec::<i32>::with_capacity`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    pub segments: Vec<PathSegment>,
+    pub span: Span,
+}
+
+impl Path {
+    /// The path's single identifier, if it is just a plain name like `x`.
+    pub fn as_ident(&self) -> Option<&Ident> {
+        match self.segments.as_slice() {
+            [segment] if segment.generics.is_empty() => Some(&segment.ident),
+            _ => None,
+        }
+    }
+
+    pub fn last(&self) -> &PathSegment {
+        self.segments.last().expect("paths have at least one segment")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathSegment {
+    pub ident: Ident,
+    /// Generic arguments: `<i32>` in `Vec::<i32>` or `Vec<i32>`.
+    pub generics: Vec<Ty>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodCall {
+    pub receiver: Expr,
+    pub method: Ident,
+    /// Turbofish arguments: `collect::<Vec<i32>>()`.
+    pub generics: Vec<Ty>,
+    pub args: Vec<Expr>,
+}
+
+/// A macro invocation. `vec!` has a single argument, the array or repeat
+/// expression between its brackets.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacCall {
+    pub name: Ident,
+    pub args: Vec<Expr>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ty {
+    pub kind: TyKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TyKind {
+    /// `i32`, `Vec<String>`, `io::Result<()>`, `T`.
+    Path(Path),
+    /// `&T` or `&mut T`.
+    Ref(Mutability, Box<Ty>),
+    /// `(A, B)`; `()` is the empty tuple.
+    Tuple(Vec<Ty>),
+    /// `[T; N]`
+    Array(Box<Ty>, Box<Expr>),
+    /// `[T]`
+    Slice(Box<Ty>),
+    /// `!`, the type of expressions that never finish.
+    Never,
+    /// `_`, a type left for inference.
+    Infer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mutability {
+    Not,
+    Mut,
+}
+
+impl Mutability {
+    pub fn prefix_str(self) -> &'static str {
+        match self {
+            Mutability::Not => "",
+            Mutability::Mut => "mut ",
+        }
+    }
+}
+
+/// What a `match` was written as, so its diagnostics can speak of that.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchSource {
+    Normal,
+    /// `if let`, which had an `else` if the `_` arm came from one.
+    IfLet {
+        has_else: bool,
+    },
+    WhileLet,
+}
+
+/// What a `loop` was written as. Only a `loop` written as one can
+/// `break` with a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoopSource {
+    Loop,
+    WhileLet,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeLimits {
+    /// `a..b`, excluding the end.
+    HalfOpen,
+    /// `a..=b`, including the end.
+    Closed,
+}
+
+impl RangeLimits {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RangeLimits::HalfOpen => "..",
+            RangeLimits::Closed => "..=",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnOp {
+    /// `-x`
+    Neg,
+    /// `!x`: logical NOT on `bool`, bitwise NOT on integers.
+    Not,
+    /// `*x`
+    Deref,
+}
+
+impl UnOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UnOp::Neg => "-",
+            UnOp::Not => "!",
+            UnOp::Deref => "*",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+        }
+    }
+
+    pub fn is_comparison(self) -> bool {
+        matches!(self, BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge)
+    }
+
+    /// `&&` and `||`, which only evaluate their right operand when needed.
+    pub fn is_lazy(self) -> bool {
+        matches!(self, BinOp::And | BinOp::Or)
+    }
+}
+
+/// The integer types, as written in a literal suffix like `100u32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntTy {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+}
+
+impl IntTy {
+    pub const ALL: [IntTy; 12] = [
+        IntTy::I8,
+        IntTy::I16,
+        IntTy::I32,
+        IntTy::I64,
+        IntTy::I128,
+        IntTy::Isize,
+        IntTy::U8,
+        IntTy::U16,
+        IntTy::U32,
+        IntTy::U64,
+        IntTy::U128,
+        IntTy::Usize,
+    ];
+
+    pub fn from_name(name: &str) -> Option<IntTy> {
+        IntTy::ALL.iter().copied().find(|ty| ty.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntTy::I8 => "i8",
+            IntTy::I16 => "i16",
+            IntTy::I32 => "i32",
+            IntTy::I64 => "i64",
+            IntTy::I128 => "i128",
+            IntTy::Isize => "isize",
+            IntTy::U8 => "u8",
+            IntTy::U16 => "u16",
+            IntTy::U32 => "u32",
+            IntTy::U64 => "u64",
+            IntTy::U128 => "u128",
+            IntTy::Usize => "usize",
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(
+            self,
+            IntTy::I8 | IntTy::I16 | IntTy::I32 | IntTy::I64 | IntTy::I128 | IntTy::Isize
+        )
+    }
+
+    /// Width in bits. `isize` and `usize` are 64 bits wide, as on the usual targets.
+    pub fn bits(self) -> u32 {
+        match self {
+            IntTy::I8 | IntTy::U8 => 8,
+            IntTy::I16 | IntTy::U16 => 16,
+            IntTy::I32 | IntTy::U32 => 32,
+            IntTy::I64 | IntTy::U64 | IntTy::Isize | IntTy::Usize => 64,
+            IntTy::I128 | IntTy::U128 => 128,
+        }
+    }
+}
+
+/// The floating-point types, as written in a literal suffix like `1.5f32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FloatTy {
+    F32,
+    F64,
+}
+
+impl FloatTy {
+    pub fn from_name(name: &str) -> Option<FloatTy> {
+        match name {
+            "f32" => Some(FloatTy::F32),
+            "f64" => Some(FloatTy::F64),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FloatTy::F32 => "f32",
+            FloatTy::F64 => "f64",
+        }
+    }
+}
@@ -0,0 +1,802 @@
+//! References.
+//!
+//! `&place` and `&mut place` borrow a place: a variable, or a field, element
+//! or referent reached from one. While a `&mut` borrow is live nothing else
+//! may use the place, and while a `&` borrow is live nothing may change it.
+//! As in rustc since non-lexical lifetimes, a borrow stored in a variable
+//! is live until the variable's last use, or throughout a loop that uses it
+//! on a later iteration; one that is not stored lasts to the end of its
+//! statement, so `for rs in &mut strings { ... }` holds its borrow for the
+//! whole loop.
+//!
+//! Writing to a place, whether by assignment or by borrowing it `&mut`,
+//! also needs the place to be mutable: the variable it starts from must be
+//! `mut`, as `game.black_pawns = 0` needs `let mut game`, unless the place
+//! is reached through a reference, which must then be a `&mut`. Assigning
+//! to a variable itself is left to the initialization check, which knows
+//! whether it is a first assignment.
+//!
+//! A closure that is not `move` borrows each variable from outside it that
+//! its body uses: mutably if the body writes to it or borrows it `&mut`, or
+//! else shared. Like a `&` or `&mut` expression, the closure holds these
+//! borrows for as long as the variable it is stored in is live, so nothing
+//! may change a variable between creating a closure that reads it and the
+//! closure's last call.
+//!
+//! The uses that decide liveness are those the resolver found, in source
+//! order, so a use in a branch not taken still counts.
+
+use std::mem;
+
+use crate::ast::{
+    Block, CaptureBy, Expr, ExprKind, ItemKind, Mutability, Pat, PatKind, Stmt, StmtKind, UnOp,
+};
+use crate::diagnostic::{Applicability, Diagnostic};
+use crate::resolve::{BindingId, Resolver};
+use crate::span::Span;
+use crate::typeck::ty::Type;
+use crate::typeck::TypeckResults;
+
+/// Methods of the standard collections that take `&mut self`.
+const MUTATING_METHODS: &[&str] = &[
+    "push",
+    "push_str",
+    "pop",
+    "insert",
+    "remove",
+    "clear",
+    "truncate",
+    "extend",
+    "append",
+    "drain",
+    "retain",
+    "dedup",
+    "sort",
+    "sort_unstable",
+    "reverse",
+    "swap",
+    "resize",
+    "iter_mut",
+    "get_mut",
+    "first_mut",
+    "last_mut",
+];
+
+pub fn check(program: &Block, types: &TypeckResults, resolver: &Resolver) -> Vec<Diagnostic> {
+    let mut checker = BorrowChecker {
+        types,
+        resolver,
+        loans: Vec::new(),
+        shared_refs: Vec::new(),
+        loops: Vec::new(),
+        closures: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    checker.block(program);
+    checker.diagnostics
+}
+
+/// A local variable and the projections that lead from it to a place.
+#[derive(Clone, Debug)]
+struct Place {
+    root: BindingId,
+    projs: Vec<Proj>,
+    /// How messages write the place: `game.black_pawns`, `*r`, `v[_]`.
+    desc: String,
+}
+
+#[derive(Clone, Debug)]
+enum Proj {
+    Field(String),
+    Index,
+    /// Through a reference of the given mutability, or some other pointer;
+    /// with how messages write the reference.
+    Deref(Option<Mutability>, String),
+}
+
+impl Place {
+    /// Whether the places may share memory: one contains the other.
+    fn overlaps(&self, other: &Place) -> bool {
+        self.root == other.root
+            && self.projs.iter().zip(&other.projs).all(|pair| match pair {
+                (Proj::Field(a), Proj::Field(b)) => a == b,
+                (Proj::Index, Proj::Index) | (Proj::Deref(..), Proj::Deref(..)) => true,
+                _ => false,
+            })
+    }
+}
+
+/// A borrow that may still be live.
+struct Loan {
+    place: Place,
+    mutable: bool,
+    /// The `&place` or `&mut place` expression, or the closure.
+    span: Span,
+    /// The variables holding the reference. A borrow no variable holds lasts
+    /// to the end of its statement.
+    holders: Vec<BindingId>,
+    /// For a closure's borrow, the use in its body that made it.
+    capture: Option<Span>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Access {
+    Read,
+    Borrow(Mutability),
+    Write,
+}
+
+struct BorrowChecker<'a> {
+    types: &'a TypeckResults,
+    resolver: &'a Resolver,
+    loans: Vec<Loan>,
+    /// Variables initialized with a `&` borrow, and the borrow: where a
+    /// write through one is reported, `&mut` is the likely fix.
+    shared_refs: Vec<(BindingId, Span)>,
+    /// The loops around the current expression, outermost first.
+    loops: Vec<Span>,
+    /// The closures around the current expression, outermost first, with
+    /// the variables from outside each that its body uses: whether mutably,
+    /// and where first.
+    closures: Vec<(Span, Vec<Capture>)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+struct Capture {
+    root: BindingId,
+    mutable: bool,
+    span: Span,
+}
+
+impl BorrowChecker<'_> {
+    fn block(&mut self, block: &Block) {
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        if let Some(tail) = &block.tail {
+            self.expr(tail);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        let mark = self.loans.len();
+        match &stmt.kind {
+            StmtKind::Let(local) => {
+                if let Some(init) = &local.init {
+                    self.expr(init);
+                    self.hold(&local.pat, init, mark);
+                }
+            }
+            StmtKind::Item(item) => {
+                if let ItemKind::Fn(decl) = &item.kind {
+                    // The body runs later, with borrows of its own.
+                    let loans = mem::take(&mut self.loans);
+                    let loops = mem::take(&mut self.loops);
+                    self.block(&decl.body);
+                    self.loans = loans;
+                    self.loops = loops;
+                }
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.expr(expr),
+            StmtKind::Empty => {}
+        }
+        // The statement's temporaries end here, and the borrows they held.
+        let mut index = 0;
+        self.loans.retain(|loan| {
+            index += 1;
+            index <= mark || !loan.holders.is_empty()
+        });
+    }
+
+    /// `let pat = init;` stores the borrows `init` makes, and any its
+    /// variable holds, in the variable `pat` binds.
+    fn hold(&mut self, pat: &Pat, init: &Expr, mark: usize) {
+        let holder = match &pat.kind {
+            PatKind::Ident(_, name, None) => self.binding_at(name.span),
+            _ => None,
+        };
+        let holder = match holder {
+            Some(holder) => holder,
+            None => return,
+        };
+        let init = strip_parens(init);
+        match &init.kind {
+            ExprKind::AddrOf(..) | ExprKind::Closure(_) => {
+                for loan in &mut self.loans[mark..] {
+                    if loan.span == init.span {
+                        loan.holders.push(holder);
+                    }
+                }
+                if let ExprKind::AddrOf(Mutability::Not, _) = init.kind {
+                    self.shared_refs.push((holder, init.span));
+                }
+            }
+            ExprKind::Path(_) => {
+                if let Some(source) = self.resolver.resolution(init.id) {
+                    for loan in &mut self.loans {
+                        if loan.holders.contains(&source.id) {
+                            loan.holders.push(holder);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn binding_at(&self, span: Span) -> Option<BindingId> {
+        self.resolver.bindings().iter().find(|binding| binding.span == span).map(|b| b.id)
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(_) | ExprKind::Continue(_) => {}
+            ExprKind::Path(_)
+            | ExprKind::Field(..)
+            | ExprKind::TupleField(..)
+            | ExprKind::Index(..)
+            | ExprKind::Unary(UnOp::Deref, _) => {
+                if let Some(place) = self.place(expr) {
+                    self.access(&place, Access::Read, expr.span);
+                }
+            }
+            ExprKind::Unary(_, operand)
+            | ExprKind::Cast(operand, _)
+            | ExprKind::Try(operand)
+            | ExprKind::Paren(operand) => self.expr(operand),
+            ExprKind::AddrOf(mutability, operand) => {
+                if let Some(place) = self.place(operand) {
+                    if *mutability == Mutability::Mut {
+                        self.check_mutable(&place, Access::Borrow(Mutability::Mut), expr.span);
+                    }
+                    self.access(&place, Access::Borrow(*mutability), expr.span);
+                    let mutable = *mutability == Mutability::Mut;
+                    let span = expr.span;
+                    self.loans.push(Loan {
+                        place,
+                        mutable,
+                        span,
+                        holders: Vec::new(),
+                        capture: None,
+                    });
+                }
+            }
+            ExprKind::Binary(_, lhs, rhs) | ExprKind::Repeat(lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Assign(place, value) | ExprKind::AssignOp(_, place, value) => {
+                self.expr(value);
+                if let Some(place) = self.place(place) {
+                    // Assigning to a variable itself is the initialization
+                    // check's to judge.
+                    if !place.projs.is_empty() {
+                        self.check_mutable(&place, Access::Write, expr.span);
+                    }
+                    self.access(&place, Access::Write, expr.span);
+                }
+            }
+            ExprKind::Range(start, end, _) => {
+                start.iter().chain(end).for_each(|operand| self.expr(operand));
+            }
+            ExprKind::Call(callee, args) => {
+                self.expr(callee);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::MethodCall(call) => {
+                if let Some(mut place) = self.place(&call.receiver) {
+                    // The receiver is borrowed, through a reference if it is one.
+                    if let Some(Type::Ref(mutability, _)) = self.types.node_type(call.receiver.id) {
+                        place.projs.push(Proj::Deref(Some(*mutability), place.desc.clone()));
+                        place.desc = format!("*{}", place.desc);
+                    }
+                    let mutability = if MUTATING_METHODS.contains(&call.method.name.as_str()) {
+                        Mutability::Mut
+                    } else {
+                        Mutability::Not
+                    };
+                    if mutability == Mutability::Mut {
+                        self.check_mutable(&place, Access::Borrow(mutability), call.receiver.span);
+                    }
+                    self.access(&place, Access::Borrow(mutability), call.receiver.span);
+                }
+                call.args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Tuple(elems) | ExprKind::Array(elems) => {
+                elems.iter().for_each(|elem| self.expr(elem));
+            }
+            ExprKind::Struct(lit) => lit.fields.iter().for_each(|field| self.expr(&field.value)),
+            ExprKind::MacCall(mac) => {
+                for arg in &mac.args {
+                    // The formatting macros borrow their arguments.
+                    match self.place(arg) {
+                        Some(place) if mac.name.name != "vec" => {
+                            self.access(&place, Access::Borrow(Mutability::Not), arg.span)
+                        }
+                        Some(place) => self.access(&place, Access::Read, arg.span),
+                        None => {}
+                    }
+                }
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Let(..) => {
+                unreachable!("`if let` and `while let` are desugared by the parser")
+            }
+            ExprKind::If(cond, then, els) => {
+                self.expr(cond);
+                self.block(then);
+                if let Some(els) = els {
+                    self.expr(els);
+                }
+            }
+            ExprKind::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(&arm.body);
+                }
+            }
+            ExprKind::While(cond, body, _) => self.in_loop(expr.span, |this| {
+                this.expr(cond);
+                this.block(body);
+            }),
+            ExprKind::Loop(body, ..) => self.in_loop(expr.span, |this| this.block(body)),
+            ExprKind::For(pat, iter, body, _) => {
+                self.expr(iter);
+                if let ExprKind::AddrOf(Mutability::Not, _) = &strip_parens(iter).kind {
+                    for (_, name) in pat.bindings() {
+                        if let Some(binding) = self.binding_at(name.span) {
+                            self.shared_refs.push((binding, iter.span));
+                        }
+                    }
+                }
+                self.in_loop(expr.span, |this| this.block(body));
+            }
+            ExprKind::Break(_, value) | ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            ExprKind::Closure(closure) => {
+                let mark = self.loans.len();
+                self.closures.push((expr.span, Vec::new()));
+                self.expr(&closure.body);
+                let (_, captures) = self.closures.pop().expect("pushed above");
+                // The body's own borrows are made each time it runs.
+                self.loans.truncate(mark);
+                if closure.capture == CaptureBy::Value {
+                    return;
+                }
+                for capture in captures {
+                    let desc = self.resolver.binding(capture.root).name.clone();
+                    self.loans.push(Loan {
+                        place: Place { root: capture.root, projs: Vec::new(), desc },
+                        mutable: capture.mutable,
+                        span: expr.span,
+                        holders: Vec::new(),
+                        capture: Some(capture.span),
+                    });
+                }
+            }
+        }
+    }
+
+    fn in_loop(&mut self, span: Span, f: impl FnOnce(&mut Self)) {
+        self.loops.push(span);
+        f(self);
+        self.loops.pop();
+    }
+
+    /// Walk `expr` as a place, returning it if it starts from a local
+    /// variable. Otherwise `expr` has been walked as a value.
+    fn place(&mut self, expr: &Expr) -> Option<Place> {
+        let (mut place, proj) = match &expr.kind {
+            ExprKind::Path(_) => {
+                let binding = self.resolver.resolution(expr.id)?;
+                let desc = binding.name.clone();
+                return Some(Place { root: binding.id, projs: Vec::new(), desc });
+            }
+            ExprKind::Paren(inner) => return self.place(inner),
+            ExprKind::Field(base, field) => (self.based(base)?, Proj::Field(field.name.clone())),
+            ExprKind::TupleField(base, index, _) => {
+                (self.based(base)?, Proj::Field(index.to_string()))
+            }
+            ExprKind::Index(base, index) => {
+                let place = self.based(base);
+                self.expr(index);
+                (place?, Proj::Index)
+            }
+            ExprKind::Unary(UnOp::Deref, base) => {
+                let place = self.place(base)?;
+                let proj = Proj::Deref(self.ref_mutability(base), place.desc.clone());
+                let desc = format!("*{}", place.desc);
+                return Some(Place { desc, ..push(place, proj) });
+            }
+            _ => {
+                self.expr(expr);
+                return None;
+            }
+        };
+        place.desc = match &proj {
+            Proj::Field(field) => format!("{}.{}", place.desc, field),
+            Proj::Index => format!("{}[_]", place.desc),
+            Proj::Deref(..) => unreachable!("handled above"),
+        };
+        Some(push(place, proj))
+    }
+
+    /// The place `base.field` or `base[index]` projects from: `base`, or
+    /// what it refers to if it is a reference.
+    fn based(&mut self, base: &Expr) -> Option<Place> {
+        let place = self.place(base)?;
+        match self.types.node_type(base.id) {
+            Some(Type::Ref(mutability, _)) => {
+                let proj = Proj::Deref(Some(*mutability), place.desc.clone());
+                Some(push(place, proj))
+            }
+            _ => Some(place),
+        }
+    }
+
+    fn ref_mutability(&self, expr: &Expr) -> Option<Mutability> {
+        match self.types.node_type(expr.id) {
+            Some(Type::Ref(mutability, _)) => Some(*mutability),
+            _ => None,
+        }
+    }
+
+    /// Report a write to `place`, or a `&mut` borrow of it, that it is not
+    /// mutable enough for.
+    fn check_mutable(&mut self, place: &Place, access: Access, span: Span) {
+        let first_deref = place.projs.iter().find_map(|proj| match proj {
+            Proj::Deref(mutability, reference) => Some((*mutability, reference)),
+            _ => None,
+        });
+        let diagnostic = match first_deref {
+            Some((Some(Mutability::Not), reference)) => {
+                self.behind_shared_ref(place, reference, access, span)
+            }
+            Some(_) => return,
+            None => {
+                let binding = self.resolver.binding(place.root);
+                if binding.mutability == Mutability::Mut {
+                    return;
+                }
+                let (what, label) = match access {
+                    Access::Write => (format!("assign to `{}`", place.desc), "cannot assign"),
+                    _ => {
+                        (format!("borrow `{}` as mutable", place.desc), "cannot borrow as mutable")
+                    }
+                };
+                let whose = if place.projs.is_empty() {
+                    "it".to_string()
+                } else {
+                    format!("`{}`", binding.name)
+                };
+                Diagnostic::error(format!(
+                    "cannot {}, as {} is not declared as mutable",
+                    what, whose
+                ))
+                .code(if access == Access::Write { "E0594" } else { "E0596" })
+                .primary(span, label)
+                .suggest(
+                    "consider changing this to be mutable",
+                    binding.span.shrink_to_lo(),
+                    "mut ",
+                    Applicability::MachineApplicable,
+                )
+            }
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn behind_shared_ref(
+        &self,
+        place: &Place,
+        reference: &str,
+        access: Access,
+        span: Span,
+    ) -> Diagnostic {
+        let (what, code, done) = match access {
+            Access::Write => (format!("assign to `{}`", place.desc), "E0594", "written"),
+            _ => (format!("borrow `{}` as mutable", place.desc), "E0596", "borrowed as mutable"),
+        };
+        let diagnostic =
+            Diagnostic::error(format!("cannot {}, which is behind a `&` reference", what))
+                .code(code)
+                .primary(
+                    span,
+                    format!(
+                        "`{}` is a `&` reference, so the data it refers to cannot be {}",
+                        reference, done
+                    ),
+                );
+        // A reference straight from a `&` borrow can be made a `&mut` one.
+        let borrow = match place.projs.first() {
+            Some(Proj::Deref(..)) => {
+                self.shared_refs.iter().find(|(binding, _)| *binding == place.root)
+            }
+            _ => None,
+        };
+        match borrow {
+            Some((_, borrow)) => diagnostic.suggest(
+                "consider changing this to be a mutable reference",
+                Span::new(borrow.start + 1, borrow.start + 1),
+                "mut ",
+                Applicability::MaybeIncorrect,
+            ),
+            None => diagnostic,
+        }
+    }
+
+    /// Report the first live borrow that `access` to `place` conflicts with.
+    fn access(&mut self, place: &Place, access: Access, span: Span) {
+        self.capture(place, access, span);
+        for loan in &self.loans {
+            let conflicts = match access {
+                Access::Read | Access::Borrow(Mutability::Not) => loan.mutable,
+                Access::Borrow(Mutability::Mut) | Access::Write => true,
+            };
+            if !conflicts || !loan.place.overlaps(place) {
+                continue;
+            }
+            if let Some(later) = self.liveness(loan, span) {
+                let diagnostic = conflict(place, loan, access, span, later);
+                self.diagnostics.push(diagnostic);
+                return;
+            }
+        }
+    }
+
+    /// Note that the closures around `span` that `place`'s variable is from
+    /// outside of use it.
+    fn capture(&mut self, place: &Place, access: Access, span: Span) {
+        let declared = self.resolver.binding(place.root).span;
+        let mutable = matches!(access, Access::Borrow(Mutability::Mut) | Access::Write);
+        for (closure, captures) in &mut self.closures {
+            if closure.contains(declared) {
+                continue;
+            }
+            match captures.iter_mut().find(|capture| capture.root == place.root) {
+                Some(capture) => capture.mutable |= mutable,
+                None => captures.push(Capture { root: place.root, mutable, span }),
+            }
+        }
+    }
+
+    /// Whether `loan` is live at `span`: `None` if not. A borrow held by a
+    /// variable comes with the variable's next use, and whether that is on
+    /// a later iteration of a loop.
+    fn liveness(&self, loan: &Loan, span: Span) -> Option<Option<(Span, bool)>> {
+        if loan.holders.is_empty() {
+            return Some(None);
+        }
+        for &holder in &loan.holders {
+            let binding = self.resolver.binding(holder);
+            let later =
+                binding.uses.iter().filter(|use_| use_.start >= span.end).min_by_key(|u| u.start);
+            if let Some(&later) = later {
+                return Some(Some((later, false)));
+            }
+            // A loop the variable was declared outside of uses it again on
+            // its next iteration.
+            let again = self
+                .loops
+                .iter()
+                .find(|lp| !lp.contains(binding.span))
+                .and_then(|lp| binding.uses.iter().find(|use_| lp.contains(**use_)));
+            if let Some(&again) = again {
+                return Some(Some((again, true)));
+            }
+        }
+        None
+    }
+}
+
+fn push(mut place: Place, proj: Proj) -> Place {
+    place.projs.push(proj);
+    place
+}
+
+fn strip_parens(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Paren(inner) => strip_parens(inner),
+        _ => expr,
+    }
+}
+
+/// The error for `access` to `place` at `span` while `loan` is live, used
+/// again at `later` if a variable holds it.
+fn conflict(
+    place: &Place,
+    loan: &Loan,
+    access: Access,
+    span: Span,
+    later: Option<(Span, bool)>,
+) -> Diagnostic {
+    let (desc, held) = (&place.desc, &loan.place.desc);
+    let (message, code, first, second, borrow) = match (access, loan.mutable) {
+        (Access::Read, _) => (
+            format!("cannot use `{}` because it was mutably borrowed", desc),
+            "E0503",
+            format!("`{}` is borrowed here", held),
+            format!("use of borrowed `{}`", held),
+            "borrow",
+        ),
+        (Access::Write, _) => (
+            format!("cannot assign to `{}` because it is borrowed", desc),
+            "E0506",
+            format!("`{}` is borrowed here", held),
+            format!("`{}` is assigned to here but it was already borrowed", desc),
+            "borrow",
+        ),
+        (Access::Borrow(Mutability::Mut), true) => (
+            format!("cannot borrow `{}` as mutable more than once at a time", desc),
+            "E0499",
+            "first mutable borrow occurs here".to_string(),
+            "second mutable borrow occurs here".to_string(),
+            "first borrow",
+        ),
+        (Access::Borrow(Mutability::Mut), false) => (
+            format!("cannot borrow `{}` as mutable because it is also borrowed as immutable", desc),
+            "E0502",
+            "immutable borrow occurs here".to_string(),
+            "mutable borrow occurs here".to_string(),
+            "immutable borrow",
+        ),
+        (Access::Borrow(Mutability::Not), _) => (
+            format!("cannot borrow `{}` as immutable because it is also borrowed as mutable", desc),
+            "E0502",
+            "mutable borrow occurs here".to_string(),
+            "immutable borrow occurs here".to_string(),
+            "mutable borrow",
+        ),
+    };
+    let mut diagnostic = Diagnostic::error(message).code(code).secondary(loan.span, first);
+    if let Some(capture) = loan.capture {
+        let label = format!("{} occurs due to use of `{}` in closure", borrow, held);
+        diagnostic = diagnostic.secondary(capture, label);
+    }
+    let diagnostic = diagnostic.primary(span, second);
+    match later {
+        Some((later, false)) => diagnostic.secondary(later, format!("{} later used here", borrow)),
+        Some((later, true)) => {
+            diagnostic.secondary(later, format!("{} used here, in later iteration of loop", borrow))
+        }
+        None => diagnostic,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Diagnostic;
+    use crate::resolve::Resolver;
+    use crate::test_util::well_typed;
+
+    fn check(src: &str) -> Vec<Diagnostic> {
+        let (program, types) = well_typed(src);
+        super::check(&program, &types, &Resolver::new(&program, &types))
+    }
+
+    /// The code, message and label messages of the only error.
+    fn error(src: &str) -> (&'static str, String, Vec<String>) {
+        let mut diagnostics = check(src);
+        assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+        let diagnostic = diagnostics.remove(0);
+        let labels = diagnostic.labels.into_iter().map(|l| l.message).collect();
+        (diagnostic.code.unwrap(), diagnostic.message, labels)
+    }
+
+    #[test]
+    fn borrows_end_at_their_last_use() {
+        let src = "let mut v = vec![1]; { let a = &mut v; a.push(2); } \
+                   let b = &mut v; b.push(3); let c = &v; c.len()";
+        assert!(check(src).is_empty());
+        assert!(check("let mut x = 1; let r = &mut x; *r = 2; let y = &x; *y").is_empty());
+        assert!(check("let x = 1; let a = &x; let b = &x; *a + *b").is_empty());
+    }
+
+    #[test]
+    fn overlapping_borrows() {
+        let (code, message, labels) =
+            error("let mut v = vec![1]; let a = &mut v; let b = &mut v; a.push(1);");
+        assert_eq!(code, "E0499");
+        assert_eq!(message, "cannot borrow `v` as mutable more than once at a time");
+        assert_eq!(
+            labels,
+            [
+                "first mutable borrow occurs here",
+                "second mutable borrow occurs here",
+                "first borrow later used here"
+            ]
+        );
+        let (code, message, _) = error("let mut v = vec![1]; let a = &v; v.push(2); a.len()");
+        assert_eq!(code, "E0502");
+        assert_eq!(
+            message,
+            "cannot borrow `v` as mutable because it is also borrowed as immutable"
+        );
+        let (code, message, _) = error("let mut x = 1; let r = &mut x; let y = &x; *r = 3;");
+        assert_eq!(code, "E0502");
+        assert_eq!(
+            message,
+            "cannot borrow `x` as immutable because it is also borrowed as mutable"
+        );
+        let (code, _, _) = error("let mut v = vec![1, 2]; for x in &mut v { v.push(*x); }");
+        assert_eq!(code, "E0499");
+        let src = "let mut v = vec![1, 2]; let r = &mut v; loop { r.push(1); let a = &mut v; }";
+        let (_, _, labels) = error(src);
+        assert_eq!(labels[2], "first borrow used here, in later iteration of loop");
+    }
+
+    #[test]
+    fn borrowed_places_cannot_be_used_or_changed() {
+        let (code, message, _) = error("let mut x = 1; let r = &x; x = 2; *r");
+        assert_eq!(code, "E0506");
+        assert_eq!(message, "cannot assign to `x` because it is borrowed");
+        let (code, message, _) = error("let mut x = 1; let r = &mut x; let y = x; *r = 3;");
+        assert_eq!(code, "E0503");
+        assert_eq!(message, "cannot use `x` because it was mutably borrowed");
+    }
+
+    #[test]
+    fn writes_need_a_mutable_place() {
+        let diagnostics = check("let v = vec![1]; v.push(2);");
+        assert_eq!(diagnostics[0].code, Some("E0596"));
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot borrow `v` as mutable, as it is not declared as mutable"
+        );
+        assert_eq!(diagnostics[0].suggestions[0].replacement, "mut ");
+        let diagnostics = check("struct G { p: i32 } let g = G { p: 1 }; g.p = 0;");
+        assert_eq!(diagnostics[0].code, Some("E0594"));
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot assign to `g.p`, as `g` is not declared as mutable"
+        );
+        assert!(check("struct G { p: i32 } let mut g = G { p: 1 }; g.p = 0;").is_empty());
+    }
+
+    #[test]
+    fn writes_through_shared_references() {
+        let diagnostics = check("let mut x = 1; let r = &x; *r = 2;");
+        assert_eq!(diagnostics[0].code, Some("E0594"));
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot assign to `*r`, which is behind a `&` reference"
+        );
+        assert_eq!(diagnostics[0].suggestions[0].replacement, "mut ");
+        let diagnostics = check("let mut x = 1; let r = &x; let m = &mut *r;");
+        assert_eq!(diagnostics[0].code, Some("E0596"));
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot borrow `*r` as mutable, which is behind a `&` reference"
+        );
+        assert!(check("let mut x = 1; let r = &mut x; *r = 2; let m = &mut *r;").is_empty());
+    }
+
+    #[test]
+    fn closures_borrow_what_they_capture() {
+        let (code, _, labels) =
+            error("let mut v = vec![1]; let mut c = || v.push(2); v.push(3); c(); v");
+        assert_eq!(code, "E0499");
+        assert_eq!(
+            labels,
+            [
+                "first mutable borrow occurs here",
+                "first borrow occurs due to use of `v` in closure",
+                "second mutable borrow occurs here",
+                "first borrow later used here"
+            ]
+        );
+        let (code, message, _) = error("let mut x = 1; let c = || x; x = 5; c()");
+        assert_eq!(code, "E0506");
+        assert_eq!(message, "cannot assign to `x` because it is borrowed");
+        // The borrows end with the closure's last call, and `move` closures have none.
+        assert!(check("let mut n = 0; let mut inc = || n += 1; inc(); inc(); n").is_empty());
+        assert!(check("let mut x = 1; let c = move || x; x = 5; c()").is_empty());
+        assert!(check("let x = 1; let a = || x; let b = || x + 1; a() + b()").is_empty());
+    }
+}
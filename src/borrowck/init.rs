@@ -0,0 +1,990 @@
+//! Definite initialization and moves.
+//!
+//! `let name;` declares a variable and leaves giving it a value for later,
+//! as in the notes:
+//!
+//! ```text
+//! let name;
+//! if user.has_nickname() {
+//!     name = user.nickname();
+//! } else {
+//!     name = generate_unique_name();
+//!     user.register(&name);
+//! }
+//! ```
+//!
+//! This pass walks the program in evaluation order, tracking for each
+//! variable the assignments that may have reached the current point and
+//! whether some path reaches it with no assignment at all. A use on such a
+//! path is an error, and so is a second assignment to a variable that is not
+//! `mut`. Where branches join, the state remembers which branch left the
+//! variable unassigned, so the error can point at the paths to blame.
+//!
+//! Using a variable whose type is not `Copy` as a value moves it out, and
+//! the variable has no value again until it is next assigned. The notes'
+//! example:
+//!
+//! ```text
+//! for s in strings { // each String is moved into s here...
+//!     println!("{}", s);
+//! } // ... and dropped here
+//! println!("{} error(s)", strings.len()); // error, use of moved value
+//! ```
+//!
+//! Passing a variable to a function, assigning it, returning it or looping
+//! over it all move it. Borrowing it, as in `for rs in &strings`, calling a
+//! method on it or formatting it with `println!` do not.
+//!
+//! Moving a field, as `let a = t.0;` does, moves only that part: `t.1` can
+//! still be used, but `t` as a whole cannot until `t.0` is assigned again.
+//! A `match` arm whose pattern binds part of the scrutinee by value moves
+//! that part out when the arm is taken, and a `move` closure takes every
+//! variable it uses when it is created.
+
+use std::collections::HashMap;
+use std::mem;
+
+use crate::ast::{
+    BindingMode, Block, CaptureBy, Expr, ExprKind, Ident, ItemKind, Mutability, Param, Pat,
+    PatKind, Stmt, StmtKind,
+};
+use crate::diagnostic::{Applicability, Diagnostic};
+use crate::span::Span;
+use crate::typeck::ty::Type;
+use crate::typeck::TypeckResults;
+
+pub fn check(program: &Block, types: &TypeckResults) -> Vec<Diagnostic> {
+    let mut checker = InitChecker {
+        types,
+        vars: Vec::new(),
+        scopes: Vec::new(),
+        state: Some(Vec::new()),
+        loops: Vec::new(),
+        report: true,
+        captures: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    checker.block(program);
+    checker.diagnostics
+}
+
+/// A variable's index in [`InitChecker::vars`] and in each [`State`]. Scopes
+/// nest, so a variable's index is free for reuse once its scope ends.
+type VarId = usize;
+
+struct Var {
+    name: String,
+    mutable: bool,
+    /// The name in the pattern that declared it.
+    span: Span,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct VarState {
+    /// The assignments that reach this point along some path.
+    assigned: Vec<Span>,
+    /// `None` if every path here assigns the variable. Otherwise, labels for
+    /// the branches taken by the paths that do not.
+    unassigned: Option<Vec<(Span, String)>>,
+    /// The moves out of the variable that reach this point along some path
+    /// with no assignment since.
+    moved: Vec<Move>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Move {
+    span: Span,
+    /// The moved value's type.
+    ty: String,
+    /// The fields leading to the part of the variable moved, empty if all
+    /// of it was.
+    path: Vec<String>,
+    kind: MoveKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MoveKind {
+    /// Used as a value: passed, assigned or returned.
+    Value,
+    /// Taken by a `for` loop to iterate over.
+    IntoIter,
+    /// Bound by value in a `match` arm's pattern.
+    Binding,
+    /// Captured by a `move` closure, which uses it at this span.
+    Closure(Span),
+}
+
+/// How an expression uses a variable it names.
+enum Access {
+    Read,
+    Borrow,
+    Move(Move),
+    /// Assigned a field of, as in `t.0 = value`.
+    Assign,
+}
+
+/// A variable or a field of one, as `t`, `(t)`, `t.0` and `s.a.b` name.
+/// A field reached through a reference, as `r.0` is for a reference `r`,
+/// is not part of the variable, which holds only the reference.
+struct LocalPlace<'e> {
+    /// The path expression naming the variable.
+    var: &'e Expr,
+    ident: &'e Ident,
+    fields: Vec<String>,
+}
+
+/// The variables from outside a `move` closure that its body uses.
+struct Captures {
+    /// The first variable declared inside the closure.
+    first: VarId,
+    /// Each captured variable, the first use of it and its type.
+    vars: Vec<(VarId, Span, Option<Type>)>,
+}
+
+/// The state of every variable in scope, or `None` where control cannot
+/// reach, such as after a `return`.
+type State = Option<Vec<VarState>>;
+
+/// What makes control take a branch: a condition's span, and wording such
+/// as "if this `if` condition is `false`".
+type Why<'a> = Option<(Span, &'a str)>;
+
+struct LoopScope {
+    label: Option<String>,
+    /// The states at the `break`s out of the loop, joined.
+    breaks: State,
+    /// The states at the `continue`s, joined.
+    continues: State,
+}
+
+struct InitChecker<'a> {
+    types: &'a TypeckResults,
+    vars: Vec<Var>,
+    scopes: Vec<HashMap<String, VarId>>,
+    state: State,
+    loops: Vec<LoopScope>,
+    /// False while a loop body is walked in search of the state at the loop's
+    /// head, so that only the last walk reports errors.
+    report: bool,
+    /// The `move` closures being walked, innermost last.
+    captures: Vec<Captures>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl InitChecker<'_> {
+    fn with_scope(&mut self, f: impl FnOnce(&mut InitChecker<'_>)) {
+        let len = self.vars.len();
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+        self.vars.truncate(len);
+        if let Some(state) = &mut self.state {
+            state.truncate(len);
+        }
+    }
+
+    /// Declare the bindings in `pat`, with a value or (for `let name;`) without.
+    fn declare(&mut self, pat: &Pat, initialized: bool) {
+        for (mode, name) in pat.bindings() {
+            self.declare_name(name, mode.mutability() == Mutability::Mut, initialized);
+        }
+    }
+
+    fn declare_name(&mut self, name: &Ident, mutable: bool, initialized: bool) {
+        let id = self.vars.len();
+        self.vars.push(Var { name: name.name.clone(), mutable, span: name.span });
+        self.scopes.last_mut().expect("inside a scope").insert(name.name.clone(), id);
+        if let Some(state) = &mut self.state {
+            state.resize(id, VarState::default());
+            state.push(if initialized {
+                VarState { assigned: vec![name.span], ..VarState::default() }
+            } else {
+                VarState { unassigned: Some(Vec::new()), ..VarState::default() }
+            });
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<VarId> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).copied()
+    }
+
+    fn var_state(&mut self, id: VarId) -> Option<&mut VarState> {
+        self.state.as_mut().and_then(|state| state.get_mut(id))
+    }
+
+    fn use_var(&mut self, place: &LocalPlace<'_>, access: Access) {
+        if let Some(id) = self.lookup(&place.ident.name) {
+            self.use_id(id, place, access);
+        }
+    }
+
+    fn use_id(&mut self, id: VarId, place: &LocalPlace<'_>, access: Access) {
+        let ty = self.types.node_type(place.var.id);
+        for captures in &mut self.captures {
+            if id < captures.first && captures.vars.iter().all(|&(var, ..)| var != id) {
+                captures.vars.push((id, place.ident.span, ty.cloned()));
+            }
+        }
+        let state = match self.var_state(id) {
+            Some(state) => state,
+            None => return,
+        };
+        // Once reported, treat the variable as having a value so later uses
+        // do not repeat the error.
+        let unassigned = state.unassigned.take();
+        let (mut moved, kept): (Vec<Move>, Vec<Move>) = mem::take(&mut state.moved)
+            .into_iter()
+            .partition(|mv| mv.path.iter().zip(&place.fields).all(|(moved, used)| moved == used));
+        state.moved = kept;
+        let possibly = !state.assigned.is_empty();
+        match &access {
+            Access::Move(mv) => state.moved.push(mv.clone()),
+            // Assigning a moved field gives it a value again; only a move of
+            // what contains it is in the way.
+            Access::Assign => moved.retain(|mv| mv.path.len() < place.fields.len()),
+            Access::Read | Access::Borrow => {}
+        }
+        if !self.report {
+            return;
+        }
+        let diagnostic = match unassigned {
+            Some(paths) => self.uninitialized(id, place.ident.span, possibly, paths),
+            None if !moved.is_empty() => self.use_after_move(id, place, &access, &moved),
+            None => return,
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Use the value of `expr`, moving it out of the variable or field it
+    /// names unless its type is `Copy`. `into_iter` if a `for` loop is
+    /// taking it to iterate over.
+    fn consume(&mut self, expr: &Expr, into_iter: bool) {
+        let ty = self.types.node_type(expr.id);
+        match (self.local_place(expr), ty) {
+            // A `&mut` passed on is reborrowed rather than moved wherever
+            // its type is known, which is too fine a distinction to draw.
+            (Some(place), Some(ty)) if !ty.is_copy() && !matches!(ty, Type::Ref(..)) => {
+                let kind = if into_iter { MoveKind::IntoIter } else { MoveKind::Value };
+                let path = place.fields.clone();
+                let mv = Move { span: expr.span, ty: ty.to_string(), path, kind };
+                self.use_var(&place, Access::Move(mv));
+            }
+            _ => self.expr(expr),
+        }
+    }
+
+    /// Use `expr` by reference, as `&expr`, a method call or `println!` do.
+    fn borrow(&mut self, expr: &Expr) {
+        match self.local_place(expr) {
+            Some(place) => self.use_var(&place, Access::Borrow),
+            None => self.expr(expr),
+        }
+    }
+
+    fn local_place<'e>(&self, expr: &'e Expr) -> Option<LocalPlace<'e>> {
+        let (base, field) = match &expr.kind {
+            ExprKind::Path(path) => {
+                return Some(LocalPlace { var: expr, ident: path.as_ident()?, fields: Vec::new() })
+            }
+            ExprKind::Paren(inner) => return self.local_place(inner),
+            ExprKind::Field(base, name) => (base, name.name.clone()),
+            ExprKind::TupleField(base, index, _) => (base, index.to_string()),
+            _ => return None,
+        };
+        if let Some(Type::Ref(..)) = self.types.node_type(base.id) {
+            return None;
+        }
+        let mut place = self.local_place(base)?;
+        place.fields.push(field);
+        Some(place)
+    }
+
+    /// The moves out of the scrutinee that `pat`'s bindings make when a
+    /// `match` arm is taken, each with the fields after `path` leading to
+    /// the part moved. Bindings that copy or borrow move nothing, and
+    /// neither does anything behind a `&` pattern.
+    fn binding_moves(&self, pat: &Pat, path: &mut Vec<String>, moves: &mut Vec<Move>) {
+        let mut sub = |pat: &Pat, field: String, moves: &mut Vec<Move>| {
+            path.push(field);
+            self.binding_moves(pat, path, moves);
+            path.pop();
+        };
+        match &pat.kind {
+            PatKind::Ident(mode, name, inner) => match self.types.node_type(pat.id) {
+                Some(ty)
+                    if matches!(mode, BindingMode::ByValue(_))
+                        && !ty.is_copy()
+                        && !matches!(ty, Type::Ref(..)) =>
+                {
+                    let ty = ty.to_string();
+                    let kind = MoveKind::Binding;
+                    moves.push(Move { span: name.span, ty, path: path.clone(), kind });
+                }
+                _ => {
+                    if let Some(inner) = inner {
+                        self.binding_moves(inner, path, moves);
+                    }
+                }
+            },
+            PatKind::Tuple(pats) | PatKind::TupleStruct(_, pats) => {
+                for (i, pat) in pats.iter().enumerate() {
+                    sub(pat, i.to_string(), moves);
+                }
+            }
+            PatKind::Struct(_, fields, _) => {
+                for field in fields {
+                    sub(&field.pat, field.name.name.clone(), moves);
+                }
+            }
+            PatKind::Or(pats) => self.binding_moves(&pats[0], path, moves),
+            PatKind::Wild
+            | PatKind::Lit(_)
+            | PatKind::Range(..)
+            | PatKind::Path(_)
+            | PatKind::Ref(..) => {}
+        }
+    }
+
+    fn uninitialized(
+        &self,
+        id: VarId,
+        span: Span,
+        possibly: bool,
+        paths: Vec<(Span, String)>,
+    ) -> Diagnostic {
+        let var = &self.vars[id];
+        let how = if possibly { "is possibly-uninitialized" } else { "isn't initialized" };
+        let mut diagnostic = Diagnostic::error(format!("used binding `{}` {}", var.name, how))
+            .code("E0381")
+            .secondary(var.span, "binding declared here but left uninitialized");
+        for (span, label) in paths {
+            diagnostic = diagnostic.secondary(span, label);
+        }
+        let it = if possibly { "it is possibly-uninitialized" } else { "it isn't initialized" };
+        diagnostic.primary(span, format!("`{}` used here but {}", var.name, it))
+    }
+
+    /// E0382 for a use of `place` after `moves`, which each moved it, part
+    /// of it or something containing it.
+    fn use_after_move(
+        &self,
+        id: VarId,
+        place: &LocalPlace<'_>,
+        access: &Access,
+        moves: &[Move],
+    ) -> Diagnostic {
+        let var = &self.vars[id];
+        let name = |fields: &[String]| {
+            fields.iter().fold(var.name.clone(), |name, field| format!("{}.{}", name, field))
+        };
+        let span = place.ident.span;
+        let (used, verb) = match access {
+            Access::Borrow => ("borrow", "borrowed"),
+            Access::Assign => ("assign to part", "partially assigned"),
+            Access::Read | Access::Move(_) => ("use", "used"),
+        };
+        // Whether only parts of what is used were moved, rather than all of
+        // it or something containing it.
+        let partial = moves.iter().all(|mv| mv.path.len() > place.fields.len());
+        let mut diagnostic = match moves.iter().find(|mv| mv.path.len() <= place.fields.len()) {
+            Some(mv) => Diagnostic::error(format!("{} of moved value: `{}`", used, name(&mv.path))),
+            None => Diagnostic::error(format!(
+                "{} of partially moved value: `{}`",
+                used,
+                name(&place.fields)
+            )),
+        }
+        .code("E0382");
+        let move_occurs = |mv: &Move| {
+            format!(
+                "{}move occurs because `{}` has type `{}`, which does not implement the `Copy` trait",
+                if partial { "partial " } else { "" },
+                name(&mv.path),
+                mv.ty
+            )
+        };
+        match moves.first() {
+            Some(mv) if mv.path.is_empty() => {
+                diagnostic = diagnostic.secondary(var.span, move_occurs(mv));
+            }
+            Some(mv) => diagnostic = diagnostic.note(move_occurs(mv)),
+            None => {}
+        }
+        for mv in moves {
+            let moved = match mv.kind {
+                MoveKind::IntoIter => {
+                    format!(
+                        "`{}` moved due to this implicit call to `.into_iter()`",
+                        name(&mv.path)
+                    )
+                }
+                MoveKind::Closure(_) => "value moved into closure here".to_string(),
+                MoveKind::Value | MoveKind::Binding if mv.path.len() > place.fields.len() => {
+                    "value partially moved here".to_string()
+                }
+                MoveKind::Value | MoveKind::Binding => "value moved here".to_string(),
+            };
+            // Only a loop can bring control back to an earlier point.
+            let at = match mv.kind {
+                MoveKind::Closure(used) => used,
+                _ => mv.span,
+            };
+            let again = if at.start >= span.start { ", in previous iteration of loop" } else { "" };
+            diagnostic = diagnostic.secondary(mv.span, format!("{}{}", moved, again));
+            if let MoveKind::Closure(used) = mv.kind {
+                diagnostic = diagnostic.secondary(used, "variable moved due to use in closure");
+            }
+        }
+        let after = if partial { "partial move" } else { "move" };
+        diagnostic = diagnostic.primary(span, format!("value {} here after {}", verb, after));
+        match moves.first() {
+            Some(mv) if mv.kind == MoveKind::IntoIter => diagnostic.suggest(
+                format!(
+                    "consider iterating over a slice of the `{}`'s content to avoid moving into the `for` loop",
+                    mv.ty
+                ),
+                mv.span.shrink_to_lo(),
+                "&",
+                Applicability::MaybeIncorrect,
+            ),
+            Some(mv) if mv.kind == MoveKind::Value => diagnostic.suggest(
+                "consider cloning the value if the performance cost is acceptable",
+                mv.span.shrink_to_hi(),
+                ".clone()",
+                Applicability::MaybeIncorrect,
+            ),
+            _ => diagnostic,
+        }
+    }
+
+    /// Assign to the variable `ident` in the expression at `span`.
+    fn assign_var(&mut self, ident: &Ident, span: Span) {
+        let id = match self.lookup(&ident.name) {
+            Some(id) => id,
+            None => return,
+        };
+        let report = self.report;
+        let state = match self.var_state(id) {
+            Some(state) => state,
+            None => return,
+        };
+        let earlier = mem::replace(&mut state.assigned, vec![span]);
+        state.unassigned = None;
+        state.moved.clear();
+        if report && !self.vars[id].mutable && !earlier.is_empty() {
+            let diagnostic = self.assigned_twice(id, span, &earlier);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn assigned_twice(&self, id: VarId, span: Span, earlier: &[Span]) -> Diagnostic {
+        let var = &self.vars[id];
+        let mut diagnostic =
+            Diagnostic::error(format!("cannot assign twice to immutable variable `{}`", var.name))
+                .code("E0384");
+        for &first in earlier.iter().filter(|&&first| first != span) {
+            diagnostic = diagnostic.secondary(first, format!("first assignment to `{}`", var.name));
+        }
+        if earlier.contains(&span) {
+            diagnostic =
+                diagnostic.note("the loop runs this assignment again on its next iteration");
+        }
+        diagnostic.primary(span, "cannot assign twice to immutable variable").suggest(
+            "consider making this binding mutable",
+            var.span.shrink_to_lo(),
+            "mut ",
+            Applicability::MaybeIncorrect,
+        )
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.with_scope(|this| {
+            // Items are in scope throughout their block, and always have a value.
+            for stmt in &block.stmts {
+                if let StmtKind::Item(item) = &stmt.kind {
+                    for name in item.names() {
+                        this.declare_name(name, false, true);
+                    }
+                }
+            }
+            for stmt in &block.stmts {
+                this.stmt(stmt);
+            }
+            if let Some(tail) = &block.tail {
+                this.consume(tail, false);
+            }
+        });
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let(local) => {
+                if let Some(init) = &local.init {
+                    self.consume(init, false);
+                }
+                self.declare(&local.pat, local.init.is_some());
+            }
+            StmtKind::Item(item) => {
+                if let ItemKind::Fn(decl) = &item.kind {
+                    self.body(&decl.params, |this| this.block(&decl.body));
+                }
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.consume(expr, false),
+            StmtKind::Empty => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(_) => {}
+            ExprKind::Path(_) | ExprKind::Field(..) | ExprKind::TupleField(..) => {
+                match self.local_place(expr) {
+                    Some(place) => self.use_var(&place, Access::Read),
+                    None => match &expr.kind {
+                        ExprKind::Field(operand, _) | ExprKind::TupleField(operand, ..) => {
+                            self.expr(operand)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+            ExprKind::AddrOf(_, operand) => self.borrow(operand),
+            ExprKind::Unary(_, operand)
+            | ExprKind::Cast(operand, _)
+            | ExprKind::Try(operand)
+            | ExprKind::Paren(operand) => self.expr(operand),
+            ExprKind::Binary(op, lhs, rhs) if op.is_lazy() => {
+                self.expr(lhs);
+                let skipped = self.state.clone();
+                self.expr(rhs);
+                self.state = self.join(vec![(skipped, None), (self.state.clone(), None)]);
+            }
+            ExprKind::Binary(_, lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Index(base, index) => {
+                self.borrow(base);
+                self.expr(index);
+            }
+            ExprKind::Repeat(elem, count) => {
+                self.consume(elem, false);
+                self.expr(count);
+            }
+            ExprKind::Assign(place, value) => {
+                self.consume(value, false);
+                match self.local_place(place) {
+                    Some(place) if place.fields.is_empty() => {
+                        self.assign_var(place.ident, expr.span)
+                    }
+                    Some(place) => self.use_var(&place, Access::Assign),
+                    None => self.expr(place),
+                }
+            }
+            ExprKind::AssignOp(_, place, value) => {
+                self.expr(value);
+                self.expr(place);
+                if let Some(place) = self.local_place(place).filter(|place| place.fields.is_empty())
+                {
+                    self.assign_var(place.ident, expr.span);
+                }
+            }
+            ExprKind::Range(start, end, _) => {
+                for operand in start.iter().chain(end) {
+                    self.expr(operand);
+                }
+            }
+            ExprKind::Call(callee, args) => {
+                self.expr(callee);
+                args.iter().for_each(|arg| self.consume(arg, false));
+            }
+            ExprKind::MethodCall(call) => {
+                self.borrow(&call.receiver);
+                call.args.iter().for_each(|arg| self.consume(arg, false));
+            }
+            ExprKind::Tuple(elems) | ExprKind::Array(elems) => {
+                elems.iter().for_each(|elem| self.consume(elem, false));
+            }
+            ExprKind::Struct(lit) => {
+                lit.fields.iter().for_each(|field| self.consume(&field.value, false));
+            }
+            ExprKind::MacCall(mac) => {
+                // `vec!` takes its elements; the formatting macros only
+                // borrow their arguments.
+                match mac.name.name.as_str() {
+                    "vec" => mac.args.iter().for_each(|arg| self.consume(arg, false)),
+                    _ => mac.args.iter().for_each(|arg| self.borrow(arg)),
+                }
+                if mac.name.name == "panic" {
+                    self.state = None;
+                }
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Let(..) => {
+                unreachable!("`if let` and `while let` are desugared by the parser")
+            }
+            ExprKind::If(cond, then, els) => {
+                self.expr(cond);
+                let skipped = self.state.clone();
+                self.block(then);
+                let then_state = mem::replace(&mut self.state, skipped);
+                if let Some(els) = els {
+                    self.expr(els);
+                }
+                let else_state = self.state.take();
+                self.state = self.join(vec![
+                    (then_state, Some((cond.span, "if this `if` condition is `true`"))),
+                    (else_state, Some((cond.span, "if this `if` condition is `false`"))),
+                ]);
+            }
+            ExprKind::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                // The variable the scrutinee is part of, looked up before an
+                // arm's bindings can shadow it.
+                let scrutinee = self
+                    .local_place(scrutinee)
+                    .and_then(|place| Some((self.lookup(&place.ident.name)?, place)));
+                let before = self.state.take();
+                let mut branches = Vec::new();
+                for arm in arms {
+                    self.state = before.clone();
+                    self.with_scope(|this| {
+                        this.declare(&arm.pat, true);
+                        if let Some(guard) = &arm.guard {
+                            this.expr(guard);
+                        }
+                        if let Some((id, place)) = &scrutinee {
+                            let mut moves = Vec::new();
+                            this.binding_moves(&arm.pat, &mut place.fields.clone(), &mut moves);
+                            for mv in moves {
+                                let fields = mv.path.clone();
+                                let part = LocalPlace { fields, ..*place };
+                                this.use_id(*id, &part, Access::Move(mv));
+                            }
+                        }
+                        this.consume(&arm.body, false);
+                    });
+                    branches.push((
+                        self.state.take(),
+                        Some((arm.pat.span, "if this pattern is matched")),
+                    ));
+                }
+                self.state = self.join(branches);
+            }
+            ExprKind::While(cond, body, label) => {
+                let (exit, breaks) = self.in_loop(label.as_ref(), |this| {
+                    this.expr(cond);
+                    let exit = this.state.clone();
+                    this.block(body);
+                    exit
+                });
+                let why = "if this condition isn't met and the `while` loop runs 0 times";
+                self.state = self.join(vec![(exit, Some((cond.span, why))), (breaks, None)]);
+            }
+            ExprKind::Loop(body, label, _) => {
+                let (_, breaks) = self.in_loop(label.as_ref(), |this| {
+                    this.block(body);
+                    None
+                });
+                self.state = self.join(vec![(breaks, None)]);
+            }
+            ExprKind::For(pat, iter, body, label) => {
+                self.consume(iter, true);
+                let (exit, breaks) = self.in_loop(label.as_ref(), |this| {
+                    let exit = this.state.clone();
+                    this.with_scope(|this| {
+                        this.declare(pat, true);
+                        this.block(body);
+                    });
+                    exit
+                });
+                let why = "if the `for` loop runs 0 times";
+                self.state = self.join(vec![(exit, Some((iter.span, why))), (breaks, None)]);
+            }
+            ExprKind::Break(label, value) => {
+                if let Some(value) = value {
+                    self.consume(value, false);
+                }
+                self.jump(label.as_ref(), true);
+            }
+            ExprKind::Continue(label) => self.jump(label.as_ref(), false),
+            ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.consume(value, false);
+                }
+                self.state = None;
+            }
+            // The body runs later, if at all, but whatever it uses must be
+            // initialized when the closure captures it, and a `move` closure
+            // moves it in then.
+            ExprKind::Closure(closure) => {
+                let by_value = closure.capture == CaptureBy::Value;
+                if by_value {
+                    self.captures.push(Captures { first: self.vars.len(), vars: Vec::new() });
+                }
+                self.body(&closure.params, |this| this.expr(&closure.body));
+                if by_value {
+                    let captures = self.captures.pop().expect("pushed above");
+                    for (id, used, ty) in captures.vars {
+                        let ty = match ty {
+                            Some(ty) if !ty.is_copy() && !matches!(ty, Type::Ref(..)) => ty,
+                            _ => continue,
+                        };
+                        if let Some(state) = self.var_state(id) {
+                            let kind = MoveKind::Closure(used);
+                            let ty = ty.to_string();
+                            state.moved.push(Move { span: expr.span, ty, path: Vec::new(), kind });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walk the body of a closure or `fn` item, which does not run here:
+    /// the state after it is the state before.
+    fn body(&mut self, params: &[Param], body: impl FnOnce(&mut InitChecker<'_>)) {
+        let state = self.state.clone();
+        let loops = mem::take(&mut self.loops);
+        self.with_scope(|this| {
+            for param in params {
+                this.declare(&param.pat, true);
+            }
+            body(this);
+        });
+        self.loops = loops;
+        self.state = state;
+    }
+
+    /// Walk a loop whose body, run by `iteration` from the state at the
+    /// loop's head, returns the state in which the loop exits without a
+    /// `break`. The head is reached both from before the loop and from the
+    /// end of every iteration, so the body is walked until the state there
+    /// stops changing, then once more to report errors. Returns the
+    /// exit state and the state at the `break`s.
+    fn in_loop(
+        &mut self,
+        label: Option<&Ident>,
+        iteration: impl Fn(&mut InitChecker<'_>) -> State,
+    ) -> (State, State) {
+        let entry = self.state.clone();
+        let report = self.report;
+        self.report = false;
+        let mut head = entry.clone();
+        loop {
+            self.state = head.clone();
+            let (_, scope) = self.iterate(label, &iteration);
+            let end = self.state.take();
+            let next = self.join(vec![(entry.clone(), None), (end, None), (scope.continues, None)]);
+            if next == head {
+                break;
+            }
+            head = next;
+        }
+        self.report = report;
+        self.state = head;
+        let (exit, scope) = self.iterate(label, &iteration);
+        (exit, scope.breaks)
+    }
+
+    fn iterate(
+        &mut self,
+        label: Option<&Ident>,
+        iteration: &impl Fn(&mut InitChecker<'_>) -> State,
+    ) -> (State, LoopScope) {
+        self.loops.push(LoopScope {
+            label: label.map(|label| label.name.clone()),
+            breaks: None,
+            continues: None,
+        });
+        let exit = iteration(self);
+        (exit, self.loops.pop().expect("pushed above"))
+    }
+
+    /// `break` (or `continue`) to the loop named `label`, or the innermost.
+    fn jump(&mut self, label: Option<&Ident>, is_break: bool) {
+        let state = self.state.take();
+        let target = match label {
+            Some(label) => {
+                self.loops.iter_mut().rev().find(|l| l.label.as_deref() == Some(&label.name))
+            }
+            None => self.loops.last_mut(),
+        };
+        if let Some(target) = target {
+            let edge = if is_break { &mut target.breaks } else { &mut target.continues };
+            *edge = join(&self.vars, vec![(edge.take(), None), (state, None)]);
+        }
+    }
+
+    fn join(&self, branches: Vec<(State, Why<'_>)>) -> State {
+        join(&self.vars, branches)
+    }
+}
+
+/// Merge the states at the ends of alternative branches. A branch may come
+/// with the span and wording of the condition under which control takes it,
+/// used to explain a variable that the branch leaves unassigned while
+/// another assigns it.
+fn join(vars: &[Var], branches: Vec<(State, Why<'_>)>) -> State {
+    let reachable: Vec<(Vec<VarState>, Why<'_>)> =
+        branches.into_iter().filter_map(|(state, why)| Some((state?, why))).collect();
+    let len = reachable.iter().map(|(state, _)| state.len()).min()?.min(vars.len());
+    let mut joined = Vec::with_capacity(len);
+    for id in 0..len {
+        let any_assigned = reachable.iter().any(|(state, _)| !state[id].assigned.is_empty());
+        let mut var = VarState::default();
+        for (state, why) in &reachable {
+            let state = &state[id];
+            for span in &state.assigned {
+                if !var.assigned.contains(span) {
+                    var.assigned.push(*span);
+                }
+            }
+            for mv in &state.moved {
+                if !var.moved.contains(mv) {
+                    var.moved.push(mv.clone());
+                }
+            }
+            if let Some(paths) = &state.unassigned {
+                let mut paths = paths.clone();
+                if let (true, true, Some((span, why))) = (paths.is_empty(), any_assigned, why) {
+                    paths.push((*span, format!("{}, `{}` is not initialized", why, vars[id].name)));
+                }
+                let unassigned = var.unassigned.get_or_insert_with(Vec::new);
+                for path in paths {
+                    if !unassigned.contains(&path) {
+                        unassigned.push(path);
+                    }
+                }
+            }
+        }
+        joined.push(var);
+    }
+    Some(joined)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Diagnostic;
+    use crate::test_util::well_typed;
+
+    fn check(src: &str) -> Vec<Diagnostic> {
+        let (program, types) = well_typed(src);
+        super::check(&program, &types)
+    }
+
+    fn messages(src: &str) -> Vec<String> {
+        check(src).into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn initialized_on_every_path() {
+        let src = "let nickname = true; let name; \
+                   if nickname { name = \"nick\"; } else { name = \"generated\"; } name";
+        assert!(check(src).is_empty());
+        assert!(check("let x; loop { x = 1; break; } x").is_empty());
+        assert!(check("let c = 2; let x; match c { 0 => x = 1, _ => x = 2 } x").is_empty());
+    }
+
+    #[test]
+    fn used_before_initialized() {
+        assert_eq!(messages("let x: i32; x + 1"), ["used binding `x` isn't initialized"]);
+        let diagnostics = check("let c = true; let name; if c { name = 1; } name");
+        assert_eq!(diagnostics[0].code, Some("E0381"));
+        assert_eq!(diagnostics[0].message, "used binding `name` is possibly-uninitialized");
+        let labels: Vec<_> = diagnostics[0].labels.iter().map(|l| l.message.as_str()).collect();
+        assert!(labels.contains(&"if this `if` condition is `false`, `name` is not initialized"));
+        assert_eq!(
+            messages("let mut x; for i in 0..3 { x = i; } x"),
+            ["used binding `x` is possibly-uninitialized"]
+        );
+    }
+
+    #[test]
+    fn assigned_twice() {
+        assert_eq!(
+            messages("let x; x = 1; x = 2; x"),
+            ["cannot assign twice to immutable variable `x`"]
+        );
+        let diagnostics = check("let x: i32; let mut i = 0; while i < 2 { x = i; i += 1; }");
+        assert_eq!(diagnostics[0].code, Some("E0384"));
+        assert_eq!(
+            diagnostics[0].notes[0].1,
+            "the loop runs this assignment again on its next iteration"
+        );
+        assert!(check("let mut x; x = 1; x = 2; x").is_empty());
+    }
+
+    #[test]
+    fn use_after_move() {
+        let src = "let strings = vec![String::new()]; for s in strings { } strings.len()";
+        let diagnostics = check(src);
+        assert_eq!(diagnostics[0].code, Some("E0382"));
+        assert_eq!(diagnostics[0].message, "borrow of moved value: `strings`");
+        let labels: Vec<&str> = diagnostics[0].labels.iter().map(|l| l.message.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "move occurs because `strings` has type `Vec<String>`, \
+                 which does not implement the `Copy` trait",
+                "`strings` moved due to this implicit call to `.into_iter()`",
+                "value borrowed here after move",
+            ]
+        );
+        assert_eq!(diagnostics[0].suggestions[0].replacement, "&");
+
+        let src = "fn take(s: String) {} let s = String::new(); take(s); take(s);";
+        assert_eq!(messages(src), ["use of moved value: `s`"]);
+        assert_eq!(check(src)[0].suggestions[0].replacement, ".clone()");
+        assert_eq!(
+            messages("let s = String::new(); let t = s; let u = s;"),
+            ["use of moved value: `s`"]
+        );
+        let src = "let s = String::new(); loop { let t = s; }";
+        let labels = check(src).remove(0).labels;
+        assert_eq!(labels[1].message, "value moved here, in previous iteration of loop");
+    }
+
+    #[test]
+    fn borrows_and_copies_do_not_move() {
+        let src = "let mut strings = vec![String::new()]; \
+                   for s in &strings { } for s in &mut strings { } strings.len()";
+        assert!(check(src).is_empty());
+        let src = "let s = String::new(); let r = &s; let t = s.clone(); let n = 5; let m = n; n";
+        assert!(check(src).is_empty());
+        assert!(check("let mut s = String::new(); let t = s; s = String::new(); s").is_empty());
+    }
+
+    #[test]
+    fn partial_moves() {
+        let src = "let t = (String::new(), 1); let a = t.0; let b = t.1; t";
+        let diagnostics = check(src);
+        assert_eq!(diagnostics[0].message, "use of partially moved value: `t`");
+        assert_eq!(
+            diagnostics[0].notes[0].1,
+            "partial move occurs because `t.0` has type `String`, \
+             which does not implement the `Copy` trait"
+        );
+        let src = "struct S { a: String, b: i32 } let mut s = S { a: String::new(), b: 1 }; \
+                   let x = s.a; s.a = String::new(); s";
+        assert!(check(src).is_empty());
+        let src = "let mut t = (String::new(), 1); let a = t; t.0 = String::new();";
+        assert_eq!(messages(src), ["assign to part of moved value: `t`"]);
+    }
+
+    #[test]
+    fn matches_and_closures_move() {
+        let src = "let s = Some(String::new()); match s { Some(x) => {}, None => {} } s";
+        assert_eq!(messages(src), ["use of partially moved value: `s`"]);
+        let src = "let s = Some(String::new()); match &s { Some(x) => {}, None => {} } s";
+        assert!(check(src).is_empty());
+
+        let src = "let s = String::new(); let f = move || s.len(); s.len()";
+        let diagnostics = check(src);
+        assert_eq!(diagnostics[0].message, "borrow of moved value: `s`");
+        let labels: Vec<&str> = diagnostics[0].labels.iter().map(|l| l.message.as_str()).collect();
+        assert!(labels.contains(&"value moved into closure here"));
+        assert!(labels.contains(&"variable moved due to use in closure"));
+        assert!(check("let s = String::new(); let f = || s.len(); s.len()").is_empty());
+    }
+}
@@ -0,0 +1,26 @@
+//! Checks on how local variables are used once the program is known to be
+//! well typed.
+//!
+//! Like rustc's borrow checker, these passes follow the paths control can
+//! take through a program rather than its types: whether every use of a
+//! variable comes after it has been given a value and before the value is
+//! moved out, and whether a variable that is not `mut` is only ever given
+//! one. Types only decide which uses move: those of values that are not
+//! `Copy`. A second pass checks that no use of a place conflicts with a
+//! live borrow of it, and that what is written to is mutable.
+
+mod borrows;
+mod init;
+
+use crate::ast::Block;
+use crate::diagnostic::Diagnostic;
+use crate::resolve::Resolver;
+use crate::typeck::TypeckResults;
+
+/// Run the flow-sensitive checks over a program, with the types `typeck`
+/// found for it and the names `resolver` resolved.
+pub fn check(program: &Block, types: &TypeckResults, resolver: &Resolver) -> Vec<Diagnostic> {
+    let mut diagnostics = init::check(program, types);
+    diagnostics.extend(borrows::check(program, types, resolver));
+    diagnostics
+}
@@ -0,0 +1,320 @@
+//! Rewrites `if let` and `while let` into the `match` they are shorthand
+//! for, so the later passes only ever see `match`:
+//!
+//! ```text
+//! if let pat = expr { block1 } else { block2 }
+//! match expr { pat => { block1 } _ => { block2 } }
+//!
+//! 'label: while let pat = expr { block }
+//! 'label: loop { match expr { pat => { block } _ => break } }
+//! ```
+//!
+//! An `else if let` is an `if let` in the `else` branch, and becomes a
+//! `match` in the outer one's `_` arm.
+
+use std::mem;
+use std::sync::Arc;
+
+use crate::ast::{
+    Arm, Block, Expr, ExprKind, ItemKind, LoopSource, MatchSource, NodeId, Pat, PatKind, StmtKind,
+};
+use crate::span::Span;
+
+/// Desugar every `if let` and `while let` in `block`. The nodes made get
+/// ids from `next_id` on, and it is left at the next unused one.
+pub fn block(block: &mut Block, next_id: &mut u32) {
+    Desugarer { next_id }.block(block);
+}
+
+/// Desugar every `if let` and `while let` in `expr`, as [`block`] does.
+pub fn expr(expr: &mut Expr, next_id: &mut u32) {
+    Desugarer { next_id }.expr(expr);
+}
+
+struct Desugarer<'a> {
+    next_id: &'a mut u32,
+}
+
+impl Desugarer<'_> {
+    fn next_id(&mut self) -> NodeId {
+        let id = NodeId(*self.next_id);
+        *self.next_id += 1;
+        id
+    }
+
+    fn mk(&mut self, kind: ExprKind, span: Span) -> Expr {
+        Expr { id: self.next_id(), kind, span }
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        for stmt in &mut block.stmts {
+            match &mut stmt.kind {
+                StmtKind::Let(local) => {
+                    if let Some(init) = &mut local.init {
+                        self.expr(init);
+                    }
+                }
+                StmtKind::Item(item) => {
+                    if let ItemKind::Fn(decl) = &mut item.kind {
+                        // Nothing else holds the declaration before
+                        // evaluation starts.
+                        if let Some(decl) = Arc::get_mut(decl) {
+                            self.block(&mut decl.body);
+                        }
+                    }
+                }
+                StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.expr(expr),
+                StmtKind::Empty => {}
+            }
+        }
+        if let Some(tail) = &mut block.tail {
+            self.expr(tail);
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::Lit(_) | ExprKind::Path(_) | ExprKind::Continue(_) => {}
+            ExprKind::Unary(_, operand)
+            | ExprKind::AddrOf(_, operand)
+            | ExprKind::Cast(operand, _)
+            | ExprKind::Field(operand, _)
+            | ExprKind::TupleField(operand, ..)
+            | ExprKind::Try(operand)
+            | ExprKind::Paren(operand) => self.expr(operand),
+            ExprKind::Binary(_, lhs, rhs)
+            | ExprKind::Assign(lhs, rhs)
+            | ExprKind::AssignOp(_, lhs, rhs)
+            | ExprKind::Index(lhs, rhs)
+            | ExprKind::Repeat(lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Range(start, end, _) => {
+                start.iter_mut().chain(end).for_each(|operand| self.expr(operand));
+            }
+            ExprKind::Call(callee, args) => {
+                self.expr(callee);
+                args.iter_mut().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::MethodCall(call) => {
+                self.expr(&mut call.receiver);
+                call.args.iter_mut().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Tuple(elems) | ExprKind::Array(elems) => {
+                elems.iter_mut().for_each(|elem| self.expr(elem));
+            }
+            ExprKind::Struct(lit) => {
+                lit.fields.iter_mut().for_each(|field| self.expr(&mut field.value));
+            }
+            ExprKind::MacCall(mac) => mac.args.iter_mut().for_each(|arg| self.expr(arg)),
+            ExprKind::Block(block) | ExprKind::Loop(block, ..) => self.block(block),
+            ExprKind::If(cond, then, els) => {
+                self.expr(cond);
+                self.block(then);
+                if let Some(els) = els {
+                    self.expr(els);
+                    // An `else` branch is a block or an `if`, and stays one
+                    // when an `else if let` becomes a `match`.
+                    if let ExprKind::Match(..) = els.kind {
+                        let span = els.span;
+                        let id = self.next_id();
+                        let tail =
+                            mem::replace(els, Box::new(self.mk(ExprKind::Tuple(Vec::new()), span)));
+                        let block = Block { id, stmts: Vec::new(), tail: Some(tail), span };
+                        **els = self.mk(ExprKind::Block(block), span);
+                    }
+                }
+            }
+            ExprKind::Let(_, scrutinee) => self.expr(scrutinee),
+            ExprKind::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                for arm in arms {
+                    if let Some(guard) = &mut arm.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(&mut arm.body);
+                }
+            }
+            ExprKind::While(cond, body, _) => {
+                self.expr(cond);
+                self.block(body);
+            }
+            ExprKind::For(_, iter, body, _) => {
+                self.expr(iter);
+                self.block(body);
+            }
+            ExprKind::Break(_, value) | ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            ExprKind::Closure(closure) => {
+                if let Some(closure) = Arc::get_mut(closure) {
+                    self.expr(&mut closure.body);
+                }
+            }
+        }
+        let kind = mem::replace(&mut expr.kind, ExprKind::Tuple(Vec::new()));
+        expr.kind = match kind {
+            ExprKind::If(cond, then, els) => match cond.kind {
+                ExprKind::Let(pat, scrutinee) => {
+                    let then = self.block_expr(then);
+                    let source = MatchSource::IfLet { has_else: els.is_some() };
+                    let els = match els {
+                        Some(els) => *els,
+                        None => self.empty_block(expr.span.shrink_to_hi()),
+                    };
+                    self.if_let(*pat, *scrutinee, then, els, cond.span, source)
+                }
+                _ => ExprKind::If(cond, then, els),
+            },
+            ExprKind::While(cond, body, label) => match cond.kind {
+                ExprKind::Let(pat, scrutinee) => {
+                    let body = self.block_expr(body);
+                    let exit = self.mk(ExprKind::Break(None, None), cond.span);
+                    let kind =
+                        self.if_let(*pat, *scrutinee, body, exit, cond.span, MatchSource::WhileLet);
+                    let tail = self.mk(kind, expr.span);
+                    let id = self.next_id();
+                    let block = Block {
+                        id,
+                        stmts: Vec::new(),
+                        tail: Some(Box::new(tail)),
+                        span: expr.span,
+                    };
+                    ExprKind::Loop(block, label, LoopSource::WhileLet)
+                }
+                _ => ExprKind::While(cond, body, label),
+            },
+            kind => kind,
+        };
+    }
+
+    /// `match scrutinee { pat => then, _ => els }`. The `_` arm is put at
+    /// `span`, the `let` it stands for.
+    fn if_let(
+        &mut self,
+        pat: Pat,
+        scrutinee: Expr,
+        then: Expr,
+        els: Expr,
+        span: Span,
+        source: MatchSource,
+    ) -> ExprKind {
+        let wild = Pat { id: self.next_id(), kind: PatKind::Wild, span };
+        let arms = vec![
+            Arm { span: pat.span.to(then.span), pat, guard: None, body: then },
+            Arm { span: els.span, pat: wild, guard: None, body: els },
+        ];
+        ExprKind::Match(Box::new(scrutinee), arms, source)
+    }
+
+    fn block_expr(&mut self, block: Block) -> Expr {
+        let span = block.span;
+        self.mk(ExprKind::Block(block), span)
+    }
+
+    fn empty_block(&mut self, span: Span) -> Expr {
+        let block = Block { id: self.next_id(), stmts: Vec::new(), tail: None, span };
+        self.block_expr(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_program;
+    use crate::test_util::well_typed;
+    use crate::{print, run};
+
+    /// `src` as the parser desugared it.
+    fn desugared(src: &str) -> String {
+        print::program(&parse_program(src).unwrap(), None)
+    }
+
+    #[test]
+    fn if_let_is_a_match() {
+        let expected =
+            "match o {\n    Some(n) => {\n        n\n    }\n    _ => {\n        0\n    }\n}";
+        assert_eq!(desugared("if let Some(n) = o { n } else { 0 }"), expected);
+        // Without `else`, the `_` arm does nothing.
+        assert_eq!(
+            desugared("if let Some(n) = o { n; }"),
+            "match o {\n    Some(n) => {\n        n;\n    }\n    _ => {}\n}"
+        );
+    }
+
+    #[test]
+    fn else_if_let_nests() {
+        let src = "if let Some(n) = o { n } else if let Some(m) = p { m } else { 0 }";
+        let expected = "\
+match o {
+    Some(n) => {
+        n
+    }
+    _ => {
+        match p {
+            Some(m) => {
+                m
+            }
+            _ => {
+                0
+            }
+        }
+    }
+}";
+        assert_eq!(desugared(src), expected);
+    }
+
+    #[test]
+    fn while_let_is_a_loop_with_a_match() {
+        let src = "'outer: while let Some(x) = v.pop() { if x > 5 { break 'outer; } }";
+        let expected = "\
+'outer: loop {
+    match v.pop() {
+        Some(x) => {
+            if x > 5 {
+                break 'outer;
+            }
+        }
+        _ => break,
+    }
+}";
+        assert_eq!(desugared(src), expected);
+    }
+
+    #[test]
+    fn desugared_forms_run_the_same() {
+        let run = |src| run(src).unwrap().to_string();
+        let src = "let o: Option<i32> = None; let p = Some(2); \
+                   if let Some(n) = o { n } else if let Some(m) = p { m } else { 0 }";
+        assert_eq!(run(src), "2");
+        let src = "let mut v = vec![1, 2, 3]; let mut sum = 0; \
+                   while let Some(x) = v.pop() { sum += x; } sum";
+        assert_eq!(run(src), "6");
+    }
+
+    #[test]
+    fn question_mark_is_shown_as_its_match() {
+        let src = "fn f(o: Option<i32>) -> Option<i32> { let x = o?; Some(x) } \
+                   fn g(r: Result<i32, u8>) -> Result<i32, u32> { Ok(r?) }";
+        let (program, types) = well_typed(src);
+        let expected = "\
+fn f(o: Option<i32>) -> Option<i32> {
+    let x = match o {
+        Some(val) => val,
+        None => return None,
+    };
+    Some(x)
+}
+fn g(r: Result<i32, u8>) -> Result<i32, u32> {
+    Ok(match r {
+        Ok(val) => val,
+        Err(err) => return Err(From::from(err)),
+    })
+}";
+        assert_eq!(print::program(&program, Some(&types)), expected);
+        // Without types, every `?` is taken to be on a `Result`.
+        assert!(desugared("o?").contains("Err(err) => return Err(From::from(err)),"));
+    }
+}
@@ -0,0 +1,289 @@
+//! Compiler-style error and warning messages.
+//!
+//! A [`Diagnostic`] is plain data: a headline, some labelled spans and a few
+//! notes. [`Diagnostic::render`] lays it out like rustc does, with the
+//! offending source line and carets underneath.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::span::{line_col, Span};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Error => f.write_str("error"),
+            Level::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// The warnings a user can silence by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lint {
+    UnreachablePatterns,
+    IrrefutableLetPatterns,
+    WhileTrue,
+    UnreachableCode,
+}
+
+impl Lint {
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnreachablePatterns => "unreachable_patterns",
+            Lint::IrrefutableLetPatterns => "irrefutable_let_patterns",
+            Lint::WhileTrue => "while_true",
+            Lint::UnreachableCode => "unreachable_code",
+        }
+    }
+}
+
+/// A span with an explanation attached. The primary label is drawn with `^`,
+/// secondary ones with `-`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+/// The kind of a `= note:` / `= help:` line under the snippet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoteKind {
+    Note,
+    Help,
+}
+
+/// How confident we are that a suggestion is the right fix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Applicability {
+    /// Applying the replacement is certainly what the user wants.
+    MachineApplicable,
+    /// The replacement is a reasonable guess.
+    MaybeIncorrect,
+}
+
+/// A proposed edit: replace the text at `span` with `replacement`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub code: Option<&'static str>,
+    /// The lint a warning belongs to, which `-A` can silence.
+    pub lint: Option<Lint>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<(NoteKind, String)>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, message: impl Into<String>) -> Diagnostic {
+        Diagnostic {
+            level,
+            code: None,
+            lint: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(Level::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(Level::Warning, message)
+    }
+
+    /// The rustc error code, e.g. `E0308` for mismatched types.
+    pub fn code(mut self, code: &'static str) -> Diagnostic {
+        self.code = Some(code);
+        self
+    }
+
+    /// Mark a warning as coming from `lint`, which is on unless allowed.
+    pub fn lint(mut self, lint: Lint) -> Diagnostic {
+        self.lint = Some(lint);
+        self.note(format!("`#[warn({})]` on by default", lint.name()))
+    }
+
+    pub fn primary(mut self, span: Span, message: impl Into<String>) -> Diagnostic {
+        self.labels.push(Label { span, message: message.into(), primary: true });
+        self
+    }
+
+    pub fn secondary(mut self, span: Span, message: impl Into<String>) -> Diagnostic {
+        self.labels.push(Label { span, message: message.into(), primary: false });
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Diagnostic {
+        self.notes.push((NoteKind::Note, note.into()));
+        self
+    }
+
+    pub fn help(mut self, help: impl Into<String>) -> Diagnostic {
+        self.notes.push((NoteKind::Help, help.into()));
+        self
+    }
+
+    pub fn suggest(
+        mut self,
+        message: impl Into<String>,
+        span: Span,
+        replacement: impl Into<String>,
+        applicability: Applicability,
+    ) -> Diagnostic {
+        self.suggestions.push(Suggestion {
+            message: message.into(),
+            span,
+            replacement: replacement.into(),
+            applicability,
+        });
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+
+    /// The span of the first primary label, if any.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter().find(|l| l.primary).map(|l| l.span)
+    }
+
+    /// Lay the diagnostic out the way rustc prints it, quoting `src`.
+    pub fn render(&self, src: &str, file: &str) -> String {
+        let mut out = format!("{}\n", self);
+        let lines: Vec<&str> = src.split('\n').collect();
+        let last_line = self
+            .labels
+            .iter()
+            .map(|l| l.span.end)
+            .chain(self.suggestions.iter().map(|s| s.span.end))
+            .map(|offset| line_col(src, offset).0)
+            .max()
+            .unwrap_or(1);
+        let width = last_line.to_string().len();
+        let gutter = " ".repeat(width);
+
+        if let Some(span) = self.primary_span().or_else(|| self.labels.first().map(|l| l.span)) {
+            let (line, col) = line_col(src, span.start);
+            out.push_str(&format!("{}--> {}:{}:{}\n", gutter, file, line, col));
+        }
+
+        if !self.labels.is_empty() {
+            out.push_str(&format!("{} |\n", gutter));
+            let mut by_line: BTreeMap<usize, Vec<(usize, usize, &Label)>> = BTreeMap::new();
+            for label in &self.labels {
+                let (line, col) = line_col(src, label.span.start);
+                let text = lines.get(line - 1).copied().unwrap_or("");
+                let (end_line, end_col) = line_col(src, label.span.end);
+                let end_col = if end_line == line { end_col } else { text.chars().count() + 1 };
+                let marks = end_col.saturating_sub(col).max(1);
+                by_line.entry(line).or_default().push((col - 1, marks, label));
+            }
+            for (line, mut labels) in by_line {
+                let text = lines.get(line - 1).copied().unwrap_or("");
+                out.push_str(&format!("{:>w$} | {}\n", line, text, w = width));
+                render_underlines(&mut out, &gutter, &mut labels);
+            }
+        }
+
+        for (kind, note) in &self.notes {
+            let kind = match kind {
+                NoteKind::Note => "note",
+                NoteKind::Help => "help",
+            };
+            out.push_str(&format!("{} = {}: {}\n", gutter, kind, note));
+        }
+        for suggestion in &self.suggestions {
+            let (line, col) = line_col(src, suggestion.span.start);
+            let text = lines.get(line - 1).copied().unwrap_or("");
+            let line_start = src[..suggestion.span.start].rfind('\n').map_or(0, |i| i + 1);
+            let mut fixed = String::from(text);
+            let lo = suggestion.span.start - line_start;
+            let hi = (suggestion.span.end - line_start).min(text.len());
+            fixed.replace_range(lo..hi, &suggestion.replacement);
+            out.push_str(&format!("help: {}\n", suggestion.message));
+            out.push_str(&format!("{} |\n", gutter));
+            out.push_str(&format!("{:>w$} | {}\n", line, fixed, w = width));
+            let added = suggestion.replacement.chars().count().max(1);
+            let mark = if suggestion.span.is_empty() { "+" } else { "~" };
+            out.push_str(&format!("{} | {}{}\n", gutter, " ".repeat(col - 1), mark.repeat(added)));
+        }
+        out
+    }
+}
+
+/// Draw the `^^^`/`---` row under one source line, then hang the messages of
+/// all but the rightmost label below it, rustc style:
+///
+/// ```text
+///   |        ---          ^ expected `&str`, found integer
+///   |        |
+///   |        expected because of this
+/// ```
+fn render_underlines(out: &mut String, gutter: &str, labels: &mut Vec<(usize, usize, &Label)>) {
+    // Wider marks first so a label nested inside another stays visible; on
+    // equal widths, a primary label is drawn over a secondary one.
+    labels.sort_by_key(|&(col, marks, label)| (std::cmp::Reverse(marks), label.primary, col));
+    let width = labels.iter().map(|&(col, marks, _)| col + marks).max().unwrap_or(0);
+    let mut row = vec![' '; width];
+    for &(col, marks, label) in labels.iter() {
+        let mark = if label.primary { '^' } else { '-' };
+        row[col..col + marks].iter_mut().for_each(|c| *c = mark);
+    }
+
+    labels.sort_by_key(|&(col, _, _)| col);
+    let mut hanging: Vec<(usize, &str)> = labels
+        .iter()
+        .filter(|(_, _, label)| !label.message.is_empty())
+        .map(|&(col, _, label)| (col, label.message.as_str()))
+        .collect();
+    let mut first_row: String = row.into_iter().collect();
+    if let Some(&(_, message)) = hanging.last() {
+        first_row = format!("{} {}", first_row, message);
+        hanging.pop();
+    }
+    out.push_str(&format!("{} | {}\n", gutter, first_row.trim_end()));
+
+    while let Some((col, message)) = hanging.last().copied() {
+        let mut connectors = vec![' '; col + 1];
+        for &(c, _) in &hanging {
+            connectors[c] = '|';
+        }
+        let connectors: String = connectors.into_iter().collect();
+        out.push_str(&format!("{} | {}\n", gutter, connectors));
+        hanging.pop();
+        let prefix: String = connectors.chars().take(col).collect();
+        out.push_str(&format!("{} | {}{}\n", gutter, prefix, message));
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{}[{}]: {}", self.level, code, self.message),
+            None => write!(f, "{}: {}", self.level, self.message),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
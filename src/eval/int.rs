@@ -0,0 +1,265 @@
+//! Integers of every width, with their type attached.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::ast::{BinOp, IntTy};
+
+/// An integer value of type `ty`.
+///
+/// The value is kept in 128 bits: sign-extended for signed types, so that
+/// `bits as i128` is the value, and zero-extended for unsigned ones. That
+/// makes wrapping arithmetic a plain 128-bit wrapping operation followed by
+/// [`Int::wrap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Int {
+    bits: u128,
+    pub ty: IntTy,
+}
+
+impl Int {
+    /// `value` as a `ty`, or `None` if it is out of range.
+    pub fn from_i128(value: i128, ty: IntTy) -> Option<Int> {
+        let int = Int::wrap(value as u128, ty);
+        let fits = if ty.is_signed() {
+            int.bits as i128 == value
+        } else {
+            value >= 0 && int.bits == value as u128
+        };
+        if fits {
+            Some(int)
+        } else {
+            None
+        }
+    }
+
+    /// `value` as a `ty`, or `None` if it is out of range.
+    pub fn from_u128(value: u128, ty: IntTy) -> Option<Int> {
+        let int = Int::wrap(value, ty);
+        let fits = int.bits == value && !(ty.is_signed() && (value as i128) < 0);
+        if fits {
+            Some(int)
+        } else {
+            None
+        }
+    }
+
+    /// Truncate `bits` to the width of `ty`, the way `as` and the
+    /// `wrapping_*` methods do.
+    pub fn wrap(bits: u128, ty: IntTy) -> Int {
+        let width = ty.bits();
+        if width == 128 {
+            return Int { bits, ty };
+        }
+        let mask = (1u128 << width) - 1;
+        let mut bits = bits & mask;
+        if ty.is_signed() && bits >> (width - 1) & 1 == 1 {
+            bits |= !mask;
+        }
+        Int { bits, ty }
+    }
+
+    pub fn min(ty: IntTy) -> Int {
+        if ty.is_signed() {
+            Int::wrap(1u128 << (ty.bits() - 1), ty)
+        } else {
+            Int { bits: 0, ty }
+        }
+    }
+
+    pub fn max(ty: IntTy) -> Int {
+        if ty.is_signed() {
+            Int::wrap((1u128 << (ty.bits() - 1)) - 1, ty)
+        } else {
+            Int::wrap(u128::MAX, ty)
+        }
+    }
+
+    /// The raw 128-bit representation described on [`Int`].
+    pub fn bits(self) -> u128 {
+        self.bits
+    }
+
+    /// The value as an `i128`, if it fits.
+    pub fn to_i128(self) -> Option<i128> {
+        if self.ty.is_signed() || (self.bits as i128) >= 0 {
+            Some(self.bits as i128)
+        } else {
+            None
+        }
+    }
+
+    /// The value as a `u128`, if it is not negative.
+    pub fn to_u128(self) -> Option<u128> {
+        if self.is_negative() {
+            None
+        } else {
+            Some(self.bits)
+        }
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.ty.is_signed() && (self.bits as i128) < 0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        if self.ty.is_signed() {
+            self.bits as i128 as f64
+        } else {
+            self.bits as f64
+        }
+    }
+
+    /// Convert a float the way `as` does: rounding toward zero, saturating
+    /// at the type's bounds, and mapping NaN to zero.
+    pub fn from_f64(value: f64, ty: IntTy) -> Int {
+        if value.is_nan() {
+            return Int { bits: 0, ty };
+        }
+        let (min, max) = (Int::min(ty), Int::max(ty));
+        if value <= min.to_f64() {
+            min
+        } else if value >= max.to_f64() {
+            max
+        } else if ty.is_signed() {
+            Int::wrap(value as i128 as u128, ty)
+        } else {
+            Int::wrap(value as u128, ty)
+        }
+    }
+
+    /// `self op rhs` for the arithmetic operators, or `None` on overflow or
+    /// division by zero.
+    pub fn checked(self, op: BinOp, rhs: Int) -> Option<Int> {
+        let ty = self.ty;
+        if ty.is_signed() {
+            let (a, b) = (self.bits as i128, rhs.bits as i128);
+            // `MIN % -1` is zero mathematically, but overflows in the division
+            // the hardware does to compute it.
+            if op == BinOp::Rem && self == Int::min(ty) && b == -1 {
+                return None;
+            }
+            let value = match op {
+                BinOp::Add => a.checked_add(b),
+                BinOp::Sub => a.checked_sub(b),
+                BinOp::Mul => a.checked_mul(b),
+                BinOp::Div => a.checked_div(b),
+                BinOp::Rem => a.checked_rem(b),
+                _ => None,
+            }?;
+            Int::from_i128(value, ty)
+        } else {
+            let (a, b) = (self.bits, rhs.bits);
+            let value = match op {
+                BinOp::Add => a.checked_add(b),
+                BinOp::Sub => a.checked_sub(b),
+                BinOp::Mul => a.checked_mul(b),
+                BinOp::Div => a.checked_div(b),
+                BinOp::Rem => a.checked_rem(b),
+                _ => None,
+            }?;
+            Int::from_u128(value, ty)
+        }
+    }
+
+    /// `self op rhs` for the arithmetic operators, wrapped to the type, and
+    /// whether it overflowed: the `overflowing_*` methods. `rhs` must not be
+    /// zero for `/` and `%`.
+    pub fn overflowing(self, op: BinOp, rhs: Int) -> (Int, bool) {
+        let (a, b) = (self.bits, rhs.bits);
+        // Two's complement makes `+`, `-` and `*` the same for both signs.
+        let bits = match op {
+            BinOp::Add => a.wrapping_add(b),
+            BinOp::Sub => a.wrapping_sub(b),
+            BinOp::Mul => a.wrapping_mul(b),
+            BinOp::Div if self.ty.is_signed() => (a as i128).wrapping_div(b as i128) as u128,
+            BinOp::Rem if self.ty.is_signed() => (a as i128).wrapping_rem(b as i128) as u128,
+            BinOp::Div => a / b,
+            BinOp::Rem => a % b,
+            _ => a,
+        };
+        (Int::wrap(bits, self.ty), self.checked(op, rhs).is_none())
+    }
+
+    /// `-self`, wrapped to the type, and whether it overflowed. Only zero
+    /// negates without overflow in an unsigned type.
+    pub fn overflowing_neg(self) -> (Int, bool) {
+        let overflowed =
+            if self.ty.is_signed() { self == Int::min(self.ty) } else { !self.is_zero() };
+        (Int::wrap(self.bits.wrapping_neg(), self.ty), overflowed)
+    }
+
+    /// `self` shifted by `amount` modulo the width of the type, and whether
+    /// `amount` was too large.
+    pub fn overflowing_shift(self, op: BinOp, amount: u128) -> (Int, bool) {
+        let width = u128::from(self.ty.bits());
+        let shifted = self.checked_shift(op, amount % width).expect("the amount is in range");
+        (shifted, amount >= width)
+    }
+
+    /// `self` to the power `exp`, wrapped to the type, and whether it
+    /// overflowed.
+    pub fn overflowing_pow(self, exp: u32) -> (Int, bool) {
+        // Square and multiply, as the standard library does.
+        let (mut base, mut exp) = (self, exp);
+        let mut result = Int::wrap(1, self.ty);
+        let mut overflowed = false;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                let (product, o) = result.overflowing(BinOp::Mul, base);
+                result = product;
+                overflowed |= o;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                let (square, o) = base.overflowing(BinOp::Mul, base);
+                base = square;
+                overflowed |= o;
+            }
+        }
+        (result, overflowed)
+    }
+
+    /// `self << amount` or `self >> amount`, or `None` if `amount` is not
+    /// less than the width of the type.
+    pub fn checked_shift(self, op: BinOp, amount: u128) -> Option<Int> {
+        if amount >= u128::from(self.ty.bits()) {
+            return None;
+        }
+        let bits = match op {
+            BinOp::Shl => self.bits << amount,
+            // Signed values shift in copies of the sign bit; unsigned ones shift in zeros.
+            BinOp::Shr if self.ty.is_signed() => ((self.bits as i128) >> amount) as u128,
+            BinOp::Shr => self.bits >> amount,
+            _ => return None,
+        };
+        Some(Int::wrap(bits, self.ty))
+    }
+}
+
+impl PartialOrd for Int {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        if self.ty != other.ty {
+            return None;
+        }
+        Some(if self.ty.is_signed() {
+            (self.bits as i128).cmp(&(other.bits as i128))
+        } else {
+            self.bits.cmp(&other.bits)
+        })
+    }
+}
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ty.is_signed() {
+            write!(f, "{}", self.bits as i128)
+        } else {
+            write!(f, "{}", self.bits)
+        }
+    }
+}
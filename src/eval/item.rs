@@ -0,0 +1,177 @@
+//! Items declared in blocks, and the values built from structs and enums.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{Interpreter, Options, Place, Scope, Slot, Value};
+use crate::ast::{
+    EnumDecl, Expr, ExprKind, ItemKind, Path, Stmt, StmtKind, StructDecl, StructFields, UseTree,
+    UseTreeKind,
+};
+use crate::parser;
+use crate::typeck::TypeckResults;
+
+/// The enums every program can use without declaring them. Their variants
+/// are imported, so `Some(x)` needs no `Option::`.
+const PRELUDE: &str = "
+    enum Option { None, Some(_) }
+    enum Result { Ok(_), Err(_) }
+    use Option::*;
+    use Result::*;
+";
+
+/// A struct or enum, or an enum variant imported by name.
+#[derive(Clone)]
+pub(super) enum TypeItem {
+    Struct(Arc<StructDecl>),
+    Enum(Arc<EnumDecl>),
+    /// `Hearts` after `use Suit::*;`: the enum and the variant's name.
+    Variant(Arc<EnumDecl>, String),
+}
+
+/// A struct or enum variant that values are built from.
+pub(super) struct Ctor {
+    /// The struct's name, or the enum's for a variant.
+    ty: String,
+    variant: Option<String>,
+    fields: StructFields,
+}
+
+impl Ctor {
+    fn variant(decl: &EnumDecl, name: &str) -> Option<Ctor> {
+        let variant = decl.variant(name)?;
+        Some(Ctor {
+            ty: decl.name.name.clone(),
+            variant: Some(variant.name.name.clone()),
+            fields: variant.fields.clone(),
+        })
+    }
+
+    pub(super) fn is_unit(&self) -> bool {
+        matches!(self.fields, StructFields::Unit)
+    }
+
+    /// The value with these fields, named `0`, `1`, ... if positional.
+    pub(super) fn build(self, fields: Vec<(String, Value)>) -> Value {
+        match self.variant {
+            Some(variant) => Value::Variant(self.ty, variant, fields),
+            None => Value::Struct(self.ty, fields),
+        }
+    }
+}
+
+impl<'a> Interpreter<'a> {
+    pub(super) fn new(types: &'a TypeckResults, options: Options) -> Interpreter<'a> {
+        let prelude = parser::parse_program(PRELUDE).expect("the prelude parses");
+        let mut interp = Interpreter {
+            types,
+            scopes: vec![Scope::default()],
+            options,
+            tables: HashMap::new(),
+            ret: None,
+            depth: 0,
+        };
+        interp.hoist_items(&prelude.stmts);
+        interp
+    }
+
+    /// Declare the items among `stmts` in the innermost scope: functions as
+    /// values, types by name, then the variants `use` items import.
+    pub(super) fn hoist_items(&mut self, stmts: &[Stmt]) {
+        let items = stmts.iter().filter_map(|stmt| match &stmt.kind {
+            StmtKind::Item(item) => Some(&item.kind),
+            _ => None,
+        });
+        let mut uses = Vec::new();
+        for item in items {
+            let scope = self.scopes.last_mut().expect("inside a block");
+            match item {
+                ItemKind::Fn(decl) => {
+                    let value = Value::Fn(Arc::clone(decl));
+                    let slot = Slot { value: Some(Place::new(value)), item: true };
+                    scope.vars.insert(decl.name.name.clone(), slot);
+                }
+                ItemKind::Struct(decl) => {
+                    scope.types.insert(decl.name.name.clone(), TypeItem::Struct(Arc::clone(decl)));
+                }
+                ItemKind::Enum(decl) => {
+                    scope.types.insert(decl.name.name.clone(), TypeItem::Enum(Arc::clone(decl)));
+                }
+                ItemKind::Use(tree) => uses.push(tree),
+            }
+        }
+        for tree in uses {
+            self.import_variants(tree, &[]);
+        }
+    }
+
+    /// Make the variants of known enums that `tree` imports available by
+    /// their own names. `parent` holds the segments before the tree's
+    /// braces, if it is nested.
+    fn import_variants(&mut self, tree: &UseTree, parent: &[&str]) {
+        let mut path = parent.to_vec();
+        path.extend(tree.prefix.segments.iter().map(|segment| segment.ident.name.as_str()));
+        let imports: Vec<(String, TypeItem)> = match (&tree.kind, path.as_slice()) {
+            (UseTreeKind::Nested(trees), _) => {
+                trees.iter().for_each(|tree| self.import_variants(tree, &path));
+                return;
+            }
+            (UseTreeKind::Glob, [.., enum_name]) => match self.type_item(enum_name) {
+                Some(TypeItem::Enum(decl)) => decl
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let name = variant.name.name.clone();
+                        (name.clone(), TypeItem::Variant(Arc::clone(decl), name))
+                    })
+                    .collect(),
+                _ => return,
+            },
+            (UseTreeKind::Simple(alias), [.., enum_name, variant]) => {
+                match self.type_item(enum_name) {
+                    Some(TypeItem::Enum(decl)) if decl.variant(variant).is_some() => {
+                        let name = alias.as_ref().map_or(*variant, |alias| alias.name.as_str());
+                        let item = TypeItem::Variant(Arc::clone(decl), variant.to_string());
+                        vec![(name.to_string(), item)]
+                    }
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+        self.scopes.last_mut().expect("inside a block").types.extend(imports);
+    }
+
+    fn type_item(&self, name: &str) -> Option<&TypeItem> {
+        self.scopes.iter().rev().find_map(|scope| scope.types.get(name))
+    }
+
+    /// The struct or variant a path names: `Point`, `Shape::Circle`, or
+    /// `Circle` after `use Shape::*;`.
+    pub(super) fn ctor(&self, path: &Path) -> Option<Ctor> {
+        if let [.., parent, last] = path.segments.as_slice() {
+            if let Some(TypeItem::Enum(decl)) = self.type_item(&parent.ident.name) {
+                return Ctor::variant(decl, &last.ident.name);
+            }
+        }
+        match self.type_item(&path.last().ident.name)? {
+            TypeItem::Struct(decl) => Some(Ctor {
+                ty: decl.name.name.clone(),
+                variant: None,
+                fields: decl.fields.clone(),
+            }),
+            TypeItem::Variant(decl, name) if path.segments.len() == 1 => Ctor::variant(decl, name),
+            _ => None,
+        }
+    }
+
+    /// The tuple struct or variant a call's callee names, if it is one.
+    pub(super) fn tuple_ctor(&self, callee: &Expr) -> Option<Ctor> {
+        match &callee.kind {
+            ExprKind::Path(path) => {
+                self.ctor(path).filter(|ctor| matches!(ctor.fields, StructFields::Tuple(_)))
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! `for` loops, and the values they iterate over.
+//!
+//! As in Rust, a `for` loop turns its operand into an iterator with
+//! [`Iterable::into_iter`], then runs its body for each item
+//! [`ValueIterator::next`] gives until there are none left. The notes'
+//! `0..20` is `std::ops::Range { start: 0, end: 20 }`, and either spelling
+//! counts up from the start. Only a range with a start can: `..5` has
+//! nowhere to begin. A range without an end, `start..`, counts until the
+//! next value would overflow, which panics as in a debug build whatever the
+//! [`OverflowMode`](super::OverflowMode): wrapping around would count
+//! forever. Arrays and vectors give their elements in order, and an
+//! `&mut` to one gives an `&mut` to each element.
+//!
+//! User types cannot be iterated over. In Rust a struct becomes an iterator
+//! by implementing `Iterator` and its `next` method, but the language has
+//! no `impl` blocks or traits, so a struct or enum has no `next` to call;
+//! the checker rejects a `for` loop over one with E0277.
+
+use std::mem;
+use std::vec;
+
+use super::int::Int;
+use super::{panic, EResult, Interpreter, Scope, Value};
+use crate::ast::{BinOp, Block, Expr, Ident, Pat, RangeLimits};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+/// A value a `for` loop can go over: Rust's `IntoIterator`.
+pub(super) trait Iterable {
+    /// The iterator over the value, or an error at `span`, where the value
+    /// came from, if it cannot be iterated over.
+    fn into_iter(self, span: Span) -> EResult<Box<dyn ValueIterator>>;
+}
+
+/// Where a `for` loop takes its items from: Rust's `Iterator`, except that
+/// taking an item can panic.
+pub(super) trait ValueIterator {
+    fn next(&mut self, span: Span) -> Option<EResult<Value>>;
+}
+
+/// `start..end`
+struct Range {
+    next: Value,
+    end: Value,
+}
+
+impl ValueIterator for Range {
+    fn next(&mut self, _span: Span) -> Option<EResult<Value>> {
+        if !self.next.compare(&self.end)?.is_lt() {
+            return None;
+        }
+        let successor = successor(&self.next).expect("a value below the end has a successor");
+        Some(Ok(mem::replace(&mut self.next, successor)))
+    }
+}
+
+/// `start..=end`
+struct RangeInclusive {
+    next: Value,
+    end: Value,
+    /// Set once `end` has been given, since it may have no successor.
+    exhausted: bool,
+}
+
+impl ValueIterator for RangeInclusive {
+    fn next(&mut self, _span: Span) -> Option<EResult<Value>> {
+        if self.exhausted || self.next.compare(&self.end)?.is_gt() {
+            return None;
+        }
+        match successor(&self.next) {
+            Some(successor) if successor.compare(&self.end)?.is_le() => {
+                Some(Ok(mem::replace(&mut self.next, successor)))
+            }
+            _ => {
+                self.exhausted = true;
+                Some(Ok(self.next.clone()))
+            }
+        }
+    }
+}
+
+/// `start..`
+struct RangeFrom {
+    next: Value,
+}
+
+impl ValueIterator for RangeFrom {
+    fn next(&mut self, span: Span) -> Option<EResult<Value>> {
+        match successor(&self.next) {
+            Some(successor) => Some(Ok(mem::replace(&mut self.next, successor))),
+            None => Some(Err(panic(span, "attempt to add with overflow"))),
+        }
+    }
+}
+
+/// The elements of an array or vector.
+struct Elems(vec::IntoIter<Value>);
+
+impl ValueIterator for Elems {
+    fn next(&mut self, _span: Span) -> Option<EResult<Value>> {
+        self.0.next().map(Ok)
+    }
+}
+
+impl Iterable for Value {
+    fn into_iter(self, span: Span) -> EResult<Box<dyn ValueIterator>> {
+        match self {
+            Value::Array(elems) | Value::Vec(elems) => Ok(Box::new(Elems(elems.into_iter()))),
+            Value::Ref(place) => match place.get() {
+                Value::Array(elems) | Value::Vec(elems) => {
+                    let refs = (0..elems.len()).map(|i| Value::Ref(place.project(i))).collect();
+                    Ok(Box::new(Elems(Vec::into_iter(refs))))
+                }
+                pointee => pointee.into_iter(span),
+            },
+            Value::Range(Some(start), end, limits)
+                if matches!(*start, Value::Int(_) | Value::Char(_)) =>
+            {
+                let next = *start;
+                Ok(match (end, limits) {
+                    (Some(end), RangeLimits::HalfOpen) => Box::new(Range { next, end: *end }),
+                    (Some(end), RangeLimits::Closed) => {
+                        Box::new(RangeInclusive { next, end: *end, exhausted: false })
+                    }
+                    (None, _) => Box::new(RangeFrom { next }),
+                })
+            }
+            other => {
+                let name = other.type_name();
+                let mut diagnostic = Diagnostic::error(format!("`{}` is not an iterator", name))
+                    .code("E0277")
+                    .primary(span, format!("`{}` is not an iterator", name));
+                diagnostic = match other {
+                    Value::Range(None, ..) => diagnostic
+                        .note("a range without a start has nowhere to count from")
+                        .help("give it one, as in `0..end`"),
+                    _ => diagnostic
+                        .note("`for` loops go over ranges with a start, arrays and vectors"),
+                };
+                Err(diagnostic.into())
+            }
+        }
+    }
+}
+
+impl Interpreter<'_> {
+    /// `'label: for pat in iter { body }`, which is worth `()` unless a
+    /// `break` says otherwise.
+    pub(super) fn for_loop(
+        &mut self,
+        pat: &Pat,
+        iter: &Expr,
+        body: &Block,
+        label: &Option<Ident>,
+    ) -> EResult<Value> {
+        let mut items = self.expr(iter)?.into_iter(iter.span)?;
+        while let Some(item) = items.next(iter.span) {
+            // Each iteration binds the pattern afresh.
+            self.scopes.push(Scope::default());
+            let result = self.bind(pat, item?).and_then(|()| self.loop_body(body, label));
+            self.scopes.pop();
+            if let Some(value) = result? {
+                return Ok(value);
+            }
+        }
+        Ok(Value::Unit)
+    }
+}
+
+/// The value after `value` in a range: the next integer or char. `None` at
+/// the end of the type, and for values that cannot be counted.
+fn successor(value: &Value) -> Option<Value> {
+    match value {
+        Value::Int(n) => n.checked(BinOp::Add, Int::wrap(1, n.ty)).map(Value::Int),
+        // Chars skip the surrogates, which are not chars.
+        Value::Char('\u{d7ff}') => Some(Value::Char('\u{e000}')),
+        Value::Char(c) => std::char::from_u32(*c as u32 + 1).map(Value::Char),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run;
+    use crate::test_util::eval;
+
+    #[test]
+    fn ranges_count_up_from_their_start() {
+        assert_eq!(eval("let mut n = 0; for i in 0..20 { n += i; } n"), "190");
+        let src = "let mut n = 0; for i in (std::ops::Range { start: 0, end: 20 }) { n += i; } n";
+        assert_eq!(eval(src), "190");
+        assert_eq!(eval("let mut n = 0u32; for i in 1..=4 { n = n * 10 + i; } n"), "1234");
+        assert_eq!(eval("let mut n = 0; for i in 3..1 { n += 1; } n"), "0");
+        assert_eq!(
+            eval("let mut v = Vec::new(); for c in 'a'..='e' { v.push(c); } v"),
+            "['a', 'b', 'c', 'd', 'e']"
+        );
+        let src = "let mut n = 0; for c in '\\u{d7fe}'..'\\u{e001}' { n += 1; } n";
+        assert_eq!(eval(src), "3");
+    }
+
+    #[test]
+    fn a_range_without_an_end_panics_at_the_end_of_its_type() {
+        let err = run("let mut s = 0; for i in 250u8.. { s += 1; } s").unwrap_err();
+        assert_eq!(err.message, "evaluation panicked: attempt to add with overflow");
+        assert_eq!(
+            eval("let mut s = 0; for i in 250u8.. { if i == 254 { break; } s += 1; } s"),
+            "4"
+        );
+    }
+
+    #[test]
+    fn arrays_and_vectors_give_their_elements() {
+        assert_eq!(eval("let mut n = 0; for x in [1, 2, 3] { n += x; } n"), "6");
+        assert_eq!(eval("let mut v = vec![1, 2]; for x in &mut v { *x *= 10; } v"), "[10, 20]");
+        assert_eq!(
+            eval("let a = [(1, 2), (3, 4)]; let mut n = 0; for (x, y) in a { n += x * y; } n"),
+            "14"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! The standard macros the notes use: `println!`, `format!`, `panic!`,
+//! `assert!`, `assert_eq!` and `vec!`.
+
+use super::value::Value;
+use super::{panic, EResult, Interpreter};
+use crate::ast::{Expr, ExprKind, LitKind, MacCall};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+impl Interpreter<'_> {
+    pub(super) fn mac_call(&mut self, mac: &MacCall, span: Span) -> EResult<Value> {
+        match mac.name.name.as_str() {
+            "println" | "print" | "eprintln" | "eprint" => {
+                let text =
+                    if mac.args.is_empty() { String::new() } else { self.format(&mac.args, span)? };
+                match mac.name.name.as_str() {
+                    "println" => println!("{}", text),
+                    "print" => print!("{}", text),
+                    "eprintln" => eprintln!("{}", text),
+                    _ => eprint!("{}", text),
+                }
+                Ok(Value::Unit)
+            }
+            "format" => Ok(Value::Str(self.format(&mac.args, span)?)),
+            "panic" => {
+                let message = if mac.args.is_empty() {
+                    "explicit panic".to_string()
+                } else {
+                    self.format(&mac.args, span)?
+                };
+                Err(panic(span, &message))
+            }
+            "assert" => {
+                let (cond, message) = mac.args.split_first().ok_or_else(|| {
+                    Diagnostic::error("the `assert!` macro needs a boolean argument")
+                        .primary(span, "")
+                })?;
+                if self.bool(cond)? {
+                    return Ok(Value::Unit);
+                }
+                if message.is_empty() {
+                    return Err(panic(cond.span, "assertion failed"));
+                }
+                let message = self.format(message, span)?;
+                Err(panic(span, &message))
+            }
+            "assert_eq" | "assert_ne" => {
+                let (left, right) = match mac.args.as_slice() {
+                    [left, right, ..] => (left, right),
+                    _ => {
+                        return Err(Diagnostic::error(format!(
+                            "`{}!` takes two arguments to compare",
+                            mac.name.name
+                        ))
+                        .primary(span, "")
+                        .into())
+                    }
+                };
+                let (left, right) = (self.expr(left)?, self.expr(right)?);
+                let (eq, mode) = (crate::ast::BinOp::Eq, self.options.overflow);
+                let equal = super::ops::binary(eq, left.clone(), right.clone(), mode, span)?;
+                let op = if mac.name.name == "assert_eq" { "==" } else { "!=" };
+                if equal == Value::Bool(op == "==") {
+                    return Ok(Value::Unit);
+                }
+                let mut message = format!("assertion `left {} right` failed", op);
+                if mac.args.len() > 2 {
+                    message = format!("{}: {}", message, self.format(&mac.args[2..], span)?);
+                }
+                Err(super::Flow::Panic(
+                    Diagnostic::error(format!("evaluation panicked: {}", message))
+                        .primary(span, message)
+                        .note(format!(" left: {}", left))
+                        .note(format!("right: {}", right)),
+                ))
+            }
+            "vec" => match mac.args.first() {
+                Some(array) => match self.expr(array)? {
+                    Value::Array(elems) => Ok(Value::Vec(elems)),
+                    other => Ok(other),
+                },
+                None => Ok(Value::Vec(Vec::new())),
+            },
+            name => Err(Diagnostic::error(format!("cannot find macro `{}` in this scope", name))
+                .primary(mac.name.span, "")
+                .into()),
+        }
+    }
+
+    /// Expand a format string and its arguments, as `format!` does. Supports
+    /// `{}` for `Display`, `{:?}` for `Debug`, and `{{`/`}}` escapes.
+    fn format(&mut self, args: &[Expr], span: Span) -> EResult<String> {
+        let (template, args) = match args.split_first() {
+            Some((Expr { kind: ExprKind::Lit(LitKind::Str(s)), .. }, rest)) => (s.clone(), rest),
+            _ => {
+                return Err(Diagnostic::error("format argument must be a string literal")
+                    .primary(args.first().map_or(span, |arg| arg.span), "")
+                    .into())
+            }
+        };
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push((self.expr(arg)?, arg.span));
+        }
+
+        let mut out = String::new();
+        let mut next = values.iter();
+        let mut used = 0;
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    out.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    out.push('}');
+                }
+                '{' => {
+                    let spec: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    let (value, arg_span) = next.next().ok_or_else(|| {
+                        Diagnostic::error(format!(
+                            "{} positional arguments in format string, but there {}",
+                            used + 1,
+                            match values.len() {
+                                1 => "is 1 argument".to_string(),
+                                n => format!("are {} arguments", n),
+                            }
+                        ))
+                        .primary(span, "")
+                    })?;
+                    used += 1;
+                    match spec.as_str() {
+                        "" => match value.display() {
+                            Some(text) => out.push_str(&text),
+                            None => {
+                                return Err(Diagnostic::error(format!(
+                                    "`{}` doesn't implement `std::fmt::Display`",
+                                    value.type_name()
+                                ))
+                                .code("E0277")
+                                .primary(*arg_span, "")
+                                .help("use `{:?}` instead")
+                                .into())
+                            }
+                        },
+                        ":?" => out.push_str(&value.to_string()),
+                        _ => {
+                            return Err(Diagnostic::error(format!(
+                                "unsupported format specification `{{{}}}`",
+                                spec
+                            ))
+                            .primary(span, "")
+                            .into())
+                        }
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        if used < values.len() {
+            let (_, arg_span) = values[used];
+            return Err(Diagnostic::error("argument never used")
+                .primary(arg_span, "argument never used")
+                .into());
+        }
+        Ok(out)
+    }
+}
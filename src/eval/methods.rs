@@ -0,0 +1,284 @@
+//! Method calls: `len`, a vector's `push` and `pop`, a string's `push_str`
+//! and `to_string`, and the integer methods that say what happens on
+//! overflow instead of leaving it to the [`OverflowMode`].
+//!
+//! For each of `add`, `sub`, `mul`, `div`, `rem`, `neg`, `shl`, `shr` and
+//! `pow`, `a.wrapping_add(b)` wraps around, `a.checked_add(b)` is `None`
+//! rather than overflowing, and `a.overflowing_add(b)` is the wrapped value
+//! with whether it overflowed. `a.saturating_add(b)` stops at the bounds of
+//! the type; it comes for `add`, `sub`, `mul`, `div` and `pow`, and for
+//! `neg` on signed types. Shift amounts and exponents are `u32`. Dividing
+//! by zero panics even in these, except that `checked_div` and
+//! `checked_rem` are `None`. Plain `pow` overflows as the operators do.
+
+use super::int::Int;
+use super::ops;
+use super::value::Value;
+use super::{is_place, mismatched, panic, EResult, Interpreter, OverflowMode, Place};
+use crate::ast::{BinOp, Expr, IntTy, MethodCall};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+/// What an integer method does when the result does not fit.
+#[derive(Clone, Copy, PartialEq)]
+enum OnOverflow {
+    Wrapping,
+    Checked,
+    Saturating,
+    Overflowing,
+    /// Whatever the [`OverflowMode`] says, for plain `pow`.
+    Mode,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Bin(BinOp),
+    Neg,
+    Pow,
+}
+
+impl Op {
+    fn from_name(name: &str) -> Option<Op> {
+        Some(match name {
+            "add" => Op::Bin(BinOp::Add),
+            "sub" => Op::Bin(BinOp::Sub),
+            "mul" => Op::Bin(BinOp::Mul),
+            "div" => Op::Bin(BinOp::Div),
+            "rem" => Op::Bin(BinOp::Rem),
+            "shl" => Op::Bin(BinOp::Shl),
+            "shr" => Op::Bin(BinOp::Shr),
+            "neg" => Op::Neg,
+            "pow" => Op::Pow,
+            _ => return None,
+        })
+    }
+}
+
+/// The integer method `name`, if a `ty` has one.
+fn int_method(name: &str, ty: IntTy) -> Option<(OnOverflow, Op)> {
+    let (on_overflow, op) = match name.split_once('_') {
+        Some(("wrapping", op)) => (OnOverflow::Wrapping, op),
+        Some(("checked", op)) => (OnOverflow::Checked, op),
+        Some(("saturating", op)) => (OnOverflow::Saturating, op),
+        Some(("overflowing", op)) => (OnOverflow::Overflowing, op),
+        None if name == "pow" => (OnOverflow::Mode, name),
+        _ => return None,
+    };
+    let op = Op::from_name(op)?;
+    let exists = match (on_overflow, op) {
+        (OnOverflow::Saturating, Op::Bin(BinOp::Rem | BinOp::Shl | BinOp::Shr)) => false,
+        (OnOverflow::Saturating, Op::Neg) => ty.is_signed(),
+        _ => true,
+    };
+    if exists {
+        Some((on_overflow, op))
+    } else {
+        None
+    }
+}
+
+impl Interpreter<'_> {
+    pub(super) fn method_call(&mut self, call: &MethodCall, span: Span) -> EResult<Value> {
+        let name = call.method.name.as_str();
+        if name == "push" || name == "pop" || name == "push_str" {
+            let vec = self.receiver_place(&call.receiver)?;
+            return self.vec_method(&vec, call);
+        }
+        let receiver = self.expr(&call.receiver)?.deref();
+        match (name, &receiver, call.args.is_empty()) {
+            ("len", Value::Array(elems) | Value::Vec(elems), true) => {
+                return Ok(usize_value(elems.len()))
+            }
+            ("len", Value::Str(s), true) => return Ok(usize_value(s.len())),
+            ("to_string", Value::Str(_), true) => return Ok(receiver),
+            (_, Value::Int(n), _) => {
+                if let Some((on_overflow, op)) = int_method(name, n.ty) {
+                    return self.int_method_call(*n, on_overflow, op, call, span);
+                }
+            }
+            _ => {}
+        }
+        Err(no_method(call, &receiver))
+    }
+
+    /// Where a method that changes its receiver finds it: the place it lies
+    /// in, or what it points to if it is a reference.
+    fn receiver_place(&mut self, receiver: &Expr) -> EResult<Place> {
+        if is_place(receiver) {
+            return Ok(self.place(receiver)?.deref());
+        }
+        Ok(match self.expr(receiver)? {
+            Value::Ref(place) => place.deref(),
+            value => Place::new(value),
+        })
+    }
+
+    /// `vec.push(value)` and `vec.pop()`, and `string.push_str(s)`.
+    fn vec_method(&mut self, vec: &Place, call: &MethodCall) -> EResult<Value> {
+        let value = match call.args.as_slice() {
+            [arg] if call.method.name != "pop" => Some(self.expr(arg)?),
+            [] if call.method.name == "pop" => None,
+            _ => return Err(no_method(call, &vec.get())),
+        };
+        let push_str = call.method.name == "push_str";
+        vec.with(|receiver| match (receiver, value) {
+            (Value::Vec(elems), Some(value)) if !push_str => {
+                elems.push(value);
+                Ok(Value::Unit)
+            }
+            (Value::Str(s), Some(Value::Str(tail))) if push_str => {
+                s.push_str(&tail);
+                Ok(Value::Unit)
+            }
+            (Value::Vec(elems), None) => Ok(option(elems.pop())),
+            (receiver, _) => Err(no_method(call, receiver)),
+        })
+    }
+
+    fn int_method_call(
+        &mut self,
+        n: Int,
+        on_overflow: OnOverflow,
+        op: Op,
+        call: &MethodCall,
+        span: Span,
+    ) -> EResult<Value> {
+        let arity = if op == Op::Neg { 0 } else { 1 };
+        if call.args.len() != arity {
+            return Err(Diagnostic::error(format!(
+                "this method takes {} argument{} but {} were supplied",
+                arity,
+                if arity == 1 { "" } else { "s" },
+                call.args.len()
+            ))
+            .code("E0061")
+            .primary(call.method.span, "")
+            .into());
+        }
+        let rhs_ty = match op {
+            Op::Bin(BinOp::Shl | BinOp::Shr) | Op::Pow => IntTy::U32,
+            _ => n.ty,
+        };
+        let rhs = match call.args.first() {
+            Some(arg) => match self.expr(arg)? {
+                Value::Int(rhs) if rhs.ty == rhs_ty => rhs,
+                other => return Err(mismatched(arg.span, rhs_ty.name(), &other)),
+            },
+            None => n,
+        };
+
+        let (value, overflowed) = match op {
+            Op::Bin(op @ (BinOp::Div | BinOp::Rem)) if rhs.is_zero() => {
+                if on_overflow == OnOverflow::Checked {
+                    return Ok(option(None));
+                }
+                return Err(ops::division_by_zero(op, span));
+            }
+            Op::Bin(op @ (BinOp::Shl | BinOp::Shr)) => n.overflowing_shift(op, rhs.bits()),
+            Op::Bin(op) => n.overflowing(op, rhs),
+            Op::Neg => n.overflowing_neg(),
+            Op::Pow => n.overflowing_pow(rhs.bits() as u32),
+        };
+        Ok(match on_overflow {
+            OnOverflow::Wrapping => Value::Int(value),
+            OnOverflow::Checked => option(if overflowed { None } else { Some(Value::Int(value)) }),
+            OnOverflow::Overflowing => {
+                Value::Tuple(vec![Value::Int(value), Value::Bool(overflowed)])
+            }
+            OnOverflow::Saturating if overflowed => {
+                // The side of the range the exact result fell off.
+                let negative = match op {
+                    Op::Bin(BinOp::Sub) if !n.ty.is_signed() => true,
+                    Op::Bin(BinOp::Add | BinOp::Sub) => n.is_negative(),
+                    Op::Bin(BinOp::Mul) => n.is_negative() != rhs.is_negative(),
+                    Op::Pow => n.is_negative() && rhs.bits() & 1 == 1,
+                    _ => false,
+                };
+                Value::Int(if negative { Int::min(n.ty) } else { Int::max(n.ty) })
+            }
+            OnOverflow::Mode if overflowed && self.options.overflow == OverflowMode::Debug => {
+                return Err(panic(span, "attempt to multiply with overflow"));
+            }
+            OnOverflow::Saturating | OnOverflow::Mode => Value::Int(value),
+        })
+    }
+}
+
+/// `Some(value)`, or `None`.
+fn option(value: Option<Value>) -> Value {
+    let option = "Option".to_string();
+    match value {
+        Some(value) => Value::Variant(option, "Some".to_string(), vec![("0".to_string(), value)]),
+        None => Value::Variant(option, "None".to_string(), Vec::new()),
+    }
+}
+
+fn no_method(call: &MethodCall, receiver: &Value) -> super::Flow {
+    Diagnostic::error(format!(
+        "no method named `{}` found for `{}` in the current scope",
+        call.method.name,
+        receiver.type_name()
+    ))
+    .code("E0599")
+    .primary(call.method.span, "method not found")
+    .into()
+}
+
+fn usize_value(n: usize) -> Value {
+    Value::Int(Int::wrap(n as u128, IntTy::Usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run;
+    use crate::test_util::eval;
+
+    #[test]
+    fn each_way_of_overflowing() {
+        let src = "let x: u8 = 250; \
+                   (x.wrapping_add(10), x.checked_add(10), x.checked_add(5), \
+                   x.saturating_add(10), x.overflowing_add(10))";
+        assert_eq!(eval(src), "(4, None, Some(255), 255, (4, true))");
+        let src = "let x: i8 = -100; \
+                   (x.saturating_sub(100), x.saturating_mul(2), x.saturating_mul(-2), \
+                   x.wrapping_neg())";
+        assert_eq!(eval(src), "(-128, -128, 127, 100)");
+        let src = "let m: i8 = -128; \
+                   (m.checked_neg(), m.saturating_neg(), m.overflowing_neg(), m.wrapping_div(-1))";
+        assert_eq!(eval(src), "(None, 127, (-128, true), -128)");
+        let src = "let x: i128 = 170141183460469231731687303715884105727; \
+                   (x.wrapping_add(1), x.checked_mul(2))";
+        assert_eq!(eval(src), "(-170141183460469231731687303715884105728, None)");
+        assert_eq!(eval("let x: u128 = 0; x.wrapping_sub(1)"), u128::MAX.to_string());
+        assert_eq!(eval("let x: isize = 5; x.overflowing_sub(7)"), "(-2, false)");
+    }
+
+    #[test]
+    fn shifts_and_powers_take_a_u32() {
+        let src = "let x: u32 = 1; \
+                   (x.wrapping_shl(33), x.checked_shl(32), x.overflowing_shr(35), 2u64.pow(10))";
+        assert_eq!(eval(src), "(2, None, (0, true), 1024)");
+        assert_eq!(eval("(3i8.saturating_pow(5), (-3i8).saturating_pow(5))"), "(127, -128)");
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(eval("let x: i8 = -100; (x.checked_div(0), x.checked_rem(0))"), "(None, None)");
+        let err = run("let x: u8 = 1; x.wrapping_div(0)").unwrap_err();
+        assert_eq!(err.message, "evaluation panicked: attempt to divide by zero");
+    }
+
+    #[test]
+    fn vectors_and_lengths() {
+        let src = "let mut v = Vec::new(); v.push(1); v.push(2); let last = v.pop(); (v, last)";
+        assert_eq!(eval(src), "([1], Some(2))");
+        assert_eq!(eval("(\"héllo\".len(), [1, 2, 3].len(), vec![0u8; 4].len())"), "(6, 3, 4)");
+    }
+
+    #[test]
+    fn strings_grow_in_place() {
+        let src = "let mut s = String::from(\"ab\"); let r = &mut s; r.push_str(\"cd\"); \
+                   s.push_str(\"e\"); (s.len(), s, \"f\".to_string(), String::new())";
+        assert_eq!(eval(src), "(5, \"abcde\", \"f\", \"\")");
+    }
+}
@@ -0,0 +1,1106 @@
+//! Evaluates programs by walking the syntax tree.
+//!
+//! Every construct produces a [`Value`], including blocks, `if`, `match` and
+//! the loops: a block is worth its tail expression, or `()` if it has none.
+//!
+//! Integers of every width, `i8` to `i128`, `u8` to `u128`, `isize` and
+//! `usize`, overflow the way [`Options::overflow`] says: with a panic, as in
+//! a debug build, or by wrapping around, as in a release build. Division by
+//! zero panics in both, and so does `i32::MIN / -1`, whose quotient no
+//! `i32` can hold. The `wrapping_*`, `checked_*`, `saturating_*` and
+//! `overflowing_*` methods choose for themselves.
+//!
+//! `return` abandons whatever the function was in the middle of, however
+//! deeply nested, and so does `?` on an `Err` or `None`. As the notes have
+//! it, `File::create(filename)?` is short for
+//!
+//! ```text
+//! match File::create(filename) {
+//!     Ok(f) => f,
+//!     Err(err) => return Err(From::from(err)),
+//! }
+//! ```
+//!
+//! where `From::from` converts the error to the one in the function's
+//! return type, which can widen a number or leave it as it is.
+//!
+//! A shared reference is transparent: `&x` evaluates to a copy of `x`'s
+//! value, which while the borrow rules hold nothing can tell from the
+//! original. An `&mut x` is a [`Place`](place::Place), through which
+//! `*r = value`, `r[i] = value` and the like write to `x` itself, as do the
+//! variables a `ref mut` pattern or a `for` loop over `&mut array` binds.
+//! A closure shares the variables in scope where it was created with that
+//! scope, so `|| count += 1` counts, except that a `move` closure has copies.
+//!
+//! Literals have the types the checker inferred for them: the `1` in
+//! `let x: u64 = 1;` is a `u64`, with no suffix needed.
+//!
+//! A `match` on integers with dense literal arms is lowered to a table, as
+//! a compiler would, unless [`Options::match_tables`] is off.
+//!
+//! Programs run on a thread of their own, whose stack is large enough for
+//! recursion a few thousand calls deep. Deeper than that, evaluation stops
+//! with a "recursion limit reached" error instead of overflowing the stack.
+
+mod int;
+mod item;
+mod iter;
+mod macros;
+mod methods;
+mod ops;
+mod pat;
+mod place;
+mod table;
+mod value;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+pub use self::int::Int;
+use self::item::{Ctor, TypeItem};
+pub use self::place::Place;
+use self::table::MatchTable;
+pub use self::value::Value;
+use crate::ast::{
+    BinOp, Block, CaptureBy, Expr, ExprKind, FieldExpr, FloatTy, FnDecl, Ident, IntTy, LitKind,
+    Mutability, NodeId, Param, Pat, PatKind, RangeLimits, Stmt, StmtKind, StructExpr, Ty, TyKind,
+    UnOp,
+};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+use crate::typeck::ty::Type;
+use crate::typeck::TypeckResults;
+
+/// Why evaluation of an expression stopped before producing a value.
+enum Flow {
+    /// The value is boxed so that `Flow`, in every evaluation result, stays small.
+    Break(Option<Ident>, Box<Value>, Span),
+    Continue(Option<Ident>, Span),
+    /// A `return`, or a `?` that returned early, with the function's value.
+    Return(Box<Value>),
+    /// A runtime error, such as overflow: the program panicked.
+    Panic(Diagnostic),
+}
+
+impl From<Diagnostic> for Flow {
+    fn from(diagnostic: Diagnostic) -> Flow {
+        Flow::Panic(diagnostic)
+    }
+}
+
+type EResult<T> = Result<T, Flow>;
+
+/// The most elements `[value; count]` may have, so that a huge count is a
+/// panic rather than an allocation that takes the process down.
+const MAX_REPEAT: usize = 1 << 20;
+
+/// How deeply expressions may nest while a program runs, counting those in
+/// every function called on the way, so that runaway recursion is an error
+/// rather than a stack overflow that takes the process down.
+const MAX_DEPTH: usize = 1 << 13;
+
+/// The stack the evaluator runs on. Each level of [`MAX_DEPTH`] takes up
+/// to some tens of kilobytes of it in a debug build, far more than the
+/// stack of a main or test thread holds.
+const STACK_SIZE: usize = 1 << 30;
+
+/// How the evaluator runs a program.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Lower dense integer `match`es to jump tables and constant tables
+    /// rather than trying their arms one by one.
+    pub match_tables: bool,
+    /// What integer arithmetic does when the result does not fit its type.
+    pub overflow: OverflowMode,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { match_tables: true, overflow: OverflowMode::Debug }
+    }
+}
+
+/// The two ways rustc compiles integer overflow, chosen by the build.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Panic, as a debug build does: `255u8 + 1` is "attempt to add with
+    /// overflow".
+    #[default]
+    Debug,
+    /// Wrap around, as a release build does: `255u8 + 1` is `0`, and shift
+    /// amounts are taken modulo the width of the type.
+    Release,
+}
+
+/// Evaluate the body of a program, as parsed by [`crate::parser::parse_program`]
+/// and checked by [`crate::typeck::check`], whose `types` give its literals
+/// theirs.
+pub fn eval_program(program: &Block, types: &TypeckResults) -> Result<Value, Diagnostic> {
+    eval_program_with(program, types, &Options::default())
+}
+
+/// Evaluate the body of a program with the given options, on a thread with
+/// a stack large enough for deep recursion.
+pub fn eval_program_with(
+    program: &Block,
+    types: &TypeckResults,
+    options: &Options,
+) -> Result<Value, Diagnostic> {
+    thread::scope(|scope| {
+        let evaluator = thread::Builder::new()
+            .name("evaluator".to_string())
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || {
+                let mut interp = Interpreter::new(types, options.clone());
+                body_value(interp.block(program))
+            })
+            .expect("the evaluator's thread starts");
+        evaluator.join().unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    })
+}
+
+/// The value of a program or function body that evaluated to `result`:
+/// that of a `return`, if one ran. A `break` or `continue` that got out of
+/// the body without meeting its loop is an error.
+fn body_value(result: EResult<Value>) -> Result<Value, Diagnostic> {
+    let flow = match result {
+        Ok(value) => return Ok(value),
+        Err(flow) => flow,
+    };
+    match flow {
+        Flow::Return(value) => Ok(*value),
+        Flow::Panic(diagnostic) => Err(diagnostic),
+        Flow::Break(_, _, span) => Err(Diagnostic::error("`break` outside of a loop")
+            .code("E0268")
+            .primary(span, "cannot `break` outside of a loop")),
+        Flow::Continue(_, span) => Err(Diagnostic::error("`continue` outside of a loop")
+            .code("E0268")
+            .primary(span, "cannot `continue` outside of a loop")),
+    }
+}
+
+/// A variable. `let name;` declares one without a value.
+#[derive(Clone)]
+struct Slot {
+    value: Option<Place>,
+    /// Whether the slot holds a `fn` item rather than a local variable.
+    item: bool,
+}
+
+/// The names declared in one block, match arm or function body.
+#[derive(Clone, Default)]
+struct Scope {
+    vars: HashMap<String, Slot>,
+    /// The structs and enums declared in the block, and the enum variants
+    /// its `use` items import by their own names.
+    types: HashMap<String, TypeItem>,
+}
+
+/// The variables a closure captured: those in scope where it was created,
+/// shared with the scopes they came from, or copied for a `move` closure.
+pub struct Captures(Vec<Scope>);
+
+struct Interpreter<'a> {
+    /// The types of the program's nodes, which its literals take.
+    types: &'a TypeckResults,
+    /// Innermost scope last. Later `let`s of the same name shadow earlier ones.
+    scopes: Vec<Scope>,
+    options: Options,
+    /// The `match`es lowered so far, by their scrutinee, or `None` for
+    /// those that could not be.
+    tables: HashMap<NodeId, Option<Arc<MatchTable>>>,
+    /// The return type written on the function being run, which `?`
+    /// converts errors to.
+    ret: Option<Ty>,
+    /// How many expressions are being evaluated, each inside the last.
+    depth: usize,
+}
+
+impl Interpreter<'_> {
+    fn block(&mut self, block: &Block) -> EResult<Value> {
+        self.scopes.push(Scope::default());
+        let result = self.block_contents(block);
+        self.scopes.pop();
+        result
+    }
+
+    fn block_contents(&mut self, block: &Block) -> EResult<Value> {
+        // Items declared in the block can be used before their declaration.
+        self.hoist_items(&block.stmts);
+        for stmt in &block.stmts {
+            self.stmt(stmt)?;
+        }
+        match &block.tail {
+            Some(tail) => self.expr(tail),
+            None => Ok(Value::Unit),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> EResult<()> {
+        match &stmt.kind {
+            StmtKind::Let(local) => match &local.init {
+                // `let ref mut r = place;` borrows the place itself.
+                Some(init) if pat::binds_by_ref_mut(&local.pat) && is_place(init) => {
+                    let place = self.place(init)?;
+                    self.bind_in(&local.pat, place.get(), Some(&place))
+                }
+                Some(init) => {
+                    let value = self.expr(init)?;
+                    self.bind(&local.pat, value)
+                }
+                None => self.declare(&local.pat),
+            },
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.expr(expr).map(drop),
+            StmtKind::Item(_) | StmtKind::Empty => Ok(()),
+        }
+    }
+
+    /// Define a variable in the innermost scope.
+    fn define(&mut self, name: &str, value: Value) {
+        let slot = Slot { value: Some(Place::new(value)), item: false };
+        self.scopes.last_mut().expect("inside a block").vars.insert(name.to_string(), slot);
+    }
+
+    /// `let name;`: declare variables to be initialized later.
+    fn declare(&mut self, pat: &Pat) -> EResult<()> {
+        match &pat.kind {
+            PatKind::Ident(_, name, None) => {
+                let slot = Slot { value: None, item: false };
+                let scope = self.scopes.last_mut().expect("inside a block");
+                scope.vars.insert(name.name.clone(), slot);
+                Ok(())
+            }
+            _ => Err(Diagnostic::error("a pattern without an initializer must be a single name")
+                .primary(pat.span, "")
+                .into()),
+        }
+    }
+
+    fn slot(&mut self, name: &Ident) -> EResult<&mut Slot> {
+        self.scopes.iter_mut().rev().find_map(|scope| scope.vars.get_mut(&name.name)).ok_or_else(
+            || {
+                Diagnostic::error(format!("cannot find value `{}` in this scope", name.name))
+                    .code("E0425")
+                    .primary(name.span, "not found in this scope")
+                    .into()
+            },
+        )
+    }
+
+    fn expr(&mut self, expr: &Expr) -> EResult<Value> {
+        if self.depth == MAX_DEPTH {
+            return Err(Diagnostic::error("recursion limit reached while evaluating the program")
+                .primary(expr.span, "nested too deeply to evaluate")
+                .note(format!(
+                    "the evaluator allows expressions to nest at most {} deep, \
+                     counting those of the functions being called",
+                    MAX_DEPTH
+                ))
+                .into());
+        }
+        self.depth += 1;
+        let result = self.expr_kind(expr);
+        self.depth -= 1;
+        result
+    }
+
+    fn expr_kind(&mut self, expr: &Expr) -> EResult<Value> {
+        match &expr.kind {
+            ExprKind::Lit(lit) => lit_value(lit, self.types.node_type(expr.id), false, expr.span),
+            ExprKind::Paren(inner) => self.expr(inner),
+            ExprKind::Tuple(elems) if elems.is_empty() => Ok(Value::Unit),
+            ExprKind::Tuple(elems) => {
+                elems.iter().map(|elem| self.expr(elem)).collect::<EResult<_>>().map(Value::Tuple)
+            }
+            ExprKind::Array(elems) => {
+                elems.iter().map(|elem| self.expr(elem)).collect::<EResult<_>>().map(Value::Array)
+            }
+            ExprKind::Repeat(elem, count) => {
+                let value = self.expr(elem)?;
+                let count = self.usize(count)?;
+                if count > MAX_REPEAT {
+                    return Err(panic(
+                        expr.span,
+                        &format!(
+                            "cannot build an array of {} elements: the evaluator allows at most {}",
+                            count, MAX_REPEAT
+                        ),
+                    ));
+                }
+                Ok(Value::Array(vec![value; count]))
+            }
+            ExprKind::Struct(lit) => {
+                let ctor = self.ctor(&lit.path);
+                if ctor.is_none() {
+                    if let Some(range) = self.range_lit(lit) {
+                        return range;
+                    }
+                }
+                let mut fields = Vec::with_capacity(lit.fields.len());
+                for field in &lit.fields {
+                    fields.push((field.name.name.clone(), self.expr(&field.value)?));
+                }
+                Ok(match ctor {
+                    Some(ctor) => ctor.build(fields),
+                    None => Value::Struct(lit.path.last().ident.name.clone(), fields),
+                })
+            }
+            ExprKind::Path(path) => {
+                if let Some(ctor) = self.ctor(path).filter(Ctor::is_unit) {
+                    return Ok(ctor.build(Vec::new()));
+                }
+                match path.as_ident() {
+                    Some(name) => self.read(name),
+                    None => associated_const(path).ok_or_else(|| {
+                        Diagnostic::error(format!(
+                            "cannot find value `{}` in this scope",
+                            path.last().ident.name
+                        ))
+                        .code("E0425")
+                        .primary(expr.span, "not found in this scope")
+                        .into()
+                    }),
+                }
+            }
+            ExprKind::Unary(UnOp::Neg, operand) => match &operand.kind {
+                // `-128i8` is in range even though `128i8` alone is not.
+                ExprKind::Lit(lit @ LitKind::Int(..)) => {
+                    lit_value(lit, self.types.node_type(operand.id), true, expr.span)
+                }
+                _ => {
+                    let value = self.expr(operand)?;
+                    ops::unary(UnOp::Neg, value, self.options.overflow, expr.span)
+                }
+            },
+            ExprKind::Unary(UnOp::Deref, operand) => match self.expr(operand)? {
+                Value::Ref(place) => Ok(place.get()),
+                value => Ok(value),
+            },
+            ExprKind::AddrOf(Mutability::Mut, operand) => {
+                let place = if is_place(operand) {
+                    self.place(operand)?
+                } else {
+                    // A borrowed temporary lives as long as the borrow.
+                    Place::new(self.expr(operand)?)
+                };
+                Ok(Value::Ref(place))
+            }
+            ExprKind::AddrOf(Mutability::Not, operand) => self.expr(operand),
+            ExprKind::Unary(op, operand) => {
+                let value = self.expr(operand)?;
+                ops::unary(*op, value, self.options.overflow, expr.span)
+            }
+            ExprKind::Binary(op, lhs, rhs) if op.is_lazy() => {
+                let lhs = self.bool(lhs)?;
+                // `&&` skips its right operand when the left is false, `||` when it is true.
+                if lhs == (*op == BinOp::Or) {
+                    return Ok(Value::Bool(lhs));
+                }
+                Ok(Value::Bool(self.bool(rhs)?))
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let a = self.expr(lhs)?;
+                let b = self.expr(rhs)?;
+                ops::binary(*op, a, b, self.options.overflow, expr.span)
+            }
+            ExprKind::Cast(operand, ty) => {
+                let value = self.expr(operand)?;
+                ops::cast(value, ty, expr.span)
+            }
+            ExprKind::Assign(place, rhs) => {
+                let value = self.expr(rhs)?;
+                self.assign(place, value)?;
+                Ok(Value::Unit)
+            }
+            ExprKind::AssignOp(op, place, rhs) => {
+                let place = self.place(place)?;
+                let rhs = self.expr(rhs)?;
+                let value = ops::binary(*op, place.get(), rhs, self.options.overflow, expr.span)?;
+                place.set(value);
+                Ok(Value::Unit)
+            }
+            ExprKind::Field(base, field) => match self.expr(base)?.deref() {
+                Value::Struct(name, fields) => fields
+                    .into_iter()
+                    .find(|(f, _)| *f == field.name)
+                    .map(|(_, value)| value)
+                    .ok_or_else(|| no_field(&field.name, &name, field.span)),
+                Value::Range(start, end, RangeLimits::HalfOpen) => {
+                    match (field.name.as_str(), start, end) {
+                        ("start", Some(bound), _) | ("end", _, Some(bound)) => Ok(*bound),
+                        (_, start, end) => {
+                            let range = Value::Range(start, end, RangeLimits::HalfOpen);
+                            Err(no_field(&field.name, &range.type_name(), field.span))
+                        }
+                    }
+                }
+                other => Err(no_field(&field.name, &other.type_name(), field.span)),
+            },
+            ExprKind::TupleField(base, index, index_span) => match self.expr(base)?.deref() {
+                Value::Tuple(mut elems) if *index < elems.len() => Ok(elems.swap_remove(*index)),
+                Value::Struct(_, mut fields) if *index < fields.len() => {
+                    Ok(fields.swap_remove(*index).1)
+                }
+                other => Err(no_field(&index.to_string(), &other.type_name(), *index_span)),
+            },
+            ExprKind::Range(start, end, limits) => {
+                let mut bound = |bound: &Option<Box<Expr>>| {
+                    bound.as_ref().map(|bound| self.expr(bound)).transpose()
+                };
+                let (start, end) = (bound(start)?, bound(end)?);
+                Ok(Value::Range(start.map(Box::new), end.map(Box::new), *limits))
+            }
+            ExprKind::Index(base, index) => {
+                let base = self.expr(base)?.deref();
+                let index_value = self.usize(index)?;
+                match base {
+                    Value::Array(mut elems) | Value::Vec(mut elems) => {
+                        check_bounds(index_value, elems.len(), expr.span)?;
+                        Ok(elems.swap_remove(index_value))
+                    }
+                    other => Err(cannot_index(&other.type_name(), expr.span)),
+                }
+            }
+            ExprKind::MethodCall(call) => self.method_call(call, expr.span),
+            ExprKind::Call(callee, args) => self.call_expr(callee, args),
+            ExprKind::MacCall(mac) => self.mac_call(mac, expr.span),
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If(cond, then, els) => {
+                if self.bool(cond)? {
+                    self.block(then)
+                } else if let Some(els) = els {
+                    self.expr(els)
+                } else {
+                    Ok(Value::Unit)
+                }
+            }
+            ExprKind::Match(scrutinee, arms, _) => self.match_expr(scrutinee, arms),
+            ExprKind::While(cond, body, label) => {
+                while self.bool(cond)? {
+                    if let Some(value) = self.loop_body(body, label)? {
+                        return Ok(value);
+                    }
+                }
+                Ok(Value::Unit)
+            }
+            ExprKind::For(pat, iter, body, label) => self.for_loop(pat, iter, body, label),
+            ExprKind::Loop(body, label, _) => loop {
+                if let Some(value) = self.loop_body(body, label)? {
+                    return Ok(value);
+                }
+            },
+            ExprKind::Break(label, value) => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => Value::Unit,
+                };
+                Err(Flow::Break(label.clone(), Box::new(value), expr.span))
+            }
+            ExprKind::Continue(label) => Err(Flow::Continue(label.clone(), expr.span)),
+            ExprKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => Value::Unit,
+                };
+                Err(Flow::Return(Box::new(value)))
+            }
+            ExprKind::Try(operand) => self.try_expr(operand, expr.span),
+            ExprKind::Let(..) => {
+                unreachable!("`if let` and `while let` are desugared by the parser")
+            }
+            ExprKind::Closure(closure) => {
+                let mut scopes = self.scopes.clone();
+                if closure.capture == CaptureBy::Value {
+                    let vars = scopes.iter_mut().flat_map(|scope| scope.vars.values_mut());
+                    for slot in vars.filter(|slot| !slot.item) {
+                        if let Some(place) = &mut slot.value {
+                            *place = Place::new(place.get());
+                        }
+                    }
+                }
+                Ok(Value::Closure(Arc::clone(closure), Arc::new(Captures(scopes))))
+            }
+        }
+    }
+
+    /// `f(args)`, or `Point(x, y)` or `Some(value)`, which build values.
+    fn call_expr(&mut self, callee: &Expr, args: &[Expr]) -> EResult<Value> {
+        if let ExprKind::Path(path) = &callee.kind {
+            let names: Vec<&str> = path.segments.iter().map(|s| s.ident.name.as_str()).collect();
+            match (names.as_slice(), args) {
+                (["Vec", "new"], []) => return Ok(Value::Vec(Vec::new())),
+                (["String", "new"], []) => return Ok(Value::Str(String::new())),
+                // A `&str` is its text, which is all a `String` is too.
+                (["String", "from"], [arg]) => return Ok(self.expr(arg)?.deref()),
+                _ => {}
+            }
+        }
+        if let Some(ctor) = self.tuple_ctor(callee) {
+            let mut fields = Vec::with_capacity(args.len());
+            for (i, arg) in args.iter().enumerate() {
+                fields.push((i.to_string(), self.expr(arg)?));
+            }
+            return Ok(ctor.build(fields));
+        }
+        match self.expr(callee)? {
+            Value::Fn(decl) => {
+                if args.len() != decl.params.len() {
+                    return Err(Diagnostic::error(format!(
+                        "this function takes {} arguments but {} were supplied",
+                        decl.params.len(),
+                        args.len()
+                    ))
+                    .code("E0061")
+                    .primary(callee.span, "")
+                    .into());
+                }
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.expr(arg)?);
+                }
+                self.call(&decl, values)
+            }
+            Value::Closure(closure, captures) => {
+                if args.len() != closure.params.len() {
+                    return Err(Diagnostic::error(format!(
+                        "this function takes {} arguments but {} were supplied",
+                        closure.params.len(),
+                        args.len()
+                    ))
+                    .code("E0057")
+                    .primary(callee.span, "")
+                    .into());
+                }
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.expr(arg)?);
+                }
+                let (params, ret) = (&closure.params, closure.ret.clone());
+                self.enter(captures.0.clone(), params, values, ret, |this| this.expr(&closure.body))
+            }
+            other => {
+                Err(Diagnostic::error(format!("expected function, found `{}`", other.type_name()))
+                    .code("E0618")
+                    .primary(callee.span, "call expression requires function")
+                    .into())
+            }
+        }
+    }
+
+    /// Call a `fn` item. Its body sees the items around the call, which are
+    /// those around its declaration, but none of the caller's locals.
+    fn call(&mut self, decl: &FnDecl, args: Vec<Value>) -> EResult<Value> {
+        // Scopes without items are left out, or each call would copy those
+        // of every call it is inside.
+        let items: Vec<Scope> = self
+            .scopes
+            .iter()
+            .map(|scope| Scope {
+                vars: scope
+                    .vars
+                    .iter()
+                    .filter(|(_, slot)| slot.item)
+                    .map(|(name, slot)| (name.clone(), slot.clone()))
+                    .collect(),
+                types: scope.types.clone(),
+            })
+            .filter(|scope| !scope.vars.is_empty() || !scope.types.is_empty())
+            .collect();
+        self.enter(items, &decl.params, args, decl.ret.clone(), |this| this.block(&decl.body))
+    }
+
+    /// Run a function or closure `body` with the variables of `scopes`, its
+    /// parameters bound to `args`, and `ret` as the return type, then go back
+    /// to the caller's.
+    fn enter(
+        &mut self,
+        scopes: Vec<Scope>,
+        params: &[Param],
+        args: Vec<Value>,
+        ret: Option<Ty>,
+        body: impl FnOnce(&mut Self) -> EResult<Value>,
+    ) -> EResult<Value> {
+        let caller = std::mem::replace(&mut self.scopes, scopes);
+        let caller_ret = std::mem::replace(&mut self.ret, ret);
+        self.scopes.push(Scope::default());
+        let result = params
+            .iter()
+            .zip(args)
+            .try_for_each(|(param, arg)| self.bind(&param.pat, arg))
+            .and_then(|()| body(self));
+        self.scopes = caller;
+        self.ret = caller_ret;
+        body_value(result).map_err(Flow::from)
+    }
+
+    /// `operand?`: the value in an `Ok` or `Some`, or a return from the
+    /// function with the `Err`, converted, or the `None`.
+    fn try_expr(&mut self, operand: &Expr, span: Span) -> EResult<Value> {
+        let (ty, variant, mut fields) = match self.expr(operand)? {
+            Value::Variant(ty, variant, fields) => (ty, variant, fields),
+            other => {
+                return Err(Diagnostic::error(format!(
+                    "the `?` operator cannot be applied to type `{}`",
+                    other.type_name()
+                ))
+                .code("E0277")
+                .primary(span, "not a `Result` or an `Option`")
+                .into())
+            }
+        };
+        match (ty.as_str(), variant.as_str(), fields.pop()) {
+            ("Result", "Ok", Some((_, value))) | ("Option", "Some", Some((_, value))) => Ok(value),
+            ("Result", "Err", Some((name, err))) => {
+                let err = self.convert_err(err, span)?;
+                let value = Value::Variant(ty, variant, vec![(name, err)]);
+                Err(Flow::Return(Box::new(value)))
+            }
+            _ => Err(Flow::Return(Box::new(Value::Variant(ty, variant, Vec::new())))),
+        }
+    }
+
+    /// `From::from(err)`, for the error type in the function's return type,
+    /// `Result<T, E>`. The conversions to numbers and `char` are the
+    /// lossless casts; every other is the identity.
+    fn convert_err(&self, err: Value, span: Span) -> EResult<Value> {
+        let target = match self.ret.as_ref().map(|ty| &ty.kind) {
+            Some(TyKind::Path(path)) => {
+                match (path.last().ident.name.as_str(), &path.last().generics[..]) {
+                    ("Result", [_, target]) => target,
+                    _ => return Ok(err),
+                }
+            }
+            _ => return Ok(err),
+        };
+        let primitive = match &target.kind {
+            TyKind::Path(path) => path.as_ident().is_some_and(|ident| {
+                let name = ident.name.as_str();
+                IntTy::from_name(name).is_some()
+                    || FloatTy::from_name(name).is_some()
+                    || name == "char"
+            }),
+            _ => false,
+        };
+        match err {
+            Value::Int(_) | Value::Float(..) | Value::Bool(_) | Value::Char(_) if primitive => {
+                ops::cast(err, target, span)
+            }
+            err => Ok(err),
+        }
+    }
+
+    /// `std::ops::Range { start, end }`, `RangeFrom { start }` or
+    /// `RangeTo { end }`, which are what `start..end`, `start..` and `..end`
+    /// build. `None` for any other struct literal, including one naming a
+    /// range with the wrong fields.
+    fn range_lit(&mut self, lit: &StructExpr) -> Option<EResult<Value>> {
+        let field = |name: &str| lit.fields.iter().find(|field| field.name.name == name);
+        let (start, end) = match (lit.path.last().ident.name.as_str(), lit.fields.len()) {
+            ("Range", 2) => (Some(field("start")?), Some(field("end")?)),
+            ("RangeFrom", 1) => (Some(field("start")?), None),
+            ("RangeTo", 1) => (None, Some(field("end")?)),
+            _ => return None,
+        };
+        let mut bound =
+            |bound: Option<&FieldExpr>| bound.map(|bound| self.expr(&bound.value)).transpose();
+        let bounds = match (start, end) {
+            // The fields are evaluated in the order they are written.
+            (Some(start), Some(end)) if start.span.start > end.span.start => {
+                bound(Some(end)).and_then(|end| Ok((bound(Some(start))?, end)))
+            }
+            (start, end) => bound(start).and_then(|start| Ok((start, bound(end)?))),
+        };
+        Some(bounds.map(|(start, end)| {
+            Value::Range(start.map(Box::new), end.map(Box::new), RangeLimits::HalfOpen)
+        }))
+    }
+
+    /// Run one iteration of a loop body. Returns the loop's value if a
+    /// `break` aimed at this loop ended it.
+    fn loop_body(&mut self, body: &Block, label: &Option<Ident>) -> EResult<Option<Value>> {
+        let targets_this = |target: &Option<Ident>| match (target, label) {
+            (None, _) => true,
+            (Some(target), Some(label)) => target.name == label.name,
+            (Some(_), None) => false,
+        };
+        match self.block(body) {
+            Ok(_) => Ok(None),
+            Err(Flow::Break(target, value, _)) if targets_this(&target) => Ok(Some(*value)),
+            Err(Flow::Continue(target, _)) if targets_this(&target) => Ok(None),
+            Err(flow) => Err(flow),
+        }
+    }
+
+    fn read(&mut self, name: &Ident) -> EResult<Value> {
+        self.slot(name)?.value.as_ref().map(Place::get).ok_or_else(|| uninitialized(name))
+    }
+
+    fn assign(&mut self, place: &Expr, value: Value) -> EResult<()> {
+        if let ExprKind::Path(path) = &place.kind {
+            if let Some(name) = path.as_ident() {
+                let slot = self.slot(name)?;
+                match &slot.value {
+                    Some(place) => place.set(value),
+                    // The deferred initialization of a `let name;`.
+                    None => slot.value = Some(Place::new(value)),
+                }
+                return Ok(());
+            }
+        }
+        self.place(place)?.set(value);
+        Ok(())
+    }
+
+    /// The place an assignment writes to or an `&mut` borrows: a variable,
+    /// a field or element of one, or what a reference points to.
+    fn place(&mut self, expr: &Expr) -> EResult<Place> {
+        match &expr.kind {
+            ExprKind::Path(path) if path.as_ident().is_some() => {
+                let name = path.as_ident().unwrap();
+                self.slot(name)?.value.clone().ok_or_else(|| uninitialized(name))
+            }
+            ExprKind::Paren(inner) => self.place(inner),
+            ExprKind::Unary(UnOp::Deref, inner) if is_place(inner) => {
+                Ok(self.place(inner)?.deref())
+            }
+            ExprKind::Unary(UnOp::Deref, inner) => match self.expr(inner)? {
+                Value::Ref(place) => Ok(place),
+                value => Ok(Place::new(value)),
+            },
+            ExprKind::Field(base, field) => {
+                let base = self.place(base)?.deref();
+                let index = base.with(|value| match value {
+                    Value::Struct(_, fields) => fields.iter().position(|(f, _)| *f == field.name),
+                    _ => None,
+                });
+                match index {
+                    Some(index) => Ok(base.project(index)),
+                    None => Err(no_field(&field.name, &base.get().type_name(), field.span)),
+                }
+            }
+            ExprKind::TupleField(base, index, index_span) => {
+                let base = self.place(base)?.deref();
+                let exists = base.with(|value| match value {
+                    Value::Tuple(elems) => *index < elems.len(),
+                    Value::Struct(_, fields) => *index < fields.len(),
+                    _ => false,
+                });
+                if !exists {
+                    return Err(no_field(&index.to_string(), &base.get().type_name(), *index_span));
+                }
+                Ok(base.project(*index))
+            }
+            ExprKind::Index(base, index) => {
+                let index = self.usize(index)?;
+                let base = self.place(base)?.deref();
+                match base.with(|value| match value {
+                    Value::Array(elems) | Value::Vec(elems) => Some(elems.len()),
+                    _ => None,
+                }) {
+                    Some(len) => {
+                        check_bounds(index, len, expr.span)?;
+                        Ok(base.project(index))
+                    }
+                    None => Err(cannot_index(&base.get().type_name(), expr.span)),
+                }
+            }
+            _ => Err(Diagnostic::error("invalid left-hand side of assignment")
+                .code("E0070")
+                .primary(expr.span, "cannot assign to this expression")
+                .into()),
+        }
+    }
+
+    fn bool(&mut self, expr: &Expr) -> EResult<bool> {
+        match self.expr(expr)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(Diagnostic::error("mismatched types")
+                .code("E0308")
+                .primary(expr.span, format!("expected `bool`, found `{}`", other.type_name()))
+                .into()),
+        }
+    }
+
+    /// An index or a repeat count, which must be a `usize`.
+    fn usize(&mut self, expr: &Expr) -> EResult<usize> {
+        match self.expr(expr)? {
+            Value::Int(n) if n.ty == IntTy::Usize => Ok(n.bits() as usize),
+            other => Err(mismatched(expr.span, IntTy::Usize.name(), &other)),
+        }
+    }
+}
+
+/// The value of a literal of type `ty`, negated if it appeared as `-lit`.
+/// Without a type from the checker, a literal has its suffix's, or else
+/// `i32` or `f64`.
+fn lit_value(lit: &LitKind, ty: Option<&Type>, negated: bool, span: Span) -> EResult<Value> {
+    match lit {
+        LitKind::Int(value, suffix) => {
+            let ty = match ty {
+                Some(Type::Int(ty)) => *ty,
+                _ => suffix.unwrap_or(IntTy::I32),
+            };
+            if negated && !ty.is_signed() {
+                let value = Value::Int(Int::wrap(*value, ty));
+                // Unsigned, so an error whatever the overflow mode.
+                return ops::unary(UnOp::Neg, value, OverflowMode::Debug, span);
+            }
+            let int = if negated {
+                // `i128::MIN` is the one value whose magnitude does not fit in an `i128`.
+                if *value > 1u128 << 127 {
+                    None
+                } else {
+                    Int::from_i128((*value as i128).wrapping_neg(), ty)
+                }
+            } else {
+                Int::from_u128(*value, ty)
+            };
+            int.map(Value::Int).ok_or_else(|| {
+                let (min, max) = (Int::min(ty), Int::max(ty));
+                Diagnostic::error(format!("literal out of range for `{}`", ty.name()))
+                    .primary(span, "")
+                    .note(format!(
+                        "the literal `{}{}` does not fit into the type `{}` whose range is `{}..={}`",
+                        if negated { "-" } else { "" },
+                        value,
+                        ty.name(),
+                        min,
+                        max
+                    ))
+                    .into()
+            })
+        }
+        LitKind::Float(value, suffix) => {
+            let ty = match ty {
+                Some(Type::Float(ty)) => *ty,
+                _ => suffix.unwrap_or(FloatTy::F64),
+            };
+            Ok(Value::float(if negated { -value } else { *value }, ty))
+        }
+        LitKind::Bool(b) => Ok(Value::Bool(*b)),
+        LitKind::Char(c) => Ok(Value::Char(*c)),
+        LitKind::Str(s) => Ok(Value::Str(s.clone())),
+    }
+}
+
+/// `i32::MAX`, `u8::MIN` and the like.
+fn associated_const(path: &crate::ast::Path) -> Option<Value> {
+    match path.segments.as_slice() {
+        [ty, name] => {
+            let ty = IntTy::from_name(&ty.ident.name)?;
+            match name.ident.name.as_str() {
+                "MIN" => Some(Value::Int(Int::min(ty))),
+                "MAX" => Some(Value::Int(Int::max(ty))),
+                "BITS" => Some(Value::Int(Int::wrap(ty.bits().into(), IntTy::U32))),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether `expr` denotes a place a value is stored in, rather than a
+/// temporary.
+fn is_place(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Path(path) => path.as_ident().is_some(),
+        ExprKind::Field(base, _) | ExprKind::TupleField(base, ..) | ExprKind::Index(base, _) => {
+            is_place(base)
+        }
+        ExprKind::Paren(inner) => is_place(inner),
+        // What a reference points to, wherever the reference came from.
+        ExprKind::Unary(UnOp::Deref, _) => true,
+        _ => false,
+    }
+}
+
+/// `Vec::new`, the one associated function there is.
+fn check_bounds(index: usize, len: usize, span: Span) -> EResult<()> {
+    if index < len {
+        Ok(())
+    } else {
+        Err(panic(
+            span,
+            &format!("index out of bounds: the len is {} but the index is {}", len, index),
+        ))
+    }
+}
+
+fn panic(span: Span, message: &str) -> Flow {
+    Diagnostic::error(format!("evaluation panicked: {}", message)).primary(span, message).into()
+}
+
+fn mismatched(span: Span, expected: &str, found: &Value) -> Flow {
+    Diagnostic::error("mismatched types")
+        .code("E0308")
+        .primary(span, format!("expected `{}`, found `{}`", expected, found.type_name()))
+        .into()
+}
+
+fn uninitialized(name: &Ident) -> Flow {
+    Diagnostic::error(format!("used binding `{}` isn't initialized", name.name))
+        .code("E0381")
+        .primary(name.span, format!("`{}` used here but it isn't initialized", name.name))
+        .into()
+}
+
+fn no_field(field: &str, ty: &str, span: Span) -> Flow {
+    Diagnostic::error(format!("no field `{}` on type `{}`", field, ty))
+        .code("E0609")
+        .primary(span, "unknown field")
+        .into()
+}
+
+fn cannot_index(ty: &str, span: Span) -> Flow {
+    Diagnostic::error(format!("cannot index into a value of type `{}`", ty))
+        .code("E0608")
+        .primary(span, "")
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run;
+    use crate::test_util::eval;
+
+    fn panics(src: &str) -> String {
+        run(src).unwrap_err().message
+    }
+
+    #[test]
+    fn notes_examples() {
+        assert_eq!(eval("let fahr = 212; 5 * (fahr-32) / 9"), "100");
+        assert_eq!(eval("let x = 1234.567 % 10.0; x"), "4.567000000000007");
+        assert_eq!(eval("let hi: u8 = 0xe0; let lo = !hi; lo"), "31");
+    }
+
+    #[test]
+    fn every_kind_of_value() {
+        assert_eq!(eval("()"), "()");
+        assert_eq!(eval("1 < 2"), "true");
+        assert_eq!(eval("-7i8 / 2"), "-3");
+        assert_eq!(eval("1.5f32 * 2.0"), "3.0");
+        assert_eq!(eval("'x'"), "'x'");
+        assert_eq!(eval("\"text\""), "\"text\"");
+        assert_eq!(eval("(1, true).1"), "true");
+        assert_eq!(eval("let a = [1, 2, 3]; a[2] + a.len()"), "6");
+        assert_eq!(eval("struct P { x: i32, y: i32 } let p = P { x: 1, y: 2 }; p.x + p.y"), "3");
+        assert_eq!(eval("[0u8; 3]"), "[0, 0, 0]");
+    }
+
+    #[test]
+    fn literals_take_their_inferred_types() {
+        assert_eq!(eval("let x: u64 = 1; x << 40"), "1099511627776");
+        assert_eq!(eval("let v = [10, 20, 30]; let i = 2; v[i]"), "30");
+        assert_eq!(eval("let mut t = 0; t += \"abc\".len(); t"), "3");
+        assert_eq!(eval("let n: u8 = 200; let m = n; m.wrapping_add(100)"), "44");
+        assert_eq!(eval("let f: f32 = 0.1; f + 0.2"), "0.3");
+    }
+
+    #[test]
+    fn mutable_references_write_to_the_place() {
+        assert_eq!(eval("let mut x = 1; let r = &mut x; *r += 1; x"), "2");
+        assert_eq!(eval("let mut a = [1, 2]; { let r = &mut a; r[0] = 5; } a"), "[5, 2]");
+        assert_eq!(
+            eval("fn inc(n: &mut i32) { *n += 1; } let mut c = 0; inc(&mut c); inc(&mut c); c"),
+            "2"
+        );
+        assert_eq!(eval("let mut a = [1, 2, 3]; for x in &mut a { *x *= 10; } a"), "[10, 20, 30]");
+        assert_eq!(
+            eval("let mut o = Some(1); if let Some(ref mut n) = o { *n = 9; } o"),
+            "Some(9)"
+        );
+    }
+
+    #[test]
+    fn closures_capture_their_environment() {
+        assert_eq!(eval("let is_even = |x: u64| x % 2 == 0; is_even(14)"), "true");
+        assert_eq!(eval("let k = 3; let times = |x: i32| x * k; times(5)"), "15");
+        assert_eq!(eval("let mut n = 0; let mut count = || n += 1; count(); count(); n"), "2");
+        assert_eq!(eval("let mut n = 5; let c = move || n * 2; n = 1; (c(), n)"), "(10, 1)");
+        assert_eq!(eval("let f = |x: i32| -> i32 { if x > 0 { return x; } 0 }; f(-1) + f(4)"), "4");
+    }
+
+    #[test]
+    fn vectors_grow_and_shrink_in_place() {
+        assert_eq!(eval("let mut v = vec![1, 2]; v.push(3); v"), "[1, 2, 3]");
+        assert_eq!(
+            eval("let mut v = Vec::new(); v.push('a'); (v.pop(), v.pop())"),
+            "(Some('a'), None)"
+        );
+        assert_eq!(
+            eval(
+                "fn add(v: &mut Vec<i32>) { v.push(7); } let mut v = vec![]; add(&mut v); v.len()"
+            ),
+            "1"
+        );
+    }
+
+    #[test]
+    fn runtime_panics() {
+        assert_eq!(
+            panics("let a = [1, 2]; let i = 5; a[i]"),
+            "evaluation panicked: index out of bounds: the len is 2 but the index is 5"
+        );
+        assert_eq!(
+            panics("let n = 1 << 30; [0u8; n]"),
+            "evaluation panicked: cannot build an array of 1073741824 elements: \
+             the evaluator allows at most 1048576"
+        );
+    }
+
+    #[test]
+    fn loops_and_labels() {
+        let src = "let apartment = [[1, 2], [3, 4], [5, 6]]; let mut found = (0, 0); \
+                   'search: for room in apartment { for spot in room { \
+                   if spot % 3 == 0 { found = (room[0], spot); break 'search; } } } found";
+        assert_eq!(eval(src), "(3, 3)");
+        let src = "let mut n = 0; 'outer: for i in 0..4 { for j in 0..4 { \
+                   if j > i { continue 'outer; } n += 1; } } n";
+        assert_eq!(eval(src), "10");
+        assert_eq!(eval("let mut i = 0; while i < 3 { i += 1; }"), "()");
+        assert_eq!(eval("let mut i = 0; let w = while i < 3 { i += 1; }; (w, i)"), "((), 3)");
+        assert_eq!(eval("let mut s = 0; let f = for i in 1..=4 { s += i; }; (f, s)"), "((), 10)");
+        assert_eq!(
+            eval(
+                "let mut v = vec![1, 2]; let mut n = 0; while let Some(x) = v.pop() { n += x; } n"
+            ),
+            "3"
+        );
+    }
+
+    #[test]
+    fn break_with_a_value() {
+        let src = "let mut counter = 0; let result = loop { counter += 1; \
+                   if counter == 10 { break counter * 2; } }; result";
+        assert_eq!(eval(src), "20");
+        assert_eq!(eval("let v = 'outer: loop { loop { break 'outer 'x'; } }; v"), "'x'");
+    }
+
+    #[test]
+    fn return_and_question_mark() {
+        let src =
+            "fn half(n: i32) -> Result<i32, u8> { if n % 2 == 1 { return Err(1); } Ok(n / 2) } \
+                   fn quarter(n: i32) -> Result<i32, u32> { let h = half(n)?; Ok(half(h)?) } \
+                   (quarter(8), quarter(6), quarter(3))";
+        assert_eq!(eval(src), "(Ok(2), Err(1), Err(1))");
+        let src =
+            "fn f(o: Option<i32>) -> Option<i32> { let x = o?; Some(x + 1) } (f(Some(1)), f(None))";
+        assert_eq!(eval(src), "(Some(2), None)");
+        // `return` abandons the tuple it was in the middle of building.
+        let src = "fn f(n: i32) -> i32 { let v = (1, if n > 0 { return 10; } else { 2 }); v.1 } \
+                   (f(1), f(-1))";
+        assert_eq!(eval(src), "(10, 2)");
+    }
+
+    #[test]
+    fn deep_recursion_is_limited() {
+        let f = "fn f(n: u64) -> u64 { if n == 0 { 0 } else { 1 + f(n - 1) } }";
+        assert_eq!(eval(&format!("{} f(1000)", f)), "1000");
+        assert_eq!(
+            panics(&format!("{} f(100000)", f)),
+            "recursion limit reached while evaluating the program"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Unary and binary operators, and `as` casts.
+
+use std::cmp::Ordering;
+
+use super::int::Int;
+use super::value::Value;
+use super::{panic, EResult, OverflowMode};
+use crate::ast::{BinOp, FloatTy, IntTy, Ty, TyKind, UnOp};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+pub(super) fn unary(op: UnOp, value: Value, mode: OverflowMode, span: Span) -> EResult<Value> {
+    match (op, value.deref()) {
+        (UnOp::Neg, Value::Int(n)) if !n.ty.is_signed() => {
+            Err(Diagnostic::error(format!("can't apply unary `-` to type `{}`", n.ty.name()))
+                .code("E0600")
+                .primary(span, "cannot apply unary operator `-`")
+                .note("unsigned values cannot be negated")
+                .into())
+        }
+        (UnOp::Neg, Value::Int(n)) => match n.overflowing_neg() {
+            (_, true) if mode == OverflowMode::Debug => {
+                Err(panic(span, "attempt to negate with overflow"))
+            }
+            (value, _) => Ok(Value::Int(value)),
+        },
+        (UnOp::Neg, Value::Float(x, ty)) => Ok(Value::float(-x, ty)),
+        // On integers `!` is bitwise NOT: `!0xe0u8` is `0x1f`.
+        (UnOp::Not, Value::Int(n)) => Ok(Value::Int(Int::wrap(!n.bits(), n.ty))),
+        (UnOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (op, value) => Err(Diagnostic::error(format!(
+            "can't apply unary `{}` to type `{}`",
+            op.as_str(),
+            value.type_name()
+        ))
+        .code("E0600")
+        .primary(span, format!("cannot apply unary operator `{}`", op.as_str()))
+        .into()),
+    }
+}
+
+/// A binary operator other than `&&` and `||`, which the interpreter
+/// evaluates lazily itself. Operands that are references stand for the
+/// values they point to.
+pub(super) fn binary(
+    op: BinOp,
+    lhs: Value,
+    rhs: Value,
+    mode: OverflowMode,
+    span: Span,
+) -> EResult<Value> {
+    let (lhs, rhs) = (lhs.deref(), rhs.deref());
+    if let (BinOp::Shl | BinOp::Shr, Value::Int(a), Value::Int(b)) = (op, &lhs, &rhs) {
+        // The shift amount may have any integer type. A release build keeps
+        // only its low bits, which for a negative amount are those of its
+        // two's complement.
+        return match (mode, b.to_u128().and_then(|amount| a.checked_shift(op, amount))) {
+            (_, Some(shifted)) => Ok(Value::Int(shifted)),
+            (OverflowMode::Debug, None) => Err(overflow(op, span)),
+            (OverflowMode::Release, None) => Ok(Value::Int(a.overflowing_shift(op, b.bits()).0)),
+        };
+    }
+    if lhs.type_name() != rhs.type_name() {
+        return Err(Diagnostic::error("mismatched types")
+            .code("E0308")
+            .primary(span, format!("expected `{}`, found `{}`", lhs.type_name(), rhs.type_name()))
+            .into());
+    }
+
+    if op.is_comparison() {
+        let ordering = lhs.compare(&rhs);
+        let result = match op {
+            // Structs have no ordering, but can still be compared for equality.
+            BinOp::Eq => lhs == rhs,
+            BinOp::Ne => lhs != rhs,
+            BinOp::Lt => ordering == Some(Ordering::Less),
+            BinOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            BinOp::Gt => ordering == Some(Ordering::Greater),
+            _ => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        };
+        if ordering.is_none() && !matches!(op, BinOp::Eq | BinOp::Ne) && !lhs.is_float() {
+            return Err(cannot_apply(op, &lhs, span));
+        }
+        return Ok(Value::Bool(result));
+    }
+
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => int_binary(op, a, b, mode, span),
+        (Value::Float(a, ty), Value::Float(b, _)) => {
+            let result = match op {
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Div => a / b,
+                BinOp::Rem => a % b,
+                _ => return Err(cannot_apply(op, &Value::Float(a, ty), span)),
+            };
+            Ok(Value::float(result, ty))
+        }
+        (Value::Bool(a), Value::Bool(b)) => match op {
+            BinOp::BitAnd => Ok(Value::Bool(a & b)),
+            BinOp::BitOr => Ok(Value::Bool(a | b)),
+            BinOp::BitXor => Ok(Value::Bool(a ^ b)),
+            _ => Err(cannot_apply(op, &Value::Bool(a), span)),
+        },
+        (Value::Str(a), Value::Str(b)) if op == BinOp::Add => Ok(Value::Str(a + &b)),
+        (lhs, _) => Err(cannot_apply(op, &lhs, span)),
+    }
+}
+
+fn int_binary(op: BinOp, a: Int, b: Int, mode: OverflowMode, span: Span) -> EResult<Value> {
+    let result = match op {
+        BinOp::BitAnd => Int::wrap(a.bits() & b.bits(), a.ty),
+        BinOp::BitOr => Int::wrap(a.bits() | b.bits(), a.ty),
+        BinOp::BitXor => Int::wrap(a.bits() ^ b.bits(), a.ty),
+        // Division by zero panics whatever the build mode, and so does the
+        // one overflowing division, `MIN / -1`, and its remainder.
+        BinOp::Div | BinOp::Rem if b.is_zero() => return Err(division_by_zero(op, span)),
+        BinOp::Div | BinOp::Rem => a.checked(op, b).ok_or_else(|| overflow(op, span))?,
+        _ => match (mode, a.overflowing(op, b)) {
+            (OverflowMode::Debug, (_, true)) => return Err(overflow(op, span)),
+            (_, (value, _)) => value,
+        },
+    };
+    Ok(Value::Int(result))
+}
+
+/// The panic for `/` or `%` by zero.
+pub(super) fn division_by_zero(op: BinOp, span: Span) -> super::Flow {
+    if op == BinOp::Div {
+        panic(span, "attempt to divide by zero")
+    } else {
+        panic(span, "attempt to calculate the remainder with a divisor of zero")
+    }
+}
+
+/// The panic for `op` overflowing in a debug build.
+pub(super) fn overflow(op: BinOp, span: Span) -> super::Flow {
+    panic(span, &format!("attempt to {} with overflow", verb(op)))
+}
+
+fn verb(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Add => "add",
+        BinOp::Sub => "subtract",
+        BinOp::Mul => "multiply",
+        BinOp::Div => "divide",
+        BinOp::Rem => "calculate the remainder",
+        BinOp::Shl => "shift left",
+        BinOp::Shr => "shift right",
+        _ => op.as_str(),
+    }
+}
+
+fn cannot_apply(op: BinOp, lhs: &Value, span: Span) -> super::Flow {
+    Diagnostic::error(format!(
+        "binary operation `{}` cannot be applied to type `{}`",
+        op.as_str(),
+        lhs.type_name()
+    ))
+    .code("E0369")
+    .primary(span, "")
+    .into()
+}
+
+/// `value as ty`.
+pub(super) fn cast(value: Value, ty: &Ty, span: Span) -> EResult<Value> {
+    let target = match &ty.kind {
+        TyKind::Path(path) => path.as_ident().map(|ident| ident.name.as_str()),
+        _ => None,
+    };
+    let int_ty = target.and_then(IntTy::from_name);
+    let float_ty = target.and_then(FloatTy::from_name);
+    let result = match (&value, int_ty, float_ty, target) {
+        (Value::Int(n), Some(ty), _, _) => Some(Value::Int(Int::wrap(n.bits(), ty))),
+        (Value::Int(n), _, Some(ty), _) => Some(Value::float(n.to_f64(), ty)),
+        (Value::Float(x, _), Some(ty), _, _) => Some(Value::Int(Int::from_f64(*x, ty))),
+        (Value::Float(x, _), _, Some(ty), _) => Some(Value::float(*x, ty)),
+        (Value::Bool(b), Some(ty), _, _) => Some(Value::Int(Int::wrap(u128::from(*b), ty))),
+        (Value::Char(c), Some(ty), _, _) => Some(Value::Int(Int::wrap(u128::from(*c), ty))),
+        // Only `u8` converts to `char`: every `u8` is a valid character, not every `u32` is.
+        (Value::Int(n), _, _, Some("char")) if n.ty == IntTy::U8 => {
+            Some(Value::Char(char::from(n.bits() as u8)))
+        }
+        (Value::Int(n), _, _, Some("char")) => {
+            return Err(Diagnostic::error(format!(
+                "only `u8` can be cast as `char`, not `{}`",
+                n.ty.name()
+            ))
+            .code("E0604")
+            .primary(span, "invalid cast")
+            .into())
+        }
+        (Value::Bool(_), _, _, Some("bool")) | (Value::Char(_), _, _, Some("char")) => {
+            Some(value.clone())
+        }
+        _ => None,
+    };
+    result.ok_or_else(|| {
+        let target = target.map_or_else(|| "this type".to_string(), |t| format!("`{}`", t));
+        Diagnostic::error(format!("casting `{}` as {} is invalid", value.type_name(), target))
+            .code("E0606")
+            .primary(span, "")
+            .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::{eval_program_with, Options, OverflowMode};
+    use crate::parser::parse_program;
+
+    /// The value of `src` in `mode`, or the message it panicked with.
+    fn eval(src: &str, mode: OverflowMode) -> String {
+        let program = parse_program(src).unwrap();
+        let (types, diagnostics) = crate::check(&program);
+        assert!(diagnostics.is_empty(), "{:#?}", diagnostics);
+        let options = Options { overflow: mode, ..Options::default() };
+        match eval_program_with(&program, &types, &options) {
+            Ok(value) => value.to_string(),
+            Err(diagnostic) => diagnostic.message,
+        }
+    }
+
+    #[test]
+    fn overflow_panics_in_debug_and_wraps_in_release() {
+        let cases = [
+            ("let x: u8 = 255; x + 1", "attempt to add with overflow", "0"),
+            (
+                "let x: usize = 0; x - 1",
+                "attempt to subtract with overflow",
+                "18446744073709551615",
+            ),
+            ("let x: i32 = 2147483647; x * 2", "attempt to multiply with overflow", "-2"),
+            ("let x: i8 = -128; -x", "attempt to negate with overflow", "-128"),
+            ("let x: i64 = 1; x << 64", "attempt to shift left with overflow", "1"),
+            ("let x: u32 = 3; x.pow(40)", "attempt to multiply with overflow", "689956897"),
+            (
+                "let x: i128 = -170141183460469231731687303715884105728; x - 1",
+                "attempt to subtract with overflow",
+                "170141183460469231731687303715884105727",
+            ),
+        ];
+        for (src, debug, release) in cases {
+            assert_eq!(eval(src, OverflowMode::Debug), format!("evaluation panicked: {}", debug));
+            assert_eq!(eval(src, OverflowMode::Release), release, "{}", src);
+        }
+    }
+
+    #[test]
+    fn division_by_zero_panics_in_both_modes() {
+        for mode in [OverflowMode::Debug, OverflowMode::Release] {
+            let x = "let x: u8 = 0; ";
+            assert_eq!(
+                eval(&format!("{}1 / x", x), mode),
+                "evaluation panicked: attempt to divide by zero"
+            );
+            assert_eq!(
+                eval(&format!("{}7 % x", x), mode),
+                "evaluation panicked: attempt to calculate the remainder with a divisor of zero"
+            );
+            assert_eq!(
+                eval("let x: i8 = -128; x / -1", mode),
+                "evaluation panicked: attempt to divide with overflow"
+            );
+        }
+        assert_eq!(eval("let x: i16 = 1000; x as u8", OverflowMode::Debug), "232");
+    }
+}
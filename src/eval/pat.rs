@@ -0,0 +1,356 @@
+//! Matching values against patterns, and `match` itself.
+
+use std::sync::Arc;
+
+use super::table::MatchTable;
+use super::{associated_const, is_place, EResult, Interpreter, Place, Scope, Value};
+use crate::ast::{Arm, BindingMode, Expr, Mutability, Pat, PatKind, RangeLimits};
+use crate::diagnostic::Diagnostic;
+
+/// A variable bound by a pattern that matched.
+struct Binding {
+    name: String,
+    value: Value,
+}
+
+impl Interpreter<'_> {
+    /// Bind the variables of an irrefutable pattern, as in a `let` or a
+    /// function parameter.
+    pub(super) fn bind(&mut self, pat: &Pat, value: Value) -> EResult<()> {
+        self.bind_in(pat, value, None)
+    }
+
+    /// Bind the variables of an irrefutable pattern to `value`, which lies
+    /// in `place` if its `ref mut` bindings are to borrow it there.
+    pub(super) fn bind_in(
+        &mut self,
+        pat: &Pat,
+        value: Value,
+        place: Option<&Place>,
+    ) -> EResult<()> {
+        let mut bindings = Vec::new();
+        if !self.match_pat(pat, &value, false, place, &mut bindings)? {
+            return Err(Diagnostic::error("refutable pattern in local binding")
+                .code("E0005")
+                .primary(pat.span, format!("pattern does not match `{}`", value))
+                .into());
+        }
+        for binding in bindings {
+            self.define(&binding.name, binding.value);
+        }
+        Ok(())
+    }
+
+    /// Run the first arm whose pattern matches and whose guard, if any,
+    /// holds.
+    pub(super) fn match_expr(&mut self, scrutinee: &Expr, arms: &[Arm]) -> EResult<Value> {
+        // A `ref mut` binding borrows part of the scrutinee, which must then
+        // be matched where it lies.
+        let by_ref_mut = arms.iter().any(|arm| binds_by_ref_mut(&arm.pat));
+        let (value, place) = if by_ref_mut && is_place(scrutinee) {
+            let place = self.place(scrutinee)?;
+            (place.get(), Some(place))
+        } else {
+            (self.expr(scrutinee)?, None)
+        };
+        let place = place.as_ref();
+        // A lowered `match` knows which arm to run without trying the others.
+        if let Some(table) = self.match_table(scrutinee, arms, &value) {
+            if let Some(index) = table.arm(&value) {
+                if let Some(constant) = table.constant(index) {
+                    return Ok(constant);
+                }
+                if let Some(result) = self.try_arm(&arms[index], &value, place)? {
+                    return Ok(result);
+                }
+            }
+        }
+        for arm in arms {
+            if let Some(result) = self.try_arm(arm, &value, place)? {
+                return Ok(result);
+            }
+        }
+        Err(Diagnostic::error("evaluation panicked: no match arm matched")
+            .primary(scrutinee.span, format!("`{}` not covered", value))
+            .into())
+    }
+
+    /// The table for a `match` on an integer, lowering it the first time.
+    fn match_table(
+        &mut self,
+        scrutinee: &Expr,
+        arms: &[Arm],
+        value: &Value,
+    ) -> Option<Arc<MatchTable>> {
+        let ty = match value {
+            Value::Int(n) if self.options.match_tables => n.ty,
+            _ => return None,
+        };
+        if let Some(table) = self.tables.get(&scrutinee.id) {
+            return table.clone();
+        }
+        let table = self.lower_match(arms, ty).map(Arc::new);
+        self.tables.insert(scrutinee.id, table.clone());
+        table
+    }
+
+    /// Run `arm` if `value`, which lies in `place` if it is known, matches
+    /// its pattern and its guard holds.
+    fn try_arm(
+        &mut self,
+        arm: &Arm,
+        value: &Value,
+        place: Option<&Place>,
+    ) -> EResult<Option<Value>> {
+        let mut bindings = Vec::new();
+        if !self.match_pat(&arm.pat, value, false, place, &mut bindings)? {
+            return Ok(None);
+        }
+        self.scopes.push(Scope::default());
+        for binding in bindings {
+            self.define(&binding.name, binding.value);
+        }
+        let guard = match &arm.guard {
+            Some(guard) => self.bool(guard),
+            None => Ok(true),
+        };
+        let result = match guard {
+            Ok(false) => {
+                self.scopes.pop();
+                return Ok(None);
+            }
+            Ok(true) => self.expr(&arm.body),
+            Err(flow) => Err(flow),
+        };
+        self.scopes.pop();
+        result.map(Some)
+    }
+
+    /// Whether `value` matches `pat`, collecting the variables it binds.
+    /// `by_ref_mut` says whether plain names bind by `ref mut`, and `place`
+    /// is where `value` lies, if the variables may borrow it.
+    fn match_pat(
+        &mut self,
+        pat: &Pat,
+        value: &Value,
+        by_ref_mut: bool,
+        place: Option<&Place>,
+        bindings: &mut Vec<Binding>,
+    ) -> EResult<bool> {
+        if let Value::Ref(target) = value {
+            // Matching an `&mut` against a pattern that is not a reference
+            // pattern matches what it points to, and the names bind by
+            // `ref mut` to its parts.
+            if !matches!(pat.kind, PatKind::Wild | PatKind::Ident(..) | PatKind::Or(_)) {
+                let by_ref_mut = !matches!(pat.kind, PatKind::Ref(..));
+                let pointee = target.get();
+                let pat = match &pat.kind {
+                    PatKind::Ref(inner, _) => inner,
+                    _ => pat,
+                };
+                return self.match_pat(pat, &pointee, by_ref_mut, Some(target), bindings);
+            }
+        }
+        match (&pat.kind, value) {
+            (PatKind::Wild, _) => Ok(true),
+            (PatKind::Ident(mode, name, sub), value) => {
+                if let Some(sub) = sub {
+                    if !self.match_pat(sub, value, by_ref_mut, place, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                let ref_mut = match *mode {
+                    BindingMode::ByRef(mutability) => mutability == Mutability::Mut,
+                    BindingMode::ByValue(mutability) => by_ref_mut && mutability == Mutability::Not,
+                };
+                let value = match place {
+                    Some(place) if ref_mut => Value::Ref(place.clone()),
+                    // A temporary, which the binding borrows all of.
+                    None if ref_mut => Value::Ref(Place::new(value.clone())),
+                    _ => value.clone(),
+                };
+                bindings.push(Binding { name: name.name.clone(), value });
+                Ok(true)
+            }
+            (PatKind::Lit(lit), value) => Ok(self.expr(lit)? == *value),
+            (PatKind::Range(start, end, limits), value) => {
+                let start = self.expr(start)?;
+                let end = self.expr(end)?;
+                let above_start = start.compare(value).is_some_and(|ord| ord.is_le());
+                let below_end = end.compare(value).is_some_and(|ord| match limits {
+                    RangeLimits::Closed => ord.is_ge(),
+                    RangeLimits::HalfOpen => ord.is_gt(),
+                });
+                Ok(above_start && below_end)
+            }
+            (PatKind::Path(path), value) => Ok(match associated_const(path) {
+                Some(constant) => constant == *value,
+                None => ctor_name(value) == Some(path.last().ident.name.as_str()),
+            }),
+            (PatKind::TupleStruct(path, pats), Value::Struct(_, fields))
+            | (PatKind::TupleStruct(path, pats), Value::Variant(_, _, fields)) => {
+                if ctor_name(value) != Some(path.last().ident.name.as_str()) {
+                    return Ok(false);
+                }
+                let values = fields.iter().map(|(_, value)| value);
+                self.match_elems(pats, values, by_ref_mut, place, bindings)
+            }
+            (PatKind::Struct(path, fields, _), Value::Struct(_, values))
+            | (PatKind::Struct(path, fields, _), Value::Variant(_, _, values)) => {
+                if ctor_name(value) != Some(path.last().ident.name.as_str()) {
+                    return Ok(false);
+                }
+                for field in fields {
+                    let index = values.iter().position(|(name, _)| *name == field.name.name);
+                    let index = match index {
+                        Some(index) => index,
+                        None => {
+                            return Err(Diagnostic::error(format!(
+                                "`{}` does not have a field named `{}`",
+                                value.type_name(),
+                                field.name.name
+                            ))
+                            .code("E0026")
+                            .primary(field.name.span, "")
+                            .into())
+                        }
+                    };
+                    let field_place = place.map(|place| place.project(index));
+                    let value = &values[index].1;
+                    let matched = self.match_pat(
+                        &field.pat,
+                        value,
+                        by_ref_mut,
+                        field_place.as_ref(),
+                        bindings,
+                    )?;
+                    if !matched {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (PatKind::Tuple(pats), Value::Unit) => Ok(pats.is_empty()),
+            (PatKind::Tuple(pats), Value::Tuple(values)) if pats.len() == values.len() => {
+                self.match_elems(pats, values.iter(), by_ref_mut, place, bindings)
+            }
+            // References are transparent, so `&pat` matches what `pat` does.
+            // Its variables bind by value again.
+            (PatKind::Ref(inner, _), value) => self.match_pat(inner, value, false, place, bindings),
+            (PatKind::Or(alternatives), value) => {
+                for alternative in alternatives {
+                    let bound = bindings.len();
+                    if self.match_pat(alternative, value, by_ref_mut, place, bindings)? {
+                        return Ok(true);
+                    }
+                    bindings.truncate(bound);
+                }
+                Ok(false)
+            }
+            (_, value) => Err(Diagnostic::error("mismatched types")
+                .code("E0308")
+                .primary(
+                    pat.span,
+                    format!("this pattern cannot match a value of type `{}`", value.type_name()),
+                )
+                .into()),
+        }
+    }
+
+    /// Match the elements of a tuple, or the fields of a tuple struct or
+    /// variant, one pattern each.
+    fn match_elems<'v>(
+        &mut self,
+        pats: &[Pat],
+        values: impl Iterator<Item = &'v Value>,
+        by_ref_mut: bool,
+        place: Option<&Place>,
+        bindings: &mut Vec<Binding>,
+    ) -> EResult<bool> {
+        for (index, (pat, value)) in pats.iter().zip(values).enumerate() {
+            let elem_place = place.map(|place| place.project(index));
+            let matched = self.match_pat(pat, value, by_ref_mut, elem_place.as_ref(), bindings)?;
+            if !matched {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// The name a constructor pattern must have to match `value`: the struct's,
+/// or the variant's.
+fn ctor_name(value: &Value) -> Option<&str> {
+    match value {
+        Value::Struct(name, _) | Value::Variant(_, name, _) => Some(name),
+        _ => None,
+    }
+}
+
+/// Whether `pat` has a `ref mut` binding.
+pub(super) fn binds_by_ref_mut(pat: &Pat) -> bool {
+    pat.bindings().iter().any(|(mode, _)| *mode == BindingMode::ByRef(Mutability::Mut))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run;
+    use crate::test_util::eval;
+
+    #[test]
+    fn literals_ranges_and_wildcards() {
+        let classify = |c: char| {
+            eval(&format!(
+                "match {:?} {{ 'a' | 'e' | 'i' | 'o' | 'u' => 1, 'a'..='z' => 2, _ => 0 }}",
+                c
+            ))
+        };
+        assert_eq!([classify('e'), classify('x'), classify('?')], ["1", "2", "0"]);
+        assert_eq!(eval("let code = 0; match code { 0 => \"ok\", _ => \"error\" }"), "\"ok\"");
+        assert_eq!(eval("match -3 { i32::MIN..=-1 => -1, 0 => 0, _ => 1 }"), "-1");
+    }
+
+    #[test]
+    fn tuples_structs_and_variants() {
+        assert_eq!(eval("match (1, (2, 3)) { (a, (_, c)) => a + c }"), "4");
+        let src = "struct Point { x: i32, y: i32 } let p = Point { x: 0, y: 7 }; \
+                   match p { Point { x: 0, y } => y, Point { .. } => -1 }";
+        assert_eq!(eval(src), "7");
+        let src = "enum Shape { Circle(f64), Rect { w: f64, h: f64 } } \
+                   let s = Shape::Rect { w: 2.0, h: 3.0 }; \
+                   match s { Shape::Circle(r) => r, Shape::Rect { w, h } => w * h }";
+        assert_eq!(eval(src), "6.0");
+    }
+
+    #[test]
+    fn first_match_wins_and_guards() {
+        assert_eq!(
+            eval("match 5 { n if n > 3 => \"big\", 5 => \"five\", _ => \"small\" }"),
+            "\"big\""
+        );
+        assert_eq!(
+            eval("match 2 { n if n > 3 => \"big\", 2 | 3 => \"two or three\", _ => \"small\" }"),
+            "\"two or three\""
+        );
+    }
+
+    #[test]
+    fn bindings_references_and_at() {
+        assert_eq!(eval("match 7 { d @ 0..=9 => d * 10, _ => 0 }"), "70");
+        assert_eq!(eval("let r = &(1, 2); match r { &(a, b) => a + b }"), "3");
+        assert_eq!(eval("let o = Some(4); match &o { Some(n) => *n, None => 0 }"), "4");
+        assert_eq!(eval("let mut p = (1, 2); match p { (ref mut a, _) => *a += 10 } p"), "(11, 2)");
+        assert_eq!(
+            eval("let mut o = Some(1); match &mut o { Some(n) => *n += 1, None => {} } o"),
+            "Some(2)"
+        );
+    }
+
+    #[test]
+    fn refutable_let_panics() {
+        assert_eq!(
+            run("let o: Option<i32> = None; let Some(x) = o; x").unwrap_err().code,
+            Some("E0005")
+        );
+    }
+}
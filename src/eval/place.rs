@@ -0,0 +1,86 @@
+//! Places: where values are stored, and the `&mut` references to them.
+//!
+//! Each variable's value lives in a cell of its own. A place is such a cell
+//! and the path of field and element indices that leads from the variable's
+//! value to the part of it meant, so `p.1` is `p`'s cell with the path
+//! `[1]`, and `a[i].x` that of `a` with `[i, 0]` if `x` is the first field.
+//! `&mut place` evaluates to a [`Value::Ref`] to it, through which writes
+//! reach the variable, however far the reference was passed around.
+//!
+//! The cells are shared and locked as a `Mutex`, not a `RefCell`, so that
+//! the values holding them can leave the evaluator's thread.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use super::value::Value;
+
+#[derive(Clone)]
+pub struct Place {
+    cell: Arc<Mutex<Value>>,
+    path: Vec<usize>,
+}
+
+impl Place {
+    /// A place of its own holding `value`, as a variable or temporary has.
+    pub(super) fn new(value: Value) -> Place {
+        Place { cell: Arc::new(Mutex::new(value)), path: Vec::new() }
+    }
+
+    /// The `index`th field or element of the value in this place.
+    pub(super) fn project(&self, index: usize) -> Place {
+        let mut path = self.path.clone();
+        path.push(index);
+        Place { cell: Arc::clone(&self.cell), path }
+    }
+
+    /// The place a reference stored here points to, following references
+    /// to references, or this place if it holds no reference. Field access,
+    /// indexing and method calls see through references this way.
+    pub(super) fn deref(&self) -> Place {
+        let mut place = self.clone();
+        while let Some(target) = place.with(|value| match value {
+            Value::Ref(target) => Some(target.clone()),
+            _ => None,
+        }) {
+            place = target;
+        }
+        place
+    }
+
+    pub(super) fn get(&self) -> Value {
+        self.with(|value| value.clone())
+    }
+
+    pub(super) fn set(&self, value: Value) {
+        self.with(|target| *target = value);
+    }
+
+    /// Run `f` on the value in this place. `f` must not evaluate anything,
+    /// which could reach the same cell.
+    pub(super) fn with<R>(&self, f: impl FnOnce(&mut Value) -> R) -> R {
+        let mut value = self.cell.lock().expect("a place is never locked across a panic");
+        let mut target = &mut *value;
+        for &index in &self.path {
+            target = match target {
+                Value::Tuple(elems) | Value::Array(elems) | Value::Vec(elems) => &mut elems[index],
+                Value::Struct(_, fields) | Value::Variant(_, _, fields) => &mut fields[index].1,
+                _ => unreachable!("the borrow checker keeps a borrowed place's value in shape"),
+            };
+        }
+        f(target)
+    }
+}
+
+/// Two references are equal when the values they point to are, as in Rust.
+impl PartialEq for Place {
+    fn eq(&self, other: &Place) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl fmt::Debug for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "&mut {:?}", self.get())
+    }
+}
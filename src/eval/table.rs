@@ -0,0 +1,220 @@
+//! Lowering dense integer `match`es to tables.
+//!
+//! A `match` whose arms test integer values, like `match code { 0 => ...,
+//! 1 => ..., 2 => ..., _ => ... }`, would otherwise try its arms one by one.
+//! When the values are dense, it is lowered the way a compiler lowers it to
+//! a jump table: an array indexed by the scrutinee's offset from the
+//! smallest value gives the arm to run. When every arm's body is a literal,
+//! their values are computed once and the match becomes an array access.
+//!
+//! A match is lowered the first time it runs, when the scrutinee's integer
+//! type is known, and the table is kept for later runs.
+
+use std::convert::TryFrom;
+
+use super::{Interpreter, Value};
+use crate::ast::{Arm, Expr, ExprKind, IntTy, Pat, PatKind, RangeLimits, UnOp};
+
+/// Fewer distinct values than this are tested quickly enough one by one.
+const MIN_VALUES: usize = 3;
+
+/// The most entries a table may have.
+const MAX_ENTRIES: i128 = 1024;
+
+/// A `match` lowered to a table.
+pub(super) struct MatchTable {
+    ty: IntTy,
+    /// The smallest value an arm tests. Entry `i` is for `min + i`.
+    min: i128,
+    /// The first arm that tests each value in the span, if any does.
+    arms: Vec<Option<usize>>,
+    /// The arm for every other value: a final `_` or binding.
+    default: Option<usize>,
+    /// The value of each arm, when all their bodies are literals.
+    consts: Option<Vec<Value>>,
+}
+
+impl MatchTable {
+    /// The arm that `value` selects, or `None` if the table does not
+    /// decide, which leaves it to trying the arms in turn.
+    pub(super) fn arm(&self, value: &Value) -> Option<usize> {
+        let n = match value {
+            Value::Int(n) if n.ty == self.ty => n.to_i128()?,
+            _ => return None,
+        };
+        let entry = n.checked_sub(self.min).and_then(|i| usize::try_from(i).ok());
+        match entry.and_then(|i| self.arms.get(i)) {
+            Some(Some(arm)) => Some(*arm),
+            _ => self.default,
+        }
+    }
+
+    /// The value of `arm`, if the table holds the arms' values.
+    pub(super) fn constant(&self, arm: usize) -> Option<Value> {
+        self.consts.as_ref().map(|values| values[arm].clone())
+    }
+}
+
+impl Interpreter<'_> {
+    /// Lower a `match` whose scrutinee is an integer of type `ty`, if it is
+    /// a dense one: at least [`MIN_VALUES`] values tested, no guards, and
+    /// every arm but a final catch-all testing literal values.
+    pub(super) fn lower_match(&mut self, arms: &[Arm], ty: IntTy) -> Option<MatchTable> {
+        let (last, init) = arms.split_last()?;
+        let default = if is_catch_all(&last.pat) && last.guard.is_none() {
+            Some(arms.len() - 1)
+        } else {
+            None
+        };
+        let tested = if default.is_some() { init } else { arms };
+        let mut values: Vec<(i128, usize)> = Vec::new();
+        for (index, arm) in tested.iter().enumerate() {
+            if arm.guard.is_some() {
+                return None;
+            }
+            self.tested_values(&arm.pat, ty, index, &mut values)?;
+        }
+        let min = values.iter().map(|(n, _)| *n).min()?;
+        let max = values.iter().map(|(n, _)| *n).max()?;
+        let span = max.checked_sub(min)?.checked_add(1)?;
+        let dense = span <= MAX_ENTRIES && span <= 2 * values.len() as i128;
+        if values.len() < MIN_VALUES || !dense {
+            return None;
+        }
+        let mut entries = vec![None; span as usize];
+        for (n, arm) in values {
+            // The first arm that tests a value is the one that runs.
+            entries[(n - min) as usize].get_or_insert(arm);
+        }
+        let consts = if arms.iter().all(|arm| is_literal(&arm.body)) {
+            arms.iter().map(|arm| self.expr(&arm.body).ok()).collect()
+        } else {
+            None
+        };
+        Some(MatchTable { ty, min, arms: entries, default, consts })
+    }
+
+    /// Add the values `pat` tests, for arm `arm`, to `values`. `None` if it
+    /// is not a literal, a range of literals, or alternatives of them.
+    fn tested_values(
+        &mut self,
+        pat: &Pat,
+        ty: IntTy,
+        arm: usize,
+        values: &mut Vec<(i128, usize)>,
+    ) -> Option<()> {
+        match &pat.kind {
+            PatKind::Lit(lit) => values.push((self.int_value(lit, ty)?, arm)),
+            PatKind::Range(start, end, limits) => {
+                let start = self.int_value(start, ty)?;
+                let mut end = self.int_value(end, ty)?;
+                if *limits == RangeLimits::HalfOpen {
+                    end = end.checked_sub(1)?;
+                }
+                if end.checked_sub(start)? >= MAX_ENTRIES {
+                    return None;
+                }
+                values.extend((start..=end).map(|n| (n, arm)));
+            }
+            PatKind::Or(alternatives) => {
+                for alternative in alternatives {
+                    self.tested_values(alternative, ty, arm, values)?;
+                }
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn int_value(&mut self, expr: &Expr, ty: IntTy) -> Option<i128> {
+        match self.expr(expr) {
+            Ok(Value::Int(n)) if n.ty == ty => n.to_i128(),
+            _ => None,
+        }
+    }
+}
+
+/// `_`, or a plain name: a pattern every value matches.
+fn is_catch_all(pat: &Pat) -> bool {
+    matches!(pat.kind, PatKind::Wild | PatKind::Ident(_, _, None))
+}
+
+/// A literal, possibly negated, whose value is the same every time.
+fn is_literal(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Lit(_) => true,
+        ExprKind::Unary(UnOp::Neg, inner) | ExprKind::Paren(inner) => is_literal(inner),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{eval_program_with, Int, Options};
+    use crate::test_util::well_typed;
+
+    /// Lower the `match` that is the tail of `src`, matching on a `ty`.
+    fn lower(src: &str, ty: IntTy) -> Option<MatchTable> {
+        let (program, types) = well_typed(src);
+        let arms = match program.tail.as_deref().map(|tail| &tail.kind) {
+            Some(ExprKind::Match(_, arms, _)) => arms,
+            _ => panic!("`{}` does not end in a `match`", src),
+        };
+        Interpreter::new(&types, Options::default()).lower_match(arms, ty)
+    }
+
+    /// The value of `src` with and without tables.
+    fn both_ways(src: &str) -> (String, String) {
+        let (program, types) = well_typed(src);
+        let eval = |match_tables| {
+            let options = Options { match_tables, ..Options::default() };
+            eval_program_with(&program, &types, &options).unwrap().to_string()
+        };
+        (eval(true), eval(false))
+    }
+
+    #[test]
+    fn dense_matches_are_lowered() {
+        let table =
+            lower("let c = 1; match c { 0 => 10, 1 | 2 => 20, 3..=5 => 30, _ => 0 }", IntTy::I32)
+                .expect("lowered");
+        assert_eq!(table.min, 0);
+        assert_eq!(table.arms, [Some(0), Some(1), Some(1), Some(2), Some(2), Some(2)]);
+        assert_eq!(table.default, Some(3));
+        let int = |n| Value::Int(Int::from_i128(n, IntTy::I32).unwrap());
+        assert_eq!(table.arm(&int(4)), Some(2));
+        assert_eq!(table.arm(&int(-1)), Some(3));
+        assert_eq!(table.constant(1), Some(int(20)));
+    }
+
+    #[test]
+    fn sparse_guarded_or_short_matches_are_not() {
+        assert!(lower("let c = 1; match c { 0 => 1, 500 => 2, 1000 => 3, _ => 0 }", IntTy::I32)
+            .is_none());
+        assert!(lower(
+            "let c = 1; match c { 0 => 1, 1 if c > 0 => 2, 2 => 3, _ => 0 }",
+            IntTy::I32
+        )
+        .is_none());
+        assert!(lower("let c = 1; match c { 0 => 1, 1 => 2, _ => 0 }", IntTy::I32).is_none());
+        // Arms that run code still get a jump table, without constants.
+        let table = lower("let c = 1; match c { 0 => c + 1, 1 => 2, 2 => 3, _ => 0 }", IntTy::I32);
+        assert!(table.expect("lowered").consts.is_none());
+    }
+
+    #[test]
+    fn tables_agree_with_trying_arms_in_turn() {
+        let arms = "0 => 10, 1 | 2 => 20, 3..=5 => 30, 4 => 99, n => n * 2";
+        for n in -3..10 {
+            let src = format!("let c = {}; match c {{ {} }}", n, arms);
+            let (table, linear) = both_ways(&src);
+            assert_eq!(table, linear, "{}", src);
+        }
+        let src = "let mut total = 0u64; for c in 0..300u16 { \
+                   total += match c { 0..=99 => 1, 100..=199 => 2, 250 => 3, _ => 4 }; } total";
+        assert_eq!(both_ways(src), ("699".to_string(), "699".to_string()));
+        let src = "let c = -2i8; match c { -3 => 'a', -2 => 'b', -1 => 'c', _ => 'z' }";
+        assert_eq!(both_ways(src), ("'b'".to_string(), "'b'".to_string()));
+    }
+}
@@ -0,0 +1,247 @@
+//! Runtime values.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+use super::int::Int;
+use super::place::Place;
+use super::Captures;
+use crate::ast::{Closure, FloatTy, FnDecl, RangeLimits};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// `()`, the value of a block without a tail, of an `if` without
+    /// `else`, and of `while` and `for` loops.
+    Unit,
+    Bool(bool),
+    Int(Int),
+    /// A float of either width. An `f32` is kept rounded to `f32` precision.
+    Float(f64, FloatTy),
+    Char(char),
+    /// A `&str` or a `String`, which hold the same text.
+    Str(String),
+    /// `(a, b, ...)` with at least one element; the empty tuple is [`Value::Unit`].
+    Tuple(Vec<Value>),
+    Array(Vec<Value>),
+    /// A vector, built by `vec![...]` or `Vec::new()`, which unlike an
+    /// array can grow and shrink.
+    Vec(Vec<Value>),
+    /// A struct value built by a struct literal, with its fields in the order
+    /// written. A tuple struct's fields are named `0`, `1`, ...
+    Struct(String, Vec<(String, Value)>),
+    /// An enum value: the enum's name, the variant's, and its fields as for
+    /// [`Value::Struct`].
+    Variant(String, String, Vec<(String, Value)>),
+    /// `start..end`, with either bound left out as in `start..` or `..`.
+    Range(Option<Box<Value>>, Option<Box<Value>>, RangeLimits),
+    /// A function declared with `fn`, named or called.
+    Fn(Arc<FnDecl>),
+    /// A closure, with the variables it captured.
+    Closure(Arc<Closure>, Arc<Captures>),
+    /// `&mut place`. Shared references are the values they point to, since
+    /// nothing can write through them.
+    Ref(Place),
+}
+
+impl Value {
+    pub fn float(value: f64, ty: FloatTy) -> Value {
+        match ty {
+            FloatTy::F32 => Value::Float(value as f32 as f64, ty),
+            FloatTy::F64 => Value::Float(value, ty),
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Value::Float(..))
+    }
+
+    /// The value a reference points to, or this value if it is not one.
+    pub fn deref(self) -> Value {
+        match self {
+            Value::Ref(place) => place.get().deref(),
+            value => value,
+        }
+    }
+
+    /// The type of the value, written as Rust would.
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Unit => "()".to_string(),
+            Value::Bool(_) => "bool".to_string(),
+            Value::Int(n) => n.ty.name().to_string(),
+            Value::Float(_, ty) => ty.name().to_string(),
+            Value::Char(_) => "char".to_string(),
+            Value::Str(_) => "&str".to_string(),
+            Value::Tuple(elems) => {
+                let names: Vec<String> = elems.iter().map(Value::type_name).collect();
+                if names.len() == 1 {
+                    format!("({},)", names[0])
+                } else {
+                    format!("({})", names.join(", "))
+                }
+            }
+            Value::Vec(elems) => match elems.first() {
+                Some(first) => format!("Vec<{}>", first.type_name()),
+                None => "Vec<_>".to_string(),
+            },
+            Value::Array(elems) => match elems.first() {
+                Some(first) => format!("[{}; {}]", first.type_name(), elems.len()),
+                None => "[_; 0]".to_string(),
+            },
+            Value::Struct(name, _) | Value::Variant(name, ..) => name.clone(),
+            Value::Range(start, end, limits) => {
+                let name = match (start, end, limits) {
+                    (None, None, _) => return "RangeFull".to_string(),
+                    (Some(_), None, _) => "RangeFrom",
+                    (None, Some(_), RangeLimits::HalfOpen) => "RangeTo",
+                    (None, Some(_), RangeLimits::Closed) => "RangeToInclusive",
+                    (Some(_), Some(_), RangeLimits::HalfOpen) => "Range",
+                    (Some(_), Some(_), RangeLimits::Closed) => "RangeInclusive",
+                };
+                let bound = start.as_ref().or(end.as_ref()).expect("one bound is given");
+                format!("{}<{}>", name, bound.type_name())
+            }
+            Value::Fn(decl) => format!("fn {}", decl.name.name),
+            Value::Closure(..) => "closure".to_string(),
+            Value::Ref(place) => format!("&mut {}", place.get().type_name()),
+        }
+    }
+
+    /// Compare two values of the same type. Returns `None` for values of
+    /// different types, for NaN, and for structs, which are not ordered.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Ref(a), Value::Ref(b)) => a.get().compare(&b.get()),
+            (Value::Unit, Value::Unit) => Some(Ordering::Equal),
+            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            (Value::Float(a, ta), Value::Float(b, tb)) if ta == tb => a.partial_cmp(b),
+            (Value::Char(a), Value::Char(b)) => a.partial_cmp(b),
+            (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+            (Value::Tuple(a), Value::Tuple(b))
+            | (Value::Array(a), Value::Array(b))
+            | (Value::Vec(a), Value::Vec(b)) => {
+                for (a, b) in a.iter().zip(b) {
+                    match a.compare(b)? {
+                        Ordering::Equal => {}
+                        unequal => return Some(unequal),
+                    }
+                }
+                Some(a.len().cmp(&b.len()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The value as `{}` formats it, or `None` if its type has no `Display`
+    /// implementation.
+    pub fn display(&self) -> Option<String> {
+        match self {
+            Value::Bool(b) => Some(b.to_string()),
+            Value::Int(n) => Some(n.to_string()),
+            Value::Float(x, FloatTy::F32) => Some((*x as f32).to_string()),
+            Value::Float(x, FloatTy::F64) => Some(x.to_string()),
+            Value::Char(c) => Some(c.to_string()),
+            Value::Str(s) => Some(s.clone()),
+            Value::Ref(place) => place.get().display(),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the value as `{:?}` would in Rust, so strings are quoted and
+/// floats always show a fractional part: `"text"`, `4.567000000000007`, `1.0`.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(x, FloatTy::F32) => write!(f, "{:?}", *x as f32),
+            Value::Float(x, FloatTy::F64) => write!(f, "{:?}", x),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::Tuple(elems) => {
+                write!(f, "(")?;
+                write_list(f, elems)?;
+                if elems.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Value::Array(elems) | Value::Vec(elems) => {
+                write!(f, "[")?;
+                write_list(f, elems)?;
+                write!(f, "]")
+            }
+            Value::Struct(name, fields) | Value::Variant(_, name, fields) => {
+                write!(f, "{}", name)?;
+                write_fields(f, fields)
+            }
+            Value::Range(start, end, limits) => {
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                write!(f, "{}", limits.as_str())?;
+                match end {
+                    Some(end) => write!(f, "{}", end),
+                    None => Ok(()),
+                }
+            }
+            Value::Fn(decl) => write!(f, "fn {}", decl.name.name),
+            Value::Closure(..) => write!(f, "closure"),
+            Value::Ref(place) => write!(f, "{}", place.get()),
+        }
+    }
+}
+
+/// Closures are values of a type each of their own, which has no `Debug`
+/// or `PartialEq`; these are for [`Value`]'s.
+impl fmt::Debug for Captures {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Captures")
+    }
+}
+
+impl PartialEq for Captures {
+    fn eq(&self, other: &Captures) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, elems: &[Value]) -> fmt::Result {
+    for (i, elem) in elems.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", elem)?;
+    }
+    Ok(())
+}
+
+/// A struct's or variant's fields: `(a, b)` if positional, `{ x: 1 }` if
+/// named, and nothing for a unit struct.
+fn write_fields(f: &mut fmt::Formatter, fields: &[(String, Value)]) -> fmt::Result {
+    if fields.is_empty() {
+        return Ok(());
+    }
+    if fields[0].0 == "0" {
+        write!(f, "(")?;
+        for (i, (_, value)) in fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        return write!(f, ")");
+    }
+    write!(f, " {{ ")?;
+    for (i, (field, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: {}", field, value)?;
+    }
+    write!(f, " }}")
+}
@@ -0,0 +1,424 @@
+//! Control-flow graphs of function bodies.
+//!
+//! A [`Cfg`] has a node for each expression, which control reaches once the
+//! expression's operands have been evaluated, and an edge for each way it
+//! can go on from there. As in rustc, conditions are never looked at: an
+//! `if` may take either branch, and a `while` loop may end before any
+//! iteration, even `while true`. Only a `loop` cannot end but by `break`.
+//!
+//! What follows an expression that never finishes, such as `return`,
+//! `panic!()` or a call to a function declared `-> !`, gets a node of its
+//! own that no edge leads to, so the rest of the graph can be built as if
+//! control went on. Code after it is unreachable.
+
+use crate::ast::{
+    BinOp, Block, Closure, Expr, ExprKind, FnDecl, Ident, ItemKind, Path, Stmt, StmtKind, Ty,
+    TyKind,
+};
+use crate::span::Span;
+
+pub type NodeIndex = usize;
+
+#[derive(Debug)]
+pub struct Node<'a> {
+    pub kind: NodeKind<'a>,
+    pub succs: Vec<NodeIndex>,
+}
+
+#[derive(Debug)]
+pub enum NodeKind<'a> {
+    /// Where the body starts.
+    Entry,
+    /// Control falling off the end of the body, with its value.
+    End,
+    /// Where `return`, `?` on an error and the end of the body all lead.
+    Exit,
+    /// An expression, once its operands have been evaluated.
+    Expr(&'a Expr),
+    /// The start of each iteration of a loop.
+    LoopHead,
+    /// A `while` or `for` loop ending because its condition was false or
+    /// its iterator ran out. `head` is `while cond` or `for pat in iter`.
+    LoopEnds { keyword: &'static str, head: Span },
+    /// What follows an expression that never finishes.
+    Diverged(&'a Expr),
+}
+
+/// A statement or the tail of a block.
+#[derive(Clone, Copy, Debug)]
+pub enum Piece<'a> {
+    Stmt(&'a Stmt),
+    Tail(&'a Expr),
+}
+
+/// The graph of one body, with the functions and closures declared in it,
+/// whose bodies have graphs of their own. Each comes with the names of the
+/// `-> !` functions it can call.
+#[derive(Debug)]
+pub struct Cfg<'a> {
+    pub nodes: Vec<Node<'a>>,
+    pub entry: NodeIndex,
+    pub end: NodeIndex,
+    pub exit: NodeIndex,
+    /// The pieces of each block, with the node control is at just before
+    /// each one. Inner blocks come before the blocks around them.
+    pub blocks: Vec<Vec<(NodeIndex, Piece<'a>)>>,
+    pub fns: Vec<(&'a FnDecl, Vec<&'a str>)>,
+    pub closures: Vec<(&'a Closure, Vec<&'a str>)>,
+}
+
+impl<'a> Cfg<'a> {
+    /// The graph of a function body, or of a whole program, in which
+    /// calls to the functions `never_fns` do not return.
+    pub fn block(body: &'a Block, never_fns: Vec<&'a str>) -> Cfg<'a> {
+        let mut builder = Builder::new(never_fns);
+        let entry = builder.cfg.entry;
+        let last = builder.block(body, entry);
+        builder.add_edge(last, builder.cfg.end);
+        builder.cfg
+    }
+
+    /// The graph of a closure body.
+    pub fn expr(body: &'a Expr, never_fns: Vec<&'a str>) -> Cfg<'a> {
+        let mut builder = Builder::new(never_fns);
+        let entry = builder.cfg.entry;
+        let last = builder.expr(body, entry);
+        builder.add_edge(last, builder.cfg.end);
+        builder.cfg
+    }
+
+    /// Which nodes control can reach from the entry.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = vec![self.entry];
+        reachable[self.entry] = true;
+        while let Some(node) = stack.pop() {
+            for &succ in &self.nodes[node].succs {
+                if !reachable[succ] {
+                    reachable[succ] = true;
+                    stack.push(succ);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// A shortest path from `from` to `to`, both included, if `to` can be
+    /// reached at all.
+    pub fn path(&self, from: NodeIndex, to: NodeIndex) -> Option<Vec<NodeIndex>> {
+        let mut parents = vec![None; self.nodes.len()];
+        let mut queue = std::collections::VecDeque::from(vec![from]);
+        parents[from] = Some(from);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to];
+                let mut node = to;
+                while node != from {
+                    node = parents[node].expect("every queued node has a parent");
+                    path.push(node);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for &succ in &self.nodes[node].succs {
+                if parents[succ].is_none() {
+                    parents[succ] = Some(node);
+                    queue.push_back(succ);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Where `break` and `continue` inside a loop go.
+struct LoopScope<'a> {
+    label: Option<&'a str>,
+    head: NodeIndex,
+    exit: NodeIndex,
+}
+
+struct Builder<'a> {
+    cfg: Cfg<'a>,
+    /// The loops around the expression being added, innermost last.
+    loops: Vec<LoopScope<'a>>,
+    /// The `-> !` functions in scope.
+    never_fns: Vec<&'a str>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(never_fns: Vec<&'a str>) -> Builder<'a> {
+        let mut builder = Builder {
+            cfg: Cfg {
+                nodes: Vec::new(),
+                entry: 0,
+                end: 0,
+                exit: 0,
+                blocks: Vec::new(),
+                fns: Vec::new(),
+                closures: Vec::new(),
+            },
+            loops: Vec::new(),
+            never_fns,
+        };
+        builder.cfg.entry = builder.add_node(NodeKind::Entry, &[]);
+        builder.cfg.end = builder.add_node(NodeKind::End, &[]);
+        builder.cfg.exit = builder.add_node(NodeKind::Exit, &[builder.cfg.end]);
+        builder
+    }
+
+    fn add_node(&mut self, kind: NodeKind<'a>, preds: &[NodeIndex]) -> NodeIndex {
+        let index = self.cfg.nodes.len();
+        self.cfg.nodes.push(Node { kind, succs: Vec::new() });
+        for &pred in preds {
+            self.add_edge(pred, index);
+        }
+        index
+    }
+
+    fn add_edge(&mut self, from: NodeIndex, to: NodeIndex) {
+        self.cfg.nodes[from].succs.push(to);
+    }
+
+    fn node(&mut self, expr: &'a Expr, preds: &[NodeIndex]) -> NodeIndex {
+        self.add_node(NodeKind::Expr(expr), preds)
+    }
+
+    /// The node after `expr`, which never finishes.
+    fn diverge(&mut self, expr: &'a Expr) -> NodeIndex {
+        self.add_node(NodeKind::Diverged(expr), &[])
+    }
+
+    fn block(&mut self, block: &'a Block, pred: NodeIndex) -> NodeIndex {
+        // The functions declared in a block are in scope all through it.
+        let outer = self.never_fns.len();
+        for stmt in &block.stmts {
+            if let StmtKind::Item(item) = &stmt.kind {
+                match &item.kind {
+                    ItemKind::Fn(decl)
+                        if matches!(decl.ret, Some(Ty { kind: TyKind::Never, .. })) =>
+                    {
+                        self.never_fns.push(&decl.name.name);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let mut pieces = Vec::new();
+        let mut pred = pred;
+        for stmt in &block.stmts {
+            pieces.push((pred, Piece::Stmt(stmt)));
+            pred = self.stmt(stmt, pred);
+        }
+        if let Some(tail) = &block.tail {
+            pieces.push((pred, Piece::Tail(tail)));
+            pred = self.expr(tail, pred);
+        }
+        self.cfg.blocks.push(pieces);
+        self.never_fns.truncate(outer);
+        pred
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt, pred: NodeIndex) -> NodeIndex {
+        match &stmt.kind {
+            StmtKind::Let(local) => match &local.init {
+                Some(init) => self.expr(init, pred),
+                None => pred,
+            },
+            StmtKind::Item(item) => {
+                if let ItemKind::Fn(decl) = &item.kind {
+                    self.cfg.fns.push((decl, self.never_fns.clone()));
+                }
+                pred
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.expr(expr, pred),
+            StmtKind::Empty => pred,
+        }
+    }
+
+    /// Evaluate `exprs` in order, then `expr` itself.
+    fn seq(
+        &mut self,
+        expr: &'a Expr,
+        exprs: impl IntoIterator<Item = &'a Expr>,
+        pred: NodeIndex,
+    ) -> NodeIndex {
+        let last = exprs.into_iter().fold(pred, |pred, e| self.expr(e, pred));
+        self.node(expr, &[last])
+    }
+
+    fn expr(&mut self, expr: &'a Expr, pred: NodeIndex) -> NodeIndex {
+        match &expr.kind {
+            ExprKind::Lit(_) | ExprKind::Path(_) => self.node(expr, &[pred]),
+            ExprKind::Unary(_, operand)
+            | ExprKind::AddrOf(_, operand)
+            | ExprKind::Cast(operand, _)
+            | ExprKind::Field(operand, _)
+            | ExprKind::TupleField(operand, ..)
+            | ExprKind::Paren(operand) => self.seq(expr, [&**operand], pred),
+            // On an error, `?` returns it.
+            ExprKind::Try(operand) => {
+                let node = self.seq(expr, [&**operand], pred);
+                self.add_edge(node, self.cfg.exit);
+                node
+            }
+            // The right operand of `&&` and `||` may be skipped.
+            ExprKind::Binary(BinOp::And | BinOp::Or, lhs, rhs) => {
+                let lhs = self.expr(lhs, pred);
+                let rhs = self.expr(rhs, lhs);
+                self.node(expr, &[lhs, rhs])
+            }
+            ExprKind::Binary(_, lhs, rhs)
+            | ExprKind::Assign(lhs, rhs)
+            | ExprKind::AssignOp(_, lhs, rhs)
+            | ExprKind::Index(lhs, rhs)
+            | ExprKind::Repeat(lhs, rhs) => self.seq(expr, [&**lhs, &**rhs], pred),
+            ExprKind::Range(start, end, _) => {
+                self.seq(expr, start.iter().chain(end).map(|operand| &**operand), pred)
+            }
+            ExprKind::Call(callee, args) => {
+                let node = self.seq(expr, std::iter::once(&**callee).chain(args), pred);
+                match &callee.kind {
+                    ExprKind::Path(path) if self.is_never_fn(path) => self.diverge(expr),
+                    _ => node,
+                }
+            }
+            ExprKind::MethodCall(call) => {
+                self.seq(expr, std::iter::once(&call.receiver).chain(&call.args), pred)
+            }
+            ExprKind::Tuple(elems) | ExprKind::Array(elems) => self.seq(expr, elems, pred),
+            ExprKind::Struct(lit) => {
+                self.seq(expr, lit.fields.iter().map(|field| &field.value), pred)
+            }
+            ExprKind::MacCall(mac) => {
+                let node = self.seq(expr, &mac.args, pred);
+                match mac.name.name.as_str() {
+                    "panic" | "unreachable" | "todo" | "unimplemented" => self.diverge(expr),
+                    _ => node,
+                }
+            }
+            ExprKind::Block(block) => {
+                let last = self.block(block, pred);
+                self.node(expr, &[last])
+            }
+            ExprKind::If(cond, then, els) => {
+                let cond = self.expr(cond, pred);
+                let then = self.block(then, cond);
+                let els = match els {
+                    Some(els) => self.expr(els, cond),
+                    None => cond,
+                };
+                self.node(expr, &[then, els])
+            }
+            ExprKind::Let(..) => {
+                unreachable!("`if let` and `while let` are desugared by the parser")
+            }
+            ExprKind::Match(scrutinee, arms, _) => {
+                let scrutinee = self.expr(scrutinee, pred);
+                let ends: Vec<NodeIndex> = arms
+                    .iter()
+                    .map(|arm| {
+                        let guard = match &arm.guard {
+                            Some(guard) => self.expr(guard, scrutinee),
+                            None => scrutinee,
+                        };
+                        self.expr(&arm.body, guard)
+                    })
+                    .collect();
+                self.node(expr, &ends)
+            }
+            ExprKind::While(cond, body, label) => {
+                let head = self.add_node(NodeKind::LoopHead, &[pred]);
+                let exit = self.node(expr, &[]);
+                let cond_end = self.expr(cond, head);
+                let head_span = Span::new(expr.span.start, cond.span.end);
+                let ends = self.add_node(
+                    NodeKind::LoopEnds { keyword: "while", head: head_span },
+                    &[cond_end],
+                );
+                self.add_edge(ends, exit);
+                let last = self.in_loop(label, head, exit, |this| this.block(body, cond_end));
+                self.add_edge(last, head);
+                exit
+            }
+            // A `while let` leaves by the `break` in its `_` arm.
+            ExprKind::Loop(body, label, _) => {
+                let head = self.add_node(NodeKind::LoopHead, &[pred]);
+                let exit = self.node(expr, &[]);
+                let last = self.in_loop(label, head, exit, |this| this.block(body, head));
+                self.add_edge(last, head);
+                exit
+            }
+            ExprKind::For(_, iter, body, label) => {
+                let iter_end = self.expr(iter, pred);
+                let head = self.add_node(NodeKind::LoopHead, &[iter_end]);
+                let exit = self.node(expr, &[]);
+                let head_span = Span::new(expr.span.start, iter.span.end);
+                let ends =
+                    self.add_node(NodeKind::LoopEnds { keyword: "for", head: head_span }, &[head]);
+                self.add_edge(ends, exit);
+                let last = self.in_loop(label, head, exit, |this| this.block(body, head));
+                self.add_edge(last, head);
+                exit
+            }
+            ExprKind::Break(label, value) => {
+                let node = self.seq(expr, value.as_deref(), pred);
+                // The resolver reports a `break` outside a loop.
+                if let Some(target) = self.target(label.as_ref()) {
+                    self.add_edge(node, self.loops[target].exit);
+                }
+                self.diverge(expr)
+            }
+            ExprKind::Continue(label) => {
+                let node = self.node(expr, &[pred]);
+                if let Some(target) = self.target(label.as_ref()) {
+                    self.add_edge(node, self.loops[target].head);
+                }
+                self.diverge(expr)
+            }
+            ExprKind::Return(value) => {
+                let node = self.seq(expr, value.as_deref(), pred);
+                self.add_edge(node, self.cfg.exit);
+                self.diverge(expr)
+            }
+            ExprKind::Closure(closure) => {
+                self.cfg.closures.push((closure, self.never_fns.clone()));
+                self.node(expr, &[pred])
+            }
+        }
+    }
+
+    /// Add a loop's body, in which `break` leads to `exit` and `continue`
+    /// to `head`.
+    fn in_loop(
+        &mut self,
+        label: &'a Option<Ident>,
+        head: NodeIndex,
+        exit: NodeIndex,
+        f: impl FnOnce(&mut Self) -> NodeIndex,
+    ) -> NodeIndex {
+        let label = label.as_ref().map(|label| label.name.as_str());
+        self.loops.push(LoopScope { label, head, exit });
+        let last = f(self);
+        self.loops.pop();
+        last
+    }
+
+    /// Whether `path` names a function that never returns: one declared
+    /// `-> !`, or `std::process::exit`.
+    fn is_never_fn(&self, path: &Path) -> bool {
+        if let Some(ident) = path.as_ident() {
+            return self.never_fns.contains(&ident.name.as_str());
+        }
+        let names: Vec<&str> = path.segments.iter().map(|s| s.ident.name.as_str()).collect();
+        matches!(names.as_slice(), ["std", "process", "exit"] | ["process", "exit"])
+    }
+
+    /// The loop a `break` or `continue` with `label` refers to.
+    fn target(&self, label: Option<&Ident>) -> Option<usize> {
+        match label {
+            Some(label) => self.loops.iter().rposition(|l| l.label == Some(label.name.as_str())),
+            None => self.loops.len().checked_sub(1),
+        }
+    }
+}
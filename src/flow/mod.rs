@@ -0,0 +1,87 @@
+//! Checks that follow the paths control can take through a function body,
+//! before anything is known about its types.
+//!
+//! Each body is turned into a [`cfg::Cfg`]. A function that must return a
+//! value may not reach the end of its body without one; as in rustc, loop
+//! conditions are not looked at, which is why Rust has `loop`. The
+//! `while_true` lint suggests it for `while true`. Code no path reaches,
+//! such as a statement after a `return`, gets an `unreachable_code`
+//! warning.
+
+pub mod cfg;
+mod returns;
+mod unreachable;
+
+use std::collections::VecDeque;
+
+use self::cfg::{Cfg, NodeKind};
+use crate::ast::{Block, Expr, ExprKind, FnDecl, LitKind};
+use crate::diagnostic::{Applicability, Diagnostic, Lint};
+use crate::span::Span;
+
+/// Run the checks over `program` and every function and closure body in it.
+pub fn check(program: &Block) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut bodies: VecDeque<(Option<&FnDecl>, Cfg)> =
+        VecDeque::from(vec![(None, Cfg::block(program, Vec::new()))]);
+    while let Some((decl, cfg)) = bodies.pop_front() {
+        if let Some(FnDecl { name, ret: Some(ret), body, .. }) = decl {
+            diagnostics.extend(returns::check(name, ret, body, &cfg));
+        }
+        diagnostics.extend(unreachable::check(&cfg));
+        for node in &cfg.nodes {
+            if let NodeKind::Expr(expr) = node.kind {
+                diagnostics.extend(while_true(expr));
+            }
+        }
+        for (decl, never_fns) in cfg.fns {
+            bodies.push_back((Some(decl), Cfg::block(&decl.body, never_fns)));
+        }
+        for (closure, never_fns) in cfg.closures {
+            bodies.push_back((None, Cfg::expr(&closure.body, never_fns)));
+        }
+    }
+    diagnostics
+}
+
+/// The `while_true` lint: `while true` is `loop`, without the guarantee
+/// that the loop only ends by `break`.
+fn while_true(expr: &Expr) -> Option<Diagnostic> {
+    let (cond, label) = match &expr.kind {
+        ExprKind::While(cond, _, label)
+            if matches!(cond.kind, ExprKind::Lit(LitKind::Bool(true))) =>
+        {
+            (cond, label)
+        }
+        _ => return None,
+    };
+    let span = Span::new(expr.span.start, cond.span.end);
+    let replacement = match label {
+        Some(label) => format!("'{}: loop", label.name),
+        None => "loop".to_string(),
+    };
+    let diagnostic = Diagnostic::warning("denote infinite loops with `loop { ... }`")
+        .primary(span, "")
+        .suggest("use `loop`", span, replacement, Applicability::MachineApplicable)
+        .lint(Lint::WhileTrue);
+    Some(diagnostic)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Lint;
+    use crate::parser::parse_program;
+
+    #[test]
+    fn while_true_suggests_loop() {
+        let diagnostics =
+            super::check(&parse_program("let mut n = 0; while true { n += 1; }").unwrap());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].lint, Some(Lint::WhileTrue));
+        assert_eq!(diagnostics[0].message, "denote infinite loops with `loop { ... }`");
+        assert_eq!(diagnostics[0].suggestions[0].replacement, "loop");
+        let diagnostics = super::check(&parse_program("'a: while true { break 'a; }").unwrap());
+        assert_eq!(diagnostics[0].suggestions[0].replacement, "'a: loop");
+        assert!(super::check(&parse_program("let c = true; while c { }").unwrap()).is_empty());
+    }
+}
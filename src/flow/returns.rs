@@ -0,0 +1,142 @@
+//! Whether a function that must give a value can instead reach the end of
+//! its body without one.
+//!
+//! The type checker leaves this to the graph: a body without a tail, or
+//! ending in a `while` or `for` loop, is fine as long as no path reaches
+//! its end. The notes' `wait_for_process` is rejected because the analysis
+//! assumes `while true` can stop, while the same loop written with `loop`
+//! passes.
+
+use super::cfg::{Cfg, NodeKind};
+use crate::ast::{Block, ExprKind, Ident, Ty, TyKind};
+use crate::diagnostic::Diagnostic;
+use crate::print;
+use crate::span::Span;
+
+/// Check the body of the function `name`, which must give a value of type
+/// `ret`.
+pub(super) fn check(name: &Ident, ret: &Ty, body: &Block, cfg: &Cfg) -> Option<Diagnostic> {
+    if matches!(&ret.kind, TyKind::Tuple(elems) if elems.is_empty()) || !body.ends_without_value() {
+        return None;
+    }
+    let path = cfg.path(cfg.entry, cfg.end)?;
+    let never = ret.kind == TyKind::Never;
+    let ret_ty = print::ty(ret);
+    let found = format!("expected `{}`, found `()`", ret_ty);
+    let mut diagnostic = Diagnostic::error("mismatched types").code("E0308");
+    // The loop the analysis assumed could end, if any.
+    let ends = path.iter().rev().find_map(|&node| match cfg.nodes[node].kind {
+        NodeKind::LoopEnds { keyword, head } => Some((keyword, head)),
+        _ => None,
+    });
+    // A loop ending the body is pointed at by its head, not its whole body.
+    let tail_span = body.tail.as_deref().map(|tail| match &tail.kind {
+        ExprKind::While(cond, ..) => Span::new(tail.span.start, cond.span.end),
+        ExprKind::For(_, iter, ..) => Span::new(tail.span.start, iter.span.end),
+        _ => tail.span,
+    });
+    diagnostic = match tail_span {
+        Some(span) => diagnostic
+            .primary(span, found)
+            .secondary(ret.span, format!("expected `{}` because of return type", ret_ty)),
+        None => diagnostic.primary(ret.span, found).secondary(
+            name.span,
+            "implicitly returns `()` as its body has no tail or `return` expression",
+        ),
+    };
+    diagnostic = match ends {
+        Some((_, head)) if Some(head) == tail_span => diagnostic,
+        Some((keyword, head)) => diagnostic.secondary(
+            head,
+            if keyword == "while" {
+                "this loop is assumed to be able to end"
+            } else {
+                "this loop ends when its iterator runs out"
+            },
+        ),
+        None => diagnostic,
+    };
+    diagnostic = match ends {
+        Some(("while", _)) if never => diagnostic
+            .note("loop conditions are not looked at, so a `while` loop may always end")
+            .help("use `loop` for a loop that can only be left by `return` or `break`"),
+        Some(("while", _)) => diagnostic
+            .note("not all control paths return a value: loop conditions are not looked at, so a `while` loop may always end")
+            .help("use `loop` for a loop that can only be left by `return` or `break`"),
+        Some((keyword, _)) => diagnostic.note(format!("`{}` loops evaluate to unit type `()`", keyword)),
+        None if never => diagnostic.help(
+            "end the body with a `loop` that has no `break`, or with a call that never returns, such as `panic!()`",
+        ),
+        None => diagnostic,
+    };
+    Some(diagnostic)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::{Diagnostic, Level};
+    use crate::parser::parse_program;
+
+    fn errors(src: &str) -> Vec<Diagnostic> {
+        let diagnostics = super::super::check(&parse_program(src).unwrap());
+        diagnostics.into_iter().filter(|d| d.level == Level::Error).collect()
+    }
+
+    fn labels(diagnostic: &Diagnostic) -> Vec<&str> {
+        diagnostic.labels.iter().map(|l| l.message.as_str()).collect()
+    }
+
+    #[test]
+    fn loop_conditions_are_not_looked_at() {
+        let src = "fn wait_for_process(n: i32) -> i32 { while true { if n > 0 { return n; } } }";
+        let diagnostics = errors(src);
+        assert_eq!(diagnostics[0].code, Some("E0308"));
+        assert_eq!(diagnostics[0].message, "mismatched types");
+        assert_eq!(
+            labels(&diagnostics[0]),
+            ["expected `i32`, found `()`", "expected `i32` because of return type"]
+        );
+        assert_eq!(
+            diagnostics[0].notes[0].1,
+            "not all control paths return a value: \
+             loop conditions are not looked at, so a `while` loop may always end"
+        );
+        let src = "fn wait_for_process(n: i32) -> i32 { loop { if n > 0 { return n; } } }";
+        assert!(errors(src).is_empty());
+    }
+
+    #[test]
+    fn every_path_must_return() {
+        assert!(
+            errors("fn f(n: i32) -> i32 { if n > 0 { return 1; } else { return 2; } }").is_empty()
+        );
+        assert!(errors("fn f() { let x = 1; }").is_empty());
+
+        let diagnostics = errors("fn f(n: i32) -> i32 { let x = 1; }");
+        assert_eq!(
+            labels(&diagnostics[0]),
+            [
+                "expected `i32`, found `()`",
+                "implicitly returns `()` as its body has no tail or `return` expression"
+            ]
+        );
+        let diagnostics = errors("fn f(n: i32) -> i32 { for i in 0..n { return i; } }");
+        assert_eq!(diagnostics[0].notes[0].1, "`for` loops evaluate to unit type `()`");
+        let diagnostics = errors("fn f(n: i32) -> i32 { while n > 0 { return 1; } let x = 2; }");
+        assert_eq!(labels(&diagnostics[0])[2], "this loop is assumed to be able to end");
+    }
+
+    #[test]
+    fn never_functions_need_a_diverging_end() {
+        let diagnostics = errors("fn bad() -> ! { let x = 1; }");
+        assert_eq!(labels(&diagnostics[0])[0], "expected `!`, found `()`");
+        assert_eq!(
+            diagnostics[0].notes[0].1,
+            "end the body with a `loop` that has no `break`, \
+             or with a call that never returns, such as `panic!()`"
+        );
+        assert!(
+            errors("fn exit() -> ! { loop {} } fn good() -> ! { let x = 1; exit(); }").is_empty()
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! The `unreachable_code` lint: statements and block tails that no path
+//! through the body reaches.
+//!
+//! As in rustc, only the first piece of dead code after an expression that
+//! never finishes is reported, together with that expression. A block whose
+//! own dead code was reported does not make what follows it dead again.
+
+use super::cfg::{Cfg, NodeKind, Piece};
+use crate::ast::{Stmt, StmtKind};
+use crate::diagnostic::{Diagnostic, Lint};
+use crate::span::Span;
+
+pub(super) fn check(cfg: &Cfg) -> Vec<Diagnostic> {
+    let reachable = cfg.reachable();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for pieces in &cfg.blocks {
+        for pair in pieces.windows(2) {
+            let [(before, prev), (at, piece)] = [pair[0], pair[1]];
+            if !reachable[before] || reachable[at] || is_item(piece) {
+                continue;
+            }
+            // The expression control cannot get past: the one a node after
+            // it names, or else the whole of the piece before.
+            let cause = match cfg.nodes[at].kind {
+                NodeKind::Diverged(expr) => expr.span,
+                _ => cause_span(prev),
+            };
+            let reported = diagnostics
+                .iter()
+                .filter_map(Diagnostic::primary_span)
+                .any(|s| span(prev).contains(s));
+            if reported {
+                continue;
+            }
+            let what = match piece {
+                Piece::Stmt(_) => "statement",
+                Piece::Tail(_) => "expression",
+            };
+            let diagnostic = Diagnostic::warning(format!("unreachable {}", what))
+                .primary(span(piece), format!("unreachable {}", what))
+                .secondary(cause, "any code following this expression is unreachable")
+                .lint(Lint::UnreachableCode);
+            diagnostics.push(diagnostic);
+            break;
+        }
+    }
+    diagnostics
+}
+
+/// Items are declarations, not code that runs.
+fn is_item(piece: Piece) -> bool {
+    matches!(piece, Piece::Stmt(stmt) if matches!(stmt.kind, StmtKind::Item(_) | StmtKind::Empty))
+}
+
+/// The expression in a piece that control cannot get past: a `let`'s
+/// initializer, or the piece itself.
+fn cause_span(piece: Piece) -> Span {
+    match piece {
+        Piece::Stmt(Stmt { kind: StmtKind::Let(local), .. }) => {
+            local.init.as_ref().map_or_else(|| span(piece), |init| init.span)
+        }
+        _ => span(piece),
+    }
+}
+
+/// Where a piece is, without the `;` of an expression statement.
+fn span(piece: Piece) -> Span {
+    match piece {
+        Piece::Stmt(stmt) => match &stmt.kind {
+            StmtKind::Semi(expr) | StmtKind::Expr(expr) => expr.span,
+            _ => stmt.span,
+        },
+        Piece::Tail(expr) => expr.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Lint;
+    use crate::parser::parse_program;
+
+    /// The dead code reported in `src` and the expression it follows, for
+    /// each warning.
+    fn unreachable(src: &str) -> Vec<(&str, &str)> {
+        let diagnostics = super::super::check(&parse_program(src).unwrap());
+        diagnostics
+            .iter()
+            .map(|d| {
+                assert_eq!(d.lint, Some(Lint::UnreachableCode));
+                (d.labels[0].span.text(src), d.labels[1].span.text(src))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn code_after_jumps() {
+        let src = "fn f() -> i32 { return 1; let x = 2; x }";
+        assert_eq!(unreachable(src), [("let x = 2;", "return 1")]);
+        assert_eq!(unreachable("loop { break; let x = 1; }"), [("let x = 1;", "break")]);
+        let src = "let mut i = 0; while i < 3 { i += 1; continue; i += 5; }";
+        assert_eq!(unreachable(src), [("i += 5", "continue")]);
+        let src = "fn f() -> i32 { let x = return 1; x }";
+        assert_eq!(unreachable(src), [("x", "return 1")]);
+    }
+
+    #[test]
+    fn code_after_expressions_that_never_finish() {
+        assert_eq!(unreachable("fn f() { loop {} let x = 1; }"), [("let x = 1;", "loop {}")]);
+        let src = "fn exit(c: i32) -> ! { loop {} } fn f() { exit(1); let y = 2; }";
+        assert_eq!(unreachable(src), [("let y = 2;", "exit(1)")]);
+        let src = "fn f(c: bool) -> i32 { if c { return 1; } else { return 2; } 3 }";
+        assert_eq!(unreachable(src), [("3", "if c { return 1; } else { return 2; }")]);
+        assert!(unreachable("fn f() { loop { break; } let x = 1; }").is_empty());
+    }
+
+    #[test]
+    fn only_the_first_dead_code_is_reported() {
+        let src = "fn f() -> i32 { { return 1; let a = 1; } let b = 2; b }";
+        assert_eq!(unreachable(src), [("let a = 1;", "return 1")]);
+        let src = "fn f() -> i32 { return 1; let a = 1; let b = 2; b }";
+        assert_eq!(unreachable(src), [("let a = 1;", "return 1")]);
+    }
+}
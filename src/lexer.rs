@@ -0,0 +1,729 @@
+//! Splits source text into tokens.
+//!
+//! The lexer understands everything the notes use: integer literals with
+//! underscores and type suffixes (`0x00ff0000_00000000_u64`, `100u32`),
+//! floats (`1234.567`), character and string literals, loop labels
+//! (`'search`), the turbofish `::<`, the range operators and every operator
+//! from the "Precedence and Associativity" section. Each token carries the
+//! byte span it was read from.
+
+use std::fmt;
+
+use crate::ast::{FloatTy, IntTy};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    /// An integer literal. `value` has underscores and the suffix stripped.
+    Int {
+        value: u128,
+        suffix: Option<IntTy>,
+    },
+    /// A floating-point literal, or an integer literal with an `f32`/`f64` suffix.
+    Float {
+        value: f64,
+        suffix: Option<FloatTy>,
+    },
+    Char(char),
+    Str(String),
+    Ident(String),
+    Keyword(Keyword),
+    /// A lifetime or loop label, without the leading quote: `'search` is `Lifetime("search")`.
+    Lifetime(String),
+    /// `_`
+    Underscore,
+
+    // Arithmetic and bitwise operators.
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Not,
+    And,
+    Or,
+    Shl,
+    Shr,
+
+    // Logical operators.
+    AndAnd,
+    OrOr,
+
+    // Comparison operators.
+    EqEq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+
+    // Assignment operators.
+    Eq,
+    PlusEq,
+    MinusEq,
+    StarEq,
+    SlashEq,
+    PercentEq,
+    CaretEq,
+    AndEq,
+    OrEq,
+    ShlEq,
+    ShrEq,
+
+    // Range operators.
+    DotDot,
+    DotDotEq,
+    /// `...`, accepted only so the parser can point out that it is spelled `..=`.
+    DotDotDot,
+
+    // Other punctuation.
+    Dot,
+    Comma,
+    Semi,
+    Colon,
+    PathSep,
+    /// `::<`, the start of a turbofish such as `collect::<Vec<i32>>()`.
+    Turbofish,
+    RArrow,
+    FatArrow,
+    Question,
+    At,
+    Pound,
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+
+    Eof,
+}
+
+macro_rules! keywords {
+    ($($variant:ident => $text:literal,)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Keyword {
+            $($variant,)*
+        }
+
+        impl Keyword {
+            pub fn lookup(s: &str) -> Option<Keyword> {
+                match s {
+                    $($text => Some(Keyword::$variant),)*
+                    _ => None,
+                }
+            }
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Keyword::$variant => $text,)*
+                }
+            }
+        }
+    };
+}
+
+keywords! {
+    As => "as",
+    Break => "break",
+    Const => "const",
+    Continue => "continue",
+    Crate => "crate",
+    Else => "else",
+    Enum => "enum",
+    False => "false",
+    Fn => "fn",
+    For => "for",
+    If => "if",
+    Impl => "impl",
+    In => "in",
+    Let => "let",
+    Loop => "loop",
+    Match => "match",
+    Mod => "mod",
+    Move => "move",
+    Mut => "mut",
+    Pub => "pub",
+    Ref => "ref",
+    Return => "return",
+    SelfValue => "self",
+    SelfType => "Self",
+    Static => "static",
+    Struct => "struct",
+    Super => "super",
+    Trait => "trait",
+    True => "true",
+    Type => "type",
+    Use => "use",
+    Where => "where",
+    While => "while",
+}
+
+impl TokenKind {
+    /// How the token is spelled, for "expected `;`, found `}`" messages.
+    pub fn describe(&self) -> String {
+        let text = match self {
+            TokenKind::Int { .. } | TokenKind::Float { .. } => return "literal".to_string(),
+            TokenKind::Char(_) => return "character literal".to_string(),
+            TokenKind::Str(_) => return "string literal".to_string(),
+            TokenKind::Ident(name) => return format!("`{}`", name),
+            TokenKind::Keyword(kw) => return format!("keyword `{}`", kw.as_str()),
+            TokenKind::Lifetime(name) => return format!("label `'{}`", name),
+            TokenKind::Eof => return "end of input".to_string(),
+            TokenKind::Underscore => "_",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::Caret => "^",
+            TokenKind::Not => "!",
+            TokenKind::And => "&",
+            TokenKind::Or => "|",
+            TokenKind::Shl => "<<",
+            TokenKind::Shr => ">>",
+            TokenKind::AndAnd => "&&",
+            TokenKind::OrOr => "||",
+            TokenKind::EqEq => "==",
+            TokenKind::Ne => "!=",
+            TokenKind::Lt => "<",
+            TokenKind::Le => "<=",
+            TokenKind::Gt => ">",
+            TokenKind::Ge => ">=",
+            TokenKind::Eq => "=",
+            TokenKind::PlusEq => "+=",
+            TokenKind::MinusEq => "-=",
+            TokenKind::StarEq => "*=",
+            TokenKind::SlashEq => "/=",
+            TokenKind::PercentEq => "%=",
+            TokenKind::CaretEq => "^=",
+            TokenKind::AndEq => "&=",
+            TokenKind::OrEq => "|=",
+            TokenKind::ShlEq => "<<=",
+            TokenKind::ShrEq => ">>=",
+            TokenKind::DotDot => "..",
+            TokenKind::DotDotEq => "..=",
+            TokenKind::DotDotDot => "...",
+            TokenKind::Dot => ".",
+            TokenKind::Comma => ",",
+            TokenKind::Semi => ";",
+            TokenKind::Colon => ":",
+            TokenKind::PathSep => "::",
+            TokenKind::Turbofish => "::<",
+            TokenKind::RArrow => "->",
+            TokenKind::FatArrow => "=>",
+            TokenKind::Question => "?",
+            TokenKind::At => "@",
+            TokenKind::Pound => "#",
+            TokenKind::OpenParen => "(",
+            TokenKind::CloseParen => ")",
+            TokenKind::OpenBrace => "{",
+            TokenKind::CloseBrace => "}",
+            TokenKind::OpenBracket => "[",
+            TokenKind::CloseBracket => "]",
+        };
+        format!("`{}`", text)
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<10} {:?}", self.span.to_string(), self.kind)
+    }
+}
+
+/// Operators, longest first so that `<<=` wins over `<<` and `<`.
+const PUNCTUATION: &[(&str, TokenKind)] = &[
+    ("<<=", TokenKind::ShlEq),
+    (">>=", TokenKind::ShrEq),
+    ("...", TokenKind::DotDotDot),
+    ("..=", TokenKind::DotDotEq),
+    ("::<", TokenKind::Turbofish),
+    ("..", TokenKind::DotDot),
+    ("::", TokenKind::PathSep),
+    ("->", TokenKind::RArrow),
+    ("=>", TokenKind::FatArrow),
+    ("==", TokenKind::EqEq),
+    ("!=", TokenKind::Ne),
+    ("<=", TokenKind::Le),
+    (">=", TokenKind::Ge),
+    ("&&", TokenKind::AndAnd),
+    ("||", TokenKind::OrOr),
+    ("+=", TokenKind::PlusEq),
+    ("-=", TokenKind::MinusEq),
+    ("*=", TokenKind::StarEq),
+    ("/=", TokenKind::SlashEq),
+    ("%=", TokenKind::PercentEq),
+    ("^=", TokenKind::CaretEq),
+    ("&=", TokenKind::AndEq),
+    ("|=", TokenKind::OrEq),
+    ("<<", TokenKind::Shl),
+    (">>", TokenKind::Shr),
+    ("+", TokenKind::Plus),
+    ("-", TokenKind::Minus),
+    ("*", TokenKind::Star),
+    ("/", TokenKind::Slash),
+    ("%", TokenKind::Percent),
+    ("^", TokenKind::Caret),
+    ("!", TokenKind::Not),
+    ("&", TokenKind::And),
+    ("|", TokenKind::Or),
+    ("=", TokenKind::Eq),
+    ("<", TokenKind::Lt),
+    (">", TokenKind::Gt),
+    (".", TokenKind::Dot),
+    (",", TokenKind::Comma),
+    (";", TokenKind::Semi),
+    (":", TokenKind::Colon),
+    ("?", TokenKind::Question),
+    ("@", TokenKind::At),
+    ("#", TokenKind::Pound),
+    ("(", TokenKind::OpenParen),
+    (")", TokenKind::CloseParen),
+    ("{", TokenKind::OpenBrace),
+    ("}", TokenKind::CloseBrace),
+    ("[", TokenKind::OpenBracket),
+    ("]", TokenKind::CloseBracket),
+];
+
+/// Split `src` into tokens. The last token is always [`TokenKind::Eof`].
+pub fn tokenize(src: &str) -> Result<Vec<Token>, Diagnostic> {
+    let mut lexer = Lexer { src, pos: 0, tokens: Vec::new() };
+    lexer.run()?;
+    Ok(lexer.tokens)
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    tokens: Vec<Token>,
+}
+
+impl<'a> Lexer<'a> {
+    fn run(&mut self) -> Result<(), Diagnostic> {
+        loop {
+            self.skip_trivia()?;
+            let start = self.pos;
+            let c = match self.peek() {
+                Some(c) => c,
+                None => {
+                    self.push(TokenKind::Eof, start);
+                    return Ok(());
+                }
+            };
+            let kind = if c.is_ascii_digit() {
+                self.number()?
+            } else if is_ident_start(c) {
+                self.ident()
+            } else if c == '\'' {
+                self.quote()?
+            } else if c == '"' {
+                self.string()?
+            } else {
+                self.punctuation()?
+            };
+            self.push(kind, start);
+        }
+    }
+
+    fn push(&mut self, kind: TokenKind, start: usize) {
+        self.tokens.push(Token { kind, span: Span::new(start, self.pos) });
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat_while(&mut self, pred: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        while self.peek().is_some_and(&pred) {
+            self.bump();
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn error_here(&self, message: &str, label: &str) -> Diagnostic {
+        let len = self.peek().map_or(0, char::len_utf8);
+        Diagnostic::error(message).primary(Span::new(self.pos, self.pos + len), label)
+    }
+
+    /// Skip whitespace, `// line` comments and (nested) `/* block */` comments.
+    fn skip_trivia(&mut self) -> Result<(), Diagnostic> {
+        loop {
+            self.eat_while(char::is_whitespace);
+            if self.rest().starts_with("//") {
+                self.eat_while(|c| c != '\n');
+            } else if self.rest().starts_with("/*") {
+                let start = self.pos;
+                self.pos += 2;
+                let mut depth = 1;
+                while depth > 0 {
+                    if self.rest().starts_with("/*") {
+                        self.pos += 2;
+                        depth += 1;
+                    } else if self.rest().starts_with("*/") {
+                        self.pos += 2;
+                        depth -= 1;
+                    } else if self.bump().is_none() {
+                        return Err(Diagnostic::error("unterminated block comment")
+                            .code("E0758")
+                            .primary(Span::new(start, start + 2), "comment starts here"));
+                    }
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn ident(&mut self) -> TokenKind {
+        let text = self.eat_while(is_ident_continue);
+        if text == "_" {
+            TokenKind::Underscore
+        } else if let Some(kw) = Keyword::lookup(text) {
+            TokenKind::Keyword(kw)
+        } else {
+            TokenKind::Ident(text.to_string())
+        }
+    }
+
+    fn number(&mut self) -> Result<TokenKind, Diagnostic> {
+        let start = self.pos;
+        // After a `.`, digits are a tuple index: `coords.1` or `t.0.1`.
+        let tuple_index = matches!(self.tokens.last(), Some(Token { kind: TokenKind::Dot, .. }));
+
+        let radix = match (self.peek(), self.peek_nth(1)) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('o')) => 8,
+            (Some('0'), Some('b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.pos += 2;
+        }
+        let digits = self.eat_while(|c| c == '_' || c.is_digit(radix));
+
+        let mut is_float = false;
+        if radix == 10 && !tuple_index {
+            // `1.5` and `1.` are floats; `0..20`, `1.max(2)` and `x.0.1` are not.
+            if self.peek() == Some('.')
+                && self.peek_nth(1).is_none_or(|c| c != '.' && !is_ident_start(c))
+            {
+                self.bump();
+                self.eat_while(|c| c == '_' || c.is_ascii_digit());
+                is_float = true;
+            }
+            if matches!(self.peek(), Some('e') | Some('E')) {
+                let sign = matches!(self.peek_nth(1), Some('+') | Some('-'));
+                let digit_at = if sign { 2 } else { 1 };
+                if self.peek_nth(digit_at).is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += digit_at;
+                    self.eat_while(|c| c == '_' || c.is_ascii_digit());
+                    is_float = true;
+                }
+            }
+        }
+        let number_end = self.pos;
+        let suffix_start = self.pos;
+        let suffix = self.eat_while(is_ident_continue);
+        let suffix_span = Span::new(suffix_start, self.pos);
+
+        let float_suffix = FloatTy::from_name(suffix);
+        if is_float || (float_suffix.is_some() && radix == 10) {
+            let text: String = self.src[start..number_end].chars().filter(|&c| c != '_').collect();
+            if !suffix.is_empty() && float_suffix.is_none() {
+                return Err(Diagnostic::error(format!(
+                    "invalid suffix `{}` for float literal",
+                    suffix
+                ))
+                .primary(suffix_span, "invalid suffix")
+                .help("valid suffixes are `f32` and `f64`"));
+            }
+            let value = text.parse::<f64>().expect("float literal text is always well formed");
+            return Ok(TokenKind::Float { value, suffix: float_suffix });
+        }
+
+        let suffix_ty = match IntTy::from_name(suffix) {
+            _ if suffix.is_empty() => None,
+            Some(ty) if !tuple_index => Some(ty),
+            _ => {
+                let message = format!("invalid suffix `{}` for number literal", suffix);
+                return Err(Diagnostic::error(message)
+                    .primary(suffix_span, "invalid suffix")
+                    .help(
+                        "the suffix must be one of the numeric types (`u32`, `isize`, `f32`, etc.)",
+                    ));
+            }
+        };
+
+        let digits: String = digits.chars().filter(|&c| c != '_').collect();
+        let span = Span::new(start, number_end);
+        if digits.is_empty() {
+            return Err(Diagnostic::error("no valid digits found for number").primary(span, ""));
+        }
+        let value = u128::from_str_radix(&digits, radix).map_err(|_| {
+            Diagnostic::error("integer literal is too large")
+                .primary(span, "")
+                .note("value exceeds limit of `340282366920938463463374607431768211455`")
+        })?;
+        Ok(TokenKind::Int { value, suffix: suffix_ty })
+    }
+
+    /// A `'` starts either a character literal or a lifetime/label.
+    fn quote(&mut self) -> Result<TokenKind, Diagnostic> {
+        let start = self.pos;
+        let is_char = match (self.peek_nth(1), self.peek_nth(2)) {
+            (Some('\\'), _) => true,
+            (Some(_), Some('\'')) => true,
+            (Some(c), _) => !is_ident_start(c),
+            (None, _) => true,
+        };
+        self.bump();
+        if !is_char {
+            let name = self.eat_while(is_ident_continue);
+            return Ok(TokenKind::Lifetime(name.to_string()));
+        }
+        let c = match self.peek() {
+            Some('\\') => self.escape()?,
+            Some('\'') => {
+                return Err(Diagnostic::error("empty character literal")
+                    .primary(Span::new(start, self.pos + 1), "empty character literal"))
+            }
+            Some(_) => self.bump().unwrap(),
+            None => {
+                return Err(Diagnostic::error("unterminated character literal")
+                    .code("E0762")
+                    .primary(Span::new(start, self.pos), ""))
+            }
+        };
+        if self.peek() != Some('\'') {
+            return Err(Diagnostic::error("unterminated character literal")
+                .code("E0762")
+                .primary(Span::new(start, self.pos), "")
+                .help("if you meant to write a string literal, use double quotes"));
+        }
+        self.bump();
+        Ok(TokenKind::Char(c))
+    }
+
+    fn string(&mut self) -> Result<TokenKind, Diagnostic> {
+        let start = self.pos;
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.bump();
+                    return Ok(TokenKind::Str(value));
+                }
+                Some('\\') if self.peek_nth(1) == Some('\n') => {
+                    // A backslash at the end of a line skips the newline and leading whitespace.
+                    self.pos += 2;
+                    self.eat_while(char::is_whitespace);
+                }
+                Some('\\') => value.push(self.escape()?),
+                Some(_) => value.push(self.bump().unwrap()),
+                None => {
+                    return Err(Diagnostic::error("unterminated double quote string")
+                        .code("E0765")
+                        .primary(Span::new(start, start + 1), ""))
+                }
+            }
+        }
+    }
+
+    /// Read an escape sequence starting at the backslash.
+    fn escape(&mut self) -> Result<char, Diagnostic> {
+        let start = self.pos;
+        self.bump();
+        let c = match self.bump() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('\\') => '\\',
+            Some('0') => '\0',
+            Some('\'') => '\'',
+            Some('"') => '"',
+            Some('x') => {
+                let digits = self.rest().get(..2).unwrap_or("");
+                let value = u8::from_str_radix(digits, 16).ok().filter(|&v| v <= 0x7f);
+                match value {
+                    Some(value) => {
+                        self.pos += 2;
+                        value as char
+                    }
+                    None => {
+                        return Err(Diagnostic::error("invalid `\\x` escape")
+                            .primary(Span::new(start, self.pos), "")
+                            .help("a `\\x` escape takes two hex digits in the range `00..=7F`"))
+                    }
+                }
+            }
+            Some('u') if self.peek() == Some('{') => {
+                self.bump();
+                let digits = self.eat_while(|c| c.is_ascii_hexdigit() || c == '_');
+                let closed = self.peek() == Some('}');
+                if closed {
+                    self.bump();
+                }
+                let span = Span::new(start, self.pos);
+                let value = u32::from_str_radix(&digits.replace('_', ""), 16).ok();
+                match value.and_then(std::char::from_u32) {
+                    Some(c) if closed => c,
+                    _ => {
+                        return Err(Diagnostic::error("invalid unicode character escape")
+                            .primary(span, "")
+                            .help("unicode escapes look like `\\u{1F600}`, up to six hex digits"))
+                    }
+                }
+            }
+            _ => {
+                return Err(Diagnostic::error("unknown character escape")
+                    .primary(Span::new(start, self.pos), "unknown character escape"))
+            }
+        };
+        Ok(c)
+    }
+
+    fn punctuation(&mut self) -> Result<TokenKind, Diagnostic> {
+        let rest = self.rest();
+        for (text, kind) in PUNCTUATION {
+            if rest.starts_with(text) {
+                self.pos += text.len();
+                return Ok(kind.clone());
+            }
+        }
+        let c = self.peek().unwrap();
+        let mut err = self.error_here(&format!("unknown start of token: {}", c.escape_debug()), "");
+        if c == '~' {
+            err = err.help("Rust uses `!` for bitwise NOT, not `~`");
+        }
+        Err(err)
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic()
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        let mut tokens: Vec<_> = tokenize(src).unwrap().into_iter().map(|t| t.kind).collect();
+        assert_eq!(tokens.pop(), Some(TokenKind::Eof));
+        tokens
+    }
+
+    fn int(value: u128, suffix: Option<IntTy>) -> TokenKind {
+        TokenKind::Int { value, suffix }
+    }
+
+    #[test]
+    fn integer_literals() {
+        assert_eq!(kinds("0x00ff0000_00000000_u64"), [int(0x00ff0000_00000000, Some(IntTy::U64))]);
+        assert_eq!(
+            kinds("100u32 1_000 0b1010 0o17"),
+            [int(100, Some(IntTy::U32)), int(1000, None), int(10, None), int(15, None)]
+        );
+        assert_eq!(kinds("2f32"), [TokenKind::Float { value: 2.0, suffix: Some(FloatTy::F32) }]);
+    }
+
+    #[test]
+    fn float_literals_and_tuple_indices() {
+        assert_eq!(kinds("1234.567"), [TokenKind::Float { value: 1234.567, suffix: None }]);
+        assert_eq!(kinds("1e3"), [TokenKind::Float { value: 1000.0, suffix: None }]);
+        // `1..2` is a range, and `t.0.1` two tuple indices.
+        assert_eq!(kinds("1..2"), [int(1, None), TokenKind::DotDot, int(2, None)]);
+        assert_eq!(
+            kinds("t.0.1"),
+            [
+                TokenKind::Ident("t".to_string()),
+                TokenKind::Dot,
+                int(0, None),
+                TokenKind::Dot,
+                int(1, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn chars_strings_and_labels() {
+        assert_eq!(
+            kinds(r"'a' '\n' '\u{1F600}'"),
+            [TokenKind::Char('a'), TokenKind::Char('\n'), TokenKind::Char('\u{1F600}')]
+        );
+        assert_eq!(kinds(r#""tab\there""#), [TokenKind::Str("tab\there".to_string())]);
+        assert_eq!(
+            kinds("'search: loop"),
+            [
+                TokenKind::Lifetime("search".to_string()),
+                TokenKind::Colon,
+                TokenKind::Keyword(Keyword::Loop)
+            ]
+        );
+    }
+
+    #[test]
+    fn longest_operator_wins() {
+        assert_eq!(
+            kinds("<<= ..= ::< && a||b"),
+            [
+                TokenKind::ShlEq,
+                TokenKind::DotDotEq,
+                TokenKind::Turbofish,
+                TokenKind::AndAnd,
+                TokenKind::Ident("a".to_string()),
+                TokenKind::OrOr,
+                TokenKind::Ident("b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_carry_byte_spans() {
+        let tokens = tokenize("let é = 10u8; // done\n/* a /* nested */ comment */ x").unwrap();
+        let spans: Vec<_> = tokens.iter().map(|t| (t.span.start, t.span.end)).collect();
+        assert_eq!(spans, [(0, 3), (4, 6), (7, 8), (9, 13), (13, 14), (52, 53), (53, 53)]);
+    }
+
+    #[test]
+    fn errors() {
+        let message = |src| tokenize(src).unwrap_err().message;
+        assert_eq!(message("\"open"), "unterminated double quote string");
+        assert_eq!(message("/* open"), "unterminated block comment");
+        assert_eq!(message("''"), "empty character literal");
+        assert_eq!(message("1.5u8"), "invalid suffix `u8` for float literal");
+        assert_eq!(message("0x"), "no valid digits found for number");
+        assert_eq!(message(r"'\q'"), "unknown character escape");
+        let error = tokenize("1 $ 2").unwrap_err();
+        assert_eq!(error.primary_span(), Some(Span::new(2, 3)));
+    }
+}
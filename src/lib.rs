@@ -0,0 +1,107 @@
+//! A small Rust-flavoured expression language.
+//!
+//! The crate models the semantics described in `notes.md`: in Rust,
+//! expressions do all the work. Source text is split into tokens by
+//! [`lexer`], parsed into an [`ast::Block`] by [`parser`], which
+//! [`desugar`]s `if let` and `while let` into `match`, checked by
+//! [`typeck`], [`flow`] and [`borrowck`], then evaluated by [`eval`]. A
+//! program is the inside of a block, so its value is that of its tail
+//! expression.
+//!
+//! ```
+//! let run = |src| expressions::run(src).unwrap().to_string();
+//!
+//! assert_eq!(run("let fahr = 212; 5 * (fahr - 32) / 9"), "100");
+//! assert_eq!(run("let x = 1234.567 % 10.0; x"), "4.567000000000007");
+//! assert_eq!(run("let hi: u8 = 0xe0; let lo = !hi; lo == 0x1f"), "true");
+//! assert_eq!(run("let msg = { let a = 2; a * 21 }; msg"), "42");
+//! assert_eq!(run("{ 42; }"), "()");
+//! assert_eq!(run("let o = Some(3); if let Some(n) = o { n * 2 } else { 0 }"), "6");
+//! ```
+
+pub mod ast;
+pub mod borrowck;
+pub mod desugar;
+pub mod diagnostic;
+pub mod eval;
+pub mod flow;
+pub mod lexer;
+pub mod parser;
+pub mod print;
+pub mod resolve;
+pub mod span;
+pub mod typeck;
+
+#[cfg(test)]
+mod test_util;
+
+pub use crate::diagnostic::Diagnostic;
+pub use crate::span::Span;
+
+/// Run the static checks over a parsed program, returning the types found,
+/// which evaluation needs, and every error and warning.
+pub fn check(program: &ast::Block) -> (typeck::TypeckResults, Vec<Diagnostic>) {
+    let (types, mut diagnostics) = typeck::check(program);
+    diagnostics.extend(flow::check(program));
+    let resolver = resolve::Resolver::new(program, &types);
+    diagnostics.extend_from_slice(resolver.diagnostics());
+    // The flow checks assume a well-typed program, as in rustc.
+    if !diagnostics.iter().any(Diagnostic::is_error) {
+        diagnostics.extend(borrowck::check(program, &types, &resolver));
+    }
+    (types, diagnostics)
+}
+
+/// Parse, check and evaluate `src`, stopping at the first error.
+pub fn run(src: &str) -> Result<eval::Value, Diagnostic> {
+    let program = parser::parse_program(src)?;
+    let (types, diagnostics) = check(&program);
+    if let Some(error) = diagnostics.into_iter().find(Diagnostic::is_error) {
+        return Err(error);
+    }
+    eval::eval_program(&program, &types)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(src: &str) -> Vec<Diagnostic> {
+        let program = parser::parse_program(src).unwrap();
+        check(&program).1.into_iter().filter(Diagnostic::is_error).collect()
+    }
+
+    #[test]
+    fn runs_the_notes_examples() {
+        let run = |src| run(src).unwrap().to_string();
+        assert_eq!(run("let fahr = 212; 5 * (fahr - 32) / 9"), "100");
+        assert_eq!(run("let hi: u8 = 0xe0; let lo = !hi; lo"), "31");
+        assert_eq!(run("let x = 3; if x > 2 { \"big\" } else { \"small\" }"), "\"big\"");
+        assert_eq!(run("let n = 7u8; n as i64 * -2"), "-14");
+        assert_eq!(run("let add = |a: i32, b: i32| a + b; add(2, 3)"), "5");
+        assert_eq!(run("let mut i = 0; while i < 10 { i += 3; } i"), "12");
+    }
+
+    #[test]
+    fn check_reports_every_error() {
+        let errors = errors("let a: i32 = true; let b: bool = 1;");
+        let codes: Vec<_> = errors.iter().map(|e| e.code).collect();
+        assert_eq!(codes, [Some("E0308"), Some("E0308")]);
+    }
+
+    #[test]
+    fn run_stops_at_the_first_error() {
+        let error = run("let a: i32 = true; a").unwrap_err();
+        assert_eq!(error.code, Some("E0308"));
+        assert_eq!(run("let x = ;").unwrap_err().message, "expected expression, found `;`");
+    }
+
+    #[test]
+    fn runs_programs_that_build_strings() {
+        let src = "let mut greeting = String::new(); greeting.push_str(\"hello\"); \
+                   let world = String::from(\"big\") + \" world\"; \
+                   let strings = vec![greeting, world, \"!\".to_string()]; \
+                   let mut total = 0; for s in &strings { total += s.len(); } (strings, total)";
+        assert_eq!(run(src).unwrap().to_string(), "([\"hello\", \"big world\", \"!\"], 15)");
+    }
+}
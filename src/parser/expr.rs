@@ -0,0 +1,806 @@
+//! Expressions.
+
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use super::precedence::{self, Assoc, InfixOp, Prec};
+use super::{PResult, Parser};
+use crate::ast::{
+    BinOp, CaptureBy, Closure, Expr, ExprKind, FieldExpr, Ident, LitKind, LoopSource, MacCall,
+    MatchSource, MethodCall, Mutability, Param, Path, PathSegment, RangeLimits, StructExpr, UnOp,
+};
+use crate::diagnostic::{Applicability, Diagnostic};
+use crate::lexer::{Keyword, TokenKind};
+use crate::span::Span;
+
+impl Parser {
+    pub fn expr(&mut self) -> PResult<Expr> {
+        self.expr_prec(0)
+    }
+
+    /// An expression in a position followed by a block, like the condition
+    /// of an `if`, where `x {` must not be read as a struct literal.
+    pub(super) fn expr_no_struct(&mut self) -> PResult<Expr> {
+        self.with_struct_literals(false, Parser::expr)
+    }
+
+    pub(super) fn with_struct_literals<T>(
+        &mut self,
+        allowed: bool,
+        f: impl FnOnce(&mut Parser) -> PResult<T>,
+    ) -> PResult<T> {
+        let saved = std::mem::replace(&mut self.no_struct_literal, !allowed);
+        let result = f(self);
+        self.no_struct_literal = saved;
+        result
+    }
+
+    /// An expression nested inside delimiters, where struct literals are fine again.
+    fn nested_expr(&mut self) -> PResult<Expr> {
+        self.with_struct_literals(true, Parser::expr)
+    }
+
+    fn expr_prec(&mut self, min_prec: Prec) -> PResult<Expr> {
+        let lhs = self.prefix_expr()?;
+        self.binary_rest(lhs, min_prec)
+    }
+
+    /// Precedence climbing: given an operand, keep absorbing binary operators
+    /// that bind at least as tightly as `min_prec`.
+    pub(super) fn binary_rest(&mut self, mut lhs: Expr, min_prec: Prec) -> PResult<Expr> {
+        // The non-chaining operator that produced `lhs`, if any, and where it was.
+        let mut last_non_chaining: Option<(&'static str, Span)> = None;
+        // Each operator nests the operands so far one level deeper.
+        let outer = self.nesting;
+
+        while let Some(info) = precedence::infix(self.peek()) {
+            if info.prec < min_prec {
+                break;
+            }
+            self.deeper()?;
+            let op_span = self.span();
+            if info.level.assoc == Assoc::NonChaining {
+                if let Some((name, first)) = last_non_chaining {
+                    if name == info.level.name {
+                        return Err(self.chained_operator_error(info.prec, first, op_span, &lhs));
+                    }
+                }
+                last_non_chaining = Some((info.level.name, op_span));
+            }
+            self.bump();
+
+            lhs = match info.op {
+                InfixOp::Cast => self.cast_expr(lhs)?,
+                InfixOp::Range(limits) => {
+                    let end = self.range_end(limits, op_span)?;
+                    let span = end.as_ref().map_or(lhs.span.to(op_span), |e| lhs.span.to(e.span));
+                    self.mk(ExprKind::Range(Some(Box::new(lhs)), end.map(Box::new), limits), span)
+                }
+                op => {
+                    let rhs = self.expr_prec(info.prec + 1)?;
+                    let span = lhs.span.to(rhs.span);
+                    let (lhs, rhs) = (Box::new(lhs), Box::new(rhs));
+                    let kind = match op {
+                        InfixOp::Binary(op) => ExprKind::Binary(op, lhs, rhs),
+                        InfixOp::Assign => ExprKind::Assign(lhs, rhs),
+                        InfixOp::AssignOp(op) => ExprKind::AssignOp(op, lhs, rhs),
+                        InfixOp::Cast | InfixOp::Range(_) => unreachable!("handled above"),
+                    };
+                    self.mk(kind, span)
+                }
+            };
+        }
+        self.nesting = outer;
+        Ok(lhs)
+    }
+
+    fn cast_expr(&mut self, lhs: Expr) -> PResult<Expr> {
+        // `x as usize < y` reads `usize<` as the start of generic arguments.
+        if let (TokenKind::Ident(name), next @ (TokenKind::Lt | TokenKind::Shl)) =
+            (self.peek(), self.peek_nth(1))
+        {
+            let what = if *next == TokenKind::Lt { "comparison" } else { "shift" };
+            let ty_span = self.span();
+            let cast = lhs.span.to(ty_span);
+            return Err(Diagnostic::error(format!(
+                "{} is interpreted as a start of generic arguments for `{}`, not a {}",
+                next.describe(),
+                name,
+                what,
+            ))
+            .primary(self.tokens[self.pos + 1].span, "interpreted as generic arguments")
+            .secondary(cast, format!("this cast is followed by a {}", what))
+            .suggest(
+                "try surrounding the cast with parentheses",
+                cast,
+                format!("({})", self.snippet(cast)),
+                Applicability::MachineApplicable,
+            ));
+        }
+        let ty = self.ty()?;
+        let span = lhs.span.to(ty.span);
+        Ok(self.mk(ExprKind::Cast(Box::new(lhs), ty), span))
+    }
+
+    /// The optional end operand of `a..` / `..b`; `..=` requires one.
+    fn range_end(&mut self, limits: RangeLimits, op_span: Span) -> PResult<Option<Expr>> {
+        if self.can_begin_expr() {
+            return Ok(Some(self.expr_prec(precedence::range_prec() + 1)?));
+        }
+        if limits == RangeLimits::Closed {
+            return Err(Diagnostic::error("inclusive range with no end")
+                .code("E0586")
+                .primary(op_span, "this `..=` needs an end")
+                .help("use `..` instead"));
+        }
+        Ok(None)
+    }
+
+    /// Prefix operators, then a postfix chain.
+    fn prefix_expr(&mut self) -> PResult<Expr> {
+        self.nested(Parser::prefix_expr_inner)
+    }
+
+    fn prefix_expr_inner(&mut self) -> PResult<Expr> {
+        let start = self.span();
+        let unary = match self.peek() {
+            TokenKind::Minus => Some(UnOp::Neg),
+            TokenKind::Not => Some(UnOp::Not),
+            TokenKind::Star => Some(UnOp::Deref),
+            _ => None,
+        };
+        if let Some(op) = unary {
+            self.bump();
+            let operand = self.prefix_expr()?;
+            let span = start.to(operand.span);
+            return Ok(self.mk(ExprKind::Unary(op, Box::new(operand)), span));
+        }
+
+        match self.peek() {
+            TokenKind::And | TokenKind::AndAnd => {
+                // `&&x` is a reference to a reference.
+                let double = self.check(&TokenKind::AndAnd);
+                self.bump();
+                let mutability =
+                    if self.eat_keyword(Keyword::Mut) { Mutability::Mut } else { Mutability::Not };
+                let operand = self.prefix_expr()?;
+                let span = start.to(operand.span);
+                let mut expr = self.mk(ExprKind::AddrOf(mutability, Box::new(operand)), span);
+                if double {
+                    expr = self.mk(ExprKind::AddrOf(Mutability::Not, Box::new(expr)), span);
+                }
+                Ok(expr)
+            }
+            TokenKind::DotDot | TokenKind::DotDotEq => {
+                let limits = if self.check(&TokenKind::DotDot) {
+                    RangeLimits::HalfOpen
+                } else {
+                    RangeLimits::Closed
+                };
+                self.bump();
+                let end = self.range_end(limits, start)?;
+                let span = end.as_ref().map_or(start, |e| start.to(e.span));
+                Ok(self.mk(ExprKind::Range(None, end.map(Box::new), limits), span))
+            }
+            TokenKind::Plus => Err(Diagnostic::error("expected expression, found `+`")
+                .primary(start, "expected expression")
+                .help("Rust has no unary `+` operator; remove the `+`")),
+            _ => {
+                let primary = self.primary_expr()?;
+                self.postfix_expr(primary)
+            }
+        }
+    }
+
+    /// Method calls, field access, calls, indexing and `?`, which bind tightest of all.
+    pub(super) fn postfix_expr(&mut self, mut expr: Expr) -> PResult<Expr> {
+        let outer = self.nesting;
+        loop {
+            if matches!(
+                self.peek(),
+                TokenKind::Dot
+                    | TokenKind::OpenParen
+                    | TokenKind::OpenBracket
+                    | TokenKind::Question
+            ) {
+                self.deeper()?;
+            }
+            expr = match self.peek() {
+                TokenKind::Dot => {
+                    self.bump();
+                    self.dot_suffix(expr)?
+                }
+                TokenKind::OpenParen => {
+                    self.bump();
+                    let args = self.comma_separated(&TokenKind::CloseParen, Parser::nested_expr)?;
+                    let span = expr.span.to(self.prev_span);
+                    self.mk(ExprKind::Call(Box::new(expr), args), span)
+                }
+                TokenKind::OpenBracket => {
+                    self.bump();
+                    let index = self.nested_expr()?;
+                    self.expect(&TokenKind::CloseBracket)?;
+                    let span = expr.span.to(self.prev_span);
+                    self.mk(ExprKind::Index(Box::new(expr), Box::new(index)), span)
+                }
+                TokenKind::Question => {
+                    let span = expr.span.to(self.bump().span);
+                    self.mk(ExprKind::Try(Box::new(expr)), span)
+                }
+                _ => {
+                    self.nesting = outer;
+                    return Ok(expr);
+                }
+            };
+        }
+    }
+
+    fn dot_suffix(&mut self, receiver: Expr) -> PResult<Expr> {
+        match self.peek().clone() {
+            TokenKind::Int { value, suffix: None } => {
+                let span = self.bump().span;
+                let index = usize::try_from(value).unwrap_or(usize::MAX);
+                let full = receiver.span.to(span);
+                Ok(self.mk(ExprKind::TupleField(Box::new(receiver), index, span), full))
+            }
+            TokenKind::Ident(_) => {
+                let name = self.ident()?;
+                let generics =
+                    if self.eat(&TokenKind::Turbofish) { self.generic_args()? } else { Vec::new() };
+                if !generics.is_empty() || self.check(&TokenKind::OpenParen) {
+                    self.expect(&TokenKind::OpenParen)?;
+                    let args = self.comma_separated(&TokenKind::CloseParen, Parser::nested_expr)?;
+                    let span = receiver.span.to(self.prev_span);
+                    let call = MethodCall { receiver, method: name, generics, args };
+                    Ok(self.mk(ExprKind::MethodCall(Box::new(call)), span))
+                } else {
+                    let span = receiver.span.to(name.span);
+                    Ok(self.mk(ExprKind::Field(Box::new(receiver), name), span))
+                }
+            }
+            _ => Err(self.unexpected("field name or method call")),
+        }
+    }
+
+    fn primary_expr(&mut self) -> PResult<Expr> {
+        let start = self.span();
+        let lit = match self.peek().clone() {
+            TokenKind::Int { value, suffix } => Some(LitKind::Int(value, suffix)),
+            TokenKind::Float { value, suffix } => Some(LitKind::Float(value, suffix)),
+            TokenKind::Char(c) => Some(LitKind::Char(c)),
+            TokenKind::Str(s) => Some(LitKind::Str(s)),
+            TokenKind::Keyword(Keyword::True) => Some(LitKind::Bool(true)),
+            TokenKind::Keyword(Keyword::False) => Some(LitKind::Bool(false)),
+            _ => None,
+        };
+        if let Some(lit) = lit {
+            self.bump();
+            return Ok(self.mk(ExprKind::Lit(lit), start));
+        }
+
+        match self.peek() {
+            TokenKind::OpenParen => self.paren_expr(),
+            TokenKind::OpenBracket => {
+                self.bump();
+                let kind = self.array_contents()?;
+                Ok(self.mk(kind, start.to(self.prev_span)))
+            }
+            TokenKind::OpenBrace
+            | TokenKind::Lifetime(_)
+            | TokenKind::Keyword(Keyword::If)
+            | TokenKind::Keyword(Keyword::Match)
+            | TokenKind::Keyword(Keyword::Loop)
+            | TokenKind::Keyword(Keyword::While)
+            | TokenKind::Keyword(Keyword::For) => self.block_like_expr(),
+            TokenKind::Or | TokenKind::OrOr | TokenKind::Keyword(Keyword::Move) => {
+                self.closure_expr()
+            }
+            TokenKind::Keyword(Keyword::Break) => {
+                self.bump();
+                let label = self.label();
+                let value = if self.can_begin_expr() { Some(Box::new(self.expr()?)) } else { None };
+                let span = start.to(self.prev_span);
+                Ok(self.mk(ExprKind::Break(label, value), span))
+            }
+            TokenKind::Keyword(Keyword::Continue) => {
+                self.bump();
+                let label = self.label();
+                let span = start.to(self.prev_span);
+                Ok(self.mk(ExprKind::Continue(label), span))
+            }
+            TokenKind::Keyword(Keyword::Return) => {
+                self.bump();
+                let value = if self.can_begin_expr() { Some(Box::new(self.expr()?)) } else { None };
+                let span = start.to(self.prev_span);
+                Ok(self.mk(ExprKind::Return(value), span))
+            }
+            TokenKind::Ident(_)
+            | TokenKind::PathSep
+            | TokenKind::Keyword(Keyword::SelfValue)
+            | TokenKind::Keyword(Keyword::SelfType)
+            | TokenKind::Keyword(Keyword::Crate)
+            | TokenKind::Keyword(Keyword::Super) => {
+                let path = self.expr_path()?;
+                if self.check(&TokenKind::Not) {
+                    if let Some(name) = path.as_ident().cloned() {
+                        return self.mac_call(name);
+                    }
+                }
+                if self.check(&TokenKind::OpenBrace) && self.looks_like_struct_literal() {
+                    return self.struct_expr(path);
+                }
+                let span = path.span;
+                Ok(self.mk(ExprKind::Path(path), span))
+            }
+            _ => Err(self.unexpected("expression")),
+        }
+    }
+
+    /// Blocks, `if`, `match` and the loops, optionally preceded by a label.
+    pub(super) fn block_like_expr(&mut self) -> PResult<Expr> {
+        let start = self.span();
+        let label = match self.peek().clone() {
+            TokenKind::Lifetime(name) => {
+                let label = Ident::new(name, self.bump().span);
+                self.expect(&TokenKind::Colon)?;
+                if !matches!(
+                    self.peek(),
+                    TokenKind::Keyword(Keyword::Loop | Keyword::While | Keyword::For)
+                ) {
+                    return Err(self.unexpected("`loop`, `while` or `for` after a label"));
+                }
+                Some(label)
+            }
+            _ => None,
+        };
+
+        let kind = match self.peek() {
+            TokenKind::OpenBrace => ExprKind::Block(self.block()?),
+            TokenKind::Keyword(Keyword::If) => return self.if_expr(),
+            TokenKind::Keyword(Keyword::Match) => {
+                self.bump();
+                let scrutinee = self.expr_no_struct()?;
+                let arms = self.match_arms()?;
+                ExprKind::Match(Box::new(scrutinee), arms, MatchSource::Normal)
+            }
+            TokenKind::Keyword(Keyword::Loop) => {
+                self.bump();
+                ExprKind::Loop(self.block()?, label, LoopSource::Loop)
+            }
+            TokenKind::Keyword(Keyword::While) => {
+                self.bump();
+                let cond = self.cond()?;
+                ExprKind::While(Box::new(cond), self.block()?, label)
+            }
+            TokenKind::Keyword(Keyword::For) => {
+                self.bump();
+                let pat = self.pat()?;
+                if !self.eat_keyword(Keyword::In) {
+                    return Err(self.unexpected("`in`"));
+                }
+                let iter = self.expr_no_struct()?;
+                ExprKind::For(Box::new(pat), Box::new(iter), self.block()?, label)
+            }
+            _ => return Err(self.unexpected("expression")),
+        };
+        let span = start.to(self.prev_span);
+        Ok(self.mk(kind, span))
+    }
+
+    fn if_expr(&mut self) -> PResult<Expr> {
+        let start = self.expect(&TokenKind::Keyword(Keyword::If))?;
+        let cond = self.cond()?;
+        if !self.check(&TokenKind::OpenBrace) {
+            return Err(self
+                .unexpected("`{`")
+                .secondary(cond.span, "this `if` expression has a condition, but no block"));
+        }
+        let then = self.block()?;
+        let els = if self.eat_keyword(Keyword::Else) {
+            let els = match self.peek() {
+                TokenKind::Keyword(Keyword::If) => self.nested(Parser::if_expr)?,
+                TokenKind::OpenBrace => {
+                    let block_start = self.span();
+                    let block = self.block()?;
+                    let span = block_start.to(self.prev_span);
+                    self.mk(ExprKind::Block(block), span)
+                }
+                _ => return Err(self.unexpected("`{` or `if`")),
+            };
+            Some(Box::new(els))
+        } else {
+            None
+        };
+        let span = start.to(self.prev_span);
+        Ok(self.mk(ExprKind::If(Box::new(cond), then, els), span))
+    }
+
+    /// The condition of an `if` or `while`: an expression, or `let pat =
+    /// expr` to test whether a value matches a pattern.
+    fn cond(&mut self) -> PResult<Expr> {
+        let start = self.span();
+        if !self.eat_keyword(Keyword::Let) {
+            return self.expr_no_struct();
+        }
+        let pat = self.pat()?;
+        self.expect(&TokenKind::Eq)?;
+        let scrutinee = self.expr_no_struct()?;
+        let span = start.to(self.prev_span);
+        Ok(self.mk(ExprKind::Let(Box::new(pat), Box::new(scrutinee)), span))
+    }
+
+    /// `'label` after `break` or `continue`.
+    fn label(&mut self) -> Option<Ident> {
+        match self.peek().clone() {
+            TokenKind::Lifetime(name) => Some(Ident::new(name, self.bump().span)),
+            _ => None,
+        }
+    }
+
+    /// `|x| x % 2 == 0`, `|x: u64| -> bool { x % 2 == 0 }`, `move || ...`.
+    fn closure_expr(&mut self) -> PResult<Expr> {
+        let start = self.span();
+        let capture =
+            if self.eat_keyword(Keyword::Move) { CaptureBy::Value } else { CaptureBy::Ref };
+        let params = if self.eat(&TokenKind::OrOr) {
+            Vec::new()
+        } else {
+            self.expect(&TokenKind::Or)?;
+            self.comma_separated(&TokenKind::Or, |p| {
+                let pat = p.pat_no_alt()?;
+                let ty = if p.eat(&TokenKind::Colon) { Some(p.ty()?) } else { None };
+                Ok(Param { pat, ty })
+            })?
+        };
+        let ret = if self.eat(&TokenKind::RArrow) { Some(self.ty()?) } else { None };
+        let body = if ret.is_some() {
+            // With a return type the body has to be a block, "for the sake of syntactic sanity".
+            if !self.check(&TokenKind::OpenBrace) {
+                let err =
+                    self.unexpected("`{`").help("a closure with a return type needs a block body");
+                // Only a body that parses can be wrapped in braces.
+                let body = match self.expr() {
+                    Ok(body) => body,
+                    Err(_) => return Err(err),
+                };
+                return Err(err.suggest(
+                    "try adding braces around the body",
+                    body.span,
+                    format!("{{ {} }}", self.snippet(body.span)),
+                    Applicability::MaybeIncorrect,
+                ));
+            }
+            let body_start = self.span();
+            let block = self.block()?;
+            let span = body_start.to(self.prev_span);
+            self.mk(ExprKind::Block(block), span)
+        } else {
+            self.expr()?
+        };
+        let span = start.to(body.span);
+        Ok(self.mk(ExprKind::Closure(Arc::new(Closure { capture, params, ret, body })), span))
+    }
+
+    /// `Name { field: value, shorthand }`, after the path.
+    fn struct_expr(&mut self, path: Path) -> PResult<Expr> {
+        self.expect(&TokenKind::OpenBrace)?;
+        let fields = self.comma_separated(&TokenKind::CloseBrace, |p| {
+            let name = p.ident()?;
+            let value = if p.eat(&TokenKind::Colon) {
+                p.nested_expr()?
+            } else {
+                let path = Path {
+                    segments: vec![PathSegment { ident: name.clone(), generics: Vec::new() }],
+                    span: name.span,
+                };
+                p.mk(ExprKind::Path(path), name.span)
+            };
+            let span = name.span.to(value.span);
+            Ok(FieldExpr { name, value, span })
+        })?;
+        let span = path.span.to(self.prev_span);
+        Ok(self.mk(ExprKind::Struct(Box::new(StructExpr { path, fields })), span))
+    }
+
+    /// After a path, decide whether `{` opens a struct literal or a block.
+    fn looks_like_struct_literal(&self) -> bool {
+        if self.no_struct_literal {
+            return false;
+        }
+        matches!(
+            (self.peek_nth(1), self.peek_nth(2)),
+            (TokenKind::CloseBrace, _)
+                | (
+                    TokenKind::Ident(_),
+                    TokenKind::Colon | TokenKind::Comma | TokenKind::CloseBrace
+                )
+        )
+    }
+
+    /// Whether the operator the parser is positioned at, the second in a
+    /// chain that began with `<`, closes type arguments rather than comparing:
+    /// `>` or `>>`, or a `<` opening nested ones that close in turn, as in
+    /// `collect<Vec<i32>>()`. In `a < b < c` nothing closes.
+    fn closes_type_arguments(&self) -> bool {
+        let mut n = 0;
+        loop {
+            match self.peek_nth(n) {
+                TokenKind::Gt | TokenKind::Shr => return true,
+                TokenKind::Lt if n == 0 => {}
+                TokenKind::Ident(_) | TokenKind::PathSep | TokenKind::Comma | TokenKind::Lt
+                    if n > 0 => {}
+                _ => return false,
+            }
+            n += 1;
+        }
+    }
+
+    /// `()`, `(expr)` or `(a, b, ...)`.
+    fn paren_expr(&mut self) -> PResult<Expr> {
+        let start = self.expect(&TokenKind::OpenParen)?;
+        let mut elems = Vec::new();
+        let mut trailing_comma = false;
+        while !self.check(&TokenKind::CloseParen) {
+            elems.push(self.nested_expr()?);
+            trailing_comma = self.eat(&TokenKind::Comma);
+            if !trailing_comma {
+                break;
+            }
+        }
+        self.expect(&TokenKind::CloseParen)?;
+        let span = start.to(self.prev_span);
+        if elems.len() == 1 && !trailing_comma {
+            let inner = elems.pop().unwrap();
+            return Ok(self.mk(ExprKind::Paren(Box::new(inner)), span));
+        }
+        Ok(self.mk(ExprKind::Tuple(elems), span))
+    }
+
+    /// What follows `[`: `a, b, c]` or `value; count]`.
+    fn array_contents(&mut self) -> PResult<ExprKind> {
+        if self.eat(&TokenKind::CloseBracket) {
+            return Ok(ExprKind::Array(Vec::new()));
+        }
+        let first = self.nested_expr()?;
+        if self.eat(&TokenKind::Semi) {
+            let count = self.nested_expr()?;
+            self.expect(&TokenKind::CloseBracket)?;
+            return Ok(ExprKind::Repeat(Box::new(first), Box::new(count)));
+        }
+        let mut elems = vec![first];
+        if self.eat(&TokenKind::Comma) {
+            elems.extend(self.comma_separated(&TokenKind::CloseBracket, Parser::nested_expr)?);
+        } else {
+            self.expect(&TokenKind::CloseBracket)?;
+        }
+        Ok(ExprKind::Array(elems))
+    }
+
+    /// A path in expression position. Generic arguments need the turbofish
+    /// here, since a bare `<` is the less-than operator.
+    pub(super) fn expr_path(&mut self) -> PResult<Path> {
+        let start = self.span();
+        // A leading `::` only anchors the path at the crate root.
+        self.eat(&TokenKind::PathSep);
+        let mut segments = Vec::new();
+        loop {
+            let ident = self.path_segment_ident()?;
+            let mut segment = PathSegment { ident, generics: Vec::new() };
+            if self.eat(&TokenKind::Turbofish) {
+                segment.generics = self.generic_args()?;
+            }
+            segments.push(segment);
+            if !self.eat(&TokenKind::PathSep) {
+                break;
+            }
+        }
+        Ok(Path { segments, span: start.to(self.prev_span) })
+    }
+
+    /// A macro call such as `println!("{}", x)` or `vec![0; n]`, after its name.
+    fn mac_call(&mut self, name: Ident) -> PResult<Expr> {
+        self.expect(&TokenKind::Not)?;
+        let start = name.span;
+        let args = match self.peek() {
+            TokenKind::OpenBracket if name.name == "vec" => {
+                let open = self.bump().span;
+                let kind = self.array_contents()?;
+                let span = open.to(self.prev_span);
+                vec![self.mk(kind, span)]
+            }
+            TokenKind::OpenParen => {
+                self.bump();
+                self.comma_separated(&TokenKind::CloseParen, Parser::nested_expr)?
+            }
+            TokenKind::OpenBracket => {
+                self.bump();
+                self.comma_separated(&TokenKind::CloseBracket, Parser::nested_expr)?
+            }
+            TokenKind::OpenBrace => {
+                self.bump();
+                self.comma_separated(&TokenKind::CloseBrace, Parser::nested_expr)?
+            }
+            _ => return Err(self.unexpected("one of `(`, `[`, or `{`")),
+        };
+        let span = start.to(self.prev_span);
+        Ok(self.mk(ExprKind::MacCall(MacCall { name, args }), span))
+    }
+
+    /// Whether the current token can start an expression. Decides whether
+    /// `a..`, `break` and `return` have an operand.
+    pub(super) fn can_begin_expr(&self) -> bool {
+        match self.peek() {
+            TokenKind::Int { .. }
+            | TokenKind::Float { .. }
+            | TokenKind::Char(_)
+            | TokenKind::Str(_)
+            | TokenKind::Ident(_)
+            | TokenKind::Lifetime(_)
+            | TokenKind::Minus
+            | TokenKind::Not
+            | TokenKind::Star
+            | TokenKind::And
+            | TokenKind::AndAnd
+            | TokenKind::Or
+            | TokenKind::OrOr
+            | TokenKind::DotDot
+            | TokenKind::DotDotEq
+            | TokenKind::PathSep
+            | TokenKind::OpenParen
+            | TokenKind::OpenBracket => true,
+            // In `for i in 0.. {`, the brace opens the loop body.
+            TokenKind::OpenBrace => !self.no_struct_literal,
+            TokenKind::Keyword(kw) => !matches!(
+                kw,
+                Keyword::As | Keyword::Else | Keyword::In | Keyword::Where | Keyword::Mut
+            ),
+            _ => false,
+        }
+    }
+
+    /// `a < b < c`, `a = b = 3` or `a..b..c`. The parser is positioned at
+    /// the second operator.
+    fn chained_operator_error(
+        &mut self,
+        prec: Prec,
+        first: Span,
+        second: Span,
+        lhs: &Expr,
+    ) -> Diagnostic {
+        let name = precedence::infix(self.peek()).expect("positioned at an operator").level.name;
+        let err = Diagnostic::error(format!("{} operators cannot be chained", name))
+            .secondary(first, "")
+            .primary(second, "");
+        match (name, &lhs.kind) {
+            // `Vec<i32>::new()` or `collect<Vec<i32>>()`: generics without the turbofish.
+            ("comparison", ExprKind::Binary(BinOp::Lt, before, _))
+                if matches!(before.kind, ExprKind::Path(_) | ExprKind::Field(..))
+                    && self.closes_type_arguments() =>
+            {
+                err.suggest(
+                    "use `::<...>` instead of `<...>` to specify type arguments",
+                    before.span.shrink_to_hi(),
+                    "::",
+                    Applicability::MaybeIncorrect,
+                )
+            }
+            ("comparison", ExprKind::Binary(_, _, middle)) => {
+                self.bump();
+                let rhs = match self.expr_prec(prec + 1) {
+                    Ok(rhs) => rhs,
+                    Err(_) => return err,
+                };
+                err.help(format!(
+                    "split the comparison into two: `{} && {} {}`",
+                    self.snippet(lhs.span),
+                    self.snippet(middle.span),
+                    self.snippet(second.to(rhs.span)),
+                ))
+            }
+            ("assignment", _) => err
+                .note("Rust doesn't support chained assignment like `a = b = 3`")
+                .help("assign each variable in its own statement"),
+            _ => err.help("use parentheses to make the grouping explicit"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_expr;
+    use crate::ast::{Expr, ExprKind};
+
+    /// The expression's tree, with each operator before its operands and
+    /// everything else as written.
+    fn tree(src: &str) -> String {
+        fn sexp(expr: &Expr, src: &str) -> String {
+            let sexp = |expr: &Expr| sexp(expr, src);
+            match &expr.kind {
+                ExprKind::Binary(op, lhs, rhs) => {
+                    format!("({} {} {})", op.as_str(), sexp(lhs), sexp(rhs))
+                }
+                ExprKind::AssignOp(op, lhs, rhs) => {
+                    format!("({}= {} {})", op.as_str(), sexp(lhs), sexp(rhs))
+                }
+                ExprKind::Assign(lhs, rhs) => format!("(= {} {})", sexp(lhs), sexp(rhs)),
+                ExprKind::Unary(op, operand) => format!("({} {})", op.as_str(), sexp(operand)),
+                ExprKind::Cast(operand, ty) => {
+                    format!("(as {} {})", sexp(operand), ty.span.text(src))
+                }
+                ExprKind::Range(start, end, limits) => {
+                    let operand = |e: &Option<Box<Expr>>| e.as_deref().map_or(String::new(), sexp);
+                    format!("({} {} {})", limits.as_str(), operand(start), operand(end))
+                }
+                _ => expr.span.text(src).to_string(),
+            }
+        }
+        sexp(&parse_expr(src).unwrap(), src)
+    }
+
+    fn error(src: &str) -> String {
+        parse_expr(src).unwrap_err().message
+    }
+
+    #[test]
+    fn precedence_follows_the_table() {
+        assert_eq!(tree("5 * (fahr - 32) / 9"), "(/ (* 5 (fahr - 32)) 9)");
+        assert_eq!(tree("a + b * c << 2"), "(<< (+ a (* b c)) 2)");
+        assert_eq!(tree("a & b ^ c | d"), "(| (^ (& a b) c) d)");
+        assert_eq!(tree("a == b && c < d || e"), "(|| (&& (== a b) (< c d)) e)");
+        assert_eq!(tree("-x as u8 + 1"), "(+ (as (- x) u8) 1)");
+        assert_eq!(tree("a..b + 1"), "(.. a (+ b 1))");
+        assert_eq!(tree("total += x * 2"), "(+= total (* x 2))");
+    }
+
+    #[test]
+    fn chainable_operators_are_left_associative() {
+        assert_eq!(tree("a - b - c"), "(- (- a b) c)");
+        assert_eq!(tree("a / b * c % d"), "(% (* (/ a b) c) d)");
+        assert_eq!(tree("x as i64 as f64"), "(as (as x i64) f64)");
+        assert_eq!(tree("a && b && c"), "(&& (&& a b) c)");
+    }
+
+    #[test]
+    fn comparisons_cannot_be_chained() {
+        assert_eq!(error("a < b < c"), "comparison operators cannot be chained");
+        assert_eq!(error("a == b != c"), "comparison operators cannot be chained");
+        let help = &parse_expr("a < b < c").unwrap_err().notes[0].1;
+        assert_eq!(help, "split the comparison into two: `a < b && b < c`");
+        // Parenthesized, the chain is fine.
+        assert_eq!(tree("(a < b) == c"), "(== (a < b) c)");
+    }
+
+    #[test]
+    fn assignments_and_ranges_cannot_be_chained() {
+        assert_eq!(error("a = b = 3"), "assignment operators cannot be chained");
+        assert_eq!(error("a += b -= 3"), "assignment operators cannot be chained");
+        assert_eq!(error("a..b..c"), "range operators cannot be chained");
+    }
+
+    #[test]
+    fn generics_without_turbofish() {
+        let error = parse_expr("Vec<i32>::new()").unwrap_err();
+        assert_eq!(error.message, "comparison operators cannot be chained");
+        assert_eq!(error.suggestions[0].replacement, "::");
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let parens = format!("{}1{}", "(".repeat(300), ")".repeat(300));
+        assert_eq!(error(&parens), "recursion limit reached while parsing");
+        let braces = format!("{}1{}", "{".repeat(300), "}".repeat(300));
+        assert_eq!(error(&braces), "recursion limit reached while parsing");
+        let negations = format!("{}1", "-".repeat(300));
+        assert_eq!(error(&negations), "recursion limit reached while parsing");
+        let parens = format!("{}1{}", "(".repeat(60), ")".repeat(60));
+        assert_eq!(tree(&parens), parens);
+    }
+
+    #[test]
+    fn closure_bodies_after_a_return_type_need_braces() {
+        let err = parse_expr("|x: i32| -> i32 x + 1").unwrap_err();
+        assert_eq!(err.message, "expected `{`, found `x`");
+        assert_eq!(err.suggestions[0].span.text("|x: i32| -> i32 x + 1"), "x + 1");
+        assert_eq!(err.suggestions[0].replacement, "{ x + 1 }");
+    }
+}
@@ -0,0 +1,175 @@
+//! Items declared inside blocks: `fn`, `struct`, `enum` and `use`.
+
+use std::sync::Arc;
+
+use super::{PResult, Parser};
+use crate::ast::{
+    EnumDecl, FieldDecl, FnDecl, Item, ItemKind, Param, Path, PathSegment, StructDecl,
+    StructFields, UseTree, UseTreeKind, Variant,
+};
+use crate::lexer::{Keyword, TokenKind};
+
+impl Parser {
+    pub(super) fn check_item(&self) -> bool {
+        matches!(
+            self.peek(),
+            TokenKind::Keyword(Keyword::Fn | Keyword::Struct | Keyword::Enum | Keyword::Use)
+        )
+    }
+
+    pub(super) fn item(&mut self) -> PResult<Item> {
+        let start = self.span();
+        let kind = match self.peek() {
+            TokenKind::Keyword(Keyword::Fn) => ItemKind::Fn(Arc::new(self.fn_decl()?)),
+            TokenKind::Keyword(Keyword::Struct) => ItemKind::Struct(Arc::new(self.struct_decl()?)),
+            TokenKind::Keyword(Keyword::Enum) => ItemKind::Enum(Arc::new(self.enum_decl()?)),
+            _ => {
+                self.expect(&TokenKind::Keyword(Keyword::Use))?;
+                let tree = self.use_tree()?;
+                self.expect(&TokenKind::Semi)?;
+                ItemKind::Use(tree)
+            }
+        };
+        Ok(Item { id: self.next_id(), kind, span: start.to(self.prev_span) })
+    }
+
+    /// `fn name(pat: ty, ...) -> ty { body }`
+    fn fn_decl(&mut self) -> PResult<FnDecl> {
+        self.expect(&TokenKind::Keyword(Keyword::Fn))?;
+        let name = self.ident()?;
+        if self.check(&TokenKind::Lt) {
+            return Err(self
+                .unexpected("`(`")
+                .help("generic functions are not supported; write the function for one type"));
+        }
+        self.expect(&TokenKind::OpenParen)?;
+        let params = self.comma_separated(&TokenKind::CloseParen, |p| {
+            let pat = p.pat_no_alt()?;
+            p.expect(&TokenKind::Colon)?;
+            Ok(Param { pat, ty: Some(p.ty()?) })
+        })?;
+        let ret = if self.eat(&TokenKind::RArrow) { Some(self.ty()?) } else { None };
+        let body = self.block()?;
+        Ok(FnDecl { name, params, ret, body })
+    }
+
+    /// `struct Name { field: ty, ... }`, `struct Name(ty, ...);` or `struct Name;`
+    fn struct_decl(&mut self) -> PResult<StructDecl> {
+        self.expect(&TokenKind::Keyword(Keyword::Struct))?;
+        let name = self.ident()?;
+        let fields = self.struct_fields()?;
+        if !matches!(fields, StructFields::Named(_)) {
+            self.expect(&TokenKind::Semi)?;
+        }
+        Ok(StructDecl { name, fields })
+    }
+
+    /// `enum Name { Variant, Variant(ty, ...), Variant { field: ty, ... }, ... }`
+    fn enum_decl(&mut self) -> PResult<EnumDecl> {
+        self.expect(&TokenKind::Keyword(Keyword::Enum))?;
+        let name = self.ident()?;
+        self.expect(&TokenKind::OpenBrace)?;
+        let variants = self.comma_separated(&TokenKind::CloseBrace, |p| {
+            Ok(Variant { name: p.ident()?, fields: p.struct_fields()? })
+        })?;
+        Ok(EnumDecl { name, variants })
+    }
+
+    /// The fields after a struct's or variant's name, if any.
+    fn struct_fields(&mut self) -> PResult<StructFields> {
+        if self.eat(&TokenKind::OpenBrace) {
+            Ok(StructFields::Named(self.comma_separated(&TokenKind::CloseBrace, |p| {
+                let name = p.ident()?;
+                p.expect(&TokenKind::Colon)?;
+                Ok(FieldDecl { name, ty: p.ty()? })
+            })?))
+        } else if self.eat(&TokenKind::OpenParen) {
+            Ok(StructFields::Tuple(self.comma_separated(&TokenKind::CloseParen, Parser::ty)?))
+        } else {
+            Ok(StructFields::Unit)
+        }
+    }
+
+    /// `a::b::c`, `a::b as c`, `a::{b, c::d}` or `a::*`, after `use`.
+    fn use_tree(&mut self) -> PResult<UseTree> {
+        let start = self.span();
+        let mut segments = Vec::new();
+        loop {
+            let ident = self.path_segment_ident()?;
+            segments.push(PathSegment { ident, generics: Vec::new() });
+            if !self.check(&TokenKind::PathSep)
+                || matches!(self.peek_nth(1), TokenKind::OpenBrace | TokenKind::Star)
+            {
+                break;
+            }
+            self.bump();
+        }
+        let prefix = Path { segments, span: start.to(self.prev_span) };
+        let kind = if self.eat(&TokenKind::PathSep) {
+            if self.eat(&TokenKind::Star) {
+                UseTreeKind::Glob
+            } else {
+                self.expect(&TokenKind::OpenBrace)?;
+                UseTreeKind::Nested(self.comma_separated(&TokenKind::CloseBrace, Parser::use_tree)?)
+            }
+        } else if self.eat_keyword(Keyword::As) {
+            UseTreeKind::Simple(Some(self.ident()?))
+        } else {
+            UseTreeKind::Simple(None)
+        };
+        Ok(UseTree { prefix, kind, span: start.to(self.prev_span) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_program;
+    use crate::ast::StmtKind;
+    use crate::run;
+
+    /// The names the items of a program declare.
+    fn item_names(src: &str) -> Vec<String> {
+        let program = parse_program(src).unwrap();
+        let mut names = Vec::new();
+        for stmt in &program.stmts {
+            if let StmtKind::Item(item) = &stmt.kind {
+                names.extend(item.names().iter().map(|name| name.name.clone()));
+            }
+        }
+        names
+    }
+
+    #[test]
+    fn items_inside_blocks() {
+        assert_eq!(item_names("fn cmp(a: i32, b: i32) -> bool { a < b } cmp(1, 2)"), ["cmp"]);
+        assert_eq!(
+            item_names("struct P { x: i32 } struct Q(u8); enum E { A, B(i32) }"),
+            ["P", "Q", "E"]
+        );
+        assert_eq!(item_names("use std::cmp::{self, Ordering as O}; use Suit::*;"), ["cmp", "O"]);
+    }
+
+    #[test]
+    fn items_are_visible_throughout_their_block() {
+        let run = |src| run(src).unwrap().to_string();
+        assert_eq!(
+            run("let n = show(); fn show() -> i32 { helper() * 2 } fn helper() -> i32 { 21 } n"),
+            "42"
+        );
+        assert_eq!(run("let p = P { x: 1 }; struct P { x: i32 } p.x"), "1");
+        let src = "use Suit::*; enum Suit { Hearts, Spades } \
+                   match Spades { Hearts => 1, Spades => 2 }";
+        assert_eq!(run(src), "2");
+    }
+
+    #[test]
+    fn fn_items_cannot_capture_locals() {
+        let error =
+            run("let limit = 3; fn under(n: i32) -> bool { n < limit } under(1)").unwrap_err();
+        assert_eq!(error.message, "can't capture dynamic environment in a fn item");
+        assert_eq!(
+            error.notes[0].1,
+            "use a closure instead: `|| { ... }` can see the variables around it"
+        );
+    }
+}
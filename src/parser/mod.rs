@@ -0,0 +1,234 @@
+//! Turns tokens into a syntax tree.
+//!
+//! Binary operators are parsed by precedence climbing over the table in
+//! [`precedence`]; everything else is ordinary recursive descent.
+
+mod expr;
+mod item;
+mod pat;
+pub mod precedence;
+mod stmt;
+mod ty;
+
+use crate::ast::{Block, Expr, ExprKind, Ident, NodeId};
+use crate::desugar;
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{self, Keyword, Token, TokenKind};
+use crate::span::Span;
+
+pub type PResult<T> = Result<T, Diagnostic>;
+
+/// How deeply expressions, patterns and types may nest. Every later pass
+/// walks the tree recursively too, so this keeps them all within the stack
+/// of an ordinary thread.
+const MAX_NESTING: usize = 64;
+
+/// Parse `src` as a single expression.
+pub fn parse_expr(src: &str) -> PResult<Expr> {
+    let mut parser = Parser::new(src)?;
+    let mut expr = parser.expr()?;
+    parser.expect_eof()?;
+    desugar::expr(&mut expr, &mut parser.next_id);
+    Ok(expr)
+}
+
+/// Parse a whole program. A program is the body of a block, without the
+/// braces: its statements run in order and its tail expression is its value.
+pub fn parse_program(src: &str) -> PResult<Block> {
+    let mut parser = Parser::new(src)?;
+    let (stmts, tail) = parser.block_body(&TokenKind::Eof)?;
+    let mut program = Block { id: parser.next_id(), stmts, tail, span: Span::new(0, src.len()) };
+    desugar::block(&mut program, &mut parser.next_id);
+    Ok(program)
+}
+
+pub struct Parser {
+    src: String,
+    tokens: Vec<Token>,
+    pos: usize,
+    prev_span: Span,
+    next_id: u32,
+    /// Set while parsing an `if`/`while`/`match`/`for` head, where `x {`
+    /// starts the body rather than a struct literal.
+    no_struct_literal: bool,
+    /// How many expressions, patterns and types are being parsed, each
+    /// inside the last.
+    nesting: usize,
+}
+
+impl Parser {
+    pub fn new(src: &str) -> PResult<Parser> {
+        Ok(Parser {
+            src: src.to_string(),
+            tokens: lexer::tokenize(src)?,
+            pos: 0,
+            prev_span: Span::default(),
+            next_id: 0,
+            no_struct_literal: false,
+            nesting: 0,
+        })
+    }
+
+    fn next_id(&mut self) -> NodeId {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn mk(&mut self, kind: ExprKind, span: Span) -> Expr {
+        Expr { id: self.next_id(), kind, span }
+    }
+
+    /// The source text under `span`, for quoting code in help messages.
+    fn snippet(&self, span: Span) -> &str {
+        span.text(&self.src)
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn peek(&self) -> &TokenKind {
+        &self.token().kind
+    }
+
+    /// The token `n` places ahead of the current one.
+    fn peek_nth(&self, n: usize) -> &TokenKind {
+        let index = (self.pos + n).min(self.tokens.len() - 1);
+        &self.tokens[index].kind
+    }
+
+    fn span(&self) -> Span {
+        self.token().span
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.token().clone();
+        self.prev_span = token.span;
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn check(&self, kind: &TokenKind) -> bool {
+        self.peek() == kind
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        let found = self.check(kind);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> PResult<Span> {
+        if self.check(kind) {
+            Ok(self.bump().span)
+        } else {
+            Err(self.unexpected(&kind.describe()))
+        }
+    }
+
+    fn check_keyword(&self, kw: Keyword) -> bool {
+        self.check(&TokenKind::Keyword(kw))
+    }
+
+    fn eat_keyword(&mut self, kw: Keyword) -> bool {
+        self.eat(&TokenKind::Keyword(kw))
+    }
+
+    fn expect_eof(&mut self) -> PResult<()> {
+        if self.check(&TokenKind::Eof) {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of input"))
+        }
+    }
+
+    fn ident(&mut self) -> PResult<Ident> {
+        match self.peek().clone() {
+            TokenKind::Ident(name) => Ok(Ident::new(name, self.bump().span)),
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    /// Eat a `>`, splitting `>>`, `>=` and `>>=` the way rustc does when
+    /// closing generics such as `Vec<Vec<i32>>`.
+    fn eat_gt(&mut self) -> bool {
+        let rest = match self.peek() {
+            TokenKind::Gt => None,
+            TokenKind::Shr => Some(TokenKind::Gt),
+            TokenKind::Ge => Some(TokenKind::Eq),
+            TokenKind::ShrEq => Some(TokenKind::Ge),
+            _ => return false,
+        };
+        let span = self.span();
+        match rest {
+            None => {
+                self.bump();
+            }
+            Some(kind) => {
+                self.prev_span = Span::new(span.start, span.start + 1);
+                self.tokens[self.pos] = Token { kind, span: Span::new(span.start + 1, span.end) };
+            }
+        }
+        true
+    }
+
+    fn expect_gt(&mut self) -> PResult<()> {
+        if self.eat_gt() {
+            Ok(())
+        } else {
+            Err(self.unexpected("`>`"))
+        }
+    }
+
+    /// Run `parse` one level of nesting deeper.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Parser) -> PResult<T>) -> PResult<T> {
+        self.deeper()?;
+        let result = parse(self);
+        self.nesting -= 1;
+        result
+    }
+
+    /// Go one level of nesting deeper, unless that would be deeper than
+    /// [`MAX_NESTING`].
+    fn deeper(&mut self) -> PResult<()> {
+        if self.nesting == MAX_NESTING {
+            return Err(Diagnostic::error("recursion limit reached while parsing")
+                .primary(self.span(), "nested too deeply")
+                .note(format!(
+                    "expressions, patterns and types may nest at most {} deep",
+                    MAX_NESTING
+                )));
+        }
+        self.nesting += 1;
+        Ok(())
+    }
+
+    /// "expected X, found Y" at the current token.
+    fn unexpected(&self, expected: &str) -> Diagnostic {
+        let found = self.peek().describe();
+        Diagnostic::error(format!("expected {}, found {}", expected, found))
+            .primary(self.span(), format!("expected {}", expected))
+    }
+
+    /// Parse `item, item, ...` up to and including `close`, allowing a trailing comma.
+    fn comma_separated<T>(
+        &mut self,
+        close: &TokenKind,
+        mut item: impl FnMut(&mut Parser) -> PResult<T>,
+    ) -> PResult<Vec<T>> {
+        let mut items = Vec::new();
+        while !self.check(close) {
+            items.push(item(self)?);
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(close)?;
+        Ok(items)
+    }
+}
@@ -0,0 +1,244 @@
+//! Patterns, as used by `let`, `match` arms, `for` loops and closure parameters.
+
+use super::{PResult, Parser};
+use crate::ast::{
+    BindingMode, Expr, ExprKind, FieldPat, Ident, LitKind, Mutability, Pat, PatKind, RangeLimits,
+    UnOp,
+};
+use crate::diagnostic::{Applicability, Diagnostic};
+use crate::lexer::{Keyword, TokenKind};
+
+impl Parser {
+    /// A pattern, including alternatives: `'a' | 'e' | 'i'`. A `|` may also
+    /// lead the first alternative.
+    pub(super) fn pat(&mut self) -> PResult<Pat> {
+        let start = self.span();
+        self.eat(&TokenKind::Or);
+        let first = self.pat_no_alt()?;
+        if !self.check(&TokenKind::Or) {
+            return Ok(first);
+        }
+        let mut alternatives = vec![first];
+        while self.eat(&TokenKind::Or) {
+            alternatives.push(self.pat_no_alt()?);
+        }
+        let span = start.to(self.prev_span);
+        Ok(Pat { id: self.next_id(), kind: PatKind::Or(alternatives), span })
+    }
+
+    /// A pattern without top-level alternatives, as closure and function
+    /// parameters take, where `|` would be ambiguous.
+    pub(super) fn pat_no_alt(&mut self) -> PResult<Pat> {
+        self.nested(Parser::pat_no_alt_inner)
+    }
+
+    fn pat_no_alt_inner(&mut self) -> PResult<Pat> {
+        let start = self.span();
+        let kind = match self.peek().clone() {
+            TokenKind::Underscore => {
+                self.bump();
+                PatKind::Wild
+            }
+            TokenKind::And | TokenKind::AndAnd => {
+                // `&&pat` is two `&` patterns.
+                let double = self.bump().kind == TokenKind::AndAnd;
+                let mutability =
+                    if self.eat_keyword(Keyword::Mut) { Mutability::Mut } else { Mutability::Not };
+                let inner = self.pat_no_alt()?;
+                let mut kind = PatKind::Ref(Box::new(inner), mutability);
+                if double {
+                    let span = start.to(self.prev_span);
+                    let inner = Pat { id: self.next_id(), kind, span };
+                    kind = PatKind::Ref(Box::new(inner), Mutability::Not);
+                }
+                kind
+            }
+            TokenKind::Keyword(Keyword::Ref) => {
+                self.bump();
+                let mutability =
+                    if self.eat_keyword(Keyword::Mut) { Mutability::Mut } else { Mutability::Not };
+                self.binding(BindingMode::ByRef(mutability))?
+            }
+            TokenKind::Keyword(Keyword::Mut) => {
+                self.bump();
+                self.binding(BindingMode::ByValue(Mutability::Mut))?
+            }
+            TokenKind::OpenParen => {
+                self.bump();
+                let mut elems = Vec::new();
+                let mut trailing_comma = false;
+                while !self.check(&TokenKind::CloseParen) {
+                    elems.push(self.pat()?);
+                    trailing_comma = self.eat(&TokenKind::Comma);
+                    if !trailing_comma {
+                        break;
+                    }
+                }
+                self.expect(&TokenKind::CloseParen)?;
+                if elems.len() == 1 && !trailing_comma {
+                    // `(pat)` is just `pat` in parentheses.
+                    let mut inner = elems.pop().unwrap();
+                    inner.span = start.to(self.prev_span);
+                    return Ok(inner);
+                }
+                PatKind::Tuple(elems)
+            }
+            TokenKind::Int { .. }
+            | TokenKind::Float { .. }
+            | TokenKind::Char(_)
+            | TokenKind::Str(_)
+            | TokenKind::Minus
+            | TokenKind::Keyword(Keyword::True | Keyword::False) => {
+                let lit = self.lit_pat_expr()?;
+                if self.is_range_op() {
+                    self.range_pat(lit)?
+                } else {
+                    PatKind::Lit(Box::new(lit))
+                }
+            }
+            TokenKind::Ident(_)
+            | TokenKind::PathSep
+            | TokenKind::Keyword(Keyword::SelfType | Keyword::Crate | Keyword::Super) => {
+                let path = self.expr_path()?;
+                if self.eat(&TokenKind::OpenParen) {
+                    let elems = self.comma_separated(&TokenKind::CloseParen, Parser::pat)?;
+                    PatKind::TupleStruct(path, elems)
+                } else if self.eat(&TokenKind::OpenBrace) {
+                    let (fields, rest) = self.field_pats()?;
+                    PatKind::Struct(path, fields, rest)
+                } else if self.is_range_op() {
+                    let span = path.span;
+                    let start = self.mk(ExprKind::Path(path), span);
+                    self.range_pat(start)?
+                } else {
+                    match path.as_ident() {
+                        // A lone name binds a variable, unless it is
+                        // capitalized: by convention that names a unit
+                        // struct, a unit variant or a constant, like `None`.
+                        Some(ident) if !ident.name.starts_with(char::is_uppercase) => {
+                            let name = ident.clone();
+                            self.binding_after(BindingMode::ByValue(Mutability::Not), name)?
+                        }
+                        _ => PatKind::Path(path),
+                    }
+                }
+            }
+            _ => return Err(self.unexpected("pattern")),
+        };
+        Ok(Pat { id: self.next_id(), kind, span: start.to(self.prev_span) })
+    }
+
+    /// `name` or `name @ pat`, after `ref`, `ref mut` or `mut`.
+    fn binding(&mut self, mode: BindingMode) -> PResult<PatKind> {
+        let name = self.ident()?;
+        self.binding_after(mode, name)
+    }
+
+    fn binding_after(&mut self, mode: BindingMode, name: Ident) -> PResult<PatKind> {
+        let sub = if self.eat(&TokenKind::At) { Some(Box::new(self.pat_no_alt()?)) } else { None };
+        Ok(PatKind::Ident(mode, name, sub))
+    }
+
+    fn is_range_op(&self) -> bool {
+        matches!(self.peek(), TokenKind::DotDot | TokenKind::DotDotEq | TokenKind::DotDotDot)
+    }
+
+    /// The rest of a range pattern whose start has been parsed, from the
+    /// range operator on.
+    fn range_pat(&mut self, start: Expr) -> PResult<PatKind> {
+        let limits = match self.peek() {
+            TokenKind::DotDot => RangeLimits::HalfOpen,
+            TokenKind::DotDotEq => RangeLimits::Closed,
+            _ => {
+                return Err(Diagnostic::error("`...` range patterns are deprecated")
+                    .code("E0783")
+                    .primary(self.span(), "")
+                    .suggest(
+                        "use `..=` for an inclusive range",
+                        self.span(),
+                        "..=",
+                        Applicability::MachineApplicable,
+                    ));
+            }
+        };
+        self.bump();
+        let end = match self.peek() {
+            TokenKind::Ident(_) | TokenKind::PathSep => {
+                let path = self.expr_path()?;
+                let span = path.span;
+                self.mk(ExprKind::Path(path), span)
+            }
+            _ => self.lit_pat_expr()?,
+        };
+        Ok(PatKind::Range(Box::new(start), Box::new(end), limits))
+    }
+
+    /// `{ field, field: pat, .. }` after a struct pattern's path. Returns the
+    /// fields and whether they end with `..`.
+    fn field_pats(&mut self) -> PResult<(Vec<FieldPat>, bool)> {
+        let mut fields = Vec::new();
+        while !self.check(&TokenKind::CloseBrace) {
+            if self.eat(&TokenKind::DotDot) {
+                self.expect(&TokenKind::CloseBrace)?;
+                return Ok((fields, true));
+            }
+            fields.push(self.field_pat()?);
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(&TokenKind::CloseBrace)?;
+        Ok((fields, false))
+    }
+
+    /// `name: pat`, or the shorthand `name`, `mut name`, `ref name` or
+    /// `ref mut name`, which binds the field to a variable of the same name.
+    fn field_pat(&mut self) -> PResult<FieldPat> {
+        let start = self.span();
+        if matches!(self.peek(), TokenKind::Ident(_))
+            && matches!(self.peek_nth(1), TokenKind::Colon)
+        {
+            let name = self.ident()?;
+            self.bump();
+            let pat = self.pat()?;
+            return Ok(FieldPat { name, pat, span: start.to(self.prev_span) });
+        }
+        let mode = if self.eat_keyword(Keyword::Ref) {
+            BindingMode::ByRef(if self.eat_keyword(Keyword::Mut) {
+                Mutability::Mut
+            } else {
+                Mutability::Not
+            })
+        } else if self.eat_keyword(Keyword::Mut) {
+            BindingMode::ByValue(Mutability::Mut)
+        } else {
+            BindingMode::ByValue(Mutability::Not)
+        };
+        let name = self.ident()?;
+        let span = start.to(self.prev_span);
+        let pat = Pat { id: self.next_id(), kind: PatKind::Ident(mode, name.clone(), None), span };
+        Ok(FieldPat { name, pat, span })
+    }
+
+    /// A literal in a pattern, including a negated number such as `-1`.
+    fn lit_pat_expr(&mut self) -> PResult<Expr> {
+        let start = self.span();
+        let negated = self.eat(&TokenKind::Minus);
+        let lit = match self.peek().clone() {
+            TokenKind::Int { value, suffix } => LitKind::Int(value, suffix),
+            TokenKind::Float { value, suffix } => LitKind::Float(value, suffix),
+            TokenKind::Char(c) if !negated => LitKind::Char(c),
+            TokenKind::Str(s) if !negated => LitKind::Str(s),
+            TokenKind::Keyword(Keyword::True) if !negated => LitKind::Bool(true),
+            TokenKind::Keyword(Keyword::False) if !negated => LitKind::Bool(false),
+            _ => return Err(self.unexpected("literal")),
+        };
+        let lit_span = self.bump().span;
+        let expr = self.mk(ExprKind::Lit(lit), lit_span);
+        if negated {
+            let span = start.to(lit_span);
+            return Ok(self.mk(ExprKind::Unary(UnOp::Neg, Box::new(expr)), span));
+        }
+        Ok(expr)
+    }
+}
@@ -0,0 +1,157 @@
+//! The binary operator table from the notes' "Precedence and Associativity"
+//! section, as data.
+//!
+//! Levels are listed from highest to lowest precedence. The chainable
+//! operators `* / % + - << >> & ^ | && || as` are left-associative; the
+//! comparison operators, the range operators and the assignment operators
+//! cannot be chained at all.
+
+use crate::ast::{BinOp, RangeLimits};
+use crate::lexer::{Keyword, TokenKind};
+
+/// What a binary-position token does once the parser has decided to apply it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InfixOp {
+    Binary(BinOp),
+    /// `as`, whose right operand is a type rather than an expression.
+    Cast,
+    Range(RangeLimits),
+    Assign,
+    AssignOp(BinOp),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a < b < c` is an error; parentheses are required.
+    NonChaining,
+}
+
+pub struct Level {
+    /// Names the family of operators in "comparison operators cannot be chained".
+    pub name: &'static str,
+    pub assoc: Assoc,
+    pub ops: &'static [(TokenKind, InfixOp)],
+}
+
+/// Every binary operator, grouped by precedence, highest first.
+pub const PRECEDENCE: &[Level] = &[
+    Level {
+        name: "cast",
+        assoc: Assoc::Left,
+        ops: &[(TokenKind::Keyword(Keyword::As), InfixOp::Cast)],
+    },
+    Level {
+        name: "multiplicative",
+        assoc: Assoc::Left,
+        ops: &[
+            (TokenKind::Star, InfixOp::Binary(BinOp::Mul)),
+            (TokenKind::Slash, InfixOp::Binary(BinOp::Div)),
+            (TokenKind::Percent, InfixOp::Binary(BinOp::Rem)),
+        ],
+    },
+    Level {
+        name: "additive",
+        assoc: Assoc::Left,
+        ops: &[
+            (TokenKind::Plus, InfixOp::Binary(BinOp::Add)),
+            (TokenKind::Minus, InfixOp::Binary(BinOp::Sub)),
+        ],
+    },
+    Level {
+        name: "shift",
+        assoc: Assoc::Left,
+        ops: &[
+            (TokenKind::Shl, InfixOp::Binary(BinOp::Shl)),
+            (TokenKind::Shr, InfixOp::Binary(BinOp::Shr)),
+        ],
+    },
+    Level {
+        name: "bitwise AND",
+        assoc: Assoc::Left,
+        ops: &[(TokenKind::And, InfixOp::Binary(BinOp::BitAnd))],
+    },
+    Level {
+        name: "bitwise XOR",
+        assoc: Assoc::Left,
+        ops: &[(TokenKind::Caret, InfixOp::Binary(BinOp::BitXor))],
+    },
+    Level {
+        name: "bitwise OR",
+        assoc: Assoc::Left,
+        ops: &[(TokenKind::Or, InfixOp::Binary(BinOp::BitOr))],
+    },
+    Level {
+        name: "comparison",
+        assoc: Assoc::NonChaining,
+        ops: &[
+            (TokenKind::EqEq, InfixOp::Binary(BinOp::Eq)),
+            (TokenKind::Ne, InfixOp::Binary(BinOp::Ne)),
+            (TokenKind::Lt, InfixOp::Binary(BinOp::Lt)),
+            (TokenKind::Le, InfixOp::Binary(BinOp::Le)),
+            (TokenKind::Gt, InfixOp::Binary(BinOp::Gt)),
+            (TokenKind::Ge, InfixOp::Binary(BinOp::Ge)),
+        ],
+    },
+    Level {
+        name: "logical AND",
+        assoc: Assoc::Left,
+        ops: &[(TokenKind::AndAnd, InfixOp::Binary(BinOp::And))],
+    },
+    Level {
+        name: "logical OR",
+        assoc: Assoc::Left,
+        ops: &[(TokenKind::OrOr, InfixOp::Binary(BinOp::Or))],
+    },
+    Level {
+        name: "range",
+        assoc: Assoc::NonChaining,
+        ops: &[
+            (TokenKind::DotDot, InfixOp::Range(RangeLimits::HalfOpen)),
+            (TokenKind::DotDotEq, InfixOp::Range(RangeLimits::Closed)),
+        ],
+    },
+    Level {
+        name: "assignment",
+        assoc: Assoc::NonChaining,
+        ops: &[
+            (TokenKind::Eq, InfixOp::Assign),
+            (TokenKind::PlusEq, InfixOp::AssignOp(BinOp::Add)),
+            (TokenKind::MinusEq, InfixOp::AssignOp(BinOp::Sub)),
+            (TokenKind::StarEq, InfixOp::AssignOp(BinOp::Mul)),
+            (TokenKind::SlashEq, InfixOp::AssignOp(BinOp::Div)),
+            (TokenKind::PercentEq, InfixOp::AssignOp(BinOp::Rem)),
+            (TokenKind::AndEq, InfixOp::AssignOp(BinOp::BitAnd)),
+            (TokenKind::OrEq, InfixOp::AssignOp(BinOp::BitOr)),
+            (TokenKind::CaretEq, InfixOp::AssignOp(BinOp::BitXor)),
+            (TokenKind::ShlEq, InfixOp::AssignOp(BinOp::Shl)),
+            (TokenKind::ShrEq, InfixOp::AssignOp(BinOp::Shr)),
+        ],
+    },
+];
+
+/// Binding power of an operator. Bigger binds tighter; 0 is below every operator.
+pub type Prec = usize;
+
+/// Prefix operators (`-`, `!`, `*`, `&`) bind tighter than anything in the table.
+pub const PREFIX: Prec = PRECEDENCE.len() + 1;
+
+pub struct OpInfo {
+    pub op: InfixOp,
+    pub prec: Prec,
+    pub level: &'static Level,
+}
+
+/// Look up `token` as a binary operator.
+pub fn infix(token: &TokenKind) -> Option<OpInfo> {
+    PRECEDENCE.iter().enumerate().find_map(|(index, level)| {
+        let (_, op) = level.ops.iter().find(|(t, _)| t == token)?;
+        Some(OpInfo { op: *op, prec: PRECEDENCE.len() - index, level })
+    })
+}
+
+/// The precedence of the range operators, which can also appear in prefix position (`..b`).
+pub fn range_prec() -> Prec {
+    infix(&TokenKind::DotDot).expect("`..` is in the table").prec
+}
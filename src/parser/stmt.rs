@@ -0,0 +1,187 @@
+//! Blocks and the statements inside them.
+
+use super::{PResult, Parser};
+use crate::ast::{Arm, Block, Expr, Local, Stmt, StmtKind};
+use crate::diagnostic::{Applicability, Diagnostic};
+use crate::lexer::{Keyword, TokenKind};
+
+impl Parser {
+    /// `{ stmts; tail }`
+    pub(super) fn block(&mut self) -> PResult<Block> {
+        let start = self.expect(&TokenKind::OpenBrace)?;
+        let (stmts, tail) =
+            self.with_struct_literals(true, |p| p.block_body(&TokenKind::CloseBrace))?;
+        let close = self.expect(&TokenKind::CloseBrace)?;
+        Ok(Block { id: self.next_id(), stmts, tail, span: start.to(close) })
+    }
+
+    /// Statements up to (not including) `close`. An expression that ends the
+    /// block without a `;` becomes the block's tail, and its value.
+    pub(super) fn block_body(
+        &mut self,
+        close: &TokenKind,
+    ) -> PResult<(Vec<Stmt>, Option<Box<Expr>>)> {
+        let mut stmts = Vec::new();
+        while !self.check(close) {
+            let start = self.span();
+            if self.eat(&TokenKind::Semi) {
+                stmts.push(Stmt { kind: StmtKind::Empty, span: start });
+                continue;
+            }
+            if self.check_keyword(Keyword::Let) {
+                stmts.push(self.let_stmt()?);
+                continue;
+            }
+            if self.check_item() {
+                let item = self.item()?;
+                let span = item.span;
+                stmts.push(Stmt { kind: StmtKind::Item(Box::new(item)), span });
+                continue;
+            }
+
+            let expr = self.stmt_expr()?;
+            if self.eat(&TokenKind::Semi) {
+                stmts.push(Stmt { kind: StmtKind::Semi(expr), span: start.to(self.prev_span) });
+            } else if self.check(close) {
+                return Ok((stmts, Some(Box::new(expr))));
+            } else if expr.kind.is_block_like() {
+                let span = expr.span;
+                stmts.push(Stmt { kind: StmtKind::Expr(expr), span });
+            } else {
+                return Err(self.missing_semi(&expr));
+            }
+        }
+        Ok((stmts, None))
+    }
+
+    /// An expression in statement position. A block-like expression such as
+    /// `if c { a } else { b }` ends the statement right after its closing
+    /// brace, unless a method call or `?` continues it.
+    fn stmt_expr(&mut self) -> PResult<Expr> {
+        let block_like = matches!(
+            self.peek(),
+            TokenKind::OpenBrace
+                | TokenKind::Lifetime(_)
+                | TokenKind::Keyword(
+                    Keyword::If | Keyword::Match | Keyword::Loop | Keyword::While | Keyword::For
+                )
+        );
+        if !block_like {
+            return self.expr();
+        }
+        let expr = self.nested(Parser::block_like_expr)?;
+        if matches!(self.peek(), TokenKind::Dot | TokenKind::Question) {
+            let expr = self.postfix_expr(expr)?;
+            return self.binary_rest(expr, 0);
+        }
+        Ok(expr)
+    }
+
+    /// `let pat: ty = init;`
+    fn let_stmt(&mut self) -> PResult<Stmt> {
+        let start = self.expect(&TokenKind::Keyword(Keyword::Let))?;
+        let pat = self.pat()?;
+        let ty = if self.eat(&TokenKind::Colon) { Some(self.ty()?) } else { None };
+        let init = if self.eat(&TokenKind::Eq) { Some(self.expr()?) } else { None };
+        if !self.eat(&TokenKind::Semi) {
+            let last = init.as_ref().map(|e| e.span).or_else(|| ty.as_ref().map(|t| t.span));
+            let err = self.unexpected("`;`");
+            return Err(match last {
+                Some(span) => err.suggest(
+                    "a `let` declaration always ends with `;`",
+                    span.shrink_to_hi(),
+                    ";",
+                    Applicability::MachineApplicable,
+                ),
+                None => err,
+            });
+        }
+        let local = Local { pat, ty, init };
+        Ok(Stmt { kind: StmtKind::Let(Box::new(local)), span: start.to(self.prev_span) })
+    }
+
+    fn missing_semi(&self, expr: &Expr) -> Diagnostic {
+        self.unexpected("`;`").suggest(
+            "add `;` here",
+            expr.span.shrink_to_hi(),
+            ";",
+            Applicability::MaybeIncorrect,
+        )
+    }
+
+    /// `{ pat if guard => expr, ... }` after the scrutinee. The comma after an arm may
+    /// be dropped if its body is a block.
+    pub(super) fn match_arms(&mut self) -> PResult<Vec<Arm>> {
+        self.expect(&TokenKind::OpenBrace)?;
+        let mut arms = Vec::new();
+        while !self.eat(&TokenKind::CloseBrace) {
+            let pat = self.pat()?;
+            let guard = if self.eat_keyword(Keyword::If) {
+                Some(Box::new(self.with_struct_literals(true, Parser::expr)?))
+            } else {
+                None
+            };
+            self.expect(&TokenKind::FatArrow)?;
+            let body = self.with_struct_literals(true, Parser::stmt_expr)?;
+            let span = pat.span.to(body.span);
+            let needs_comma = !body.kind.is_block_like();
+            let ate_comma = self.eat(&TokenKind::Comma);
+            arms.push(Arm { pat, guard, body, span });
+            if !ate_comma && needs_comma && !self.check(&TokenKind::CloseBrace) {
+                return Err(self.unexpected("`,`").suggest(
+                    "add a comma after the arm",
+                    span.shrink_to_hi(),
+                    ",",
+                    Applicability::MachineApplicable,
+                ));
+            }
+        }
+        Ok(arms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_program;
+    use crate::ast::{ExprKind, StmtKind};
+    use crate::run;
+
+    #[test]
+    fn tail_expression_versus_statement() {
+        let program = parse_program("let msg = { let a = 2; a * 21 }; msg").unwrap();
+        assert!(matches!(program.stmts[0].kind, StmtKind::Let(_)));
+        assert!(matches!(program.tail.as_deref().map(|e| &e.kind), Some(ExprKind::Path(_))));
+
+        let program = parse_program("{ 42; }").unwrap();
+        let block = match program.tail.as_deref().map(|e| &e.kind) {
+            Some(ExprKind::Block(block)) => block,
+            other => panic!("expected a block, found {:?}", other),
+        };
+        assert!(block.tail.is_none());
+        assert!(matches!(block.stmts[0].kind, StmtKind::Semi(_)));
+    }
+
+    #[test]
+    fn block_like_expressions_end_statements() {
+        let program = parse_program("if a { 1 } else { 2 } let b = 3;").unwrap();
+        assert!(matches!(program.stmts[0].kind, StmtKind::Expr(_)));
+        assert!(matches!(program.stmts[1].kind, StmtKind::Let(_)));
+        assert!(program.tail.is_none());
+        // Other expressions need a `;` before the next statement.
+        let error = parse_program("let a = 1; a + 1 let b = 2;").unwrap_err();
+        assert_eq!(error.message, "expected `;`, found keyword `let`");
+    }
+
+    #[test]
+    fn blocks_if_match_and_loops_are_values() {
+        let run = |src| run(src).unwrap().to_string();
+        assert_eq!(run("let msg = { let a = 2; a * 21 }; msg"), "42");
+        assert_eq!(run("let x = { 42; }; x"), "()");
+        assert_eq!(
+            run("let n = 4; let s = if n % 2 == 0 { \"even\" } else { \"odd\" }; s"),
+            "\"even\""
+        );
+        assert_eq!(run("let n = Some(3); match n { None => 0, Some(c) => 255 - c }"), "252");
+        assert_eq!(run("let mut i = 1; let p = loop { i *= 2; if i > 50 { break i; } }; p"), "64");
+    }
+}
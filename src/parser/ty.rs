@@ -0,0 +1,117 @@
+//! Types, as they appear in `as` casts, `let` annotations and turbofish arguments.
+
+use super::{PResult, Parser};
+use crate::ast::{Ident, Mutability, Path, PathSegment, Ty, TyKind};
+use crate::lexer::{Keyword, TokenKind};
+
+impl Parser {
+    pub fn ty(&mut self) -> PResult<Ty> {
+        self.nested(Parser::ty_inner)
+    }
+
+    fn ty_inner(&mut self) -> PResult<Ty> {
+        let start = self.span();
+        let kind = match self.peek() {
+            TokenKind::And | TokenKind::AndAnd => {
+                let double = self.check(&TokenKind::AndAnd);
+                self.bump();
+                let mutability =
+                    if self.eat_keyword(Keyword::Mut) { Mutability::Mut } else { Mutability::Not };
+                let inner = self.ty()?;
+                let span = start.to(inner.span);
+                let kind = TyKind::Ref(mutability, Box::new(inner));
+                if double {
+                    TyKind::Ref(Mutability::Not, Box::new(Ty { kind, span }))
+                } else {
+                    kind
+                }
+            }
+            TokenKind::OpenParen => {
+                self.bump();
+                let mut elems = Vec::new();
+                let mut trailing_comma = false;
+                while !self.check(&TokenKind::CloseParen) {
+                    elems.push(self.ty()?);
+                    trailing_comma = self.eat(&TokenKind::Comma);
+                    if !trailing_comma {
+                        break;
+                    }
+                }
+                self.expect(&TokenKind::CloseParen)?;
+                if elems.len() == 1 && !trailing_comma {
+                    // `(T)` is just `T` in parentheses.
+                    let mut inner = elems.pop().unwrap();
+                    inner.span = start.to(self.prev_span);
+                    return Ok(inner);
+                }
+                TyKind::Tuple(elems)
+            }
+            TokenKind::OpenBracket => {
+                self.bump();
+                let elem = self.ty()?;
+                let kind = if self.eat(&TokenKind::Semi) {
+                    TyKind::Array(Box::new(elem), Box::new(self.expr()?))
+                } else {
+                    TyKind::Slice(Box::new(elem))
+                };
+                self.expect(&TokenKind::CloseBracket)?;
+                kind
+            }
+            TokenKind::Not => {
+                self.bump();
+                TyKind::Never
+            }
+            TokenKind::Underscore => {
+                self.bump();
+                TyKind::Infer
+            }
+            _ => TyKind::Path(self.ty_path()?),
+        };
+        Ok(Ty { kind, span: start.to(self.prev_span) })
+    }
+
+    /// A path in type position, where generic arguments follow a plain `<`.
+    fn ty_path(&mut self) -> PResult<Path> {
+        let start = self.span();
+        self.eat(&TokenKind::PathSep);
+        let mut segments = Vec::new();
+        loop {
+            let ident = self.path_segment_ident()?;
+            let mut segment = PathSegment { ident, generics: Vec::new() };
+            if self.eat(&TokenKind::Lt) || self.eat(&TokenKind::Turbofish) {
+                segment.generics = self.generic_args()?;
+            }
+            segments.push(segment);
+            if !self.eat(&TokenKind::PathSep) {
+                break;
+            }
+        }
+        Ok(Path { segments, span: start.to(self.prev_span) })
+    }
+
+    /// The types between `<` and `>`, after the `<` has been eaten.
+    pub(super) fn generic_args(&mut self) -> PResult<Vec<Ty>> {
+        let mut args = Vec::new();
+        while !self.eat_gt() {
+            args.push(self.ty()?);
+            if !self.eat(&TokenKind::Comma) {
+                self.expect_gt()?;
+                break;
+            }
+        }
+        Ok(args)
+    }
+
+    /// A path segment: an identifier or one of the path keywords `self`, `Self`, `crate`, `super`.
+    pub(super) fn path_segment_ident(&mut self) -> PResult<Ident> {
+        match self.peek() {
+            TokenKind::Keyword(
+                kw @ (Keyword::SelfValue | Keyword::SelfType | Keyword::Crate | Keyword::Super),
+            ) => {
+                let name = kw.as_str();
+                Ok(Ident::new(name, self.bump().span))
+            }
+            _ => self.ident(),
+        }
+    }
+}
@@ -0,0 +1,618 @@
+//! Prints a syntax tree back as source text, for showing what the parser
+//! desugared a program into.
+//!
+//! The output is laid out afresh, four spaces to an indent, so only the
+//! structure of the source survives: comments and the original spacing are
+//! gone, and literals are written in decimal.
+//!
+//! `?` stays in the tree, since what it expands to depends on its operand's
+//! type, but is printed as the `match` it stands for: on an `Option`,
+//!
+//! ```text
+//! match expr {
+//!     Some(val) => val,
+//!     None => return None,
+//! }
+//! ```
+//!
+//! and otherwise the `Result` form, whose `Err` arm is
+//! `Err(err) => return Err(From::from(err))`.
+
+use crate::ast::{
+    Arm, BindingMode, Block, CaptureBy, Closure, EnumDecl, Expr, ExprKind, FnDecl, Ident, Item,
+    ItemKind, LitKind, Param, Pat, PatKind, Path, Stmt, StmtKind, StructDecl, StructFields, Ty,
+    TyKind, UseTree, UseTreeKind,
+};
+use crate::typeck::ty::Type;
+use crate::typeck::TypeckResults;
+
+/// The source text of a program: the inside of its block, without braces.
+/// `types`, if given, tells which `?`s are on an `Option`.
+pub fn program(program: &Block, types: Option<&TypeckResults>) -> String {
+    let mut printer = Printer { types, ..Printer::default() };
+    printer.block_body(program);
+    printer.out
+}
+
+/// The source text of a single expression.
+pub fn expr(expr: &Expr) -> String {
+    let mut printer = Printer::default();
+    printer.expr(expr);
+    printer.out
+}
+
+/// The source text of a type.
+pub fn ty(ty: &Ty) -> String {
+    let mut printer = Printer::default();
+    printer.ty(ty);
+    printer.out
+}
+
+#[derive(Default)]
+struct Printer<'a> {
+    out: String,
+    indent: usize,
+    types: Option<&'a TypeckResults>,
+}
+
+impl Printer<'_> {
+    fn word(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    /// `items` printed one after another with `", "` between them.
+    fn commasep<T>(&mut self, items: &[T], mut print: impl FnMut(&mut Self, &T)) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.word(", ");
+            }
+            print(self, item);
+        }
+    }
+
+    /// The statements and tail of a block, one to a line.
+    fn block_body(&mut self, block: &Block) {
+        let lines = block.stmts.iter().map(Some).chain(block.tail.as_ref().map(|_| None));
+        for (i, stmt) in lines.enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            match stmt {
+                Some(stmt) => self.stmt(stmt),
+                None => self.expr(block.tail.as_ref().expect("the last line is the tail")),
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        if block.stmts.is_empty() && block.tail.is_none() {
+            self.word("{}");
+            return;
+        }
+        self.word("{");
+        self.indent += 1;
+        self.newline();
+        self.block_body(block);
+        self.indent -= 1;
+        self.newline();
+        self.word("}");
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let(local) => {
+                self.word("let ");
+                self.pat(&local.pat);
+                if let Some(ty) = &local.ty {
+                    self.word(": ");
+                    self.ty(ty);
+                }
+                if let Some(init) = &local.init {
+                    self.word(" = ");
+                    self.expr(init);
+                }
+                self.word(";");
+            }
+            StmtKind::Item(item) => self.item(item),
+            StmtKind::Expr(expr) => self.expr(expr),
+            StmtKind::Semi(expr) => {
+                self.expr(expr);
+                self.word(";");
+            }
+            StmtKind::Empty => self.word(";"),
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        match &item.kind {
+            ItemKind::Fn(decl) => self.fn_decl(decl),
+            ItemKind::Struct(decl) => self.struct_decl(decl),
+            ItemKind::Enum(decl) => self.enum_decl(decl),
+            ItemKind::Use(tree) => {
+                self.word("use ");
+                self.use_tree(tree);
+                self.word(";");
+            }
+        }
+    }
+
+    fn fn_decl(&mut self, decl: &FnDecl) {
+        self.word("fn ");
+        self.ident(&decl.name);
+        self.word("(");
+        self.commasep(&decl.params, Printer::param);
+        self.word(")");
+        if let Some(ret) = &decl.ret {
+            self.word(" -> ");
+            self.ty(ret);
+        }
+        self.word(" ");
+        self.block(&decl.body);
+    }
+
+    fn struct_decl(&mut self, decl: &StructDecl) {
+        self.word("struct ");
+        self.ident(&decl.name);
+        self.struct_fields(&decl.fields);
+        if !matches!(decl.fields, StructFields::Named(_)) {
+            self.word(";");
+        }
+    }
+
+    fn enum_decl(&mut self, decl: &EnumDecl) {
+        self.word("enum ");
+        self.ident(&decl.name);
+        self.word(" {");
+        self.indent += 1;
+        for variant in &decl.variants {
+            self.newline();
+            self.ident(&variant.name);
+            self.struct_fields(&variant.fields);
+            self.word(",");
+        }
+        self.indent -= 1;
+        self.newline();
+        self.word("}");
+    }
+
+    fn struct_fields(&mut self, fields: &StructFields) {
+        match fields {
+            StructFields::Named(fields) => {
+                self.word(" { ");
+                self.commasep(fields, |p, field| {
+                    p.ident(&field.name);
+                    p.word(": ");
+                    p.ty(&field.ty);
+                });
+                self.word(" }");
+            }
+            StructFields::Tuple(tys) => {
+                self.word("(");
+                self.commasep(tys, Printer::ty);
+                self.word(")");
+            }
+            StructFields::Unit => {}
+        }
+    }
+
+    fn use_tree(&mut self, tree: &UseTree) {
+        self.path(&tree.prefix, false);
+        match &tree.kind {
+            UseTreeKind::Simple(None) => {}
+            UseTreeKind::Simple(Some(alias)) => {
+                self.word(" as ");
+                self.ident(alias);
+            }
+            UseTreeKind::Nested(trees) => {
+                self.word("::{");
+                self.commasep(trees, Printer::use_tree);
+                self.word("}");
+            }
+            UseTreeKind::Glob => self.word("::*"),
+        }
+    }
+
+    fn param(&mut self, param: &Param) {
+        self.pat(&param.pat);
+        if let Some(ty) = &param.ty {
+            self.word(": ");
+            self.ty(ty);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(lit) => self.lit(lit),
+            ExprKind::Path(path) => self.path(path, true),
+            ExprKind::Unary(op, operand) => {
+                self.word(op.as_str());
+                self.expr(operand);
+            }
+            ExprKind::AddrOf(mutability, operand) => {
+                self.word("&");
+                self.word(mutability.prefix_str());
+                self.expr(operand);
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                self.expr(lhs);
+                self.word(&format!(" {} ", op.as_str()));
+                self.expr(rhs);
+            }
+            ExprKind::Assign(lhs, rhs) => {
+                self.expr(lhs);
+                self.word(" = ");
+                self.expr(rhs);
+            }
+            ExprKind::AssignOp(op, lhs, rhs) => {
+                self.expr(lhs);
+                self.word(&format!(" {}= ", op.as_str()));
+                self.expr(rhs);
+            }
+            ExprKind::Cast(operand, ty) => {
+                self.expr(operand);
+                self.word(" as ");
+                self.ty(ty);
+            }
+            ExprKind::Range(start, end, limits) => {
+                if let Some(start) = start {
+                    self.expr(start);
+                }
+                self.word(limits.as_str());
+                if let Some(end) = end {
+                    self.expr(end);
+                }
+            }
+            ExprKind::Call(callee, args) => {
+                self.expr(callee);
+                self.word("(");
+                self.commasep(args, Printer::expr);
+                self.word(")");
+            }
+            ExprKind::MethodCall(call) => {
+                self.expr(&call.receiver);
+                self.word(".");
+                self.ident(&call.method);
+                self.generics(&call.generics, true);
+                self.word("(");
+                self.commasep(&call.args, Printer::expr);
+                self.word(")");
+            }
+            ExprKind::Field(base, name) => {
+                self.expr(base);
+                self.word(".");
+                self.ident(name);
+            }
+            ExprKind::TupleField(base, index, _) => {
+                self.expr(base);
+                self.word(&format!(".{}", index));
+            }
+            ExprKind::Index(base, index) => {
+                self.expr(base);
+                self.word("[");
+                self.expr(index);
+                self.word("]");
+            }
+            ExprKind::Try(operand) => {
+                let is_option = self.types.and_then(|types| types.node_type(operand.id));
+                let arms = match is_option {
+                    Some(Type::Adt(name, _)) if name == "Option" => {
+                        ["Some(val) => val,", "None => return None,"]
+                    }
+                    _ => ["Ok(val) => val,", "Err(err) => return Err(From::from(err)),"],
+                };
+                self.word("match ");
+                self.expr(operand);
+                self.word(" {");
+                self.indent += 1;
+                for arm in &arms {
+                    self.newline();
+                    self.word(arm);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.word("}");
+            }
+            ExprKind::Paren(inner) => {
+                self.word("(");
+                self.expr(inner);
+                self.word(")");
+            }
+            ExprKind::Tuple(elems) => {
+                self.word("(");
+                self.commasep(elems, Printer::expr);
+                if elems.len() == 1 {
+                    self.word(",");
+                }
+                self.word(")");
+            }
+            ExprKind::Array(elems) => {
+                self.word("[");
+                self.commasep(elems, Printer::expr);
+                self.word("]");
+            }
+            ExprKind::Repeat(elem, len) => {
+                self.word("[");
+                self.expr(elem);
+                self.word("; ");
+                self.expr(len);
+                self.word("]");
+            }
+            ExprKind::Struct(lit) => {
+                self.path(&lit.path, true);
+                self.word(" { ");
+                self.commasep(&lit.fields, |p, field| {
+                    p.ident(&field.name);
+                    p.word(": ");
+                    p.expr(&field.value);
+                });
+                self.word(" }");
+            }
+            ExprKind::MacCall(mac) => {
+                self.ident(&mac.name);
+                self.word("!");
+                // `vec![1, 2]` holds the array `[1, 2]`, which brings its own
+                // brackets.
+                match mac.args.as_slice() {
+                    [arg] if mac.name.name == "vec" => self.expr(arg),
+                    args => {
+                        self.word("(");
+                        self.commasep(args, Printer::expr);
+                        self.word(")");
+                    }
+                }
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If(cond, then, els) => {
+                self.word("if ");
+                self.expr(cond);
+                self.word(" ");
+                self.block(then);
+                if let Some(els) = els {
+                    self.word(" else ");
+                    self.expr(els);
+                }
+            }
+            ExprKind::Let(pat, scrutinee) => {
+                self.word("let ");
+                self.pat(pat);
+                self.word(" = ");
+                self.expr(scrutinee);
+            }
+            ExprKind::Match(scrutinee, arms, _) => {
+                self.word("match ");
+                self.expr(scrutinee);
+                self.word(" {");
+                self.indent += 1;
+                for arm in arms {
+                    self.newline();
+                    self.arm(arm);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.word("}");
+            }
+            ExprKind::While(cond, body, label) => {
+                self.label(label);
+                self.word("while ");
+                self.expr(cond);
+                self.word(" ");
+                self.block(body);
+            }
+            ExprKind::Loop(body, label, _) => {
+                self.label(label);
+                self.word("loop ");
+                self.block(body);
+            }
+            ExprKind::For(pat, iter, body, label) => {
+                self.label(label);
+                self.word("for ");
+                self.pat(pat);
+                self.word(" in ");
+                self.expr(iter);
+                self.word(" ");
+                self.block(body);
+            }
+            ExprKind::Break(label, value) => {
+                self.word("break");
+                if let Some(label) = label {
+                    self.word(&format!(" '{}", label.name));
+                }
+                if let Some(value) = value {
+                    self.word(" ");
+                    self.expr(value);
+                }
+            }
+            ExprKind::Continue(label) => {
+                self.word("continue");
+                if let Some(label) = label {
+                    self.word(&format!(" '{}", label.name));
+                }
+            }
+            ExprKind::Return(value) => {
+                self.word("return");
+                if let Some(value) = value {
+                    self.word(" ");
+                    self.expr(value);
+                }
+            }
+            ExprKind::Closure(closure) => self.closure(closure),
+        }
+    }
+
+    fn label(&mut self, label: &Option<Ident>) {
+        if let Some(label) = label {
+            self.word(&format!("'{}: ", label.name));
+        }
+    }
+
+    fn arm(&mut self, arm: &Arm) {
+        self.pat(&arm.pat);
+        if let Some(guard) = &arm.guard {
+            self.word(" if ");
+            self.expr(guard);
+        }
+        self.word(" => ");
+        self.expr(&arm.body);
+        if !matches!(arm.body.kind, ExprKind::Block(_)) {
+            self.word(",");
+        }
+    }
+
+    fn closure(&mut self, closure: &Closure) {
+        if closure.capture == CaptureBy::Value {
+            self.word("move ");
+        }
+        self.word("|");
+        self.commasep(&closure.params, Printer::param);
+        self.word("|");
+        if let Some(ret) = &closure.ret {
+            self.word(" -> ");
+            self.ty(ret);
+        }
+        self.word(" ");
+        self.expr(&closure.body);
+    }
+
+    fn lit(&mut self, lit: &LitKind) {
+        let text = match lit {
+            LitKind::Int(n, suffix) => format!("{}{}", n, suffix.map_or("", |ty| ty.name())),
+            LitKind::Float(x, suffix) => format!("{:?}{}", x, suffix.map_or("", |ty| ty.name())),
+            LitKind::Bool(b) => b.to_string(),
+            LitKind::Char(c) => format!("{:?}", c),
+            LitKind::Str(s) => format!("{:?}", s),
+        };
+        self.word(&text);
+    }
+
+    fn pat(&mut self, pat: &Pat) {
+        match &pat.kind {
+            PatKind::Wild => self.word("_"),
+            PatKind::Ident(mode, name, sub) => {
+                match mode {
+                    BindingMode::ByValue(mutability) => self.word(mutability.prefix_str()),
+                    BindingMode::ByRef(mutability) => {
+                        self.word("ref ");
+                        self.word(mutability.prefix_str());
+                    }
+                }
+                self.ident(name);
+                if let Some(sub) = sub {
+                    self.word(" @ ");
+                    self.pat(sub);
+                }
+            }
+            PatKind::Lit(lit) => self.expr(lit),
+            PatKind::Range(start, end, limits) => {
+                self.expr(start);
+                self.word(limits.as_str());
+                self.expr(end);
+            }
+            PatKind::Path(path) => self.path(path, true),
+            PatKind::TupleStruct(path, pats) => {
+                self.path(path, true);
+                self.word("(");
+                self.commasep(pats, Printer::pat);
+                self.word(")");
+            }
+            PatKind::Struct(path, fields, rest) => {
+                self.path(path, true);
+                self.word(" { ");
+                self.commasep(fields, |p, field| {
+                    p.ident(&field.name);
+                    p.word(": ");
+                    p.pat(&field.pat);
+                });
+                if *rest {
+                    self.word(if fields.is_empty() { ".." } else { ", .." });
+                }
+                self.word(" }");
+            }
+            PatKind::Tuple(pats) => {
+                self.word("(");
+                self.commasep(pats, Printer::pat);
+                if pats.len() == 1 {
+                    self.word(",");
+                }
+                self.word(")");
+            }
+            PatKind::Ref(inner, mutability) => {
+                self.word("&");
+                self.word(mutability.prefix_str());
+                self.pat(inner);
+            }
+            PatKind::Or(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        self.word(" | ");
+                    }
+                    self.pat(alternative);
+                }
+            }
+        }
+    }
+
+    fn ty(&mut self, ty: &Ty) {
+        match &ty.kind {
+            TyKind::Path(path) => self.path(path, false),
+            TyKind::Ref(mutability, inner) => {
+                self.word("&");
+                self.word(mutability.prefix_str());
+                self.ty(inner);
+            }
+            TyKind::Tuple(tys) => {
+                self.word("(");
+                self.commasep(tys, Printer::ty);
+                if tys.len() == 1 {
+                    self.word(",");
+                }
+                self.word(")");
+            }
+            TyKind::Array(elem, len) => {
+                self.word("[");
+                self.ty(elem);
+                self.word("; ");
+                self.expr(len);
+                self.word("]");
+            }
+            TyKind::Slice(elem) => {
+                self.word("[");
+                self.ty(elem);
+                self.word("]");
+            }
+            TyKind::Never => self.word("!"),
+            TyKind::Infer => self.word("_"),
+        }
+    }
+
+    /// A path, with `::` before generic arguments in an expression or
+    /// pattern: `Vec::<i32>::new`.
+    fn path(&mut self, path: &Path, turbofish: bool) {
+        for (i, segment) in path.segments.iter().enumerate() {
+            if i > 0 {
+                self.word("::");
+            }
+            self.ident(&segment.ident);
+            self.generics(&segment.generics, turbofish);
+        }
+    }
+
+    fn generics(&mut self, generics: &[Ty], turbofish: bool) {
+        if generics.is_empty() {
+            return;
+        }
+        self.word(if turbofish { "::<" } else { "<" });
+        self.commasep(generics, Printer::ty);
+        self.word(">");
+    }
+
+    fn ident(&mut self, ident: &Ident) {
+        self.word(&ident.name);
+    }
+}
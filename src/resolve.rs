@@ -0,0 +1,633 @@
+//! Name resolution.
+//!
+//! Every `let`, `match` arm, `for` loop and closure parameter introduces new
+//! bindings, as does every item declared in a block. A later binding of the
+//! same name shadows an earlier one without disturbing it. As the notes put
+//! it, `let line = line?;` declares a second variable, of a different type,
+//! whose initializer still refers to the first.
+//!
+//! The resolver builds the tree of scopes in a program, gives each binding
+//! an id, and records which binding every variable use refers to. As in
+//! rustc, a `let` opens a scope of its own covering the rest of its block,
+//! so shadowing is just an inner scope hiding an outer name. An item, on the
+//! other hand, belongs to its whole block: a `fn` can be called before its
+//! declaration. Its body cannot see the locals around it, though; only a
+//! closure can capture those.
+//!
+//! Loop labels are resolved here too. A `break 'label` or `continue 'label`
+//! must name a loop around it, in the same function or closure, and a label
+//! may not reuse the name of one it is nested in.
+//!
+//! ```
+//! use expressions::{parser, resolve::Resolver, typeck};
+//!
+//! let src = "let x = 5u8; let x = x as i64 * 2; x";
+//! let program = parser::parse_program(src).unwrap();
+//! let (types, _) = typeck::check(&program);
+//! let resolver = Resolver::new(&program, &types);
+//!
+//! let last_use = expressions::Span::new(src.len() - 1, src.len());
+//! let visible = resolver.bindings_at(last_use);
+//! assert_eq!(visible.len(), 1);
+//! let x = visible[0];
+//! assert_eq!(x.ty.as_ref().unwrap().to_string(), "i64");
+//! let shadowed = resolver.binding(x.shadows.unwrap());
+//! assert_eq!(shadowed.ty.as_ref().unwrap().to_string(), "u8");
+//! ```
+
+use std::collections::HashMap;
+
+use crate::ast::{
+    Block, Expr, ExprKind, Ident, Item, ItemKind, Mutability, NodeId, Pat, PatKind, Stmt, StmtKind,
+};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+use crate::typeck::ty::Type;
+use crate::typeck::TypeckResults;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BindingId(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(usize);
+
+/// A name introduced by a pattern or an item.
+#[derive(Clone, Debug)]
+pub struct Binding {
+    pub id: BindingId,
+    pub kind: BindingKind,
+    pub name: String,
+    pub mutability: Mutability,
+    /// The name in the pattern or item.
+    pub span: Span,
+    /// The scope the binding belongs to; it is visible throughout it.
+    pub scope: ScopeId,
+    /// The binding of the same name this one hides, if any was visible.
+    pub shadows: Option<BindingId>,
+    /// The binding's type, if the checker found one.
+    pub ty: Option<Type>,
+    /// Where the binding is used, in the order the uses were resolved.
+    pub uses: Vec<Span>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BindingKind {
+    /// A variable bound by a pattern.
+    Local,
+    Fn,
+    Struct,
+    Enum,
+    /// A name brought in by `use`.
+    Import,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// The whole program, or a `{ ... }` block.
+    Block,
+    /// The rest of a block after a `let` statement.
+    Let,
+    /// A `match` arm.
+    Arm,
+    /// The body of a `for` loop, where the loop pattern's bindings live.
+    For,
+    /// A closure's body, with its parameters.
+    Closure,
+    /// A `fn` item's body, with its parameters. The locals of the scopes
+    /// around it are out of its reach; only items are visible.
+    Fn,
+}
+
+#[derive(Clone, Debug)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    /// The source the scope covers: a binding in it can be used anywhere here.
+    pub span: Span,
+    pub bindings: Vec<BindingId>,
+}
+
+/// The scope tree of a program and what each name in it refers to.
+#[derive(Debug)]
+pub struct Resolver {
+    scopes: Vec<Scope>,
+    bindings: Vec<Binding>,
+    /// The binding each variable use refers to, by the use's node.
+    uses: HashMap<NodeId, BindingId>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolver {
+    /// Resolve the names in `program`, taking binding types from `types`.
+    pub fn new(program: &Block, types: &TypeckResults) -> Resolver {
+        let mut builder = Builder {
+            resolver: Resolver {
+                scopes: Vec::new(),
+                bindings: Vec::new(),
+                uses: HashMap::new(),
+                diagnostics: Vec::new(),
+            },
+            types,
+            current: None,
+            loops: Vec::new(),
+        };
+        builder.block(program);
+        builder.resolver
+    }
+
+    pub fn binding(&self, id: BindingId) -> &Binding {
+        &self.bindings[id.0]
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.0]
+    }
+
+    pub fn scopes(&self) -> &[Scope] {
+        &self.scopes
+    }
+
+    /// Errors found while resolving: uses of locals that are out of reach,
+    /// and `break`s and `continue`s without a loop to leave.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// The binding a variable use refers to, or `None` if the expression is
+    /// not a use of a local variable.
+    pub fn resolution(&self, expr: NodeId) -> Option<&Binding> {
+        self.uses.get(&expr).map(|&id| self.binding(id))
+    }
+
+    /// The bindings visible at `span`, in the order they were declared. A
+    /// shadowed binding is not visible: for each name, only the one a use at
+    /// `span` would refer to is included, and neither are the locals around
+    /// a `fn` item from inside its body.
+    pub fn bindings_at(&self, span: Span) -> Vec<&Binding> {
+        let innermost = self
+            .scopes
+            .iter()
+            .enumerate()
+            .filter(|(_, scope)| scope.span.contains(span))
+            .max_by_key(|&(id, _)| self.depth(ScopeId(id)));
+        let mut visible: Vec<&Binding> = Vec::new();
+        let mut scope = innermost.map(|(id, _)| ScopeId(id));
+        let mut in_fn = false;
+        while let Some(id) = scope {
+            let scope_ref = self.scope(id);
+            for &binding in scope_ref.bindings.iter().rev() {
+                let binding = self.binding(binding);
+                let reachable = !in_fn || binding.kind != BindingKind::Local;
+                if reachable && visible.iter().all(|seen| seen.name != binding.name) {
+                    visible.push(binding);
+                }
+            }
+            in_fn |= scope_ref.kind == ScopeKind::Fn;
+            scope = scope_ref.parent;
+        }
+        visible.sort_by_key(|binding| binding.id);
+        visible
+    }
+
+    fn depth(&self, mut id: ScopeId) -> usize {
+        let mut depth = 0;
+        while let Some(parent) = self.scope(id).parent {
+            depth += 1;
+            id = parent;
+        }
+        depth
+    }
+
+    /// The binding `name` refers to from `scope`, and whether finding it
+    /// meant leaving the body of a `fn` item.
+    fn lookup(&self, mut scope: Option<ScopeId>, name: &str) -> Option<(BindingId, bool)> {
+        let mut left_fn = false;
+        while let Some(id) = scope {
+            let scope_ref = self.scope(id);
+            let found = scope_ref.bindings.iter().rev().find(|&&b| self.binding(b).name == name);
+            if let Some(&binding) = found {
+                return Some((binding, left_fn));
+            }
+            left_fn |= scope_ref.kind == ScopeKind::Fn;
+            scope = scope_ref.parent;
+        }
+        None
+    }
+}
+
+struct Builder<'a> {
+    resolver: Resolver,
+    types: &'a TypeckResults,
+    current: Option<ScopeId>,
+    /// The loops around the current expression, innermost last.
+    loops: Vec<LoopScope>,
+}
+
+enum LoopScope {
+    Loop(Option<Ident>),
+    /// The body of a closure or `fn` item, which `break` and `continue`
+    /// cannot leave.
+    Boundary(ScopeKind, Span),
+}
+
+impl Builder<'_> {
+    /// Open a scope inside the current one. It stays current until `f`
+    /// returns.
+    fn in_scope(&mut self, kind: ScopeKind, span: Span, f: impl FnOnce(&mut Self)) {
+        let id = self.open(kind, span);
+        let outer = self.current.replace(id);
+        f(self);
+        self.current = outer;
+    }
+
+    fn open(&mut self, kind: ScopeKind, span: Span) -> ScopeId {
+        let id = ScopeId(self.resolver.scopes.len());
+        self.resolver.scopes.push(Scope { kind, parent: self.current, span, bindings: Vec::new() });
+        id
+    }
+
+    fn bind(&mut self, kind: BindingKind, name: &Ident, mutability: Mutability, ty: Option<Type>) {
+        let scope = self.current.expect("inside a scope");
+        let id = BindingId(self.resolver.bindings.len());
+        let shadows = self.resolver.lookup(Some(scope), &name.name).map(|(binding, _)| binding);
+        self.resolver.bindings.push(Binding {
+            id,
+            kind,
+            name: name.name.clone(),
+            mutability,
+            span: name.span,
+            scope,
+            shadows,
+            ty,
+            uses: Vec::new(),
+        });
+        self.resolver.scopes[scope.0].bindings.push(id);
+    }
+
+    /// Bind the names in `pat` in the current scope.
+    fn pat(&mut self, pat: &Pat) {
+        match &pat.kind {
+            PatKind::Ident(mode, name, sub) => {
+                let ty = self.types.node_type(pat.id).cloned();
+                self.bind(BindingKind::Local, name, mode.mutability(), ty);
+                if let Some(sub) = sub {
+                    self.pat(sub);
+                }
+            }
+            PatKind::Tuple(pats) | PatKind::TupleStruct(_, pats) => {
+                pats.iter().for_each(|pat| self.pat(pat));
+            }
+            PatKind::Struct(_, fields, _) => fields.iter().for_each(|field| self.pat(&field.pat)),
+            PatKind::Ref(pat, _) => self.pat(pat),
+            // Every alternative binds the same names; the first stands for all.
+            PatKind::Or(pats) => self.pat(&pats[0]),
+            PatKind::Wild | PatKind::Lit(_) | PatKind::Range(..) | PatKind::Path(_) => {}
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.in_scope(ScopeKind::Block, block.span, |this| {
+            // Items are visible throughout the block, even before they are
+            // declared.
+            for stmt in &block.stmts {
+                if let StmtKind::Item(item) = &stmt.kind {
+                    this.item_names(item);
+                }
+            }
+            // Each `let` opens a scope for the rest of the block, which stays
+            // open until the block ends.
+            let outer = this.current;
+            for stmt in &block.stmts {
+                this.stmt(stmt, block.span);
+            }
+            if let Some(tail) = &block.tail {
+                this.expr(tail);
+            }
+            this.current = outer;
+        });
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, block_span: Span) {
+        match &stmt.kind {
+            StmtKind::Let(local) => {
+                if let Some(init) = &local.init {
+                    self.expr(init);
+                }
+                let rest = Span::new(stmt.span.end, block_span.end);
+                self.current = Some(self.open(ScopeKind::Let, rest));
+                self.pat(&local.pat);
+            }
+            StmtKind::Item(item) => {
+                if let ItemKind::Fn(decl) = &item.kind {
+                    self.in_scope(ScopeKind::Fn, decl.body.span, |this| {
+                        decl.params.iter().for_each(|param| this.pat(&param.pat));
+                        this.in_loop(LoopScope::Boundary(ScopeKind::Fn, item.span), |this| {
+                            this.block(&decl.body)
+                        });
+                    });
+                }
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.expr(expr),
+            StmtKind::Empty => {}
+        }
+    }
+
+    fn item_names(&mut self, item: &Item) {
+        let (kind, ty) = match &item.kind {
+            ItemKind::Fn(_) => (BindingKind::Fn, self.types.node_type(item.id).cloned()),
+            ItemKind::Struct(decl) => {
+                (BindingKind::Struct, Some(Type::Adt(decl.name.name.clone(), Vec::new())))
+            }
+            ItemKind::Enum(decl) => {
+                (BindingKind::Enum, Some(Type::Adt(decl.name.name.clone(), Vec::new())))
+            }
+            ItemKind::Use(_) => (BindingKind::Import, None),
+        };
+        for name in item.names() {
+            self.bind(kind, name, Mutability::Not, ty.clone());
+        }
+    }
+
+    /// Resolve a use of `ident` in the expression `id`.
+    fn use_name(&mut self, id: NodeId, ident: &Ident) {
+        let (binding, left_fn) = match self.resolver.lookup(self.current, &ident.name) {
+            Some(found) => found,
+            None => return,
+        };
+        let declared = self.resolver.binding(binding);
+        if left_fn && declared.kind == BindingKind::Local {
+            let diagnostic = Diagnostic::error("can't capture dynamic environment in a fn item")
+                .code("E0434")
+                .primary(ident.span, "")
+                .secondary(
+                    declared.span,
+                    format!("`{}` is a local declared outside the `fn`", ident.name),
+                )
+                .help("use a closure instead: `|| { ... }` can see the variables around it");
+            self.resolver.diagnostics.push(diagnostic);
+            return;
+        }
+        self.resolver.uses.insert(id, binding);
+        self.resolver.bindings[binding.0].uses.push(ident.span);
+    }
+
+    /// Run `f` inside a loop or boundary. A loop's label must not shadow
+    /// that of a loop around it.
+    fn in_loop(&mut self, scope: LoopScope, f: impl FnOnce(&mut Self)) {
+        if let LoopScope::Loop(Some(label)) = &scope {
+            if let Some(outer) = self.label(&label.name) {
+                let diagnostic = Diagnostic::error(format!(
+                    "label name `'{}` shadows a label name that is already in scope",
+                    label.name
+                ))
+                .primary(label.span, format!("label `'{}` already in scope", label.name))
+                .secondary(outer.span, "first declared here")
+                .help("give the inner loop a label of its own");
+                self.resolver.diagnostics.push(diagnostic);
+            }
+        }
+        self.loops.push(scope);
+        f(self);
+        self.loops.pop();
+    }
+
+    /// The label of a loop around the current expression named `name`,
+    /// looking past closures and `fn` items.
+    fn label(&self, name: &str) -> Option<&Ident> {
+        self.loops.iter().rev().find_map(|scope| match scope {
+            LoopScope::Loop(Some(label)) if label.name == name => Some(label),
+            _ => None,
+        })
+    }
+
+    /// Check that a `break` or `continue`, spelled `keyword`, has a loop to
+    /// leave: the one `label` names, or else the innermost.
+    fn jump(&mut self, keyword: &str, label: Option<&Ident>, span: Span) {
+        let boundary =
+            self.loops.iter().rposition(|scope| matches!(scope, LoopScope::Boundary(..)));
+        let reachable = &self.loops[boundary.map_or(0, |i| i + 1)..];
+        let diagnostic = match label {
+            Some(label) => {
+                let names = |scope: &LoopScope| match scope {
+                    LoopScope::Loop(Some(found)) => found.name == label.name,
+                    _ => false,
+                };
+                if reachable.iter().any(names) {
+                    return;
+                }
+                match self.label(&label.name) {
+                    Some(outer) => {
+                        Diagnostic::error(format!("use of unreachable label `'{}`", label.name))
+                            .code("E0767")
+                            .primary(label.span, format!("unreachable label `'{}`", label.name))
+                            .secondary(outer.span, "unreachable label defined here")
+                            .note("labels are unreachable through functions and closures")
+                    }
+                    None => Diagnostic::error(format!("use of undeclared label `'{}`", label.name))
+                        .code("E0426")
+                        .primary(label.span, format!("undeclared label `'{}`", label.name)),
+                }
+            }
+            None if !reachable.is_empty() => return,
+            None => match boundary.map(|i| &self.loops[i]) {
+                Some(LoopScope::Boundary(ScopeKind::Closure, closure)) => {
+                    Diagnostic::error(format!("`{}` inside of a closure", keyword))
+                        .code("E0267")
+                        .primary(span, format!("cannot `{}` inside of a closure", keyword))
+                        .secondary(*closure, "enclosing closure")
+                }
+                _ => Diagnostic::error(format!("`{}` outside of a loop", keyword))
+                    .code("E0268")
+                    .primary(span, format!("cannot `{}` outside of a loop", keyword)),
+            },
+        };
+        self.resolver.diagnostics.push(diagnostic);
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Lit(_) => {}
+            ExprKind::Path(path) => {
+                if let Some(ident) = path.as_ident() {
+                    self.use_name(expr.id, ident);
+                }
+            }
+            ExprKind::Unary(_, operand)
+            | ExprKind::AddrOf(_, operand)
+            | ExprKind::Cast(operand, _)
+            | ExprKind::Field(operand, _)
+            | ExprKind::TupleField(operand, ..)
+            | ExprKind::Try(operand)
+            | ExprKind::Paren(operand) => self.expr(operand),
+            ExprKind::Binary(_, lhs, rhs)
+            | ExprKind::Assign(lhs, rhs)
+            | ExprKind::AssignOp(_, lhs, rhs)
+            | ExprKind::Index(lhs, rhs)
+            | ExprKind::Repeat(lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Range(start, end, _) => {
+                start.iter().chain(end).for_each(|operand| self.expr(operand));
+            }
+            ExprKind::Call(callee, args) => {
+                self.expr(callee);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::MethodCall(call) => {
+                self.expr(&call.receiver);
+                call.args.iter().for_each(|arg| self.expr(arg));
+            }
+            ExprKind::Tuple(elems) | ExprKind::Array(elems) => {
+                elems.iter().for_each(|elem| self.expr(elem));
+            }
+            ExprKind::Struct(lit) => lit.fields.iter().for_each(|field| self.expr(&field.value)),
+            ExprKind::MacCall(mac) => mac.args.iter().for_each(|arg| self.expr(arg)),
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::Let(..) => {
+                unreachable!("`if let` and `while let` are desugared by the parser")
+            }
+            ExprKind::If(cond, then, els) => {
+                self.expr(cond);
+                self.block(then);
+                if let Some(els) = els {
+                    self.expr(els);
+                }
+            }
+            ExprKind::Match(scrutinee, arms, _) => {
+                self.expr(scrutinee);
+                for arm in arms {
+                    self.in_scope(ScopeKind::Arm, arm.span, |this| {
+                        this.pat(&arm.pat);
+                        if let Some(guard) = &arm.guard {
+                            this.expr(guard);
+                        }
+                        this.expr(&arm.body);
+                    });
+                }
+            }
+            ExprKind::While(cond, body, label) => {
+                self.in_loop(LoopScope::Loop(label.clone()), |this| {
+                    this.expr(cond);
+                    this.block(body);
+                });
+            }
+            ExprKind::Loop(body, label, _) => {
+                self.in_loop(LoopScope::Loop(label.clone()), |this| this.block(body));
+            }
+            ExprKind::For(pat, iter, body, label) => {
+                self.expr(iter);
+                self.in_loop(LoopScope::Loop(label.clone()), |this| {
+                    this.in_scope(ScopeKind::For, body.span, |this| {
+                        this.pat(pat);
+                        this.block(body);
+                    });
+                });
+            }
+            ExprKind::Break(label, value) => {
+                self.jump("break", label.as_ref(), expr.span);
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            ExprKind::Continue(label) => self.jump("continue", label.as_ref(), expr.span),
+            ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            ExprKind::Closure(closure) => {
+                self.in_scope(ScopeKind::Closure, closure.body.span, |this| {
+                    closure.params.iter().for_each(|param| this.pat(&param.pat));
+                    this.in_loop(LoopScope::Boundary(ScopeKind::Closure, expr.span), |this| {
+                        this.expr(&closure.body)
+                    });
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::well_typed;
+
+    fn resolve(src: &str) -> Resolver {
+        let (program, types) = well_typed(src);
+        Resolver::new(&program, &types)
+    }
+
+    /// The text of each use of `binding`.
+    fn uses<'a>(src: &'a str, binding: &Binding) -> Vec<&'a str> {
+        binding.uses.iter().map(|span| span.text(src)).collect()
+    }
+
+    #[test]
+    fn shadowing_declares_a_second_variable() {
+        let src = "fn read() -> Result<i32, u8> { Ok(1) } \
+                   fn f() -> Result<i32, u8> { let line = read(); let line = line?; Ok(line) } f()";
+        let resolver = resolve(src);
+        let lines: Vec<_> = resolver.bindings().iter().filter(|b| b.name == "line").collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].shadows, Some(lines[0].id));
+        assert_eq!(lines[0].ty.as_ref().unwrap().to_string(), "Result<i32, u8>");
+        assert_eq!(lines[1].ty.as_ref().unwrap().to_string(), "i32");
+        // The second `line`'s initializer uses the first.
+        let first_use = lines[0].uses[0];
+        assert!(first_use.start > lines[1].span.start && first_use.end <= src.find("?;").unwrap());
+        assert_eq!(lines[1].uses.len(), 1);
+    }
+
+    #[test]
+    fn bindings_at_sees_the_innermost() {
+        let src = "let x = 1; let y = 2; match y { x => x + 1 }";
+        let resolver = resolve(src);
+        let at = Span::new(src.len() - 7, src.len() - 6);
+        let visible: Vec<_> =
+            resolver.bindings_at(at).iter().map(|b| (b.name.as_str(), b.span.start)).collect();
+        assert_eq!(visible, [("y", 15), ("x", 32)]);
+        let scope = resolver.scope(resolver.bindings()[2].scope);
+        assert_eq!(scope.kind, ScopeKind::Arm);
+    }
+
+    #[test]
+    fn items_belong_to_their_whole_block() {
+        let src = "let a = twice(2); fn twice(n: i32) -> i32 { n * 2 } a";
+        let resolver = resolve(src);
+        let twice = resolver.bindings().iter().find(|b| b.name == "twice").unwrap();
+        assert_eq!(twice.kind, BindingKind::Fn);
+        assert_eq!(uses(src, twice), ["twice"]);
+        assert!(resolver.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn fn_items_cannot_capture_locals() {
+        let resolver = resolve("let n = 1; fn get() -> i32 { n } get()");
+        let diagnostic = &resolver.diagnostics()[0];
+        assert_eq!(diagnostic.code, Some("E0434"));
+        assert_eq!(diagnostic.message, "can't capture dynamic environment in a fn item");
+        assert!(resolve("let n = 1; let get = || n; get()").diagnostics().is_empty());
+    }
+
+    #[test]
+    fn loop_labels() {
+        let message = |src| resolve(src).diagnostics()[0].message.clone();
+        assert_eq!(
+            message("'a: loop { 'a: loop { break 'a; } }"),
+            "label name `'a` shadows a label name that is already in scope"
+        );
+        assert_eq!(message("loop { break 'nope; }"), "use of undeclared label `'nope`");
+        assert_eq!(message("continue;"), "`continue` outside of a loop");
+        // A closure's body is not inside the loops around it.
+        assert_eq!(
+            message("'a: loop { let f = || { break 'a; }; }"),
+            "use of unreachable label `'a`"
+        );
+        assert!(resolve("'a: loop { 'b: loop { break 'a; } }").diagnostics().is_empty());
+    }
+}
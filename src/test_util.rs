@@ -0,0 +1,25 @@
+//! Helpers the tests of several passes share.
+
+use crate::ast::Block;
+use crate::diagnostic::Diagnostic;
+use crate::parser::parse_program;
+use crate::typeck::{self, TypeckResults};
+
+/// The value of the program `src`, which must run without errors.
+pub fn eval(src: &str) -> String {
+    crate::run(src).unwrap().to_string()
+}
+
+/// What the type checker reports about the program `src`, which must parse.
+pub fn check_types(src: &str) -> Vec<Diagnostic> {
+    typeck::check(&parse_program(src).unwrap()).1
+}
+
+/// The program `src` and its types, for testing the passes after type
+/// checking. It must parse and type check without errors.
+pub fn well_typed(src: &str) -> (Block, TypeckResults) {
+    let program = parse_program(src).unwrap();
+    let (types, diagnostics) = typeck::check(&program);
+    assert!(!diagnostics.iter().any(Diagnostic::is_error), "{:#?}", diagnostics);
+    (program, types)
+}
@@ -0,0 +1,768 @@
+//! Exhaustiveness and reachability of `match` arms.
+//!
+//! The arms' patterns are the rows of a matrix, and both questions come
+//! down to whether a pattern is *useful* after some rows: whether some value
+//! matches it but none of the rows (Maranget, "Warnings for pattern
+//! matching"). An arm that is not useful after the arms above it can never
+//! run. A `match` is exhaustive when a wildcard after all its arms would not
+//! be useful; when it would be, the values that reach it are the witnesses
+//! the error names: "`King` not covered".
+//!
+//! Integers and `char`s are matched by ranges. A column of them is split at
+//! every boundary of the ranges its patterns mention, so each piece is
+//! either inside or outside each pattern.
+
+use std::convert::TryFrom;
+
+use super::pat::bound_value;
+use super::ty::Type;
+use super::{int_const, Checker, StructDef};
+use crate::ast::{
+    Arm, Expr, ExprKind, IntTy, LitKind, MatchSource, Pat, PatKind, Path, RangeLimits,
+};
+use crate::diagnostic::{Diagnostic, Lint};
+
+/// Added to a signed integer's two's complement bits so that the values
+/// keep their order as `u128`s.
+const SIGN: u128 = 1 << 127;
+
+/// A pattern reduced to what matters for matching: the constructor it
+/// requires, and the patterns for that constructor's fields.
+#[derive(Clone, Debug)]
+enum DPat {
+    Wild,
+    Ctor(Ctor, Vec<DPat>),
+    Or(Vec<DPat>),
+}
+
+type Row = Vec<DPat>;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Ctor {
+    /// The only constructor of a tuple, a struct or a reference.
+    Single,
+    /// An enum variant, by its index in the declaration.
+    Variant(usize),
+    Bool(bool),
+    /// The integers or `char`s from the first bound to the second,
+    /// inclusive, encoded as by [`encode`].
+    Range(u128, u128),
+    /// A float or string literal, or a constructor of a type we know
+    /// nothing about. It matches only itself.
+    Opaque(String),
+}
+
+/// The constructors a type's values are built with.
+enum Signature {
+    Finite(Vec<Ctor>),
+    /// Floats, strings and arrays, which no set of constructor patterns
+    /// covers.
+    Infinite,
+    /// A type the checker could not infer, or never saw declared.
+    Unknown,
+}
+
+/// The matrix algorithm, run for one `match`.
+struct MatchCx<'c> {
+    checker: &'c Checker,
+    /// Whether the constructors the arms use for a type of unknown
+    /// definition count as all of them. Programs use types they never
+    /// declare, so `match rank { Jack => 10, Queen => 10 }` is not rejected
+    /// for want of knowing `Rank`; for reachability, nothing is assumed.
+    trust_unknown: bool,
+}
+
+impl Checker {
+    /// Warn about arms that can never run, and report values that no arm
+    /// matches. `ty` is the scrutinee's type.
+    pub(super) fn check_match(
+        &mut self,
+        scrutinee: &Expr,
+        ty: &Type,
+        arms: &[Arm],
+        source: MatchSource,
+    ) {
+        let ty = self.infer.resolve_final(ty);
+        let pats: Vec<DPat> = arms.iter().map(|arm| self.lower(&arm.pat, &ty)).collect();
+        let tys = [ty.clone()];
+        let mut diagnostics = Vec::new();
+
+        let cx = MatchCx { checker: self, trust_unknown: false };
+        let mut rows: Vec<Row> = Vec::new();
+        let mut catch_all = None;
+        for (arm, pat) in arms.iter().zip(&pats) {
+            if cx.useful(&rows, std::slice::from_ref(pat), &tys).is_empty() {
+                // The `_` arm of an `if let` or `while let` is unreachable
+                // when its pattern always matches.
+                if source != MatchSource::Normal {
+                    diagnostics.push(irrefutable_let(&arms[0].pat, source));
+                    continue;
+                }
+                let mut diagnostic = Diagnostic::warning("unreachable pattern")
+                    .primary(arm.pat.span, "unreachable pattern")
+                    .lint(Lint::UnreachablePatterns);
+                if let Some(span) = catch_all {
+                    diagnostic = diagnostic.secondary(span, "matches any value");
+                }
+                diagnostics.push(diagnostic);
+            }
+            if arm.guard.is_none() {
+                if matches!(pat, DPat::Wild) && catch_all.is_none() {
+                    catch_all = Some(arm.pat.span);
+                }
+                rows.push(vec![pat.clone()]);
+            }
+        }
+
+        let cx = MatchCx { checker: self, trust_unknown: true };
+        let mut witnesses: Vec<String> = Vec::new();
+        for witness in cx.useful(&rows, &[DPat::Wild], &tys) {
+            let witness = format!("`{}`", self.print(&witness[0], &ty));
+            if !witnesses.contains(&witness) {
+                witnesses.push(witness);
+            }
+        }
+        if !witnesses.is_empty() {
+            let listed = join_witnesses(&witnesses);
+            let s = if witnesses.len() == 1 { "" } else { "s" };
+            diagnostics.push(
+                Diagnostic::error(format!("non-exhaustive patterns: {} not covered", listed))
+                    .code("E0004")
+                    .primary(scrutinee.span, format!("pattern{} {} not covered", s, listed))
+                    .help(format!(
+                        "ensure that all possible cases are being handled by adding a match arm \
+                         with a wildcard pattern or an explicit pattern such as {}",
+                        witnesses[0]
+                    )),
+            );
+        }
+        self.diagnostics.extend(diagnostics);
+    }
+
+    /// Reduce `pat`, which matches values of type `ty`, to constructors.
+    fn lower(&self, pat: &Pat, ty: &Type) -> DPat {
+        // A pattern that is not a reference pattern looks through references
+        // to the value underneath.
+        if let Type::Ref(_, inner) = ty {
+            let derefs = !matches!(
+                pat.kind,
+                PatKind::Wild | PatKind::Ident(..) | PatKind::Ref(..) | PatKind::Or(_)
+            );
+            if derefs {
+                return DPat::Ctor(Ctor::Single, vec![self.lower(pat, inner)]);
+            }
+        }
+        match &pat.kind {
+            PatKind::Wild | PatKind::Ident(_, _, None) => DPat::Wild,
+            PatKind::Ident(_, _, Some(sub)) => self.lower(sub, ty),
+            PatKind::Lit(lit) => match &lit.kind {
+                ExprKind::Lit(LitKind::Bool(b)) => DPat::Ctor(Ctor::Bool(*b), Vec::new()),
+                _ => match pat_value(lit, ty) {
+                    Some(value) => DPat::Ctor(Ctor::Range(value, value), Vec::new()),
+                    None => DPat::Ctor(Ctor::Opaque(format!("{:?}", lit.kind)), Vec::new()),
+                },
+            },
+            PatKind::Range(start, end, limits) => {
+                let end = match (pat_value(end, ty), limits) {
+                    (Some(end), RangeLimits::HalfOpen) => end.checked_sub(1),
+                    (end, _) => end,
+                };
+                match (pat_value(start, ty), end) {
+                    (Some(start), Some(end)) if start <= end => {
+                        DPat::Ctor(Ctor::Range(start, end), Vec::new())
+                    }
+                    _ => DPat::Ctor(Ctor::Opaque(format!("{:?}", pat.kind)), Vec::new()),
+                }
+            }
+            PatKind::Path(path) => {
+                if let Some(value) = path_value(path, ty) {
+                    return DPat::Ctor(Ctor::Range(value, value), Vec::new());
+                }
+                DPat::Ctor(self.path_ctor(path, ty), Vec::new())
+            }
+            PatKind::TupleStruct(path, pats) => {
+                let ctor = self.path_ctor(path, ty);
+                let tys = self.field_types(ty, &ctor, pats.len());
+                let fields = pats.iter().zip(&tys).map(|(pat, ty)| self.lower(pat, ty)).collect();
+                DPat::Ctor(ctor, fields)
+            }
+            PatKind::Struct(path, field_pats, _) => {
+                let ctor = self.path_ctor(path, ty);
+                let def = match self.ctor_def(ty, &ctor) {
+                    Some(def) => def,
+                    None => return DPat::Ctor(ctor, Vec::new()),
+                };
+                let fields = def
+                    .fields
+                    .iter()
+                    .map(|(name, ty)| {
+                        match field_pats.iter().find(|field| field.name.name == *name) {
+                            Some(field) => self.lower(&field.pat, ty),
+                            None => DPat::Wild,
+                        }
+                    })
+                    .collect();
+                DPat::Ctor(ctor, fields)
+            }
+            PatKind::Tuple(pats) => {
+                let tys = self.field_types(ty, &Ctor::Single, pats.len());
+                let fields = pats.iter().zip(&tys).map(|(pat, ty)| self.lower(pat, ty)).collect();
+                DPat::Ctor(Ctor::Single, fields)
+            }
+            PatKind::Ref(inner, _) => {
+                let inner_ty = match ty {
+                    Type::Ref(_, inner) => (**inner).clone(),
+                    _ => Type::Error,
+                };
+                DPat::Ctor(Ctor::Single, vec![self.lower(inner, &inner_ty)])
+            }
+            PatKind::Or(alternatives) => {
+                DPat::Or(alternatives.iter().map(|pat| self.lower(pat, ty)).collect())
+            }
+        }
+    }
+
+    /// The constructor a pattern's path names among those of `ty`.
+    fn path_ctor(&self, path: &Path, ty: &Type) -> Ctor {
+        let name = &path.last().ident.name;
+        if let Type::Adt(adt, args) = ty {
+            if let Some(variants) = self.variants_of(adt, args) {
+                if let Some(index) = variants.iter().position(|(variant, _)| variant == name) {
+                    return Ctor::Variant(index);
+                }
+            } else if self.struct_def(adt).is_some() {
+                return Ctor::Single;
+            }
+        }
+        let segments: Vec<&str> =
+            path.segments.iter().map(|segment| segment.ident.name.as_str()).collect();
+        Ctor::Opaque(segments.join("::"))
+    }
+
+    /// The variants of the enum `name`, with `args` for `Option<T>` and
+    /// `Result<T, E>`, or `None` if it is not a known enum.
+    fn variants_of(&self, name: &str, args: &[Type]) -> Option<Vec<(String, StructDef)>> {
+        if let Some(def) = self.enum_def(name) {
+            return Some(def.variants.clone());
+        }
+        let arg = |i: usize| args.get(i).cloned().unwrap_or(Type::Error);
+        let unit = || StructDef { fields: Vec::new(), tuple: false };
+        let wrap = |ty: Type| StructDef { fields: vec![("0".to_string(), ty)], tuple: true };
+        match name {
+            "Option" => {
+                Some(vec![("None".to_string(), unit()), ("Some".to_string(), wrap(arg(0)))])
+            }
+            "Result" => {
+                Some(vec![("Ok".to_string(), wrap(arg(0))), ("Err".to_string(), wrap(arg(1)))])
+            }
+            _ => None,
+        }
+    }
+
+    fn signature(&self, ty: &Type) -> Signature {
+        match ty {
+            Type::Bool => Signature::Finite(vec![Ctor::Bool(false), Ctor::Bool(true)]),
+            Type::Int(int) => {
+                let (min, max) = int_domain(*int);
+                Signature::Finite(vec![Ctor::Range(min, max)])
+            }
+            // The surrogates are not `char`s.
+            Type::Char => {
+                Signature::Finite(vec![Ctor::Range(0, 0xd7ff), Ctor::Range(0xe000, 0x10_ffff)])
+            }
+            Type::Tuple(_) | Type::Ref(..) => Signature::Finite(vec![Ctor::Single]),
+            // `!` has no values, so no arm is needed.
+            Type::Never => Signature::Finite(Vec::new()),
+            Type::Adt(name, args) => match self.variants_of(name, args) {
+                Some(variants) => {
+                    Signature::Finite((0..variants.len()).map(Ctor::Variant).collect())
+                }
+                None if self.struct_def(name).is_some() => Signature::Finite(vec![Ctor::Single]),
+                None => Signature::Unknown,
+            },
+            Type::Str | Type::Float(_) | Type::Array(..) | Type::Slice(_) | Type::Fn(..) => {
+                Signature::Infinite
+            }
+            Type::Var(_) | Type::IntVar(_) | Type::FloatVar(_) | Type::Error => Signature::Unknown,
+        }
+    }
+
+    /// The fields of `ctor` as a value of type `ty`, if the type is known.
+    fn ctor_def(&self, ty: &Type, ctor: &Ctor) -> Option<StructDef> {
+        let positional = |tys: Vec<Type>| StructDef {
+            fields: tys.into_iter().enumerate().map(|(i, ty)| (i.to_string(), ty)).collect(),
+            tuple: true,
+        };
+        match (ty, ctor) {
+            (Type::Tuple(elems), Ctor::Single) => Some(positional(elems.clone())),
+            (Type::Ref(_, inner), Ctor::Single) => Some(positional(vec![(**inner).clone()])),
+            (Type::Adt(name, _), Ctor::Single) => self.struct_def(name).cloned(),
+            (Type::Adt(name, args), Ctor::Variant(index)) => {
+                let mut variants = self.variants_of(name, args)?;
+                (*index < variants.len()).then(|| variants.swap_remove(*index).1)
+            }
+            _ => None,
+        }
+    }
+
+    /// The types of `ctor`'s fields, or `arity` unknown ones if `ty` is
+    /// not known.
+    fn field_types(&self, ty: &Type, ctor: &Ctor, arity: usize) -> Vec<Type> {
+        match self.ctor_def(ty, ctor) {
+            Some(def) if def.fields.len() == arity => {
+                def.fields.into_iter().map(|(_, ty)| ty).collect()
+            }
+            _ => vec![Type::Error; arity],
+        }
+    }
+
+    /// A witness as Rust would write it: `King`, `Some(_)`, `(false, _)`,
+    /// `i32::MIN..=-1_i32`.
+    fn print(&self, pat: &DPat, ty: &Type) -> String {
+        let (ctor, fields) = match pat {
+            DPat::Wild => return "_".to_string(),
+            DPat::Or(alternatives) => return self.print(&alternatives[0], ty),
+            DPat::Ctor(ctor, fields) => (ctor, fields),
+        };
+        match (ctor, ty) {
+            (Ctor::Bool(b), _) => b.to_string(),
+            (Ctor::Range(start, end), _) if start == end => print_value(*start, ty),
+            (Ctor::Range(start, end), _) => {
+                format!("{}..={}", print_value(*start, ty), print_value(*end, ty))
+            }
+            (Ctor::Opaque(text), _) => text.clone(),
+            (Ctor::Single, Type::Ref(_, inner)) => format!("&{}", self.print(&fields[0], inner)),
+            (Ctor::Single, Type::Tuple(elems)) => {
+                let printed: Vec<String> =
+                    fields.iter().zip(elems).map(|(field, ty)| self.print(field, ty)).collect();
+                match printed.as_slice() {
+                    [only] => format!("({},)", only),
+                    _ => format!("({})", printed.join(", ")),
+                }
+            }
+            (Ctor::Single, Type::Adt(name, _)) | (Ctor::Variant(_), Type::Adt(name, _)) => {
+                let def = self
+                    .ctor_def(ty, ctor)
+                    .unwrap_or(StructDef { fields: Vec::new(), tuple: false });
+                let name = match ctor {
+                    Ctor::Variant(index) => self.variant_name(name, ty, *index),
+                    _ => name.clone(),
+                };
+                self.print_fields(&name, &def, fields)
+            }
+            _ => "_".to_string(),
+        }
+    }
+
+    /// How a witness names a variant: by itself if a `use` brought it into
+    /// scope, as the prelude does for `Some` and `None`, and after its enum
+    /// otherwise.
+    fn variant_name(&self, enum_name: &str, ty: &Type, index: usize) -> String {
+        let args = match ty {
+            Type::Adt(_, args) => args.as_slice(),
+            _ => &[],
+        };
+        let variants = self.variants_of(enum_name, args).unwrap_or_default();
+        let variant = match variants.get(index) {
+            Some((variant, _)) => variant,
+            None => return "_".to_string(),
+        };
+        if self.enum_def(enum_name).is_none() {
+            return variant.clone();
+        }
+        let imported = self.scopes.iter().rev().find_map(|scope| scope.variants.get(variant));
+        match imported {
+            Some((imported_enum, _)) if imported_enum == enum_name => variant.clone(),
+            _ => format!("{}::{}", enum_name, variant),
+        }
+    }
+
+    /// `Name`, `Name(a, b)` or `Name { x: a, .. }`, leaving out the named
+    /// fields any value matches.
+    fn print_fields(&self, name: &str, def: &StructDef, fields: &[DPat]) -> String {
+        if def.is_unit() {
+            return name.to_string();
+        }
+        let printed = def
+            .fields
+            .iter()
+            .zip(fields)
+            .map(|((field, ty), pat)| (field, matches!(pat, DPat::Wild), self.print(pat, ty)));
+        if def.tuple {
+            let printed: Vec<String> = printed.map(|(_, _, pat)| pat).collect();
+            return format!("{}({})", name, printed.join(", "));
+        }
+        let mut shown: Vec<String> = printed
+            .filter(|(_, wild, _)| !wild)
+            .map(|(field, _, pat)| format!("{}: {}", field, pat))
+            .collect();
+        if shown.len() < def.fields.len() {
+            shown.push("..".to_string());
+        }
+        format!("{} {{ {} }}", name, shown.join(", "))
+    }
+}
+
+impl MatchCx<'_> {
+    /// The ways `v` is useful after `rows`: for each, a row of patterns
+    /// that `v` matches and no row does. `tys` are the columns' types.
+    fn useful(&self, rows: &[Row], v: &[DPat], tys: &[Type]) -> Vec<Row> {
+        let (head, rest) = match v.split_first() {
+            Some(split) => split,
+            None if rows.is_empty() => return vec![Vec::new()],
+            None => return Vec::new(),
+        };
+        let rows = expand_or(rows);
+        let ty = &tys[0];
+        match head {
+            DPat::Or(alternatives) => alternatives
+                .iter()
+                .flat_map(|alternative| {
+                    let v: Row =
+                        std::iter::once(alternative.clone()).chain(rest.to_vec()).collect();
+                    self.useful(&rows, &v, tys)
+                })
+                .collect(),
+            DPat::Ctor(ctor, fields) => {
+                let heads = head_ctors(&rows);
+                split(ctor, &heads)
+                    .into_iter()
+                    .flat_map(|part| {
+                        let v: Row = fields.iter().chain(rest).cloned().collect();
+                        self.specialize(&rows, &part, fields.len(), &v, tys)
+                    })
+                    .collect()
+            }
+            DPat::Wild => {
+                let heads = head_ctors(&rows);
+                let all = match self.checker.signature(ty) {
+                    Signature::Finite(all) if !heads.is_empty() => Some(all),
+                    // A type with no values, such as `enum Void {}`, needs no
+                    // arms at all.
+                    Signature::Finite(all) if all.is_empty() && rows.is_empty() => Some(all),
+                    Signature::Unknown if self.trust_unknown && !heads.is_empty() => {
+                        Some(heads.iter().map(|(ctor, _)| ctor.clone()).collect())
+                    }
+                    _ => None,
+                };
+                let parts: Vec<Ctor> =
+                    all.iter().flatten().flat_map(|ctor| split(ctor, &heads)).collect();
+                let missing: Vec<&Ctor> = parts
+                    .iter()
+                    .filter(|part| heads.iter().all(|(head, _)| !covers(head, part)))
+                    .collect();
+                if all.is_some() && missing.is_empty() {
+                    // Every constructor appears: the value is useful if it
+                    // is under one of them.
+                    return parts
+                        .iter()
+                        .flat_map(|part| {
+                            let arity = heads
+                                .iter()
+                                .find(|(head, _)| covers(head, part))
+                                .map_or(0, |(_, arity)| *arity);
+                            let v: Row = std::iter::repeat_n(DPat::Wild, arity)
+                                .chain(rest.to_vec())
+                                .collect();
+                            self.specialize(&rows, part, arity, &v, tys)
+                        })
+                        .collect();
+                }
+                // Some constructor is missing, so only the rows that match
+                // anything here matter, and the witnesses start with it.
+                let default: Vec<Row> = rows
+                    .iter()
+                    .filter(|row| matches!(row[0], DPat::Wild))
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                let witnesses = self.useful(&default, rest, &tys[1..]);
+                let firsts: Vec<DPat> = if all.is_none() {
+                    vec![DPat::Wild]
+                } else {
+                    missing
+                        .into_iter()
+                        .map(|ctor| {
+                            let arity =
+                                self.checker.ctor_def(ty, ctor).map_or(0, |def| def.fields.len());
+                            DPat::Ctor(ctor.clone(), vec![DPat::Wild; arity])
+                        })
+                        .collect()
+                };
+                witnesses
+                    .iter()
+                    .flat_map(|witness| {
+                        firsts.iter().map(move |first| {
+                            std::iter::once(first.clone()).chain(witness.iter().cloned()).collect()
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Usefulness among values built with `ctor`: its `arity` fields take
+    /// the first column's place. `v` has already been so expanded.
+    fn specialize(
+        &self,
+        rows: &[Row],
+        ctor: &Ctor,
+        arity: usize,
+        v: &[DPat],
+        tys: &[Type],
+    ) -> Vec<Row> {
+        let rows: Vec<Row> = rows
+            .iter()
+            .filter_map(|row| {
+                let fields = match &row[0] {
+                    DPat::Wild => vec![DPat::Wild; arity],
+                    DPat::Ctor(head, fields) if covers(head, ctor) => fields.clone(),
+                    _ => return None,
+                };
+                Some(fields.into_iter().chain(row[1..].iter().cloned()).collect())
+            })
+            .collect();
+        let tys: Vec<Type> = self
+            .checker
+            .field_types(&tys[0], ctor, arity)
+            .into_iter()
+            .chain(tys[1..].to_vec())
+            .collect();
+        self.useful(&rows, v, &tys)
+            .into_iter()
+            .map(|mut witness| {
+                let rest = witness.split_off(arity);
+                let first = DPat::Ctor(ctor.clone(), witness);
+                std::iter::once(first).chain(rest).collect()
+            })
+            .collect()
+    }
+}
+
+/// The rows with or-patterns in the first column replaced by a row for
+/// each alternative.
+fn expand_or(rows: &[Row]) -> Vec<Row> {
+    let mut expanded = Vec::new();
+    for row in rows {
+        match &row[0] {
+            DPat::Or(alternatives) => {
+                let alternatives: Vec<Row> = alternatives
+                    .iter()
+                    .map(|alternative| {
+                        std::iter::once(alternative.clone())
+                            .chain(row[1..].iter().cloned())
+                            .collect()
+                    })
+                    .collect();
+                expanded.extend(expand_or(&alternatives));
+            }
+            _ => expanded.push(row.clone()),
+        }
+    }
+    expanded
+}
+
+/// The constructors in the first column, with their arities.
+fn head_ctors(rows: &[Row]) -> Vec<(Ctor, usize)> {
+    let mut heads: Vec<(Ctor, usize)> = Vec::new();
+    for row in rows {
+        if let DPat::Ctor(ctor, fields) = &row[0] {
+            if heads.iter().all(|(head, _)| head != ctor) {
+                heads.push((ctor.clone(), fields.len()));
+            }
+        }
+    }
+    heads
+}
+
+/// Whether a pattern with constructor `head` matches every value built
+/// with `ctor`. Ranges are split beforehand, so a piece is either inside
+/// a pattern's range or outside it.
+fn covers(head: &Ctor, ctor: &Ctor) -> bool {
+    match (head, ctor) {
+        (Ctor::Range(start, end), Ctor::Range(lo, hi)) => start <= lo && hi <= end,
+        _ => head == ctor,
+    }
+}
+
+/// `ctor` cut at the boundaries of the ranges among `heads`.
+fn split(ctor: &Ctor, heads: &[(Ctor, usize)]) -> Vec<Ctor> {
+    let (lo, hi) = match ctor {
+        Ctor::Range(lo, hi) => (*lo, *hi),
+        _ => return vec![ctor.clone()],
+    };
+    let mut cuts: Vec<u128> = Vec::new();
+    for (head, _) in heads {
+        if let Ctor::Range(start, end) = head {
+            cuts.push(*start);
+            cuts.extend(end.checked_add(1));
+        }
+    }
+    cuts.retain(|&cut| lo < cut && cut <= hi);
+    cuts.sort_unstable();
+    cuts.dedup();
+    let mut parts = Vec::with_capacity(cuts.len() + 1);
+    let mut start = lo;
+    for cut in cuts {
+        parts.push(Ctor::Range(start, cut - 1));
+        start = cut;
+    }
+    parts.push(Ctor::Range(start, hi));
+    parts
+}
+
+/// The warning for an `if let` or `while let` whose pattern always matches.
+fn irrefutable_let(pat: &Pat, source: MatchSource) -> Diagnostic {
+    let (kind, consequence, help) = match source {
+        MatchSource::WhileLet => (
+            "while let",
+            "the loop will never exit",
+            "consider instead using a `loop { ... }` with a `let` inside it",
+        ),
+        _ => ("if let", "the `if let` is useless", "consider replacing the `if let` with a `let`"),
+    };
+    Diagnostic::warning(format!("irrefutable `{}` pattern", kind))
+        .primary(pat.span, "")
+        .note(format!("this pattern will always match, so {}", consequence))
+        .help(help)
+        .lint(Lint::IrrefutableLetPatterns)
+}
+
+/// "`A`", "`A` and `B`", "`A`, `B` and `C`", or "`A`, `B`, `C` and 2 more".
+fn join_witnesses(witnesses: &[String]) -> String {
+    match witnesses {
+        [only] => only.clone(),
+        [init @ .., last] if witnesses.len() <= 3 => format!("{} and {}", init.join(", "), last),
+        _ => format!("{} and {} more", witnesses[..3].join(", "), witnesses.len() - 3),
+    }
+}
+
+/// The smallest and largest values of `int`, encoded.
+fn int_domain(int: IntTy) -> (u128, u128) {
+    let bits = int.bits();
+    if int.is_signed() {
+        let max = if bits == 128 { i128::MAX } else { (1 << (bits - 1)) - 1 };
+        (encode(-max - 1, int), encode(max, int))
+    } else if bits == 128 {
+        (0, u128::MAX)
+    } else {
+        (0, (1 << bits) - 1)
+    }
+}
+
+fn encode(value: i128, int: IntTy) -> u128 {
+    if int.is_signed() {
+        value as u128 ^ SIGN
+    } else {
+        value as u128
+    }
+}
+
+/// The encoded value of a literal or constant in a pattern matching `ty`.
+fn pat_value(expr: &Expr, ty: &Type) -> Option<u128> {
+    if let ExprKind::Path(path) = &expr.kind {
+        return path_value(path, ty);
+    }
+    let value = bound_value(expr)?;
+    match ty {
+        Type::Int(int) if int.is_signed() => Some(encode(value, *int)),
+        Type::Int(_) | Type::Char => u128::try_from(value).ok(),
+        _ => None,
+    }
+}
+
+/// The encoded value of `i32::MIN` and the like.
+fn path_value(path: &Path, ty: &Type) -> Option<u128> {
+    let int = match (int_const(path)?, ty) {
+        (Type::Int(int), Type::Int(expected)) if int == *expected => int,
+        _ => return None,
+    };
+    let (min, max) = int_domain(int);
+    Some(if path.last().ident.name == "MIN" { min } else { max })
+}
+
+/// An integer or `char` as a pattern: `5_i32`, `i32::MAX`, `'a'`.
+fn print_value(value: u128, ty: &Type) -> String {
+    match ty {
+        Type::Int(int) => {
+            let (min, max) = int_domain(*int);
+            if int.is_signed() && value == min {
+                format!("{}::MIN", int.name())
+            } else if value == max {
+                format!("{}::MAX", int.name())
+            } else if int.is_signed() {
+                format!("{}_{}", (value ^ SIGN) as i128, int.name())
+            } else {
+                format!("{}_{}", value, int.name())
+            }
+        }
+        Type::Char => match u32::try_from(value).ok().and_then(char::from_u32) {
+            Some(c) => format!("{:?}", c),
+            None => value.to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::{Level, Lint};
+    use crate::test_util::check_types;
+
+    /// The witnesses of the one non-exhaustive `match` in `src`.
+    fn not_covered(src: &str) -> String {
+        let diagnostics = check_types(src);
+        assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+        assert_eq!(diagnostics[0].code, Some("E0004"));
+        let message = &diagnostics[0].message;
+        message.strip_prefix("non-exhaustive patterns: ").unwrap().to_string()
+    }
+
+    #[test]
+    fn enums() {
+        let src = "enum Rank { Jack, Queen, King, Ace } use Rank::*; let r = Jack; \
+                   match r { Jack => 10, Queen => 10, Ace => 11 }";
+        assert_eq!(not_covered(src), "`King` not covered");
+        assert_eq!(
+            not_covered("let o = Some(1); match o { Some(n) if n > 0 => 1, None => 0 }"),
+            "`Some(_)` not covered"
+        );
+        assert!(check_types("enum Void {} fn f(v: Void) -> i32 { match v {} } 1").is_empty());
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(
+            not_covered("let x = 5u8; match x { 0..=100 => 1 }"),
+            "`101_u8..=u8::MAX` not covered"
+        );
+        assert_eq!(
+            not_covered("let x = 5i8; match x { i8::MIN..=-1 => 1, 1..=i8::MAX => 2 }"),
+            "`0_i8` not covered"
+        );
+        assert!(check_types("let x = 5u8; match x { 0..=127 => 1, 128..=255 => 2 }").is_empty());
+    }
+
+    #[test]
+    fn tuples_and_structs() {
+        assert_eq!(
+            not_covered("let b = (true, false); match b { (true, _) => 1, (false, true) => 2 }"),
+            "`(false, false)` not covered"
+        );
+        let src = "struct P { x: bool, y: i8 } let p = P { x: true, y: 0 }; \
+                   match p { P { x: true, y } => y, P { x: false, y: 0..=127 } => 1 }";
+        assert_eq!(not_covered(src), "`P { x: false, y: i8::MIN..=-1_i8 }` not covered");
+    }
+
+    #[test]
+    fn unreachable_arms() {
+        for src in [
+            "let x = 5; match x { _ => 1, 3 => 2 }",
+            "let o = Some(1); match o { Some(_) => 1, None => 2, Some(3) => 3 }",
+        ] {
+            let diagnostics = check_types(src);
+            assert_eq!(diagnostics.len(), 1);
+            assert_eq!(diagnostics[0].level, Level::Warning);
+            assert_eq!(diagnostics[0].lint, Some(Lint::UnreachablePatterns));
+        }
+    }
+}
@@ -0,0 +1,1199 @@
+//! Typing expressions, blocks and statements.
+
+use super::ty::Type;
+use super::{ctor_type, forgot_semicolon, int_const, literal_len, value_span, Body, Checker, Loop};
+use crate::ast::{
+    Arm, Block, Expr, ExprKind, Ident, LitKind, Local, LoopSource, MacCall, MatchSource, Path,
+    RangeLimits, Stmt, StmtKind, StructExpr, UnOp,
+};
+use crate::diagnostic::{Applicability, Diagnostic};
+use crate::span::Span;
+
+impl Checker {
+    pub(super) fn block(&mut self, block: &Block) -> Type {
+        let ty = self.with_scope(|this| {
+            this.hoist_items(&block.stmts);
+            for stmt in &block.stmts {
+                this.stmt(stmt);
+            }
+            match &block.tail {
+                Some(tail) => this.expr(tail),
+                None if this.diverges => Type::Never,
+                None => Type::unit(),
+            }
+        });
+        self.record(block.id, &ty);
+        ty
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let(local) => self.local(local),
+            StmtKind::Item(item) => self.item(item),
+            // A block-like expression statement such as an `if` without a
+            // `;` after it must produce `()`.
+            StmtKind::Expr(expr) => {
+                let ty = match &expr.kind {
+                    ExprKind::If(cond, then, None) => {
+                        let ty = self.if_expr(cond, then, None, false);
+                        self.record(expr.id, &ty);
+                        ty
+                    }
+                    ExprKind::Match(
+                        scrutinee,
+                        arms,
+                        source @ MatchSource::IfLet { has_else: false },
+                    ) => {
+                        let ty = self.match_expr(scrutinee, arms, *source, false);
+                        self.record(expr.id, &ty);
+                        ty
+                    }
+                    _ => self.expr(expr),
+                };
+                if !self.infer.coerce(&ty, &Type::unit()) {
+                    let diagnostic = self.mismatch(&Type::unit(), &ty, value_span(expr));
+                    self.diagnostics.push(forgot_semicolon(diagnostic, expr));
+                }
+            }
+            StmtKind::Semi(expr) => {
+                self.expr(expr);
+            }
+            StmtKind::Empty => {}
+        }
+    }
+
+    fn local(&mut self, local: &Local) {
+        let declared = local.ty.as_ref().map(|ty| (self.lower_ty(ty), ty.span));
+        let ty = match (declared, &local.init) {
+            (Some((declared, ty_span)), Some(init)) => {
+                let found = self.expr(init);
+                if !self.infer.coerce(&found, &declared) {
+                    let diagnostic = self
+                        .mismatch(&declared, &found, value_span(init))
+                        .secondary(ty_span, "expected due to this");
+                    let diagnostic = self.unit_block_help(diagnostic, &declared, init);
+                    self.diagnostics.push(diagnostic);
+                }
+                declared
+            }
+            (Some((declared, _)), None) => declared,
+            (None, Some(init)) => self.expr(init),
+            (None, None) => self.infer.new_var(),
+        };
+        self.pat(&local.pat, &ty);
+    }
+
+    /// Check `expr` against an expected type.
+    pub(super) fn expect(&mut self, expr: &Expr, expected: &Type) -> Type {
+        let ty = self.expr(expr);
+        if !self.infer.coerce(&ty, expected) {
+            let diagnostic = self.mismatch(expected, &ty, value_span(expr));
+            let diagnostic = self.unit_block_help(diagnostic, expected, expr);
+            self.diagnostics.push(diagnostic);
+        }
+        ty
+    }
+
+    /// A block with a tail expression, checked where `()` was expected,
+    /// probably has a `;` missing from its last statement.
+    fn unit_block_help(&self, diagnostic: Diagnostic, expected: &Type, expr: &Expr) -> Diagnostic {
+        match (&expr.kind, self.resolve(expected).is_unit()) {
+            (ExprKind::Block(Block { tail: Some(tail), .. }), true) => {
+                forgot_semicolon(diagnostic, tail)
+            }
+            _ => diagnostic,
+        }
+    }
+
+    pub(super) fn expr(&mut self, expr: &Expr) -> Type {
+        let outer = std::mem::replace(&mut self.diverges, false);
+        let ty = self.expr_kind(expr);
+        if self.infer.shallow_resolve(&ty) == Type::Never {
+            self.diverges = true;
+        }
+        self.diverges |= outer;
+        self.record(expr.id, &ty);
+        ty
+    }
+
+    fn expr_kind(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Lit(lit) => self.lit(lit),
+            ExprKind::Path(path) => self.path(path),
+            ExprKind::Unary(op @ (UnOp::Neg | UnOp::Not), operand) => {
+                self.unary(*op, operand, expr.span)
+            }
+            ExprKind::Unary(op, operand) => {
+                let ty = self.expr(operand);
+                match (op, self.infer.shallow_resolve(&ty)) {
+                    (UnOp::Deref, Type::Ref(_, inner)) => *inner,
+                    (
+                        UnOp::Deref,
+                        ty @ (Type::Bool
+                        | Type::Char
+                        | Type::Int(_)
+                        | Type::Float(_)
+                        | Type::IntVar(_)
+                        | Type::FloatVar(_)
+                        | Type::Tuple(_)
+                        | Type::Array(..)),
+                    ) => {
+                        let diagnostic = Diagnostic::error(format!(
+                            "type `{}` cannot be dereferenced",
+                            self.resolve(&ty)
+                        ))
+                        .code("E0614")
+                        .primary(expr.span, "");
+                        self.diagnostics.push(diagnostic);
+                        Type::Error
+                    }
+                    _ => self.infer.new_var(),
+                }
+            }
+            ExprKind::AddrOf(mutability, operand) => {
+                Type::Ref(*mutability, Box::new(self.expr(operand)))
+            }
+            ExprKind::Binary(op, lhs, rhs) => self.binary(*op, lhs, rhs),
+            ExprKind::Assign(place, value) => {
+                let ty = self.expr(place);
+                self.expect(value, &ty);
+                Type::unit()
+            }
+            ExprKind::AssignOp(op, place, value) => {
+                self.binary(*op, place, value);
+                Type::unit()
+            }
+            ExprKind::Cast(operand, ty) => {
+                self.expr(operand);
+                self.lower_ty(ty)
+            }
+            ExprKind::Range(start, end, limits) => {
+                let elem = self.infer.new_var();
+                for operand in start.iter().chain(end) {
+                    self.expect(operand, &elem);
+                }
+                let name = match (start.is_some(), end.is_some(), limits) {
+                    (true, true, RangeLimits::HalfOpen) => "Range",
+                    (true, true, RangeLimits::Closed) => "RangeInclusive",
+                    (true, false, _) => "RangeFrom",
+                    (false, true, RangeLimits::HalfOpen) => "RangeTo",
+                    (false, true, RangeLimits::Closed) => "RangeToInclusive",
+                    (false, false, _) => return Type::adt("RangeFull", Vec::new()),
+                };
+                Type::adt(name, vec![elem])
+            }
+            ExprKind::Call(callee, args) => self.call(callee, args),
+            ExprKind::MethodCall(call) => self.method_call(call),
+            ExprKind::Field(base, field) => {
+                let base_ty = self.expr(base);
+                self.struct_field(&base_ty, &field.name, field.span)
+            }
+            ExprKind::TupleField(base, index, index_span) => {
+                let base_ty = self.expr(base);
+                if self.struct_of(&base_ty).is_some() {
+                    return self.struct_field(&base_ty, &index.to_string(), *index_span);
+                }
+                match self.infer.shallow_resolve(&base_ty) {
+                    Type::Tuple(elems) if *index < elems.len() => elems[*index].clone(),
+                    Type::Tuple(_) => {
+                        let diagnostic = Diagnostic::error(format!(
+                            "no field `{}` on type `{}`",
+                            index,
+                            self.resolve(&base_ty)
+                        ))
+                        .code("E0609")
+                        .primary(*index_span, "unknown field");
+                        self.diagnostics.push(diagnostic);
+                        Type::Error
+                    }
+                    _ => self.infer.new_var(),
+                }
+            }
+            ExprKind::Index(base, index) => {
+                let base_ty = self.expr(base);
+                let mut base_ty = self.infer.shallow_resolve(&base_ty);
+                while let Type::Ref(_, inner) = base_ty {
+                    base_ty = self.infer.shallow_resolve(&inner);
+                }
+                let elem = match base_ty {
+                    Type::Array(elem, _) | Type::Slice(elem) => Some(*elem),
+                    Type::Adt(name, mut args) if name == "Vec" && args.len() == 1 => args.pop(),
+                    _ => None,
+                };
+                match elem {
+                    Some(elem) if !matches!(index.kind, ExprKind::Range(..)) => {
+                        self.expect(index, &Type::Int(crate::ast::IntTy::Usize));
+                        elem
+                    }
+                    Some(elem) => {
+                        self.expr(index);
+                        Type::Slice(Box::new(elem))
+                    }
+                    None => {
+                        self.expr(index);
+                        self.infer.new_var()
+                    }
+                }
+            }
+            ExprKind::Try(operand) => self.try_expr(expr, operand),
+            ExprKind::Paren(inner) => self.expr(inner),
+            ExprKind::Tuple(elems) => {
+                Type::Tuple(elems.iter().map(|elem| self.expr(elem)).collect())
+            }
+            ExprKind::Array(elems) => {
+                let elem = self.infer.new_var();
+                for e in elems {
+                    self.expect(e, &elem);
+                }
+                Type::Array(Box::new(elem), Some(elems.len() as u128))
+            }
+            ExprKind::Repeat(elem, count) => {
+                let elem = self.expr(elem);
+                self.expect(count, &Type::Int(crate::ast::IntTy::Usize));
+                Type::Array(Box::new(elem), literal_len(count))
+            }
+            ExprKind::Struct(lit) => {
+                let ctor = match self.ctor(&lit.path) {
+                    Some(ctor) => ctor,
+                    None if range_fields(&lit.path.last().ident.name).is_some() => {
+                        return self.range_lit(lit);
+                    }
+                    None => {
+                        for field in &lit.fields {
+                            self.expr(&field.value);
+                        }
+                        return ctor_type(&lit.path, true).unwrap_or_else(|| self.infer.new_var());
+                    }
+                };
+                for field in &lit.fields {
+                    match ctor.def.fields.iter().find(|(f, _)| *f == field.name.name) {
+                        Some((_, field_ty)) => {
+                            self.expect(&field.value, field_ty);
+                        }
+                        None => {
+                            self.expr(&field.value);
+                            let diagnostic = Diagnostic::error(format!(
+                                "{} `{}` has no field named `{}`",
+                                ctor.kind(),
+                                ctor.name,
+                                field.name.name
+                            ))
+                            .code(if ctor.is_variant { "E0559" } else { "E0560" })
+                            .primary(field.name.span, "unknown field");
+                            self.diagnostics.push(diagnostic);
+                        }
+                    }
+                }
+                self.missing_fields(
+                    lit,
+                    ctor.def.fields.iter().map(|(f, _)| f.as_str()),
+                    &ctor.name,
+                );
+                ctor.ty
+            }
+            ExprKind::MacCall(mac) => self.mac_call(mac),
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If(cond, then, els) => self.if_expr(cond, then, els.as_deref(), true),
+            ExprKind::Let(..) => {
+                unreachable!("`if let` and `while let` are desugared by the parser")
+            }
+            ExprKind::Match(scrutinee, arms, source) => {
+                self.match_expr(scrutinee, arms, *source, true)
+            }
+            ExprKind::While(cond, body, label) => {
+                let head = Span::new(expr.span.start, cond.span.end);
+                self.in_loop(label, "while", head, Type::unit(), |this| {
+                    this.expect(cond, &Type::Bool);
+                    this.loop_body(body);
+                })
+            }
+            ExprKind::Loop(body, label, LoopSource::Loop) => {
+                let ty = self.infer.new_var();
+                self.in_loop(label, "loop", expr.span, ty, |this| this.loop_body(body))
+            }
+            ExprKind::Loop(body, label, LoopSource::WhileLet) => {
+                let head = match body.tail.as_deref().map(|tail| &tail.kind) {
+                    Some(ExprKind::Match(scrutinee, ..)) => {
+                        Span::new(expr.span.start, scrutinee.span.end)
+                    }
+                    _ => expr.span,
+                };
+                self.in_loop(label, "while let", head, Type::unit(), |this| this.loop_body(body))
+            }
+            ExprKind::For(pat, iter, body, label) => {
+                let iter_ty = self.expr(iter);
+                let elem = self.iter_elem(iter, &iter_ty);
+                let head = Span::new(expr.span.start, iter.span.end);
+                self.in_loop(label, "for", head, Type::unit(), |this| {
+                    this.with_scope(|this| {
+                        this.pat(pat, &elem);
+                        this.loop_body(body);
+                    })
+                })
+            }
+            ExprKind::Break(label, value) => {
+                self.break_expr(expr.span, label.as_ref(), value.as_deref());
+                Type::Never
+            }
+            ExprKind::Continue(_) => Type::Never,
+            ExprKind::Return(value) => {
+                self.return_expr(expr.span, value.as_deref());
+                Type::Never
+            }
+            ExprKind::Closure(closure) => self.with_scope(|this| {
+                let loops = std::mem::take(&mut this.loops);
+                let diverges = std::mem::take(&mut this.diverges);
+                let params = closure
+                    .params
+                    .iter()
+                    .map(|param| {
+                        let ty = match &param.ty {
+                            Some(ty) => this.lower_ty(ty),
+                            None => this.infer.new_var(),
+                        };
+                        this.pat(&param.pat, &ty);
+                        ty
+                    })
+                    .collect();
+                let (ret, ret_span) = match &closure.ret {
+                    Some(ret) => (this.lower_ty(ret), Some(ret.span)),
+                    None => (this.infer.new_var(), None),
+                };
+                let body = this.body.replace(Body { ret: ret.clone(), ret_span });
+                this.expect(&closure.body, &ret);
+                this.loops = loops;
+                this.body = body;
+                this.diverges = diverges;
+                Type::Fn(params, Box::new(ret))
+            }),
+        }
+    }
+
+    /// `return value`, or `return` alone for `return ()`.
+    fn return_expr(&mut self, span: Span, value: Option<&Expr>) {
+        let (ret, ret_span) = match &self.body {
+            Some(body) => (body.ret.clone(), body.ret_span),
+            None => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                return;
+            }
+        };
+        let diagnostic = match value {
+            Some(value) => {
+                let ty = self.expr(value);
+                if self.infer.coerce(&ty, &ret) {
+                    return;
+                }
+                self.mismatch(&ret, &ty, value_span(value))
+            }
+            None if self.infer.unify(&ret, &Type::unit()) => return,
+            None => Diagnostic::error("`return;` in a function whose return type is not `()`")
+                .code("E0069")
+                .primary(span, "return type is not `()`"),
+        };
+        let diagnostic = match ret_span {
+            Some(ret_span) => diagnostic.secondary(
+                ret_span,
+                format!("expected `{}` because of return type", self.resolve(&ret)),
+            ),
+            None => diagnostic,
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// `operand?`: the value inside an `Ok` or `Some`, with the `Err` or
+    /// `None` returned from the function.
+    fn try_expr(&mut self, expr: &Expr, operand: &Expr) -> Type {
+        let ty = self.expr(operand);
+        let question = Span::new(expr.span.end - 1, expr.span.end);
+        let (family, output, residual) = match self.infer.shallow_resolve(&ty) {
+            Type::Adt(name, mut args) if name == "Result" && args.len() == 2 => {
+                let err = args.pop().unwrap();
+                ("Result", args.pop().unwrap(), Some(err))
+            }
+            Type::Adt(name, mut args) if name == "Option" && args.len() == 1 => {
+                ("Option", args.pop().unwrap(), None)
+            }
+            Type::Var(_) | Type::Error => return self.infer.new_var(),
+            other => {
+                let diagnostic = Diagnostic::error(
+                    "the `?` operator can only be applied to values that implement `Try`",
+                )
+                .code("E0277")
+                .primary(
+                    expr.span,
+                    format!(
+                        "the `?` operator cannot be applied to type `{}`",
+                        self.resolve(&other)
+                    ),
+                );
+                self.diagnostics.push(diagnostic);
+                return Type::Error;
+            }
+        };
+        let (ret, ret_span) = match &self.body {
+            Some(body) => (self.infer.shallow_resolve(&body.ret), body.ret_span),
+            None => return output,
+        };
+        let diagnostic = match ret {
+            Type::Adt(name, args) if name == family && args.len() == residual.iter().len() + 1 => {
+                let (from, to) = match (residual, args.last()) {
+                    (Some(from), Some(to)) => (from, to.clone()),
+                    _ => return output,
+                };
+                if self.infer.unify(&to, &from) || self.resolve(&to).has_from(&self.resolve(&from))
+                {
+                    return output;
+                }
+                let (from, to) = (self.resolve(&from), self.resolve(&to));
+                let diagnostic =
+                    Diagnostic::error(format!("`?` couldn't convert the error to `{}`", to))
+                        .code("E0277")
+                        .primary(
+                            question,
+                            format!("the trait `From<{}>` is not implemented for `{}`", from, to),
+                        )
+                        .note(
+                            "the question mark operation (`?`) implicitly performs a conversion \
+                             on the error value using the `From` trait",
+                        );
+                match ret_span {
+                    Some(span) => diagnostic.secondary(span, format!("expected `{}` because of this", to)),
+                    None => diagnostic,
+                }
+            }
+            // A closure without a written return type returns what `?` does.
+            Type::Var(_) => {
+                let ret_ty = match residual {
+                    Some(err) => Type::adt("Result", vec![self.infer.new_var(), err]),
+                    None => Type::adt("Option", vec![self.infer.new_var()]),
+                };
+                let ret = self.body.as_ref().map(|body| body.ret.clone()).expect("checked above");
+                self.infer.unify(&ret, &ret_ty);
+                return output;
+            }
+            Type::Error => return output,
+            Type::Adt(name, _) if name == "Result" || name == "Option" => Diagnostic::error(format!(
+                "the `?` operator can only be used on `{}`s, not `{}`s, in a function that returns `{}`",
+                name, family, name
+            ))
+            .code("E0277")
+            .primary(
+                question,
+                if name == "Option" {
+                    "use `.ok()?` if you want to discard the error information"
+                } else {
+                    "use `.ok_or(...)?` to provide an error compatible with the function's return type"
+                },
+            ),
+            ret => Diagnostic::error(
+                "the `?` operator can only be used in a function that returns `Result` or `Option`",
+            )
+            .code("E0277")
+            .primary(
+                question,
+                format!("cannot use the `?` operator in a function that returns `{}`", self.resolve(&ret)),
+            ),
+        };
+        self.diagnostics.push(diagnostic);
+        output
+    }
+
+    /// E0063 for the fields of `ctor` in `declared` that `lit` leaves out.
+    fn missing_fields<'a>(
+        &mut self,
+        lit: &StructExpr,
+        declared: impl Iterator<Item = &'a str>,
+        ctor: &str,
+    ) {
+        let missing: Vec<String> = declared
+            .filter(|f| lit.fields.iter().all(|field| field.name.name != *f))
+            .map(|f| format!("`{}`", f))
+            .collect();
+        if !missing.is_empty() {
+            let s = if missing.len() == 1 { "" } else { "s" };
+            let diagnostic = Diagnostic::error(format!(
+                "missing field{} {} in initializer of `{}`",
+                s,
+                missing.join(", "),
+                ctor
+            ))
+            .code("E0063")
+            .primary(lit.path.span, format!("missing field{}", s));
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// `std::ops::Range { start: 0, end: 20 }`, which is what `0..20`
+    /// builds, or a `RangeFrom` or `RangeTo` spelled out the same way.
+    /// Both bounds have the range's item type.
+    fn range_lit(&mut self, lit: &StructExpr) -> Type {
+        let name = &lit.path.last().ident.name;
+        let declared = range_fields(name).unwrap_or_default();
+        let elem = self.infer.new_var();
+        for field in &lit.fields {
+            if declared.contains(&field.name.name.as_str()) {
+                self.expect(&field.value, &elem);
+                continue;
+            }
+            self.expr(&field.value);
+            let diagnostic = Diagnostic::error(format!(
+                "struct `{}` has no field named `{}`",
+                name, field.name.name
+            ))
+            .code("E0560")
+            .primary(field.name.span, "unknown field");
+            self.diagnostics.push(diagnostic);
+        }
+        self.missing_fields(lit, declared.iter().copied(), name);
+        Type::adt(name.as_str(), vec![elem])
+    }
+
+    /// The type of `field` in a value of type `base_ty`. Only the fields of
+    /// declared structs and of ranges are known.
+    fn struct_field(&mut self, base_ty: &Type, field: &str, span: Span) -> Type {
+        if let Type::Adt(name, args) = self.infer.shallow_resolve(base_ty) {
+            if let (Some(declared), [elem]) = (range_fields(&name), args.as_slice()) {
+                if declared.contains(&field) {
+                    return elem.clone();
+                }
+            }
+        }
+        let (name, def) = match self.struct_of(base_ty) {
+            Some(found) => found,
+            None => return self.infer.new_var(),
+        };
+        if let Some((_, ty)) = def.fields.iter().find(|(f, _)| f == field) {
+            return ty.clone();
+        }
+        let diagnostic = Diagnostic::error(format!("no field `{}` on type `{}`", field, name))
+            .code("E0609")
+            .primary(span, "unknown field");
+        self.diagnostics.push(diagnostic);
+        Type::Error
+    }
+
+    fn lit(&mut self, lit: &LitKind) -> Type {
+        match lit {
+            LitKind::Int(_, Some(ty)) => Type::Int(*ty),
+            LitKind::Int(_, None) => self.infer.new_int_var(),
+            LitKind::Float(_, Some(ty)) => Type::Float(*ty),
+            LitKind::Float(_, None) => self.infer.new_float_var(),
+            LitKind::Bool(_) => Type::Bool,
+            LitKind::Char(_) => Type::Char,
+            LitKind::Str(_) => Type::str_ref(),
+        }
+    }
+
+    fn path(&mut self, path: &Path) -> Type {
+        if let Some(ident) = path.as_ident() {
+            return match ident.name.as_str() {
+                "None" => {
+                    let elem = self.infer.new_var();
+                    Type::adt("Option", vec![elem])
+                }
+                "Some" => {
+                    let elem = self.infer.new_var();
+                    Type::Fn(vec![elem.clone()], Box::new(Type::adt("Option", vec![elem])))
+                }
+                "Ok" | "Err" => {
+                    let (ok, err) = (self.infer.new_var(), self.infer.new_var());
+                    let param = if ident.name == "Ok" { ok.clone() } else { err.clone() };
+                    Type::Fn(vec![param], Box::new(Type::adt("Result", vec![ok, err])))
+                }
+                name => self.lookup(name),
+            };
+        }
+        if let Some(ty) = int_const(path) {
+            return ty;
+        }
+        if let Some(ty) = self.ctor(path).and_then(|ctor| ctor.value_type()) {
+            return ty;
+        }
+        let names: Vec<&str> = path.segments.iter().map(|s| s.ident.name.as_str()).collect();
+        if let ["std", "process", "exit"] | ["process", "exit"] = names.as_slice() {
+            return Type::Fn(vec![Type::Int(crate::ast::IntTy::I32)], Box::new(Type::Never));
+        }
+        if names == ["Vec", "new"] {
+            return Type::Fn(Vec::new(), Box::new(Type::adt("Vec", vec![self.infer.new_var()])));
+        }
+        let string = Box::new(Type::adt("String", Vec::new()));
+        match names.as_slice() {
+            ["String", "new"] => return Type::Fn(Vec::new(), string),
+            ["String", "from"] => return Type::Fn(vec![Type::str_ref()], string),
+            _ => {}
+        }
+        ctor_type(path, false).unwrap_or_else(|| self.infer.new_var())
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Expr]) -> Type {
+        // `Point(1, 2)` and `Pet::Cat(name)` build values of a type we can name.
+        if let ExprKind::Path(path) = &callee.kind {
+            let is_prelude_ctor = matches!(
+                path.as_ident().map(|ident| ident.name.as_str()),
+                Some("Some" | "Ok" | "Err")
+            );
+            let ctor = if is_prelude_ctor { None } else { self.ctor(path) };
+            let ty = match &ctor {
+                Some(ctor) => Some(ctor.ty.clone()),
+                None if !is_prelude_ctor => ctor_type(path, true),
+                None => None,
+            };
+            if let Some(ty) = ty {
+                let fields = match ctor {
+                    Some(ctor) if ctor.def.tuple && ctor.def.fields.len() == args.len() => {
+                        ctor.def.fields.into_iter().map(|(_, ty)| ty).collect()
+                    }
+                    _ => Vec::new(),
+                };
+                for (i, arg) in args.iter().enumerate() {
+                    match fields.get(i) {
+                        Some(field) => self.expect(arg, field),
+                        None => self.expr(arg),
+                    };
+                }
+                self.record(callee.id, &ty);
+                return ty;
+            }
+        }
+        let callee_ty = self.expr(callee);
+        match self.infer.shallow_resolve(&callee_ty) {
+            Type::Fn(params, ret) if params.len() == args.len() => {
+                for (arg, param) in args.iter().zip(&params) {
+                    self.expect(arg, param);
+                }
+                *ret
+            }
+            Type::Fn(params, ret) => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let plural = |n: usize| if n == 1 { "" } else { "s" };
+                let diagnostic = Diagnostic::error(format!(
+                    "this function takes {} argument{} but {} argument{} {} supplied",
+                    params.len(),
+                    plural(params.len()),
+                    args.len(),
+                    plural(args.len()),
+                    if args.len() == 1 { "was" } else { "were" }
+                ))
+                .code("E0061")
+                .primary(
+                    callee.span,
+                    format!("expected {} argument{}", params.len(), plural(params.len())),
+                );
+                self.diagnostics.push(diagnostic);
+                *ret
+            }
+            _ => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                self.infer.new_var()
+            }
+        }
+    }
+
+    /// A `match`, or the `if let` or `while let` it was desugared from.
+    /// `value_used` is as for [`Checker::if_expr`].
+    fn match_expr(
+        &mut self,
+        scrutinee: &Expr,
+        arms: &[Arm],
+        source: MatchSource,
+        value_used: bool,
+    ) -> Type {
+        let scrutinee_ty = self.expr(scrutinee);
+        let scrutinee_diverges = self.diverges;
+        let mut arms_diverge = !arms.is_empty();
+        // The first arm's type is what the others must agree with.
+        let mut result: Option<Type> = None;
+        let mut earlier = Vec::new();
+        // Patterns that are already wrong make poor witnesses.
+        let mut pats_ok = true;
+        for arm in arms {
+            let mut ty = self.with_scope(|this| {
+                let errors = this.diagnostics.len();
+                this.pat(&arm.pat, &scrutinee_ty);
+                pats_ok &= this.diagnostics.len() == errors;
+                if let Some(guard) = &arm.guard {
+                    this.expect(guard, &Type::Bool);
+                }
+                this.diverges = false;
+                let ty = this.expr(&arm.body);
+                arms_diverge &= this.diverges;
+                ty
+            });
+            let span = value_span(&arm.body);
+            // The body of a `while let`, or of an `if let` without `else`,
+            // is checked as that of a `while` or `if` would be.
+            if result.is_none() {
+                if let ExprKind::Block(body) = &arm.body.kind {
+                    let without_else = source == MatchSource::IfLet { has_else: false };
+                    if source == MatchSource::WhileLet || without_else && !value_used {
+                        self.demand_unit_block(body, &ty);
+                        ty = Type::unit();
+                    } else if without_else {
+                        if !self.infer.coerce(&ty, &Type::unit()) {
+                            // The `_` arm stands at the `let`.
+                            let if_span = arms[arms.len() - 1].pat.span.to(body.span);
+                            let diagnostic =
+                                Diagnostic::error("`if` may be missing an `else` clause")
+                                    .code("E0317")
+                                    .primary(if_span, self.expected_found(&ty, &Type::unit()))
+                                    .secondary(span, "found here")
+                                    .note("`if` expressions without `else` evaluate to `()`")
+                                    .help(
+                                        "consider adding an `else` block that evaluates to \
+                                             the expected type",
+                                    );
+                            self.diagnostics.push(diagnostic);
+                        }
+                        ty = Type::unit();
+                    }
+                }
+            }
+            // Arms that never finish give no value to agree on.
+            if self.infer.shallow_resolve(&ty) == Type::Never {
+                continue;
+            }
+            match &result {
+                None => result = Some(ty),
+                Some(expected) => {
+                    if !self.infer.coerce(&ty, expected) {
+                        let diagnostic = match source {
+                            MatchSource::IfLet { .. } => {
+                                Diagnostic::error("`if` and `else` have incompatible types")
+                                    .code("E0308")
+                                    .primary(span, self.expected_found(expected, &ty))
+                                    .secondary(earlier[0], "expected because of this")
+                            }
+                            _ => {
+                                let expected_name = self.resolve(expected).to_string();
+                                let mut diagnostic =
+                                    Diagnostic::error("`match` arms have incompatible types")
+                                        .code("E0308")
+                                        .primary(span, self.expected_found(expected, &ty));
+                                for &earlier_span in &earlier {
+                                    diagnostic = diagnostic.secondary(
+                                        earlier_span,
+                                        format!("this is found to be of type `{}`", expected_name),
+                                    );
+                                }
+                                diagnostic
+                            }
+                        };
+                        self.diagnostics.push(diagnostic);
+                    }
+                }
+            }
+            earlier.push(span);
+        }
+        if pats_ok {
+            self.check_match(scrutinee, &scrutinee_ty, arms, source);
+        }
+        self.diverges = scrutinee_diverges || arms_diverge;
+        result.unwrap_or(Type::Never)
+    }
+
+    /// `value_used` is false for an `if` in statement position, whose value
+    /// is thrown away.
+    fn if_expr(&mut self, cond: &Expr, then: &Block, els: Option<&Expr>, value_used: bool) -> Type {
+        self.expect(cond, &Type::Bool);
+        // Only an `if` whose branches both diverge does.
+        let cond_diverges = std::mem::take(&mut self.diverges);
+        let then_ty = self.block(then);
+        let then_diverges = std::mem::replace(&mut self.diverges, cond_diverges);
+        let then_span = then.tail.as_deref().map_or(then.span, value_span);
+        match els {
+            Some(els) => {
+                let else_ty = self.expr(els);
+                self.diverges = cond_diverges || then_diverges && self.diverges;
+                let then_diverges = self.infer.shallow_resolve(&then_ty) == Type::Never;
+                if !then_diverges && !self.infer.coerce(&else_ty, &then_ty) {
+                    let diagnostic = Diagnostic::error("`if` and `else` have incompatible types")
+                        .code("E0308")
+                        .primary(value_span(els), self.expected_found(&then_ty, &else_ty))
+                        .secondary(then_span, "expected because of this");
+                    self.diagnostics.push(diagnostic);
+                }
+                if then_diverges {
+                    else_ty
+                } else {
+                    then_ty
+                }
+            }
+            // As a statement, the block is just one more place that must be
+            // `()`, and a value there is most likely a missing `;`.
+            None if !value_used => {
+                self.demand_unit_block(then, &then_ty);
+                Type::unit()
+            }
+            None => {
+                // Without an `else`, the value when the condition is false is `()`.
+                if !self.infer.coerce(&then_ty, &Type::unit()) {
+                    let if_span = cond.span.to(then.span);
+                    let diagnostic = Diagnostic::error("`if` may be missing an `else` clause")
+                        .code("E0317")
+                        .primary(if_span, self.expected_found(&then_ty, &Type::unit()))
+                        .secondary(then_span, "found here")
+                        .note("`if` expressions without `else` evaluate to `()`")
+                        .help(
+                            "consider adding an `else` block that evaluates to the expected type",
+                        );
+                    self.diagnostics.push(diagnostic);
+                }
+                Type::unit()
+            }
+        }
+    }
+
+    /// Check a loop whose value has type `ty`, running `f` on its parts
+    /// while its `break`s can find it. Returns the loop's type.
+    fn in_loop(
+        &mut self,
+        label: &Option<Ident>,
+        keyword: &'static str,
+        head: Span,
+        ty: Type,
+        f: impl FnOnce(&mut Checker),
+    ) -> Type {
+        let label = label.as_ref().map(|label| label.name.clone());
+        self.loops.push(Loop { label, keyword, head, ty, first_break: None });
+        // A loop body may never run, or be left by a `break`.
+        let diverges = self.diverges;
+        f(self);
+        self.diverges = diverges;
+        // A `loop` that no `break` leaves never finishes.
+        let l = self.loops.pop().expect("pushed above");
+        match l.first_break {
+            None if l.keyword == "loop" => Type::Never,
+            _ => l.ty,
+        }
+    }
+
+    /// `break 'label value`: the value, or `()` without one, is the loop's.
+    /// Only a `loop` can be left with a value.
+    fn break_expr(&mut self, span: Span, label: Option<&Ident>, value: Option<&Expr>) {
+        let target = match label {
+            Some(label) => {
+                self.loops.iter().rposition(|l| l.label.as_deref() == Some(label.name.as_str()))
+            }
+            None => self.loops.len().checked_sub(1),
+        };
+        // The resolver reports a `break` without a loop.
+        let index = match target {
+            Some(index) => index,
+            None => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                return;
+            }
+        };
+        let found = match value {
+            Some(value) => self.expr(value),
+            None => Type::unit(),
+        };
+        let target = &self.loops[index];
+        if value.is_some() && target.keyword != "loop" {
+            let keyword = target.keyword;
+            let without_value = match label {
+                Some(label) => format!("break '{}", label.name),
+                None => "break".to_string(),
+            };
+            let diagnostic =
+                Diagnostic::error(format!("`break` with value from a `{}` loop", keyword))
+                    .code("E0571")
+                    .primary(span, "can only break with a value inside `loop`")
+                    .secondary(
+                        target.head,
+                        format!("you can't `break` with a value in a `{}` loop", keyword),
+                    )
+                    .suggest(
+                        format!(
+                            "use `break` on its own without a value inside this `{}` loop",
+                            keyword
+                        ),
+                        span,
+                        without_value,
+                        Applicability::MaybeIncorrect,
+                    );
+            self.diagnostics.push(diagnostic);
+            return;
+        }
+        let (expected, first_break) = (target.ty.clone(), target.first_break);
+        let value_span = value.map_or(span, value_span);
+        if self.infer.coerce(&found, &expected) {
+            self.loops[index].first_break.get_or_insert(value_span);
+            return;
+        }
+        let mut diagnostic = self.mismatch(&expected, &found, value_span);
+        if let Some(first_break) = first_break {
+            diagnostic = diagnostic.secondary(first_break, "expected because of this `break`");
+        }
+        if value.is_none() {
+            diagnostic = diagnostic.help("give the `break` a value of the expected type");
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// A loop body runs for its effects: its value must be `()`.
+    fn loop_body(&mut self, body: &Block) {
+        let ty = self.block(body);
+        self.demand_unit_block(body, &ty);
+    }
+
+    /// Require a block whose value nobody uses to be `()`. If it is not, the
+    /// tail expression is almost always a statement that lost its `;`.
+    fn demand_unit_block(&mut self, block: &Block, ty: &Type) {
+        if self.infer.coerce(ty, &Type::unit()) {
+            return;
+        }
+        let diagnostic = match &block.tail {
+            Some(tail) => {
+                let diagnostic = self.mismatch(&Type::unit(), ty, value_span(tail));
+                forgot_semicolon(diagnostic, tail)
+            }
+            None => self.mismatch(&Type::unit(), ty, block.span),
+        };
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// The type of the items a `for` loop gets from iterating over `iter`,
+    /// of type `iter_ty`. E0277 if it is something no loop can go over.
+    fn iter_elem(&mut self, iter: &Expr, iter_ty: &Type) -> Type {
+        match self.infer.shallow_resolve(iter_ty) {
+            Type::Adt(name, mut args)
+                if args.len() == 1
+                    && matches!(name.as_str(), "Range" | "RangeInclusive" | "RangeFrom") =>
+            {
+                let elem = args.pop().unwrap();
+                match self.infer.shallow_resolve(&elem) {
+                    Type::Bool | Type::Float(_) | Type::FloatVar(_) | Type::Str => {
+                        let diagnostic = self
+                            .not_iterator_diagnostic(iter, iter_ty)
+                            .note("only ranges of integers or chars can be stepped through");
+                        self.diagnostics.push(diagnostic);
+                        Type::Error
+                    }
+                    _ => elem,
+                }
+            }
+            Type::Adt(name, mut args) if name == "Vec" && args.len() == 1 => args.pop().unwrap(),
+            Type::Adt(name, _)
+                if matches!(name.as_str(), "RangeTo" | "RangeToInclusive" | "RangeFull") =>
+            {
+                let diagnostic = self
+                    .not_iterator_diagnostic(iter, iter_ty)
+                    .note("a range without a start has nowhere to count from");
+                let diagnostic = match &iter.kind {
+                    ExprKind::Range(None, Some(_), _) => diagnostic.suggest(
+                        "give it a start",
+                        iter.span.shrink_to_lo(),
+                        "0",
+                        Applicability::MaybeIncorrect,
+                    ),
+                    _ => diagnostic,
+                };
+                self.diagnostics.push(diagnostic);
+                Type::Error
+            }
+            Type::Bool
+            | Type::Int(_)
+            | Type::IntVar(_)
+            | Type::Float(_)
+            | Type::FloatVar(_)
+            | Type::Char
+            | Type::Tuple(_) => self.not_iterator(iter, iter_ty),
+            Type::Adt(ref name, _)
+                if self.struct_def(name).is_some()
+                    || self.enum_def(name).is_some() && name != "Option" && name != "Result" =>
+            {
+                let diagnostic = self.not_iterator_diagnostic(iter, iter_ty).note(
+                    "there are no `impl` blocks to give a struct or enum the `next` method \
+                     of an iterator",
+                );
+                self.diagnostics.push(diagnostic);
+                Type::Error
+            }
+            Type::Array(elem, _) => *elem,
+            Type::Ref(mutability, inner) => match self.infer.shallow_resolve(&inner) {
+                Type::Array(elem, _) | Type::Slice(elem) => Type::Ref(mutability, elem),
+                Type::Adt(name, mut args) if name == "Vec" && args.len() == 1 => {
+                    Type::Ref(mutability, Box::new(args.pop().unwrap()))
+                }
+                _ => self.infer.new_var(),
+            },
+            _ => self.infer.new_var(),
+        }
+    }
+
+    fn not_iterator(&mut self, iter: &Expr, iter_ty: &Type) -> Type {
+        let diagnostic = self.not_iterator_diagnostic(iter, iter_ty);
+        self.diagnostics.push(diagnostic);
+        Type::Error
+    }
+
+    fn not_iterator_diagnostic(&self, iter: &Expr, iter_ty: &Type) -> Diagnostic {
+        let ty = self.resolve(iter_ty);
+        Diagnostic::error(format!("`{}` is not an iterator", ty))
+            .code("E0277")
+            .primary(iter.span, format!("`{}` is not an iterator", ty))
+    }
+
+    fn mac_call(&mut self, mac: &MacCall) -> Type {
+        let name = mac.name.name.as_str();
+        let args = match (name, mac.args.split_first()) {
+            // The format string itself is not an expression to type.
+            (
+                "println" | "print" | "eprintln" | "eprint" | "format" | "panic",
+                Some((first, rest)),
+            ) if matches!(first.kind, ExprKind::Lit(LitKind::Str(_))) => rest,
+            _ => mac.args.as_slice(),
+        };
+        match name {
+            "assert" => {
+                if let Some((cond, rest)) = args.split_first() {
+                    self.expect(cond, &Type::Bool);
+                    for arg in rest {
+                        self.expr(arg);
+                    }
+                }
+                Type::unit()
+            }
+            "assert_eq" | "assert_ne" if args.len() >= 2 => {
+                let left = self.expr(&args[0]);
+                self.expect(&args[1], &left);
+                for arg in &args[2..] {
+                    self.expr(arg);
+                }
+                Type::unit()
+            }
+            "vec" => {
+                let array = args.first().map(|array| self.expr(array));
+                let elem = match array.map(|ty| self.infer.shallow_resolve(&ty)) {
+                    Some(Type::Array(elem, _)) => *elem,
+                    _ => self.infer.new_var(),
+                };
+                Type::adt("Vec", vec![elem])
+            }
+            _ => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                match name {
+                    "format" => Type::adt("String", Vec::new()),
+                    "println" | "print" | "eprintln" | "eprint" | "assert_eq" | "assert_ne" => {
+                        Type::unit()
+                    }
+                    "panic" | "unreachable" | "todo" | "unimplemented" => Type::Never,
+                    _ => self.infer.new_var(),
+                }
+            }
+        }
+    }
+}
+
+/// The fields of the range structs a literal can build: `Range`,
+/// `RangeFrom` and `RangeTo`. The inclusive ranges keep theirs private.
+fn range_fields(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        "Range" => Some(&["start", "end"]),
+        "RangeFrom" => Some(&["start"]),
+        "RangeTo" => Some(&["end"]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::check_types;
+
+    fn codes(src: &str) -> Vec<&'static str> {
+        check_types(src).iter().filter(|d| d.is_error()).map(|d| d.code.unwrap_or("")).collect()
+    }
+
+    #[test]
+    fn break_values_give_the_loop_its_type() {
+        assert!(check_types("let x: i32 = 'a: loop { loop { break 'a 5; } };").is_empty());
+        assert_eq!(
+            codes("let c = true; let x = loop { if c { break 1; } break \"a\"; };"),
+            ["E0308"]
+        );
+        assert_eq!(codes("let x: i32 = loop { break; };"), ["E0308"]);
+    }
+
+    #[test]
+    fn only_loop_breaks_with_a_value() {
+        let diagnostics = check_types("let x = for i in 0..3 { break i; };");
+        assert_eq!(diagnostics[0].code, Some("E0571"));
+        assert_eq!(diagnostics[0].message, "`break` with value from a `for` loop");
+        assert_eq!(diagnostics[0].suggestions[0].replacement, "break");
+        assert_eq!(codes("let mut i = 0; while i < 3 { i += 1; break 7; }"), ["E0571"]);
+        assert_eq!(codes("'a: while true { loop { break 'a 1; } }"), ["E0571"]);
+    }
+
+    #[test]
+    fn for_loops_need_an_iterator() {
+        let diagnostics = check_types("for i in ..5 { }");
+        assert_eq!(diagnostics[0].code, Some("E0277"));
+        assert_eq!(diagnostics[0].message, "`RangeTo<{integer}>` is not an iterator");
+        assert_eq!(diagnostics[0].suggestions[0].message, "give it a start");
+        assert_eq!(diagnostics[0].suggestions[0].replacement, "0");
+        assert_eq!(check_types("for x in 5 {}")[0].message, "`{integer}` is not an iterator");
+        let src = "enum Dir { Up } struct S { n: u32 } let s = S { n: 3 }; \
+                   for d in Dir::Up {} for x in s {}";
+        let diagnostics = check_types(src);
+        assert_eq!(diagnostics[0].message, "`Dir` is not an iterator");
+        assert_eq!(diagnostics[1].message, "`S` is not an iterator");
+        assert!(check_types("let v = vec![1, 2]; for x in &v { let y: &i32 = x; }").is_empty());
+        assert_eq!(codes("for x in [1, 2] { let y: bool = x; }"), ["E0308"]);
+    }
+
+    #[test]
+    fn return_needs_the_return_type() {
+        let diagnostics = check_types("fn f() -> i32 { return; } f()");
+        assert_eq!(diagnostics[0].code, Some("E0069"));
+        assert_eq!(diagnostics[0].labels[1].message, "expected `i32` because of return type");
+        assert_eq!(codes("fn f() -> i32 { return true; } f()"), ["E0308"]);
+        assert!(check_types("fn f(n: i32) -> i32 { if n > 0 { return 1; } 2 }").is_empty());
+    }
+
+    #[test]
+    fn question_mark_errors() {
+        let messages = |src| check_types(src).into_iter().map(|d| d.message).collect::<Vec<_>>();
+        assert_eq!(
+            messages("fn f() -> i32 { let x = Some(1)?; x }"),
+            ["the `?` operator can only be used in a function that returns `Result` or `Option`"]
+        );
+        assert_eq!(
+            messages(
+                "fn f() -> Option<i32> { let r: Result<i32, u8> = Ok(1); let x = r?; Some(x) }"
+            ),
+            ["the `?` operator can only be used on `Option`s, not `Result`s, \
+                 in a function that returns `Option`"]
+        );
+        assert_eq!(
+            messages("fn f() -> Result<i32, u8> { let x = 5?; Ok(x) }"),
+            ["the `?` operator can only be applied to values that implement `Try`"]
+        );
+    }
+
+    #[test]
+    fn question_mark_converts_the_error() {
+        let src =
+            "fn f() -> Result<i32, u8> { let r: Result<i32, u32> = Ok(1); let x = r?; Ok(x) }";
+        let diagnostics = check_types(src);
+        assert_eq!(diagnostics[0].message, "`?` couldn't convert the error to `u8`");
+        assert_eq!(
+            diagnostics[0].labels[0].message,
+            "the trait `From<u32>` is not implemented for `u8`"
+        );
+        let src =
+            "fn f() -> Result<i32, u32> { let r: Result<i32, u8> = Ok(1); let x = r?; Ok(x) }";
+        assert!(check_types(src).is_empty());
+    }
+}
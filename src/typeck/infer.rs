@@ -0,0 +1,156 @@
+//! Type inference by unification.
+
+use super::ty::{Type, VarId};
+use crate::ast::{FloatTy, IntTy};
+
+/// The inference variables created while checking a program, and what each
+/// has been found to be.
+#[derive(Default)]
+pub struct InferCtxt {
+    vars: Vec<Option<Type>>,
+}
+
+impl InferCtxt {
+    pub fn new_var(&mut self) -> Type {
+        Type::Var(self.fresh())
+    }
+
+    pub fn new_int_var(&mut self) -> Type {
+        Type::IntVar(self.fresh())
+    }
+
+    pub fn new_float_var(&mut self) -> Type {
+        Type::FloatVar(self.fresh())
+    }
+
+    fn fresh(&mut self) -> VarId {
+        self.vars.push(None);
+        self.vars.len() - 1
+    }
+
+    /// Follow variable bindings until reaching a type that is not a bound variable.
+    pub fn shallow_resolve(&self, ty: &Type) -> Type {
+        let mut ty = ty.clone();
+        while let Type::Var(v) | Type::IntVar(v) | Type::FloatVar(v) = ty {
+            match &self.vars[v] {
+                Some(bound) => ty = bound.clone(),
+                None => break,
+            }
+        }
+        ty
+    }
+
+    /// Substitute everything known about the variables in `ty`.
+    pub fn resolve(&self, ty: &Type) -> Type {
+        match self.shallow_resolve(ty) {
+            Type::Tuple(elems) => Type::Tuple(elems.iter().map(|t| self.resolve(t)).collect()),
+            Type::Array(elem, len) => Type::Array(Box::new(self.resolve(&elem)), len),
+            Type::Slice(elem) => Type::Slice(Box::new(self.resolve(&elem))),
+            Type::Ref(m, inner) => Type::Ref(m, Box::new(self.resolve(&inner))),
+            Type::Adt(name, args) => {
+                Type::Adt(name, args.iter().map(|t| self.resolve(t)).collect())
+            }
+            Type::Fn(params, ret) => Type::Fn(
+                params.iter().map(|t| self.resolve(t)).collect(),
+                Box::new(self.resolve(&ret)),
+            ),
+            ty => ty,
+        }
+    }
+
+    /// Like [`InferCtxt::resolve`], but also applies the literal defaults:
+    /// integers that nothing constrained are `i32`, floats are `f64`.
+    pub fn resolve_final(&self, ty: &Type) -> Type {
+        match self.resolve(ty) {
+            Type::IntVar(_) => Type::Int(IntTy::I32),
+            Type::FloatVar(_) => Type::Float(FloatTy::F64),
+            Type::Tuple(elems) => {
+                Type::Tuple(elems.iter().map(|t| self.resolve_final(t)).collect())
+            }
+            Type::Array(elem, len) => Type::Array(Box::new(self.resolve_final(&elem)), len),
+            Type::Slice(elem) => Type::Slice(Box::new(self.resolve_final(&elem))),
+            Type::Ref(m, inner) => Type::Ref(m, Box::new(self.resolve_final(&inner))),
+            Type::Adt(name, args) => {
+                Type::Adt(name, args.iter().map(|t| self.resolve_final(t)).collect())
+            }
+            Type::Fn(params, ret) => Type::Fn(
+                params.iter().map(|t| self.resolve_final(t)).collect(),
+                Box::new(self.resolve_final(&ret)),
+            ),
+            ty => ty,
+        }
+    }
+
+    /// Make `a` and `b` the same type, binding variables as needed. Returns
+    /// false if they cannot be; bindings made before the conflict was found
+    /// are kept, which only matters for the quality of later messages.
+    pub fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        let (a, b) = (self.shallow_resolve(a), self.shallow_resolve(b));
+        match (&a, &b) {
+            (Type::Error, _) | (_, Type::Error) => true,
+            (Type::Var(x), Type::Var(y))
+            | (Type::IntVar(x), Type::IntVar(y))
+            | (Type::FloatVar(x), Type::FloatVar(y))
+                if x == y =>
+            {
+                true
+            }
+            (Type::Var(v), _) => self.bind(*v, b),
+            (_, Type::Var(v)) => self.bind(*v, a),
+            (Type::IntVar(v), Type::IntVar(_) | Type::Int(_)) => self.bind(*v, b),
+            (Type::Int(_), Type::IntVar(v)) => self.bind(*v, a),
+            (Type::FloatVar(v), Type::FloatVar(_) | Type::Float(_)) => self.bind(*v, b),
+            (Type::Float(_), Type::FloatVar(v)) => self.bind(*v, a),
+            (Type::Tuple(xs), Type::Tuple(ys)) | (Type::Adt(_, xs), Type::Adt(_, ys))
+                if xs.len() == ys.len() && same_name(&a, &b) =>
+            {
+                xs.iter().zip(ys).all(|(x, y)| self.unify(x, y))
+            }
+            (Type::Array(x, n), Type::Array(y, m)) => {
+                self.unify(x, y) && (n.is_none() || m.is_none() || n == m)
+            }
+            (Type::Slice(x), Type::Slice(y)) => self.unify(x, y),
+            (Type::Ref(mx, x), Type::Ref(my, y)) => mx == my && self.unify(x, y),
+            (Type::Fn(xs, x), Type::Fn(ys, y)) if xs.len() == ys.len() => {
+                xs.iter().zip(ys).all(|(a, b)| self.unify(a, b)) && self.unify(x, y)
+            }
+            _ => a == b,
+        }
+    }
+
+    /// Let a value of type `found` be used where `expected` is wanted: as
+    /// [`InferCtxt::unify`], except that `!` fits anywhere.
+    pub fn coerce(&mut self, found: &Type, expected: &Type) -> bool {
+        self.shallow_resolve(found) == Type::Never || self.unify(expected, found)
+    }
+
+    fn bind(&mut self, var: VarId, ty: Type) -> bool {
+        if self.occurs(var, &ty) {
+            return false;
+        }
+        self.vars[var] = Some(ty);
+        true
+    }
+
+    /// Whether `var` appears inside `ty`, which would make binding it infinite.
+    fn occurs(&self, var: VarId, ty: &Type) -> bool {
+        match self.shallow_resolve(ty) {
+            Type::Var(v) | Type::IntVar(v) | Type::FloatVar(v) => v == var,
+            Type::Tuple(elems) | Type::Adt(_, elems) => elems.iter().any(|t| self.occurs(var, t)),
+            Type::Array(elem, _) | Type::Slice(elem) | Type::Ref(_, elem) => {
+                self.occurs(var, &elem)
+            }
+            Type::Fn(params, ret) => {
+                params.iter().any(|t| self.occurs(var, t)) || self.occurs(var, &ret)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn same_name(a: &Type, b: &Type) -> bool {
+    match (a, b) {
+        (Type::Adt(x, _), Type::Adt(y, _)) => x == y,
+        _ => true,
+    }
+}
@@ -0,0 +1,171 @@
+//! Items declared in blocks: their signatures and bodies.
+
+use super::ty::Type;
+use super::{forgot_semicolon, value_span, Body, Checker, EnumDef, StructDef};
+use crate::ast::{FnDecl, Item, ItemKind, Stmt, StmtKind, StructFields, Ty, UseTree, UseTreeKind};
+
+impl Checker {
+    /// Declare the types and functions among `stmts` in the current scope,
+    /// so they can be used anywhere in the block, even before the
+    /// declaration. Types go first, since signatures may name them, then
+    /// the enum variants that `use` items import.
+    pub(super) fn hoist_items(&mut self, stmts: &[Stmt]) {
+        let items: Vec<&Item> = stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Item(item) => Some(&**item),
+                _ => None,
+            })
+            .collect();
+        for item in &items {
+            match &item.kind {
+                ItemKind::Struct(decl) => {
+                    let def = self.struct_fields(&decl.fields);
+                    let scope = self.scopes.last_mut().expect("inside a scope");
+                    scope.structs.insert(decl.name.name.clone(), def);
+                }
+                ItemKind::Enum(decl) => {
+                    let variants = decl
+                        .variants
+                        .iter()
+                        .map(|variant| {
+                            (variant.name.name.clone(), self.struct_fields(&variant.fields))
+                        })
+                        .collect();
+                    let scope = self.scopes.last_mut().expect("inside a scope");
+                    scope.enums.insert(decl.name.name.clone(), EnumDef { variants });
+                }
+                _ => {}
+            }
+        }
+        for item in &items {
+            if let ItemKind::Use(tree) = &item.kind {
+                self.import_variants(tree, &[]);
+            }
+        }
+        for item in &items {
+            if let ItemKind::Fn(decl) = &item.kind {
+                let params =
+                    decl.params.iter().map(|param| self.param_ty(param.ty.as_ref())).collect();
+                let ret = decl.ret.as_ref().map_or_else(Type::unit, |ret| self.lower_ty(ret));
+                let ty = Type::Fn(params, Box::new(ret));
+                self.record(item.id, &ty);
+                let scope = self.scopes.last_mut().expect("inside a scope");
+                scope.fns.insert(decl.name.name.clone(), ty);
+            }
+        }
+    }
+
+    fn struct_fields(&mut self, fields: &StructFields) -> StructDef {
+        match fields {
+            StructFields::Named(fields) => StructDef {
+                fields: fields
+                    .iter()
+                    .map(|field| (field.name.name.clone(), self.lower_ty(&field.ty)))
+                    .collect(),
+                tuple: false,
+            },
+            StructFields::Tuple(tys) => StructDef {
+                fields: tys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| (i.to_string(), self.lower_ty(ty)))
+                    .collect(),
+                tuple: true,
+            },
+            StructFields::Unit => StructDef { fields: Vec::new(), tuple: false },
+        }
+    }
+
+    /// Record the variants of known enums that `tree` imports: `use
+    /// Suit::*;`, `use Suit::{Hearts, Spades};` or `use Suit::Hearts as H;`.
+    /// `parent` holds the segments before the tree's braces, if it is
+    /// nested.
+    fn import_variants(&mut self, tree: &UseTree, parent: &[&str]) {
+        let mut path = parent.to_vec();
+        path.extend(tree.prefix.segments.iter().map(|segment| segment.ident.name.as_str()));
+        let imports: Vec<(String, String, String)> = match (&tree.kind, path.as_slice()) {
+            (UseTreeKind::Nested(trees), _) => {
+                trees.iter().for_each(|tree| self.import_variants(tree, &path));
+                return;
+            }
+            (UseTreeKind::Glob, [.., enum_name]) => match self.enum_def(enum_name) {
+                Some(def) => def
+                    .variants
+                    .iter()
+                    .map(|(variant, _)| (variant.clone(), enum_name.to_string(), variant.clone()))
+                    .collect(),
+                None => return,
+            },
+            (UseTreeKind::Simple(alias), [.., enum_name, variant]) => {
+                if self.variant(enum_name, variant).is_none() {
+                    return;
+                }
+                let name = alias.as_ref().map_or(*variant, |alias| alias.name.as_str());
+                vec![(name.to_string(), enum_name.to_string(), variant.to_string())]
+            }
+            _ => return,
+        };
+        let scope = self.scopes.last_mut().expect("inside a scope");
+        for (name, enum_name, variant) in imports {
+            scope.variants.insert(name, (enum_name, variant));
+        }
+    }
+
+    fn param_ty(&mut self, ty: Option<&Ty>) -> Type {
+        match ty {
+            Some(ty) => self.lower_ty(ty),
+            None => self.infer.new_var(),
+        }
+    }
+
+    pub(super) fn item(&mut self, item: &Item) {
+        if let ItemKind::Fn(decl) = &item.kind {
+            let sig = self.types.get(&item.id).cloned();
+            if let Some(Type::Fn(params, ret)) = sig {
+                self.fn_body(decl, &params, &ret);
+            }
+        }
+    }
+
+    /// Check a function's body against its signature. The body starts a
+    /// fresh set of locals: only items from the blocks around it are visible.
+    fn fn_body(&mut self, decl: &FnDecl, params: &[Type], ret: &Type) {
+        let outer = std::mem::replace(&mut self.fn_scope, self.scopes.len());
+        let loops = std::mem::take(&mut self.loops);
+        let ret_span = decl.ret.as_ref().map(|ret| ret.span);
+        let body = self.body.replace(Body { ret: ret.clone(), ret_span });
+        let diverges = std::mem::take(&mut self.diverges);
+        self.with_scope(|this| {
+            for (param, ty) in decl.params.iter().zip(params) {
+                this.pat(&param.pat, ty);
+            }
+            let body = &decl.body;
+            let ty = this.block(body);
+            // Falling off the end of a body is for the flow check to
+            // report, since only it can tell whether control gets there.
+            let falls_off = ty.is_unit() && body.ends_without_value();
+            if !falls_off && !this.infer.coerce(&ty, ret) {
+                let span = body.tail.as_deref().map_or(body.span, value_span);
+                let mut diagnostic = this.mismatch(ret, &ty, span);
+                diagnostic = match (&decl.ret, &body.tail) {
+                    (Some(ret_ty), _) => diagnostic.secondary(
+                        ret_ty.span,
+                        format!("expected `{}` because of return type", this.resolve(ret)),
+                    ),
+                    (None, Some(tail)) => forgot_semicolon(diagnostic, tail),
+                    (None, None) => diagnostic,
+                };
+                if this.resolve(ret) == Type::Never {
+                    diagnostic = diagnostic
+                        .note("`!` is the type of a function that never returns, so its body may not finish");
+                }
+                this.diagnostics.push(diagnostic);
+            }
+        });
+        self.fn_scope = outer;
+        self.loops = loops;
+        self.body = body;
+        self.diverges = diverges;
+    }
+}
@@ -0,0 +1,227 @@
+//! The signatures of the methods the evaluator provides.
+//!
+//! | method                                  | receiver                     | type          |
+//! |-----------------------------------------|------------------------------|---------------|
+//! | `len()`                                 | `str`, `String`, arrays, `Vec` | `usize`     |
+//! | `to_string()`                           | `str`, `String`              | `String`      |
+//! | `push_str(&str)`                        | `String`                     | `()`          |
+//! | `push(T)`                               | `Vec<T>`                     | `()`          |
+//! | `pop()`                                 | `Vec<T>`                     | `Option<T>`   |
+//! | `wrapping_op`, `saturating_op`, `pow`   | an integer type `T`          | `T`           |
+//! | `checked_op`                            | an integer type `T`          | `Option<T>`   |
+//! | `overflowing_op`                        | an integer type `T`          | `(T, bool)`   |
+//!
+//! where `op` is `add`, `sub`, `mul`, `div`, `rem`, `shl`, `shr`, `neg` or
+//! `pow`, except that there is no `saturating_rem`, `saturating_shl` or
+//! `saturating_shr`, and `saturating_neg` is for signed types. The argument
+//! is a `T`, but shift amounts and exponents are `u32`, and the `neg`
+//! methods take none. Any other method on an integer type is E0599. Methods
+//! on other types are not declared anywhere the checker can see, so their
+//! calls are given an unknown type.
+
+use super::ty::Type;
+use super::Checker;
+use crate::ast::{IntTy, MethodCall};
+use crate::diagnostic::Diagnostic;
+
+/// What an integer method gives when the result does not fit, which
+/// decides its return type.
+#[derive(Clone, Copy, PartialEq)]
+enum OnOverflow {
+    /// `wrapping_*`, `saturating_*` and `pow`: a `T`.
+    Value,
+    Checked,
+    Overflowing,
+}
+
+/// The argument an integer method takes.
+#[derive(Clone, Copy, PartialEq)]
+enum Arg {
+    None,
+    /// Another value of the receiver's type.
+    Same,
+    /// A shift amount or exponent.
+    U32,
+}
+
+/// The integer method `name`, if integers of the receiver's signedness, or
+/// of either when it is not yet known, have it.
+fn int_method(name: &str, signed: Option<bool>) -> Option<(OnOverflow, Arg)> {
+    let (prefix, op) = match name.split_once('_') {
+        Some((prefix, op)) => (prefix, op),
+        None if name == "pow" => ("", name),
+        None => return None,
+    };
+    let on_overflow = match prefix {
+        "wrapping" | "saturating" | "" => OnOverflow::Value,
+        "checked" => OnOverflow::Checked,
+        "overflowing" => OnOverflow::Overflowing,
+        _ => return None,
+    };
+    let arg = match op {
+        "add" | "sub" | "mul" | "div" => Arg::Same,
+        "rem" if prefix != "saturating" => Arg::Same,
+        "shl" | "shr" if prefix != "saturating" => Arg::U32,
+        "pow" => Arg::U32,
+        "neg" if prefix != "saturating" || signed != Some(false) => Arg::None,
+        _ => return None,
+    };
+    Some((on_overflow, arg))
+}
+
+impl Checker {
+    /// `receiver.method(args)`.
+    pub(super) fn method_call(&mut self, call: &MethodCall) -> Type {
+        let receiver = self.expr(&call.receiver);
+        // The receiver is dereferenced as far as needed: `(&v).len()`.
+        let mut receiver = self.infer.shallow_resolve(&receiver);
+        while let Type::Ref(_, inner) = receiver {
+            receiver = self.infer.shallow_resolve(&inner);
+        }
+        let name = call.method.name.as_str();
+        let usize = Type::Int(IntTy::Usize);
+        let (params, ret) = match (&receiver, name) {
+            (Type::Str | Type::Array(..) | Type::Slice(_), "len") => (Vec::new(), usize),
+            (Type::Adt(adt, _), "len") if adt == "String" || adt == "Vec" => (Vec::new(), usize),
+            (Type::Str, "to_string") => (Vec::new(), Type::adt("String", Vec::new())),
+            (Type::Adt(adt, _), "to_string") if adt == "String" => (Vec::new(), receiver.clone()),
+            (Type::Adt(adt, _), "push_str") if adt == "String" => {
+                (vec![Type::str_ref()], Type::unit())
+            }
+            (Type::Adt(adt, args), "push") if adt == "Vec" => (args.clone(), Type::unit()),
+            (Type::Adt(adt, args), "pop") if adt == "Vec" => {
+                (Vec::new(), Type::adt("Option", args.clone()))
+            }
+            (Type::Int(_) | Type::IntVar(_), _) => {
+                let signed = match receiver {
+                    Type::Int(ty) => Some(ty.is_signed()),
+                    _ => None,
+                };
+                match int_method(name, signed) {
+                    Some((on_overflow, arg)) => {
+                        let params = match arg {
+                            Arg::None => Vec::new(),
+                            Arg::Same => vec![receiver.clone()],
+                            Arg::U32 => vec![Type::Int(IntTy::U32)],
+                        };
+                        let ret = match on_overflow {
+                            OnOverflow::Value => receiver,
+                            OnOverflow::Checked => Type::adt("Option", vec![receiver]),
+                            OnOverflow::Overflowing => Type::Tuple(vec![receiver, Type::Bool]),
+                        };
+                        (params, ret)
+                    }
+                    None if signed.is_some() => {
+                        let diagnostic = Diagnostic::error(format!(
+                            "no method named `{}` found for type `{}` in the current scope",
+                            name,
+                            self.resolve(&receiver)
+                        ))
+                        .code("E0599")
+                        .primary(call.method.span, "method not found");
+                        self.diagnostics.push(diagnostic);
+                        self.method_args(call, None);
+                        return Type::Error;
+                    }
+                    None => {
+                        self.method_args(call, None);
+                        return self.infer.new_var();
+                    }
+                }
+            }
+            _ => {
+                self.method_args(call, None);
+                return self.infer.new_var();
+            }
+        };
+        self.method_args(call, Some(&params));
+        ret
+    }
+
+    /// Check a method's arguments against its parameter types, when they
+    /// are known.
+    fn method_args(&mut self, call: &MethodCall, params: Option<&[Type]>) {
+        let params = match params {
+            Some(params) if params.len() == call.args.len() => params,
+            Some(params) => {
+                let plural = |n: usize| if n == 1 { "" } else { "s" };
+                let diagnostic = Diagnostic::error(format!(
+                    "this method takes {} argument{} but {} argument{} {} supplied",
+                    params.len(),
+                    plural(params.len()),
+                    call.args.len(),
+                    plural(call.args.len()),
+                    if call.args.len() == 1 { "was" } else { "were" }
+                ))
+                .code("E0061")
+                .primary(
+                    call.method.span,
+                    format!("expected {} argument{}", params.len(), plural(params.len())),
+                );
+                self.diagnostics.push(diagnostic);
+                &[]
+            }
+            None => &[],
+        };
+        for (i, arg) in call.args.iter().enumerate() {
+            match params.get(i) {
+                Some(param) => self.expect(arg, param),
+                None => self.expr(arg),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::check_types;
+
+    fn messages(src: &str) -> Vec<String> {
+        check_types(src).into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn integer_methods_have_signatures() {
+        let src = "let x: u8 = 250; let a: u8 = x.wrapping_add(10); \
+                   let b: Option<u8> = x.checked_add(1); \
+                   let c: (u8, bool) = x.overflowing_mul(2); let d: u8 = x.saturating_pow(2u32); \
+                   let e: i8 = (-3i8).saturating_neg();";
+        assert!(check_types(src).is_empty());
+        assert!(check_types(
+            "let v = vec![1, 2]; let n: usize = v.len(); let p: Option<i32> = v.pop();"
+        )
+        .is_empty());
+        assert_eq!(
+            messages("let x: u8 = 1; let y: u16 = x.wrapping_add(1);"),
+            ["mismatched types"]
+        );
+        assert_eq!(messages("let x: u8 = 1; x.wrapping_add(1u16)"), ["mismatched types"]);
+        assert_eq!(messages("let x: u8 = 1; x.wrapping_shl(1u8)"), ["mismatched types"]);
+    }
+
+    #[test]
+    fn unknown_methods_and_wrong_arities() {
+        let diagnostics = check_types("let x: u8 = 1; x.frobnicate()");
+        assert_eq!(diagnostics[0].code, Some("E0599"));
+        assert_eq!(
+            diagnostics[0].message,
+            "no method named `frobnicate` found for type `u8` in the current scope"
+        );
+        assert_eq!(
+            messages("let x: u8 = 1; x.saturating_neg()"),
+            ["no method named `saturating_neg` found for type `u8` in the current scope"]
+        );
+        assert!(check_types("let x: i8 = 1; x.saturating_neg()").is_empty());
+
+        let diagnostics = check_types("let x: u8 = 1; x.wrapping_add()");
+        assert_eq!(diagnostics[0].code, Some("E0061"));
+        assert_eq!(
+            diagnostics[0].message,
+            "this method takes 1 argument but 0 arguments were supplied"
+        );
+        assert_eq!(
+            messages("let x: u8 = 1; x.wrapping_neg(1)"),
+            ["this method takes 0 arguments but 1 argument was supplied"]
+        );
+    }
+}
@@ -0,0 +1,552 @@
+//! Static type checking.
+//!
+//! The checker infers a type for every expression by unification and
+//! reports the "mismatched types" family of errors before anything runs:
+//! `if` and `else` branches of different types, an `if` without `else` whose
+//! block is not `()`, and `match` arms that disagree. A value where `()` is
+//! expected, such as the tail of a loop body, gets a "did you forget a `;`?"
+//! suggestion, since that is nearly always what happened.
+//!
+//! Functions, structs and enums declared in a block are known throughout
+//! it, so calls, struct literals, field accesses and patterns are checked
+//! against their declarations. Each `match` must cover every value of its
+//! scrutinee's type, and an arm no value can reach gets a warning. A `loop`
+//! is worth the value its `break`s give it, which must all have one type;
+//! the other loops are worth `()` and cannot `break` with a value. A `for`
+//! loop must go over something iterable: a range with a start, an array or
+//! a vector, not `..5` or a number.
+//!
+//! Expressions that never finish, such as `return`, `break`, `panic!()`, a
+//! `loop` without `break` or a call to a function declared `-> !`, are of
+//! type `!`, which fits wherever a value is expected: an `if` branch that
+//! returns need not agree with the other.
+//!
+//! Each operator takes only the operands Rust defines it for. Unary `-`
+//! rejects unsigned integers ("can't apply unary `-` to type `u32`"), even
+//! when only a later annotation makes a literal unsigned; `!` is bitwise NOT
+//! on integers and logical NOT on `bool`; the two sides of a comparison
+//! must have the same type, and those of `&&` and `||` must be `bool`.
+//!
+//! The integer methods such as `wrapping_add` and `checked_mul`, and
+//! `len`, have their signatures checked; `200u8.wrapping_add(1)` is a `u8`.
+//!
+//! A `return` must give the function's return type, and `?` may only be
+//! used on a `Result` in a function returning `Result`, or on an `Option`
+//! in one returning `Option`. The error a `Result` carries must convert to
+//! the function's by one of the standard `From` impls. Whether control can
+//! reach the end of a function body without a value is left to
+//! [`crate::flow`], which follows the paths through it.
+//!
+//! Programs are often fragments, like the examples in the notes, that use
+//! names they never declare (`user.is_hobbit()`). Such names are given an
+//! unknown type rather than rejected, so the checker can still find the
+//! errors that do not depend on them.
+
+mod exhaust;
+mod expr;
+mod infer;
+mod item;
+mod methods;
+mod ops;
+mod pat;
+pub mod ty;
+
+use std::collections::HashMap;
+
+use self::infer::InferCtxt;
+use self::ty::Type;
+use crate::ast::{self, Block, Expr, ExprKind, FloatTy, IntTy, NodeId, Path, TyKind};
+use crate::diagnostic::{Applicability, Diagnostic};
+use crate::span::Span;
+
+/// The types the checker found, by node.
+#[derive(Debug, Default)]
+pub struct TypeckResults {
+    types: HashMap<NodeId, Type>,
+}
+
+impl TypeckResults {
+    /// The type of an expression, block or pattern, with unconstrained
+    /// literals defaulted to `i32` and `f64`.
+    pub fn node_type(&self, id: NodeId) -> Option<&Type> {
+        self.types.get(&id)
+    }
+}
+
+/// Type-check a program, returning the types found and the errors reported.
+pub fn check(program: &Block) -> (TypeckResults, Vec<Diagnostic>) {
+    let mut checker = Checker::default();
+    checker.block(program);
+    checker.check_negations();
+    let types =
+        checker.types.iter().map(|(id, ty)| (*id, checker.infer.resolve_final(ty))).collect();
+    (TypeckResults { types }, checker.diagnostics)
+}
+
+/// The names declared in one block, arm, closure or function body.
+#[derive(Default)]
+struct Scope {
+    locals: HashMap<String, Type>,
+    /// Functions declared in the block, with their signatures.
+    fns: HashMap<String, Type>,
+    structs: HashMap<String, StructDef>,
+    enums: HashMap<String, EnumDef>,
+    /// Enum variants a `use` made available by their own names, with the
+    /// enum each belongs to: `use Suit::*;` brings in `Hearts`.
+    variants: HashMap<String, (String, String)>,
+}
+
+/// A struct's or variant's fields and their types. A tuple struct's fields
+/// are named `0`, `1`, ...
+#[derive(Clone)]
+struct StructDef {
+    fields: Vec<(String, Type)>,
+    tuple: bool,
+}
+
+impl StructDef {
+    fn is_unit(&self) -> bool {
+        !self.tuple && self.fields.is_empty()
+    }
+}
+
+struct EnumDef {
+    variants: Vec<(String, StructDef)>,
+}
+
+/// A declared struct or enum variant, as struct literals, constructor calls
+/// and patterns use it.
+struct Ctor {
+    /// The type of the values built: the struct, or the variant's enum.
+    ty: Type,
+    /// How messages name it: `Point`, or `Shape::Circle` for a variant.
+    name: String,
+    is_variant: bool,
+    def: StructDef,
+}
+
+impl Ctor {
+    /// "struct" or "variant", as in "variant `Shape::Circle` has no field
+    /// named `r`".
+    fn kind(&self) -> &'static str {
+        if self.is_variant {
+            "variant"
+        } else {
+            "struct"
+        }
+    }
+
+    /// The type of the constructor used as a value: the value itself for a
+    /// unit struct or variant, a function for a tuple-like one.
+    fn value_type(&self) -> Option<Type> {
+        if self.def.tuple {
+            let params = self.def.fields.iter().map(|(_, ty)| ty.clone()).collect();
+            Some(Type::Fn(params, Box::new(self.ty.clone())))
+        } else if self.def.is_unit() {
+            Some(self.ty.clone())
+        } else {
+            None
+        }
+    }
+}
+
+/// A loop around the expression being checked, for the `break`s in it.
+struct Loop {
+    label: Option<String>,
+    /// `loop`, `while`, `while let` or `for`. Only a `loop` can `break`
+    /// with a value.
+    keyword: &'static str,
+    /// The loop up to its body: `while cond` or `for pat in iter`.
+    head: Span,
+    /// The type of the loop's value, which every `break` must give it.
+    ty: Type,
+    /// The first `break` that did, to point at when another disagrees.
+    first_break: Option<Span>,
+}
+
+/// The function or closure whose body is being checked, for the `return`s
+/// and `?`s in it.
+struct Body {
+    ret: Type,
+    /// The written return type, if there is one.
+    ret_span: Option<Span>,
+}
+
+#[derive(Default)]
+struct Checker {
+    infer: InferCtxt,
+    /// Innermost scope last.
+    scopes: Vec<Scope>,
+    /// The first of `scopes` that belongs to the `fn` item being checked.
+    /// Locals in the scopes before it are out of its reach.
+    fn_scope: usize,
+    /// Names used without a declaration in sight. Each gets one unknown
+    /// type, shared by all its uses.
+    free: HashMap<String, Type>,
+    types: HashMap<NodeId, Type>,
+    /// The loops around the expression being checked, innermost last. A
+    /// closure or `fn` body starts with none.
+    loops: Vec<Loop>,
+    /// `None` at the top level of the program, which no `return` leaves
+    /// with a type in mind.
+    body: Option<Body>,
+    /// Whether the expression being checked has met one of type `!`, such
+    /// as a `return` or `panic!()`, that control cannot get past. A block
+    /// that ends in one is of type `!` too.
+    diverges: bool,
+    /// Negated integer literals whose type was not yet known, to be checked
+    /// for signedness once it is.
+    negated: Vec<(Type, Span)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn record(&mut self, id: NodeId, ty: &Type) {
+        self.types.insert(id, ty.clone());
+    }
+
+    fn with_scope<T>(&mut self, f: impl FnOnce(&mut Checker) -> T) -> T {
+        self.scopes.push(Scope::default());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    fn declare(&mut self, name: &str, ty: Type) {
+        self.scopes.last_mut().expect("inside a scope").locals.insert(name.to_string(), ty);
+    }
+
+    fn lookup(&mut self, name: &str) -> Type {
+        for (depth, scope) in self.scopes.iter().enumerate().rev() {
+            if let Some(ty) = scope.locals.get(name) {
+                // A `fn` item cannot use the locals around it; the resolver
+                // reports that, so here the use just has no type.
+                return if depth >= self.fn_scope { ty.clone() } else { Type::Error };
+            }
+            if let Some(ty) = scope.fns.get(name) {
+                return ty.clone();
+            }
+            let ctor = match (scope.structs.get(name), scope.variants.get(name)) {
+                (Some(def), _) => Some(Ctor {
+                    ty: Type::adt(name, Vec::new()),
+                    name: name.to_string(),
+                    is_variant: false,
+                    def: def.clone(),
+                }),
+                (None, Some((enum_name, variant))) => self.variant(enum_name, variant),
+                (None, None) => None,
+            };
+            if let Some(ty) = ctor.and_then(|ctor| ctor.value_type()) {
+                return ty;
+            }
+        }
+        if let Some(ty) = self.free.get(name) {
+            return ty.clone();
+        }
+        let ty = self.infer.new_var();
+        self.free.insert(name.to_string(), ty.clone());
+        ty
+    }
+
+    fn struct_def(&self, name: &str) -> Option<&StructDef> {
+        self.scopes.iter().rev().find_map(|scope| scope.structs.get(name))
+    }
+
+    fn enum_def(&self, name: &str) -> Option<&EnumDef> {
+        self.scopes.iter().rev().find_map(|scope| scope.enums.get(name))
+    }
+
+    fn variant(&self, enum_name: &str, variant: &str) -> Option<Ctor> {
+        let def = self.enum_def(enum_name)?;
+        let (_, def) = def.variants.iter().find(|(name, _)| name == variant)?;
+        Some(Ctor {
+            ty: Type::adt(enum_name, Vec::new()),
+            name: format!("{}::{}", enum_name, variant),
+            is_variant: true,
+            def: def.clone(),
+        })
+    }
+
+    /// The declared struct or variant a path names: `Point`,
+    /// `Shape::Circle`, or `Circle` after `use Shape::*;`.
+    fn ctor(&self, path: &Path) -> Option<Ctor> {
+        if let [.., parent, last] = path.segments.as_slice() {
+            if let Some(ctor) = self.variant(&parent.ident.name, &last.ident.name) {
+                return Some(ctor);
+            }
+        }
+        let name = &path.last().ident.name;
+        if path.segments.len() == 1 {
+            let imported = self.scopes.iter().rev().find_map(|scope| scope.variants.get(name));
+            if let Some((enum_name, variant)) = imported {
+                return self.variant(enum_name, variant);
+            }
+        }
+        let def = self.struct_def(name)?;
+        Some(Ctor {
+            ty: Type::adt(name, Vec::new()),
+            name: name.clone(),
+            is_variant: false,
+            def: def.clone(),
+        })
+    }
+
+    /// The struct definition for values of type `ty`, looking through
+    /// references as field access does.
+    fn struct_of(&self, ty: &Type) -> Option<(String, &StructDef)> {
+        let mut ty = self.infer.shallow_resolve(ty);
+        while let Type::Ref(_, inner) = ty {
+            ty = self.infer.shallow_resolve(&inner);
+        }
+        match ty {
+            Type::Adt(name, _) => self.struct_def(&name).map(|def| (name, def)),
+            _ => None,
+        }
+    }
+
+    fn resolve(&self, ty: &Type) -> Type {
+        self.infer.resolve(ty)
+    }
+
+    /// Require `found` to be `expected`, reporting a mismatch at `span` if not.
+    fn demand(&mut self, expected: &Type, found: &Type, span: Span) -> bool {
+        if self.infer.coerce(found, expected) {
+            return true;
+        }
+        let diagnostic = self.mismatch(expected, found, span);
+        self.diagnostics.push(diagnostic);
+        false
+    }
+
+    /// E0308, "mismatched types", with "expected X, found Y" at `span`.
+    fn mismatch(&self, expected: &Type, found: &Type, span: Span) -> Diagnostic {
+        Diagnostic::error("mismatched types")
+            .code("E0308")
+            .primary(span, self.expected_found(expected, found))
+    }
+
+    fn expected_found(&self, expected: &Type, found: &Type) -> String {
+        format!(
+            "expected {}, found {}",
+            self.resolve(expected).describe(),
+            self.resolve(found).describe()
+        )
+    }
+
+    /// The type written in a `let` annotation, a cast or a closure signature.
+    fn lower_ty(&mut self, ty: &ast::Ty) -> Type {
+        match &ty.kind {
+            TyKind::Path(path) => {
+                let segment = path.last();
+                let name = segment.ident.name.as_str();
+                if path.segments.len() == 1 && segment.generics.is_empty() {
+                    if let Some(int) = IntTy::from_name(name) {
+                        return Type::Int(int);
+                    }
+                    if let Some(float) = FloatTy::from_name(name) {
+                        return Type::Float(float);
+                    }
+                    match name {
+                        "bool" => return Type::Bool,
+                        "char" => return Type::Char,
+                        "str" => return Type::Str,
+                        _ => {}
+                    }
+                }
+                let args = segment.generics.iter().map(|arg| self.lower_ty(arg)).collect();
+                Type::Adt(name.to_string(), args)
+            }
+            TyKind::Ref(mutability, inner) => {
+                Type::Ref(*mutability, Box::new(self.lower_ty(inner)))
+            }
+            TyKind::Tuple(elems) => {
+                Type::Tuple(elems.iter().map(|elem| self.lower_ty(elem)).collect())
+            }
+            TyKind::Array(elem, len) => {
+                Type::Array(Box::new(self.lower_ty(elem)), literal_len(len))
+            }
+            TyKind::Slice(elem) => Type::Slice(Box::new(self.lower_ty(elem))),
+            TyKind::Never => Type::Never,
+            TyKind::Infer => self.infer.new_var(),
+        }
+    }
+}
+
+/// The type a constructor path builds, for `Point(1, 2)`, `Pet::Hyena` or
+/// `Shape::Circle { .. }`: the path names a variant of the enum before it,
+/// or a struct by itself. `lone_names` says whether a single capitalized
+/// name counts; in a plain value position `Jack` may be a unit variant of an
+/// enum we know nothing about.
+fn ctor_type(path: &Path, lone_names: bool) -> Option<Type> {
+    let is_type_name = |name: &str| name.starts_with(|c: char| c.is_ascii_uppercase());
+    match path.segments.as_slice() {
+        [.., parent, last]
+            if is_type_name(&last.ident.name) && is_type_name(&parent.ident.name) =>
+        {
+            Some(Type::Adt(parent.ident.name.clone(), Vec::new()))
+        }
+        [.., last] if is_type_name(&last.ident.name) && (lone_names || path.segments.len() > 1) => {
+            Some(Type::Adt(last.ident.name.clone(), Vec::new()))
+        }
+        _ => None,
+    }
+}
+
+/// The type of `i32::MAX` and the other integer limits.
+fn int_const(path: &Path) -> Option<Type> {
+    match path.segments.as_slice() {
+        [ty, constant] if matches!(constant.ident.name.as_str(), "MIN" | "MAX") => {
+            IntTy::from_name(&ty.ident.name).map(Type::Int)
+        }
+        _ => None,
+    }
+}
+
+/// The length in `[T; 3]` or `[0; 3]`, if it is a literal.
+fn literal_len(expr: &Expr) -> Option<u128> {
+    match &expr.kind {
+        ExprKind::Lit(ast::LitKind::Int(n, _)) => Some(*n),
+        _ => None,
+    }
+}
+
+/// Where to point when an expression's value has the wrong type: for a
+/// block, its tail expression.
+fn value_span(expr: &Expr) -> Span {
+    match &expr.kind {
+        ExprKind::Block(block) => block.tail.as_deref().map_or(block.span, value_span),
+        _ => expr.span,
+    }
+}
+
+/// Add the help for a value found where `()` was expected: the expression
+/// most likely ends a statement whose `;` was left out by accident.
+fn forgot_semicolon(diagnostic: Diagnostic, expr: &Expr) -> Diagnostic {
+    diagnostic.suggest(
+        "did you forget a `;`?",
+        value_span(expr).shrink_to_hi(),
+        ";",
+        Applicability::MachineApplicable,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::Applicability;
+    use crate::test_util::check_types;
+
+    /// The one error `src` has, with its primary label.
+    fn error(src: &str) -> (String, String) {
+        let mut diagnostics = check_types(src);
+        assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+        let diagnostic = diagnostics.remove(0);
+        let primary = diagnostic.labels.iter().find(|label| label.primary).unwrap();
+        (diagnostic.message.clone(), primary.message.clone())
+    }
+
+    #[test]
+    fn if_and_else_must_agree() {
+        let src = "fn is_hobbit() -> bool { true } if is_hobbit() { \"eleventy-one\" } else { 9 }";
+        assert_eq!(
+            error(src),
+            (
+                "`if` and `else` have incompatible types".to_string(),
+                "expected `&str`, found integer".to_string()
+            )
+        );
+        let diagnostic = &check_types(src)[0];
+        assert_eq!(diagnostic.code, Some("E0308"));
+        assert_eq!(diagnostic.labels.len(), 2);
+    }
+
+    #[test]
+    fn if_without_else_is_unit() {
+        let diagnostics = check_types("let x = 3; let y: i32 = if x > 2 { 1 };");
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, [Some("E0317"), Some("E0308")]);
+        assert_eq!(diagnostics[0].message, "`if` may be missing an `else` clause");
+        assert!(check_types("let x = 3; if x > 2 { println!(\"big\"); }").is_empty());
+    }
+
+    #[test]
+    fn match_arms_must_agree() {
+        assert_eq!(
+            error("let n = Some(3); match n { Some(c) => c, _ => None }"),
+            (
+                "`match` arms have incompatible types".to_string(),
+                "expected integer, found `Option<_>`".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn annotations_and_calls_are_checked() {
+        assert_eq!(error("let a: bool = 1;").1, "expected `bool`, found integer");
+        assert_eq!(error("fn f(x: u8) -> u8 { x } f('c')").1, "expected `u8`, found `char`");
+        assert_eq!(
+            error("struct P { x: i32 } let p = P { x: 1.5 };").1,
+            "expected `i32`, found floating-point number"
+        );
+        assert!(check_types("let a: u64 = 1; let b = a + 2; let c: u64 = b;").is_empty());
+    }
+
+    #[test]
+    fn forgotten_semicolon_is_suggested() {
+        let src = "fn changed() -> bool { true } fn compute_size() -> i32 { 1 } \
+                   if changed() { compute_size() } let z = 1;";
+        let diagnostics = check_types(src);
+        assert_eq!(diagnostics.len(), 1);
+        let suggestion = &diagnostics[0].suggestions[0];
+        assert_eq!(suggestion.message, "did you forget a `;`?");
+        assert_eq!(suggestion.replacement, ";");
+        assert_eq!(suggestion.applicability, Applicability::MachineApplicable);
+        assert_eq!(suggestion.span.start, src.find("() }").unwrap() + 2);
+    }
+
+    #[test]
+    fn unit_bodies_get_the_suggestion() {
+        for src in [
+            "let mut i = 0; while i < 3 { i += 1; i * 2 }",
+            "for i in 0..2 { i }",
+            "fn f() { 2 + 2 }",
+            "loop { 1 }",
+        ] {
+            let diagnostics = check_types(src);
+            assert_eq!(diagnostics[0].suggestions[0].message, "did you forget a `;`?", "{}", src);
+        }
+        // Where the value is used, the block's type is not `()`.
+        assert!(check_types("fn f() -> i32 { 2 + 2 }").is_empty());
+    }
+
+    #[test]
+    fn diverging_expressions_fit_any_type() {
+        let src = "fn exit(code: i32) -> ! { panic!(\"exit {}\", code) } \
+                   fn f(n: i32) -> i32 { if n > 0 { n } else { exit(1) } }";
+        assert!(check_types(src).is_empty());
+        assert!(check_types(
+            "fn f(n: i32) -> i32 { let x = match n { 0 => return 5, k => k * 2 }; x }"
+        )
+        .is_empty());
+        let src = "let mut i = 0; let v: Vec<i32> = loop { \
+                   i += 1; let x: String = if i < 3 { continue } else { break vec![i] }; }; v";
+        assert!(check_types(src).is_empty());
+        assert!(check_types("let x: i32 = panic!(\"no\"); x").is_empty());
+        assert!(
+            check_types("fn serve_forever() -> ! { let mut n = 0; loop { n += 1; } }").is_empty()
+        );
+    }
+
+    #[test]
+    fn never_functions_may_not_finish() {
+        assert_eq!(
+            error("fn f() -> ! { 5 }"),
+            ("mismatched types".to_string(), "expected `!`, found integer".to_string())
+        );
+        let diagnostics = check_types("fn bad(c: bool) -> ! { if c { loop {} } }");
+        assert_eq!(diagnostics[0].code, Some("E0308"));
+        assert_eq!(
+            diagnostics[0].notes[0].1,
+            "`!` is the type of a function that never returns, so its body may not finish"
+        );
+    }
+}
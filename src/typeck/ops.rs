@@ -0,0 +1,289 @@
+//! The operator typing table: which operand types each unary and binary
+//! operator takes, and the type it gives.
+//!
+//! | operator              | operands                                  | type      |
+//! |-----------------------|-------------------------------------------|-----------|
+//! | `-x`                  | signed integers, floats                   | `x`'s     |
+//! | `!x`                  | integers (bitwise NOT), `bool`            | `x`'s     |
+//! | `+ - * / %`           | two of one integer or float type          | theirs    |
+//! | `+`                   | `String` and `&str`                       | `String`  |
+//! | `& \| ^`              | two of one integer type, or two `bool`s   | theirs    |
+//! | `<< >>`               | an integer, shifted by any integer        | the left  |
+//! | `== != < <= > >=`     | two of one type                           | `bool`    |
+//! | `&& \|\|`             | two `bool`s                               | `bool`    |
+//!
+//! An arithmetic or bitwise operand may also be a reference to a number,
+//! as in `&a + b`. Whether an unsuffixed literal is signed may only be
+//! known later, when inference gives it a type: `let x: u32 = -5;` is
+//! rejected once the checker has seen the annotation.
+
+use super::ty::Type;
+use super::{value_span, Checker};
+use crate::ast::{BinOp, Expr, UnOp};
+use crate::diagnostic::Diagnostic;
+use crate::span::Span;
+
+/// The kinds of operand the table tells apart.
+#[derive(Clone, Copy, PartialEq)]
+enum Operand {
+    Signed,
+    Unsigned,
+    /// An unsuffixed integer literal's type, not yet known to be either.
+    Integer,
+    Float,
+    Bool,
+    /// A type not yet known, or already in error, which is given the
+    /// benefit of the doubt.
+    Unknown,
+    Other,
+}
+
+impl Operand {
+    fn of(ty: &Type) -> Operand {
+        match ty {
+            Type::Int(int) if int.is_signed() => Operand::Signed,
+            Type::Int(_) => Operand::Unsigned,
+            Type::IntVar(_) => Operand::Integer,
+            Type::Float(_) | Type::FloatVar(_) => Operand::Float,
+            Type::Bool => Operand::Bool,
+            Type::Var(_) | Type::Error | Type::Never => Operand::Unknown,
+            _ => Operand::Other,
+        }
+    }
+
+    fn is_integer(self) -> bool {
+        matches!(self, Operand::Signed | Operand::Unsigned | Operand::Integer)
+    }
+}
+
+/// Whether the unary operator `op` applies to `operand`.
+fn unary_applies(op: UnOp, operand: Operand) -> bool {
+    match op {
+        UnOp::Neg => matches!(
+            operand,
+            Operand::Signed | Operand::Integer | Operand::Float | Operand::Unknown
+        ),
+        UnOp::Not => operand.is_integer() || matches!(operand, Operand::Bool | Operand::Unknown),
+        UnOp::Deref => true,
+    }
+}
+
+/// Whether the binary operator `op` applies to a left operand of kind
+/// `lhs`. Comparisons apply to every type; `&&` and `||` are not here, as
+/// they take nothing but `bool`.
+fn binary_applies(op: BinOp, lhs: Operand) -> bool {
+    let numeric = lhs.is_integer() || matches!(lhs, Operand::Float | Operand::Unknown);
+    match op {
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem => numeric,
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => {
+            lhs.is_integer() || matches!(lhs, Operand::Bool | Operand::Unknown)
+        }
+        BinOp::Shl | BinOp::Shr => lhs.is_integer() || lhs == Operand::Unknown,
+        _ => true,
+    }
+}
+
+impl Checker {
+    /// `-operand` or `!operand`, the unary operators other than `*`.
+    pub(super) fn unary(&mut self, op: UnOp, operand: &Expr, span: Span) -> Type {
+        let operand_ty = self.expr(operand);
+        let ty = self.deref_number(&operand_ty);
+        if !unary_applies(op, Operand::of(&ty)) {
+            let diagnostic = self.cannot_apply_unary(op, &ty, span);
+            self.diagnostics.push(diagnostic);
+            return Type::Error;
+        }
+        if op == UnOp::Neg && Operand::of(&ty) == Operand::Integer {
+            self.negated.push((ty.clone(), span));
+        }
+        ty
+    }
+
+    /// E0600 for each negated literal that inference has since made
+    /// unsigned, once the whole program has been seen.
+    pub(super) fn check_negations(&mut self) {
+        for (ty, span) in std::mem::take(&mut self.negated) {
+            let ty = self.infer.shallow_resolve(&ty);
+            if Operand::of(&ty) == Operand::Unsigned {
+                let diagnostic = self.cannot_apply_unary(UnOp::Neg, &ty, span);
+                self.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    /// The notes' "can't apply unary `-` to type `u32`".
+    fn cannot_apply_unary(&self, op: UnOp, ty: &Type, span: Span) -> Diagnostic {
+        let ty = self.resolve(ty);
+        let diagnostic =
+            Diagnostic::error(format!("can't apply unary `{}` to type `{}`", op.as_str(), ty))
+                .code("E0600")
+                .primary(span, format!("cannot apply unary operator `{}`", op.as_str()));
+        match (op, Operand::of(&ty)) {
+            (UnOp::Neg, Operand::Unsigned) => diagnostic.note("unsigned values cannot be negated"),
+            (UnOp::Not, _) => {
+                diagnostic.note("`!` is bitwise NOT on integers and logical NOT on `bool`")
+            }
+            _ => diagnostic,
+        }
+    }
+
+    /// `lhs op rhs`, for every binary operator.
+    pub(super) fn binary(&mut self, op: BinOp, lhs: &Expr, rhs: &Expr) -> Type {
+        if op.is_lazy() {
+            self.expect(lhs, &Type::Bool);
+            self.expect(rhs, &Type::Bool);
+            return Type::Bool;
+        }
+        let lhs_ty = self.expr(lhs);
+        if op.is_comparison() {
+            // The two sides must have the very same type.
+            self.expect(rhs, &lhs_ty);
+            return Type::Bool;
+        }
+        // `String + &str` appends.
+        if op == BinOp::Add && self.resolve(&lhs_ty) == Type::adt("String", Vec::new()) {
+            self.expect(rhs, &Type::str_ref());
+            return lhs_ty;
+        }
+
+        let lhs_ty = self.deref_number(&lhs_ty);
+        if !binary_applies(op, Operand::of(&lhs_ty)) {
+            self.expr(rhs);
+            let diagnostic = self.cannot_apply_binary(op, &lhs_ty, lhs.span.to(rhs.span));
+            self.diagnostics.push(diagnostic);
+            return Type::Error;
+        }
+        let rhs_ty = self.expr(rhs);
+        let rhs_ty = self.deref_number(&rhs_ty);
+        if matches!(op, BinOp::Shl | BinOp::Shr) {
+            // The shift amount's type is independent of the value shifted.
+            let amount = Operand::of(&rhs_ty);
+            if !amount.is_integer() && amount != Operand::Unknown {
+                let message = format!(
+                    "no implementation for `{} {} {}`",
+                    self.resolve(&lhs_ty),
+                    op.as_str(),
+                    self.resolve(&rhs_ty)
+                );
+                let diagnostic = Diagnostic::error(message.clone())
+                    .code("E0277")
+                    .primary(lhs.span.to(rhs.span), message)
+                    .note("shift amounts may be of any integer type");
+                self.diagnostics.push(diagnostic);
+            }
+            return lhs_ty;
+        }
+        self.demand(&lhs_ty, &rhs_ty, value_span(rhs));
+        lhs_ty
+    }
+
+    /// `ty`, or the number it refers to if it is a reference to one, since
+    /// the arithmetic and bitwise operators also take `&i32` and the like.
+    fn deref_number(&self, ty: &Type) -> Type {
+        let ty = self.infer.shallow_resolve(ty);
+        if let Type::Ref(_, inner) = &ty {
+            let inner = self.infer.shallow_resolve(inner);
+            if matches!(inner, Type::Int(_) | Type::IntVar(_) | Type::Float(_) | Type::FloatVar(_))
+            {
+                return inner;
+            }
+        }
+        ty
+    }
+
+    fn cannot_apply_binary(&self, op: BinOp, lhs_ty: &Type, span: Span) -> Diagnostic {
+        let lhs_ty = self.resolve(lhs_ty);
+        let diagnostic = Diagnostic::error(format!(
+            "binary operation `{}` cannot be applied to type `{}`",
+            op.as_str(),
+            lhs_ty
+        ))
+        .code("E0369")
+        .primary(span, "");
+        if op == BinOp::Add && lhs_ty == Type::str_ref() {
+            diagnostic
+                .note("string concatenation requires an owned `String` on the left")
+                .help("create an owned `String` with `.to_string()` or `String::from`")
+        } else {
+            diagnostic
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse_program;
+    use crate::test_util::check_types;
+
+    /// The code and message of the only error in `src`.
+    fn error(src: &str) -> (&'static str, String) {
+        let mut diagnostics = check_types(src);
+        assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+        let diagnostic = diagnostics.remove(0);
+        (diagnostic.code.unwrap_or(""), diagnostic.message)
+    }
+
+    #[test]
+    fn unsigned_values_cannot_be_negated() {
+        let diagnostics = check_types("let x = -100u32; x");
+        assert_eq!(diagnostics[0].code, Some("E0600"));
+        assert_eq!(diagnostics[0].message, "can't apply unary `-` to type `u32`");
+        assert_eq!(diagnostics[0].notes[0].1, "unsigned values cannot be negated");
+        // The literal's type is only known once the annotation is seen.
+        assert_eq!(
+            error("let x: u32 = -5; x"),
+            ("E0600", "can't apply unary `-` to type `u32`".into())
+        );
+        assert_eq!(error("let x = -5; let y: u32 = x; y").0, "E0600");
+        assert_eq!(error("-true"), ("E0600", "can't apply unary `-` to type `bool`".into()));
+        assert!(check_types("let x: i8 = -5; -x").is_empty());
+    }
+
+    #[test]
+    fn not_is_bitwise_on_integers() {
+        assert!(check_types("let hi: u8 = 0xe0; !hi == 0x1f").is_empty());
+        assert!(check_types("let b = true; !b").is_empty());
+        let diagnostics = check_types("!1.5");
+        assert_eq!(diagnostics[0].message, "can't apply unary `!` to type `{float}`");
+        assert_eq!(
+            diagnostics[0].notes[0].1,
+            "`!` is bitwise NOT on integers and logical NOT on `bool`"
+        );
+    }
+
+    #[test]
+    fn there_is_no_unary_plus() {
+        let err = parse_program("+5").unwrap_err();
+        assert_eq!(err.message, "expected expression, found `+`");
+        assert_eq!(err.notes[0].1, "Rust has no unary `+` operator; remove the `+`");
+    }
+
+    #[test]
+    fn binary_operands() {
+        let (code, message) = error("\"a\" + \"b\"");
+        assert_eq!(
+            (code, message.as_str()),
+            ("E0369", "binary operation `+` cannot be applied to type `&str`")
+        );
+        assert_eq!(error("true + 1").1, "binary operation `+` cannot be applied to type `bool`");
+        assert!(check_types("let s = String::new(); let t: String = s + \"b\";").is_empty());
+        assert!(check_types("let a = 5; &a + 1").is_empty());
+        assert!(check_types("let t = true; t & false").is_empty());
+    }
+
+    #[test]
+    fn shifts_take_any_integer_amount() {
+        assert!(check_types("1u8 << 3i64").is_empty());
+        let (code, message) = error("1u8 << 1.5");
+        assert_eq!((code, message.as_str()), ("E0277", "no implementation for `u8 << {float}`"));
+    }
+
+    #[test]
+    fn comparisons_and_logic_need_exact_types() {
+        assert_eq!(error("1u8 == 1u16"), ("E0308", "mismatched types".into()));
+        assert!(check_types("(1, 2) < (1, 3)").is_empty());
+        assert_eq!(error("1 && true"), ("E0308", "mismatched types".into()));
+        assert_eq!(error("true || 0"), ("E0308", "mismatched types".into()));
+        assert!(check_types("let a = 1; a > 0 && a < 3 || false").is_empty());
+    }
+}
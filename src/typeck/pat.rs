@@ -0,0 +1,353 @@
+//! Typing patterns and the variables they bind.
+
+use std::convert::TryFrom;
+
+use super::ty::Type;
+use super::{ctor_type, int_const, Checker};
+use crate::ast::{
+    BindingMode, Expr, ExprKind, FieldPat, LitKind, Mutability, Pat, PatKind, Path, RangeLimits,
+    UnOp,
+};
+use crate::diagnostic::Diagnostic;
+
+impl Checker {
+    /// Check `pat` against the type of the value it will match, declaring
+    /// its bindings in the current scope.
+    pub(super) fn pat(&mut self, pat: &Pat, expected: &Type) {
+        self.pat_in(pat, expected, None);
+    }
+
+    /// `by_ref` is the default binding mode. Once a pattern that is not a
+    /// reference pattern has matched a reference, as `Some(x)` matches an
+    /// `&Option<T>`, the variables inside bind by reference: `x` is a `&T`.
+    fn pat_in(&mut self, pat: &Pat, expected: &Type, by_ref: Option<Mutability>) {
+        self.record(pat.id, expected);
+        match &pat.kind {
+            PatKind::Wild => {}
+            PatKind::Ident(mode, name, sub) => {
+                let ty = match (*mode, by_ref) {
+                    (BindingMode::ByRef(mutability), _)
+                    | (BindingMode::ByValue(Mutability::Not), Some(mutability)) => {
+                        Type::Ref(mutability, Box::new(expected.clone()))
+                    }
+                    (BindingMode::ByValue(_), _) => expected.clone(),
+                };
+                self.record(pat.id, &ty);
+                self.declare(&name.name, ty);
+                if let Some(sub) = sub {
+                    self.pat_in(sub, expected, by_ref);
+                }
+            }
+            PatKind::Lit(lit) => {
+                let ty = self.expr(lit);
+                // A string literal matches a `&str` as it is; other literals
+                // look through references.
+                let expected = if matches!(lit.kind, ExprKind::Lit(LitKind::Str(_))) {
+                    expected.clone()
+                } else {
+                    self.peel_refs(expected, by_ref).0
+                };
+                if !self.infer.unify(&expected, &ty) {
+                    self.pat_mismatch(pat, &expected, &ty);
+                }
+            }
+            PatKind::Range(start, end, limits) => {
+                let (expected, _) = self.peel_refs(expected, by_ref);
+                self.range_pat(pat, start, end, *limits, &expected);
+            }
+            PatKind::Tuple(pats) => {
+                let (expected, by_ref) = self.peel_refs(expected, by_ref);
+                let elems: Vec<Type> = pats.iter().map(|_| self.infer.new_var()).collect();
+                let tuple = Type::Tuple(elems.clone());
+                if !self.infer.unify(&expected, &tuple) {
+                    self.pat_mismatch(pat, &expected, &tuple);
+                }
+                for (pat, elem) in pats.iter().zip(&elems) {
+                    self.pat_in(pat, elem, by_ref);
+                }
+            }
+            PatKind::TupleStruct(path, pats) => {
+                let (expected, by_ref) = self.peel_refs(expected, by_ref);
+                let fields = self.tuple_struct_pat(pat, path, pats, &expected);
+                for (pat, field) in pats.iter().zip(&fields) {
+                    self.pat_in(pat, field, by_ref);
+                }
+            }
+            PatKind::Struct(path, fields, rest) => {
+                let (expected, by_ref) = self.peel_refs(expected, by_ref);
+                let types = self.struct_pat(pat, path, fields, *rest, &expected);
+                for (field, ty) in fields.iter().zip(&types) {
+                    self.pat_in(&field.pat, ty, by_ref);
+                }
+            }
+            PatKind::Path(path) => {
+                let (expected, _) = self.peel_refs(expected, by_ref);
+                let ty = if path.as_ident().is_some_and(|ident| ident.name == "None") {
+                    Some(Type::adt("Option", vec![self.infer.new_var()]))
+                } else if let Some(ctor) = self.ctor(path) {
+                    Some(ctor.ty)
+                } else {
+                    int_const(path).or_else(|| ctor_type(path, false))
+                };
+                if let Some(ty) = ty {
+                    if !self.infer.unify(&expected, &ty) {
+                        self.pat_mismatch(pat, &expected, &ty);
+                    }
+                }
+            }
+            PatKind::Ref(inner, mutability) => {
+                let inner_ty = self.infer.new_var();
+                let ty = Type::Ref(*mutability, Box::new(inner_ty.clone()));
+                if !self.infer.unify(expected, &ty) {
+                    self.pat_mismatch(pat, expected, &ty);
+                }
+                self.pat_in(inner, &inner_ty, None);
+            }
+            PatKind::Or(pats) => self.or_pat(pats, expected, by_ref),
+        }
+    }
+
+    /// Look through the references around `expected`, as a non-reference
+    /// pattern does, returning the type underneath and the binding mode
+    /// for the pattern's variables. Going through a `&` makes them shared
+    /// references even inside an `&mut`.
+    fn peel_refs(
+        &mut self,
+        expected: &Type,
+        by_ref: Option<Mutability>,
+    ) -> (Type, Option<Mutability>) {
+        let mut ty = self.infer.shallow_resolve(expected);
+        let mut by_ref = by_ref;
+        while let Type::Ref(mutability, inner) = ty {
+            by_ref = Some(match by_ref {
+                Some(Mutability::Not) => Mutability::Not,
+                _ => mutability,
+            });
+            ty = self.infer.shallow_resolve(&inner);
+        }
+        (ty, by_ref)
+    }
+
+    fn range_pat(
+        &mut self,
+        pat: &Pat,
+        start: &Expr,
+        end: &Expr,
+        limits: RangeLimits,
+        expected: &Type,
+    ) {
+        let ty = self.expr(start);
+        self.expect(end, &ty);
+        if !self.infer.unify(expected, &ty) {
+            self.pat_mismatch(pat, expected, &ty);
+            return;
+        }
+        let numeric = matches!(
+            self.infer.shallow_resolve(&ty),
+            Type::Char | Type::Int(_) | Type::Float(_) | Type::IntVar(_) | Type::FloatVar(_)
+        );
+        if !numeric && !matches!(self.infer.shallow_resolve(&ty), Type::Var(_) | Type::Error) {
+            let diagnostic =
+                Diagnostic::error("only `char` and numeric types are allowed in range patterns")
+                    .code("E0029")
+                    .primary(pat.span, format!("this is of type `{}`", self.resolve(&ty)));
+            self.diagnostics.push(diagnostic);
+            return;
+        }
+        if let (Some(lo), Some(hi)) = (bound_value(start), bound_value(end)) {
+            let diagnostic = match limits {
+                RangeLimits::Closed if lo > hi => {
+                    Diagnostic::error("lower range bound must be less than or equal to upper")
+                        .code("E0030")
+                        .primary(start.span, "lower bound larger than upper bound")
+                }
+                RangeLimits::HalfOpen if lo >= hi => {
+                    Diagnostic::error("lower range bound must be less than upper")
+                        .code("E0579")
+                        .primary(start.span, "")
+                }
+                _ => return,
+            };
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Check `Some(x)` or `Shape::Circle(r)` against `expected`, returning
+    /// the types for the subpatterns.
+    fn tuple_struct_pat(
+        &mut self,
+        pat: &Pat,
+        path: &Path,
+        pats: &[Pat],
+        expected: &Type,
+    ) -> Vec<Type> {
+        let name = path.as_ident().map(|ident| ident.name.as_str());
+        let (ty, fields) = match (name, pats.len()) {
+            (Some("Some"), 1) => {
+                let elem = self.infer.new_var();
+                (Type::adt("Option", vec![elem.clone()]), vec![elem])
+            }
+            (Some(ctor @ ("Ok" | "Err")), 1) => {
+                let (ok, err) = (self.infer.new_var(), self.infer.new_var());
+                let field = if ctor == "Ok" { ok.clone() } else { err.clone() };
+                (Type::adt("Result", vec![ok, err]), vec![field])
+            }
+            _ => match self.ctor(path) {
+                Some(ctor) if ctor.def.tuple => {
+                    if ctor.def.fields.len() != pats.len() {
+                        let plural = |n: usize| if n == 1 { "" } else { "s" };
+                        let (found, wanted) = (pats.len(), ctor.def.fields.len());
+                        let diagnostic = Diagnostic::error(format!(
+                            "this pattern has {} field{}, but the corresponding tuple {} has {} field{}",
+                            found,
+                            plural(found),
+                            ctor.kind(),
+                            wanted,
+                            plural(wanted)
+                        ))
+                        .code("E0023")
+                        .primary(pat.span, format!("expected {} field{}, found {}", wanted, plural(wanted), found));
+                        self.diagnostics.push(diagnostic);
+                    }
+                    let mut fields: Vec<Type> =
+                        ctor.def.fields.into_iter().map(|(_, ty)| ty).collect();
+                    fields.resize_with(pats.len(), || Type::Error);
+                    (ctor.ty, fields)
+                }
+                _ => {
+                    let ty = ctor_type(path, true).unwrap_or_else(|| self.infer.new_var());
+                    (ty, pats.iter().map(|_| self.infer.new_var()).collect())
+                }
+            },
+        };
+        if !self.infer.unify(expected, &ty) {
+            self.pat_mismatch(pat, expected, &ty);
+        }
+        fields
+    }
+
+    /// Check `Point { x, y: 0, .. }` against `expected`, returning the types
+    /// for the fields' patterns.
+    fn struct_pat(
+        &mut self,
+        pat: &Pat,
+        path: &Path,
+        fields: &[FieldPat],
+        rest: bool,
+        expected: &Type,
+    ) -> Vec<Type> {
+        let ctor = match self.ctor(path) {
+            Some(ctor) => ctor,
+            None => {
+                if let Some(ty) = ctor_type(path, true) {
+                    if !self.infer.unify(expected, &ty) {
+                        self.pat_mismatch(pat, expected, &ty);
+                    }
+                }
+                return fields.iter().map(|_| self.infer.new_var()).collect();
+            }
+        };
+        if !self.infer.unify(expected, &ctor.ty) {
+            self.pat_mismatch(pat, expected, &ctor.ty);
+        }
+        let mut types = Vec::with_capacity(fields.len());
+        for field in fields {
+            match ctor.def.fields.iter().find(|(name, _)| *name == field.name.name) {
+                Some((_, ty)) => types.push(ty.clone()),
+                None => {
+                    let diagnostic = Diagnostic::error(format!(
+                        "{} `{}` does not have a field named `{}`",
+                        ctor.kind(),
+                        ctor.name,
+                        field.name.name
+                    ))
+                    .code("E0026")
+                    .primary(
+                        field.name.span,
+                        format!("{} `{}` does not have this field", ctor.kind(), ctor.name),
+                    );
+                    self.diagnostics.push(diagnostic);
+                    types.push(Type::Error);
+                }
+            }
+        }
+        let missing: Vec<String> = ctor
+            .def
+            .fields
+            .iter()
+            .filter(|(name, _)| fields.iter().all(|field| field.name.name != *name))
+            .map(|(name, _)| format!("`{}`", name))
+            .collect();
+        if !rest && !missing.is_empty() {
+            let s = if missing.len() == 1 { "" } else { "s" };
+            let diagnostic = Diagnostic::error(format!(
+                "pattern does not mention field{} {}",
+                s,
+                missing.join(", ")
+            ))
+            .code("E0027")
+            .primary(pat.span, format!("missing field{} {}", s, missing.join(", ")))
+            .help("if you don't care about the missing fields, you can explicitly ignore them with `..`");
+            self.diagnostics.push(diagnostic);
+        }
+        types
+    }
+
+    /// Check each alternative of an or-pattern against the same type. They
+    /// must all bind the same variables, with the same types.
+    fn or_pat(&mut self, pats: &[Pat], expected: &Type, by_ref: Option<Mutability>) {
+        let first = &pats[0];
+        self.pat_in(first, expected, by_ref);
+        let first_bindings = first.bindings();
+        let first_types: Vec<Type> =
+            first_bindings.iter().map(|(_, name)| self.lookup(&name.name)).collect();
+        for alternative in &pats[1..] {
+            self.pat_in(alternative, expected, by_ref);
+            let bindings = alternative.bindings();
+            let unbound = first_bindings
+                .iter()
+                .map(|(_, name)| (name, alternative))
+                .chain(bindings.iter().map(|(_, name)| (name, first)))
+                .filter(|(name, other)| {
+                    other.bindings().iter().all(|(_, bound)| bound.name != name.name)
+                });
+            for (name, other) in unbound {
+                let diagnostic = Diagnostic::error(format!(
+                    "variable `{}` is not bound in all patterns",
+                    name.name
+                ))
+                .code("E0408")
+                .primary(other.span, format!("pattern doesn't bind `{}`", name.name))
+                .secondary(name.span, "variable not in all patterns");
+                self.diagnostics.push(diagnostic);
+            }
+            for ((_, name), first_ty) in first_bindings.iter().zip(&first_types) {
+                if let Some((_, bound)) = bindings.iter().find(|(_, bound)| bound.name == name.name)
+                {
+                    let ty = self.lookup(&bound.name);
+                    self.demand(first_ty, &ty, bound.span);
+                }
+            }
+        }
+        // The first alternative's types are the ones the arm sees.
+        for ((_, name), ty) in first_bindings.iter().zip(first_types) {
+            self.declare(&name.name, ty);
+        }
+    }
+
+    fn pat_mismatch(&mut self, pat: &Pat, expected: &Type, found: &Type) {
+        let diagnostic = self.mismatch(expected, found, pat.span);
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+/// The value of a range pattern's bound, if it is a literal: an integer,
+/// possibly negated, or a `char`.
+pub(super) fn bound_value(expr: &Expr) -> Option<i128> {
+    match &expr.kind {
+        ExprKind::Lit(LitKind::Int(n, _)) => i128::try_from(*n).ok(),
+        ExprKind::Lit(LitKind::Char(c)) => Some(i128::from(u32::from(*c))),
+        ExprKind::Unary(UnOp::Neg, inner) => bound_value(inner).map(|n| -n),
+        _ => None,
+    }
+}
@@ -0,0 +1,186 @@
+//! The types the checker reasons about.
+
+use std::fmt;
+
+use crate::ast::{FloatTy, IntTy, Mutability};
+
+/// An inference variable, standing for a type not yet known.
+pub type VarId = usize;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Bool,
+    Char,
+    /// `str`, normally seen behind a reference as `&str`.
+    Str,
+    Int(IntTy),
+    Float(FloatTy),
+    /// `(A, B)`; the unit type `()` is the empty tuple.
+    Tuple(Vec<Type>),
+    /// `[T; N]`, with `N` unknown when the length is not a literal.
+    Array(Box<Type>, Option<u128>),
+    /// `[T]`
+    Slice(Box<Type>),
+    Ref(Mutability, Box<Type>),
+    /// A named type such as `String`, `Option<T>`, `Range<T>` or a user's `Pet`.
+    Adt(String, Vec<Type>),
+    Fn(Vec<Type>, Box<Type>),
+    /// `!`, the type of expressions that never finish, such as `return` or
+    /// a call to `std::process::exit`. A value of it fits where any type is
+    /// expected, since there never is one.
+    Never,
+    /// Any type: a variable that is not yet known.
+    Var(VarId),
+    /// An unsuffixed integer literal's type, which must be some integer type
+    /// and defaults to `i32`.
+    IntVar(VarId),
+    /// An unsuffixed float literal's type, defaulting to `f64`.
+    FloatVar(VarId),
+    /// The type of an expression that already produced an error. It unifies
+    /// with everything, so one mistake is reported once.
+    Error,
+}
+
+impl Type {
+    pub fn unit() -> Type {
+        Type::Tuple(Vec::new())
+    }
+
+    pub fn is_unit(&self) -> bool {
+        matches!(self, Type::Tuple(elems) if elems.is_empty())
+    }
+
+    /// `&str`, the type of string literals.
+    pub fn str_ref() -> Type {
+        Type::Ref(Mutability::Not, Box::new(Type::Str))
+    }
+
+    pub fn adt(name: &str, args: Vec<Type>) -> Type {
+        Type::Adt(name.to_string(), args)
+    }
+
+    /// Whether using a value of this type copies it rather than moving it.
+    /// Types not yet known count as `Copy`, so that nothing is reported
+    /// about them. Structs and enums are never `Copy`, since nothing here
+    /// can derive it.
+    pub fn is_copy(&self) -> bool {
+        match self {
+            Type::Bool
+            | Type::Char
+            | Type::Int(_)
+            | Type::Float(_)
+            | Type::IntVar(_)
+            | Type::FloatVar(_)
+            | Type::Ref(Mutability::Not, _)
+            | Type::Fn(..)
+            | Type::Never
+            | Type::Var(_)
+            | Type::Error => true,
+            Type::Tuple(elems) => elems.iter().all(Type::is_copy),
+            Type::Array(elem, _) => elem.is_copy(),
+            Type::Adt(name, args) => match name.as_str() {
+                "Option" | "Result" | "RangeTo" | "RangeToInclusive" | "RangeFull" | "Ordering" => {
+                    args.iter().all(Type::is_copy)
+                }
+                _ => false,
+            },
+            Type::Str | Type::Slice(_) | Type::Ref(Mutability::Mut, _) => false,
+        }
+    }
+
+    /// Whether the standard library has `impl From<from> for Self`, as `?`
+    /// needs to convert an error: the identity, the lossless numeric
+    /// widenings, `bool` and `char` to integers, `u8` to `char`, and `&str`
+    /// to `String`.
+    pub fn has_from(&self, from: &Type) -> bool {
+        let int_from_int = |to: IntTy, from: IntTy| match (to, from) {
+            (IntTy::Usize, from) => matches!(from, IntTy::U8 | IntTy::U16),
+            (IntTy::Isize, from) => matches!(from, IntTy::U8 | IntTy::I8 | IntTy::I16),
+            (_, IntTy::Usize) | (_, IntTy::Isize) => false,
+            (to, from) => to.bits() > from.bits() && (to.is_signed() || !from.is_signed()),
+        };
+        match (self, from) {
+            (to, from) if to == from => true,
+            (Type::Int(to), Type::Int(from)) => int_from_int(*to, *from),
+            (Type::Int(_), Type::Bool) => true,
+            (Type::Int(to), Type::Char) => matches!(to, IntTy::U32 | IntTy::U64 | IntTy::U128),
+            (Type::Char, Type::Int(IntTy::U8)) => true,
+            (Type::Float(FloatTy::F64), Type::Float(FloatTy::F32)) => true,
+            (Type::Float(to), Type::Int(from)) => {
+                let bits = if *to == FloatTy::F32 { 16 } else { 32 };
+                from.bits() <= bits && !matches!(from, IntTy::Isize | IntTy::Usize)
+            }
+            (Type::Adt(name, args), Type::Ref(Mutability::Not, inner)) => {
+                name == "String" && args.is_empty() && **inner == Type::Str
+            }
+            _ => false,
+        }
+    }
+
+    /// How error messages refer to the type: in backticks, except for the
+    /// literal types, which rustc describes in words ("expected `&str`,
+    /// found integer").
+    pub fn describe(&self) -> String {
+        match self {
+            Type::IntVar(_) => "integer".to_string(),
+            Type::FloatVar(_) => "floating-point number".to_string(),
+            ty => format!("`{}`", ty),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "bool"),
+            Type::Char => write!(f, "char"),
+            Type::Str => write!(f, "str"),
+            Type::Int(ty) => write!(f, "{}", ty.name()),
+            Type::Float(ty) => write!(f, "{}", ty.name()),
+            Type::Tuple(elems) => {
+                write!(f, "(")?;
+                write_list(f, elems)?;
+                if elems.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Type::Array(elem, Some(len)) => write!(f, "[{}; {}]", elem, len),
+            Type::Array(elem, None) => write!(f, "[{}; _]", elem),
+            Type::Slice(elem) => write!(f, "[{}]", elem),
+            Type::Ref(mutability, inner) => write!(f, "&{}{}", mutability.prefix_str(), inner),
+            Type::Adt(name, args) => {
+                write!(f, "{}", name)?;
+                if !args.is_empty() {
+                    write!(f, "<")?;
+                    write_list(f, args)?;
+                    write!(f, ">")?;
+                }
+                Ok(())
+            }
+            Type::Fn(params, ret) => {
+                write!(f, "fn(")?;
+                write_list(f, params)?;
+                write!(f, ")")?;
+                if !ret.is_unit() {
+                    write!(f, " -> {}", ret)?;
+                }
+                Ok(())
+            }
+            Type::Never => write!(f, "!"),
+            Type::Var(_) | Type::Error => write!(f, "_"),
+            Type::IntVar(_) => write!(f, "{{integer}}"),
+            Type::FloatVar(_) => write!(f, "{{float}}"),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, types: &[Type]) -> fmt::Result {
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", ty)?;
+    }
+    Ok(())
+}